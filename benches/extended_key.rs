@@ -0,0 +1,84 @@
+//! What [`ElectrumExtendedKeyEnum`] saves over boxed trait objects when converting many keys to descriptors: the
+//! allocation per key, and telling xprvs from xpubs without a downcast. The allocation is small next to parsing the
+//! base58 of the keys, expect both within a few percent. Run with `cargo bench`.
+
+use bitcoin::bip32::{ExtendedPrivKey, ExtendedPubKey};
+use bitcoin::secp256k1::Secp256k1;
+use bitcoin::Network;
+use libelectrum2descriptors::{
+    ElectrumExtendedKey, ElectrumExtendedKeyEnum, ElectrumExtendedPrivKey, ElectrumExtendedPubKey,
+};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// The keys converted, half of them xprvs
+const KEYS: usize = 10_000;
+
+fn main() {
+    let secp = Secp256k1::new();
+    let keys = (0..KEYS)
+        .map(|i| {
+            let xprv = ExtendedPrivKey::new_master(Network::Bitcoin, &i.to_be_bytes()).unwrap();
+            let kind = ["standard", "p2wpkh-p2sh", "p2wpkh"][i % 3].to_string();
+            if i % 2 == 0 {
+                ElectrumExtendedPrivKey::new(xprv, kind).xkey_str()
+            } else {
+                ElectrumExtendedPubKey::new(ExtendedPubKey::from_priv(&secp, &xprv), kind)
+                    .xkey_str()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let boxed = time(|| {
+        let mut private = 0;
+        for key in &keys {
+            let key: Box<dyn ElectrumExtendedKey> = match ElectrumExtendedPrivKey::from_str(key) {
+                Ok(xprv) => {
+                    private += 1;
+                    Box::new(xprv)
+                }
+                Err(_) => Box::new(ElectrumExtendedPubKey::from_str(key).unwrap()),
+            };
+            key.to_descriptors();
+        }
+        assert_eq!(private, KEYS / 2);
+    });
+    let enumerated = time(|| {
+        let mut private = 0;
+        for key in &keys {
+            let key = match ElectrumExtendedPrivKey::from_str(key) {
+                Ok(xprv) => ElectrumExtendedKeyEnum::Priv(xprv),
+                Err(_) => {
+                    ElectrumExtendedKeyEnum::Pub(ElectrumExtendedPubKey::from_str(key).unwrap())
+                }
+            };
+            private += usize::from(key.is_private());
+            key.to_descriptors();
+        }
+        assert_eq!(private, KEYS / 2);
+    });
+    println!("{} keys to descriptors", KEYS);
+    println!(
+        "Box<dyn ElectrumExtendedKey>: {:>8.2} ms",
+        boxed.as_secs_f64() * 1000.0
+    );
+    println!(
+        "ElectrumExtendedKeyEnum:      {:>8.2} ms",
+        enumerated.as_secs_f64() * 1000.0
+    );
+    println!(
+        "the enum takes {:.1}% of the time of the trait objects",
+        enumerated.as_secs_f64() / boxed.as_secs_f64() * 100.0
+    );
+}
+
+/// The average duration of `f` over a second, but at least 3 runs
+fn time(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    let mut runs = 0;
+    while runs < 3 || start.elapsed() < Duration::from_secs(1) {
+        f();
+        runs += 1;
+    }
+    start.elapsed() / runs
+}
@@ -0,0 +1,60 @@
+//! How much cheaper [`ElectrumWalletFile::peek`] is than parsing a wallet file with a long history, which electrum
+//! writes before the wallet_type as it sorts the keys. Run with `cargo bench`.
+
+use libelectrum2descriptors::ElectrumWalletFile;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// The transactions of the generated history
+const TRANSACTIONS: usize = 20_000;
+
+fn main() {
+    let tempdir = tempfile::tempdir().unwrap();
+    let wallet_file = tempdir.path().join("wallet");
+    let mut wallet: Value = serde_json::from_str(
+        &std::fs::read_to_string(Path::new("tests/wallets/default_segwit")).unwrap(),
+    )
+    .unwrap();
+    let transactions = (0..TRANSACTIONS)
+        .map(|i| (format!("{:064x}", i), Value::String("02".repeat(250))))
+        .collect::<serde_json::Map<_, _>>();
+    let verified = (0..TRANSACTIONS)
+        .map(|i| {
+            (
+                format!("{:064x}", i),
+                json!([700_000 + i, 1_600_000_000 + i, i, "00"]),
+            )
+        })
+        .collect::<serde_json::Map<_, _>>();
+    wallet["transactions"] = Value::Object(transactions);
+    wallet["verified_tx3"] = Value::Object(verified);
+    // the maps of json values are sorted like electrum sorts the keys
+    std::fs::write(&wallet_file, serde_json::to_string_pretty(&wallet).unwrap()).unwrap();
+    let size = std::fs::metadata(&wallet_file).unwrap().len();
+
+    let peek = time(|| {
+        ElectrumWalletFile::peek(&wallet_file).unwrap();
+    });
+    let parse = time(|| {
+        ElectrumWalletFile::from_file(&wallet_file).unwrap();
+    });
+    println!("wallet file of {} MB", size / 1_000_000);
+    println!("peek:      {:>8.2} ms", peek.as_secs_f64() * 1000.0);
+    println!("from_file: {:>8.2} ms", parse.as_secs_f64() * 1000.0);
+    println!(
+        "peek is {:.0} times faster",
+        parse.as_secs_f64() / peek.as_secs_f64()
+    );
+}
+
+/// The average duration of `f` over a second, but at least 3 runs
+fn time(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    let mut runs = 0;
+    while runs < 3 || start.elapsed() < Duration::from_secs(1) {
+        f();
+        runs += 1;
+    }
+    start.elapsed() / runs
+}
@@ -0,0 +1,57 @@
+//! What the secp256k1 context shared by the crate saves when converting many descriptors with xprvs, each of which
+//! derives its xpub: a context created per conversion allocates and randomizes its tables every time. Run with
+//! `cargo bench`.
+
+use bitcoin::bip32::ExtendedPrivKey;
+use bitcoin::secp256k1::Secp256k1;
+use bitcoin::Network;
+use libelectrum2descriptors::ElectrumWalletFile;
+use std::time::{Duration, Instant};
+
+/// The descriptors converted
+const DESCRIPTORS: usize = 10_000;
+
+fn main() {
+    let descriptors = (0..DESCRIPTORS)
+        .map(|i| {
+            let xprv = ExtendedPrivKey::new_master(Network::Bitcoin, &i.to_be_bytes()).unwrap();
+            format!("wpkh({}/0/*)", xprv)
+        })
+        .collect::<Vec<_>>();
+
+    let shared = time(|| {
+        for descriptor in &descriptors {
+            ElectrumWalletFile::from_descriptor(descriptor).unwrap();
+        }
+    });
+    // what creating a context per conversion, as before the shared one, would add
+    let contexts = time(|| {
+        for _ in &descriptors {
+            std::hint::black_box(Secp256k1::new());
+        }
+    });
+    println!("{} descriptors with xprvs", DESCRIPTORS);
+    println!(
+        "shared context:         {:>8.2} ms",
+        shared.as_secs_f64() * 1000.0
+    );
+    println!(
+        "context per conversion: {:>8.2} ms",
+        (shared + contexts).as_secs_f64() * 1000.0
+    );
+    println!(
+        "the shared context is {:.1} times faster",
+        (shared + contexts).as_secs_f64() / shared.as_secs_f64()
+    );
+}
+
+/// The average duration of `f` over a second, but at least 3 runs
+fn time(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    let mut runs = 0;
+    while runs < 3 || start.elapsed() < Duration::from_secs(1) {
+        f();
+        runs += 1;
+    }
+    start.elapsed() / runs
+}
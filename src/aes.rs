@@ -0,0 +1,227 @@
+//! The AES block cipher as specified by [FIPS-197](https://csrc.nist.gov/pubs/fips/197/final), decryption only, in
+//! the CBC mode with PKCS#7 padding that electrum encrypts wallet files and keystore secrets with. It favours
+//! simplicity over speed, and its table lookups aren't constant time: it only decrypts wallets on the user's machine.
+
+/// A key expanded into the round keys, for 128 or 256 bit keys
+pub(crate) struct Aes {
+    round_keys: Vec<[u8; 4]>,
+    inverse_sbox: [u8; 256],
+}
+
+impl Aes {
+    /// Expand a key of 16 or 32 bytes, None for other lengths
+    pub(crate) fn new(key: &[u8]) -> Option<Self> {
+        if key.len() != 16 && key.len() != 32 {
+            return None;
+        }
+        let sbox = sbox();
+        let mut inverse_sbox = [0u8; 256];
+        for (i, s) in sbox.iter().enumerate() {
+            inverse_sbox[usize::from(*s)] = i as u8;
+        }
+
+        let words = key.len() / 4;
+        let rounds = words + 6;
+        let mut round_keys = key
+            .chunks_exact(4)
+            .map(|word| [word[0], word[1], word[2], word[3]])
+            .collect::<Vec<_>>();
+        let mut rcon = 1u8;
+        for i in words..4 * (rounds + 1) {
+            let mut word = round_keys[i - 1];
+            if i % words == 0 {
+                word.rotate_left(1);
+                word = word.map(|b| sbox[usize::from(b)]);
+                word[0] ^= rcon;
+                rcon = xtime(rcon);
+            } else if words > 6 && i % words == 4 {
+                word = word.map(|b| sbox[usize::from(b)]);
+            }
+            let previous = round_keys[i - words];
+            round_keys.push([0, 1, 2, 3].map(|j| previous[j] ^ word[j]));
+        }
+        Some(Aes {
+            round_keys,
+            inverse_sbox,
+        })
+    }
+
+    /// Decrypt a block with the inverse cipher, the state is stored column by column like the block
+    pub(crate) fn decrypt_block(&self, block: &mut [u8; 16]) {
+        let rounds = self.round_keys.len() / 4 - 1;
+        self.add_round_key(block, rounds);
+        for round in (1..rounds).rev() {
+            self.inverse_shift_sub(block);
+            self.add_round_key(block, round);
+            inverse_mix_columns(block);
+        }
+        self.inverse_shift_sub(block);
+        self.add_round_key(block, 0);
+    }
+
+    /// Decrypt in CBC mode and remove the PKCS#7 padding. None if the data isn't whole blocks or the padding is
+    /// invalid, as with a wrong key.
+    pub(crate) fn decrypt_cbc(&self, iv: &[u8; 16], data: &[u8]) -> Option<Vec<u8>> {
+        if data.is_empty() || !data.len().is_multiple_of(16) {
+            return None;
+        }
+        let mut previous = *iv;
+        let mut plain = Vec::with_capacity(data.len());
+        for chunk in data.chunks_exact(16) {
+            let mut block = [0u8; 16];
+            block.copy_from_slice(chunk);
+            self.decrypt_block(&mut block);
+            plain.extend(block.iter().zip(previous).map(|(b, p)| b ^ p));
+            previous.copy_from_slice(chunk);
+        }
+        let padding = usize::from(*plain.last()?);
+        if padding == 0
+            || padding > 16
+            || plain[plain.len() - padding..]
+                .iter()
+                .any(|b| usize::from(*b) != padding)
+        {
+            return None;
+        }
+        plain.truncate(plain.len() - padding);
+        Some(plain)
+    }
+
+    fn add_round_key(&self, block: &mut [u8; 16], round: usize) {
+        for (column, word) in self.round_keys[4 * round..4 * round + 4].iter().enumerate() {
+            for (row, b) in word.iter().enumerate() {
+                block[4 * column + row] ^= b;
+            }
+        }
+    }
+
+    /// Shift the rows to the right by their index and substitute the bytes, the two commute
+    fn inverse_shift_sub(&self, block: &mut [u8; 16]) {
+        let state = *block;
+        for column in 0..4 {
+            for row in 0..4 {
+                block[4 * ((column + row) % 4) + row] =
+                    self.inverse_sbox[usize::from(state[4 * column + row])];
+            }
+        }
+    }
+}
+
+fn inverse_mix_columns(block: &mut [u8; 16]) {
+    for column in block.chunks_exact_mut(4) {
+        let a = [column[0], column[1], column[2], column[3]];
+        for (row, b) in column.iter_mut().enumerate() {
+            *b = multiply(a[row], 14)
+                ^ multiply(a[(row + 1) % 4], 11)
+                ^ multiply(a[(row + 2) % 4], 13)
+                ^ multiply(a[(row + 3) % 4], 9);
+        }
+    }
+}
+
+/// Multiply by x in GF(2^8) modulo the polynomial of AES
+fn xtime(b: u8) -> u8 {
+    (b << 1) ^ if b & 0x80 != 0 { 0x1b } else { 0 }
+}
+
+fn multiply(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 == 1 {
+            product ^= a;
+        }
+        a = xtime(a);
+        b >>= 1;
+    }
+    product
+}
+
+/// The substitution box: the multiplicative inverse in GF(2^8) followed by the affine transformation. The powers of
+/// 3 go through all the non-zero elements, walking their inverses along with them.
+fn sbox() -> [u8; 256] {
+    let mut sbox = [0u8; 256];
+    let (mut p, mut q) = (1u8, 1u8);
+    loop {
+        // p times 3, q divided by 3
+        p ^= xtime(p);
+        q ^= q << 1;
+        q ^= q << 2;
+        q ^= q << 4;
+        if q & 0x80 != 0 {
+            q ^= 0x09;
+        }
+        sbox[usize::from(p)] =
+            q ^ q.rotate_left(1) ^ q.rotate_left(2) ^ q.rotate_left(3) ^ q.rotate_left(4) ^ 0x63;
+        if p == 1 {
+            break;
+        }
+    }
+    sbox[0] = 0x63;
+    sbox
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_sbox() {
+        let sbox = sbox();
+        assert_eq!(
+            [sbox[0x00], sbox[0x01], sbox[0x53], sbox[0xff]],
+            [0x63, 0x7c, 0xed, 0x16]
+        );
+    }
+
+    #[test]
+    fn test_block() {
+        // the examples of appendix C of FIPS-197
+        let plain = hex("00112233445566778899aabbccddeeff");
+        for (key, cipher) in [
+            (
+                "000102030405060708090a0b0c0d0e0f",
+                "69c4e0d86a7b0430d8cdb78070b4c55a",
+            ),
+            (
+                "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+                "8ea2b7ca516745bfeafc49904b496089",
+            ),
+        ] {
+            let mut block = [0u8; 16];
+            block.copy_from_slice(&hex(cipher));
+            Aes::new(&hex(key)).unwrap().decrypt_block(&mut block);
+            assert_eq!(block.to_vec(), plain);
+        }
+        assert!(Aes::new(&[0; 24]).is_none());
+    }
+
+    #[test]
+    fn test_cbc() {
+        // the first two blocks of F.2.2 of NIST SP 800-38A, and "hello" padded, encrypted with openssl
+        let aes = Aes::new(&hex("2b7e151628aed2a6abf7158809cf4f3c")).unwrap();
+        let mut iv = [0u8; 16];
+        iv.copy_from_slice(&hex("000102030405060708090a0b0c0d0e0f"));
+        let cipher = hex("7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b2");
+        let mut block = [0u8; 16];
+        block.copy_from_slice(&cipher[16..]);
+        aes.decrypt_block(&mut block);
+        let plain = block
+            .iter()
+            .zip(&cipher[..16])
+            .map(|(b, c)| b ^ c)
+            .collect::<Vec<_>>();
+        assert_eq!(plain, hex("ae2d8a571e03ac9c9eb76fac45af8e51"));
+
+        let hello = hex("d8666ea8aad65cc08354b4bc43d4ff56");
+        assert_eq!(aes.decrypt_cbc(&iv, &hello).unwrap(), b"hello");
+        assert!(aes.decrypt_cbc(&iv, &hello[..15]).is_none());
+        assert!(aes.decrypt_cbc(&[0; 16], &hello).is_none());
+    }
+}
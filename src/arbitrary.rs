@@ -0,0 +1,223 @@
+//! Generating valid wallets from unstructured bytes, for fuzzing and property tests of code handling electrum
+//! wallets. A fuzzer's input is read with [`ArbitraryInput`], e.g. in a cargo-fuzz target:
+//!
+//! ```
+//! use libelectrum2descriptors::arbitrary::{Arbitrary, ArbitraryInput};
+//! use libelectrum2descriptors::ElectrumWalletFile;
+//!
+//! fn fuzz_target(data: &[u8]) {
+//!     let wallet = ElectrumWalletFile::arbitrary(&mut ArbitraryInput::new(data));
+//!     let _ = wallet.to_descriptors();
+//! }
+//! # fuzz_target(&[1, 2, 3]);
+//! ```
+//!
+//! Every input gives a wallet: once the bytes run out, zeros are read.
+
+use crate::{
+    electrum_wallet_file::{Keystore, WalletType},
+    utils::secp,
+    ElectrumWalletFile, ScriptKind,
+};
+use bitcoin::{
+    bip32::{ChildNumber, DerivationPath, ExtendedPrivKey, ExtendedPubKey},
+    hashes::{sha256, Hash},
+    Network,
+};
+
+/// The most cosigners of generated multisig wallets, more only slow down the callers
+const MAX_COSIGNERS: u8 = 5;
+
+/// The bytes values are generated from
+pub struct ArbitraryInput<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ArbitraryInput<'a> {
+    /// Read the bytes from the start
+    pub fn new(data: &'a [u8]) -> Self {
+        ArbitraryInput { data }
+    }
+
+    /// The next byte, 0 when the bytes ran out
+    pub fn byte(&mut self) -> u8 {
+        match self.data.split_first() {
+            Some((byte, rest)) => {
+                self.data = rest;
+                *byte
+            }
+            None => 0,
+        }
+    }
+
+    /// A number in `range`, which must not be empty
+    pub fn int_in_range(&mut self, range: std::ops::RangeInclusive<u8>) -> u8 {
+        let span = range.end() - range.start() + 1;
+        range.start() + self.byte() % span
+    }
+
+    /// Whether the next byte is odd
+    pub fn bool(&mut self) -> bool {
+        self.byte() & 1 == 1
+    }
+
+    /// One of the values
+    pub fn choose<T: Copy>(&mut self, values: &[T]) -> T {
+        values[self.byte() as usize % values.len()]
+    }
+
+    /// A 32 byte seed, the rest of the bytes hashed when fewer are left
+    fn seed(&mut self) -> [u8; 32] {
+        if self.data.len() >= 32 {
+            let (bytes, rest) = self.data.split_at(32);
+            self.data = rest;
+            let mut seed = [0; 32];
+            seed.copy_from_slice(bytes);
+            seed
+        } else {
+            let seed = sha256::Hash::hash(self.data).to_byte_array();
+            self.data = &[];
+            seed
+        }
+    }
+}
+
+/// Types that can be generated from an [`ArbitraryInput`]
+pub trait Arbitrary: Sized {
+    /// Generate a value from the next bytes of the input
+    fn arbitrary(input: &mut ArbitraryInput) -> Self;
+}
+
+impl Arbitrary for ScriptKind {
+    fn arbitrary(input: &mut ArbitraryInput) -> Self {
+        input.choose(&ScriptKind::ALL)
+    }
+}
+
+impl Arbitrary for Network {
+    /// Mainnet or testnet: signet and regtest keys are encoded like testnet ones
+    fn arbitrary(input: &mut ArbitraryInput) -> Self {
+        input.choose(&[Network::Bitcoin, Network::Testnet])
+    }
+}
+
+impl Arbitrary for WalletType {
+    /// Standard or a multisig wallet with up to [`MAX_COSIGNERS`] cosigners and a threshold they can meet
+    fn arbitrary(input: &mut ArbitraryInput) -> Self {
+        if input.bool() {
+            WalletType::Standard
+        } else {
+            let cosigners = input.int_in_range(2..=MAX_COSIGNERS);
+            WalletType::multisig(input.int_in_range(1..=cosigners).into(), cosigners.into())
+                .expect("the threshold is within the cosigners")
+        }
+    }
+}
+
+impl Arbitrary for Keystore {
+    /// A singlesig keystore of any kind and network
+    fn arbitrary(input: &mut ArbitraryInput) -> Self {
+        let kind = input.choose(&[
+            ScriptKind::Standard,
+            ScriptKind::P2wpkhP2sh,
+            ScriptKind::P2wpkh,
+            ScriptKind::P2tr,
+        ]);
+        let network = Network::arbitrary(input);
+        arbitrary_keystore(input, kind, network, None)
+    }
+}
+
+impl Arbitrary for ElectrumWalletFile {
+    /// A standard or multisig wallet, the cosigners have the same script kind and network and distinct keys
+    fn arbitrary(input: &mut ArbitraryInput) -> Self {
+        let network = Network::arbitrary(input);
+        let (keystores, threshold) = match WalletType::arbitrary(input) {
+            WalletType::Standard => {
+                let kind = input.choose(&[
+                    ScriptKind::Standard,
+                    ScriptKind::P2wpkhP2sh,
+                    ScriptKind::P2wpkh,
+                    ScriptKind::P2tr,
+                ]);
+                (vec![arbitrary_keystore(input, kind, network, None)], 1)
+            }
+            WalletType::Multisig(multisig) => {
+                let kind = input.choose(&[
+                    ScriptKind::Standard,
+                    ScriptKind::P2wshP2sh,
+                    ScriptKind::P2wsh,
+                ]);
+                let keystores = (0..multisig.cosigners())
+                    .map(|i| arbitrary_keystore(input, kind, network, Some(i)))
+                    .collect();
+                (keystores, multisig.threshold())
+            }
+            WalletType::Imported => unreachable!("arbitrary wallet types have extended keys"),
+        };
+        ElectrumWalletFile::new(&keystores, threshold)
+            .expect("the generated keystores make a valid wallet")
+    }
+}
+
+/// A keystore of a master key generated from a seed, with or without its secrets and an account derived along
+/// the bip of the kind. The seeds of cosigners are salted with their `cosigner` index so that their keys differ
+/// even when the input ran out.
+fn arbitrary_keystore(
+    input: &mut ArbitraryInput,
+    kind: ScriptKind,
+    network: Network,
+    cosigner: Option<u8>,
+) -> Keystore {
+    let mut seed = input.seed().to_vec();
+    seed.extend(cosigner);
+    let seed = sha256::Hash::hash(&seed).to_byte_array();
+    let master = ExtendedPrivKey::new_master(network, &seed).expect("32 bytes are a valid seed");
+    let multisig = cosigner.is_some();
+    let derive_account = input.bool();
+    let derivation = if derive_account {
+        account_derivation(kind, network, multisig)
+    } else {
+        DerivationPath::master()
+    };
+    let secp = secp();
+    let xprv = master
+        .derive_priv(secp, &derivation)
+        .expect("hardened derivations are valid");
+    let xkey = if input.bool() {
+        xprv.to_string()
+    } else {
+        ExtendedPubKey::from_priv(secp, &xprv).to_string()
+    };
+    let kind_str = match kind {
+        ScriptKind::P2tr => ScriptKind::Standard.as_kind_str(),
+        kind => kind.as_kind_str(),
+    };
+    let mut keystore = Keystore::new(kind_str, &xkey).expect("generated keys are valid");
+    if derive_account {
+        keystore = keystore.with_origin(master.fingerprint(secp), &derivation);
+    }
+    if kind == ScriptKind::P2tr {
+        keystore.script_type = "p2tr".into();
+    }
+    if input.bool() {
+        keystore = keystore.with_label(&format!("key {}", input.byte()));
+    }
+    keystore
+}
+
+/// The derivation of the first account of the bip for a script kind: 44, 45, 48, 49, 84 or 86
+fn account_derivation(kind: ScriptKind, network: Network, multisig: bool) -> DerivationPath {
+    let hardened = |index| ChildNumber::Hardened { index };
+    let coin = hardened(if network == Network::Bitcoin { 0 } else { 1 });
+    let path = match (kind, multisig) {
+        (ScriptKind::Standard, true) => vec![hardened(45)],
+        (ScriptKind::Standard, false) => vec![hardened(44), coin, hardened(0)],
+        (ScriptKind::P2wpkhP2sh, _) => vec![hardened(49), coin, hardened(0)],
+        (ScriptKind::P2wpkh, _) => vec![hardened(84), coin, hardened(0)],
+        (ScriptKind::P2tr, _) => vec![hardened(86), coin, hardened(0)],
+        (ScriptKind::P2wshP2sh, _) => vec![hardened(48), coin, hardened(0), hardened(1)],
+        (ScriptKind::P2wsh, _) => vec![hardened(48), coin, hardened(0), hardened(2)],
+    };
+    DerivationPath::from(path)
+}
@@ -0,0 +1,205 @@
+use crate::electrum_wallet_file::ParseOptions;
+use crate::{batch, inflate::inflate, Electrum2DescriptorError, ElectrumWalletFile};
+use std::path::Path;
+
+/// The signatures of the zip records
+const END_OF_CENTRAL_DIRECTORY: &[u8] = b"PK\x05\x06";
+const CENTRAL_DIRECTORY_HEADER: &[u8] = b"PK\x01\x02";
+const LOCAL_FILE_HEADER: &[u8] = b"PK\x03\x04";
+
+/// The end of central directory record is at most this far from the end, behind a comment of up to 64 KiB
+const END_OF_CENTRAL_DIRECTORY_SEARCH: usize = 22 + 0xffff;
+
+/// The largest entry inflated, wallet files are far smaller. Inflating stops at the size an entry declares, so a
+/// small archive declaring more can't expand into gigabytes.
+const MAX_ENTRY_SIZE: usize = 1 << 28;
+
+/// The wallets of a zip backup, by the name of their entry, each one parsed or the reason it failed
+pub type BackupWallets = Vec<(String, Result<ElectrumWalletFile, Electrum2DescriptorError>)>;
+
+impl ElectrumWalletFile {
+    /// Read the wallets of a zip backup, as exported by electrum on android, with the name of their entry. The
+    /// entries that aren't wallet files, such as metadata, are skipped. The wallets electrum encrypted with their
+    /// password are decrypted with `password`, see [`ElectrumWalletFile::from_slice_with_password`], and fail with
+    /// [`Electrum2DescriptorError::EncryptedWallet`] without one. Entries encrypted by the zip archive always fail
+    /// so, the crate doesn't decrypt zip encryption.
+    pub fn from_backup_zip(
+        path: &Path,
+        password: Option<&str>,
+    ) -> Result<BackupWallets, Electrum2DescriptorError> {
+        log_debug!("reading the zip backup {}", path.display());
+        let archive = std::fs::read(path)?;
+        let mut wallets = Vec::new();
+        for entry in entries(&archive)? {
+            if entry.name.ends_with('/') {
+                continue;
+            }
+            let name = entry
+                .name
+                .rsplit('/')
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            if name.starts_with('.') {
+                continue;
+            }
+            let content = match entry.content() {
+                Ok(content) => content,
+                Err(e) => {
+                    wallets.push((name, Err(e)));
+                    continue;
+                }
+            };
+            let options = ParseOptions::default();
+            match (ElectrumWalletFile::peek_reader(&content[..]), password) {
+                (Ok(peeked), Some(password)) if peeked.encrypted => {
+                    let wallet =
+                        ElectrumWalletFile::from_slice_with_password(&content, password, &options);
+                    wallets.push((name, wallet))
+                }
+                (Ok(peeked), None) if peeked.encrypted => {
+                    wallets.push((name, Err(Electrum2DescriptorError::EncryptedWallet)))
+                }
+                (Ok(peeked), password) if peeked.wallet_type.is_some() => {
+                    let wallet = match password {
+                        Some(password) => ElectrumWalletFile::from_slice_with_password(
+                            &content, password, &options,
+                        ),
+                        None => {
+                            ElectrumWalletFile::from_reader_with_options(&content[..], &options)
+                        }
+                    };
+                    wallets.push((name, wallet))
+                }
+                (Ok(_), _) => {}
+                (Err(Electrum2DescriptorError::Serde(_)), _)
+                    if !batch::starts_like_json(&content) => {}
+                (Err(e), _) => wallets.push((name, Err(e))),
+            }
+        }
+        log_debug!("found {} wallets in the zip backup", wallets.len());
+        if wallets.is_empty() {
+            return Err(Electrum2DescriptorError::InvalidZip(
+                "no wallet file in the archive".to_string(),
+            ));
+        }
+        Ok(wallets)
+    }
+}
+
+/// A file of a zip archive, as listed in its central directory
+struct Entry<'a> {
+    name: String,
+    /// Encrypted by the archive, bit 0 of the general purpose flags
+    encrypted: bool,
+    method: u16,
+    crc32: u32,
+    size: usize,
+    data: &'a [u8],
+}
+
+impl Entry<'_> {
+    /// The uncompressed content, checked against the crc of the entry
+    fn content(&self) -> Result<Vec<u8>, Electrum2DescriptorError> {
+        if self.encrypted {
+            return Err(Electrum2DescriptorError::EncryptedWallet);
+        }
+        if self.size > MAX_ENTRY_SIZE {
+            return Err(Electrum2DescriptorError::InvalidZip(format!(
+                "{} is too large for a wallet file",
+                self.name
+            )));
+        }
+        let content = match self.method {
+            0 => self.data.to_vec(),
+            8 => inflate(self.data, self.size)?,
+            method => {
+                return Err(Electrum2DescriptorError::InvalidZip(format!(
+                    "{} uses the unsupported compression method {}",
+                    self.name, method
+                )))
+            }
+        };
+        if content.len() != self.size || crc32fast::hash(&content) != self.crc32 {
+            return Err(Electrum2DescriptorError::InvalidZip(format!(
+                "{} is corrupt",
+                self.name
+            )));
+        }
+        Ok(content)
+    }
+}
+
+/// The entries of the central directory of a zip archive
+fn entries(archive: &[u8]) -> Result<Vec<Entry<'_>>, Electrum2DescriptorError> {
+    let invalid = |reason: &str| Electrum2DescriptorError::InvalidZip(reason.to_string());
+    let search = archive
+        .len()
+        .saturating_sub(END_OF_CENTRAL_DIRECTORY_SEARCH);
+    let end = archive[search..]
+        .windows(4)
+        .rposition(|window| window == END_OF_CENTRAL_DIRECTORY)
+        .map(|position| search + position)
+        .ok_or_else(|| invalid("not a zip archive"))?;
+    let count = usize::from(u16_at(archive, end + 10)?);
+    let mut offset = u32_at(archive, end + 16)? as usize;
+    if count == 0xffff || offset == 0xffff_ffff {
+        return Err(invalid("zip64 archives aren't supported"));
+    }
+
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0..count {
+        if archive.get(offset..offset + 4) != Some(CENTRAL_DIRECTORY_HEADER) {
+            return Err(invalid("invalid central directory"));
+        }
+        let flags = u16_at(archive, offset + 8)?;
+        let method = u16_at(archive, offset + 10)?;
+        let crc32 = u32_at(archive, offset + 16)?;
+        let compressed_size = u32_at(archive, offset + 20)? as usize;
+        let size = u32_at(archive, offset + 24)? as usize;
+        let name_len = usize::from(u16_at(archive, offset + 28)?);
+        let extra_len = usize::from(u16_at(archive, offset + 30)?);
+        let comment_len = usize::from(u16_at(archive, offset + 32)?);
+        let local_header = u32_at(archive, offset + 42)? as usize;
+        let name = archive
+            .get(offset + 46..offset + 46 + name_len)
+            .ok_or_else(|| invalid("truncated central directory"))?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        offset += 46 + name_len + extra_len + comment_len;
+
+        // the local header repeats the name, but its extra field may differ from the central one
+        if archive.get(local_header..local_header + 4) != Some(LOCAL_FILE_HEADER) {
+            return Err(invalid("invalid local file header"));
+        }
+        let start = local_header
+            + 30
+            + usize::from(u16_at(archive, local_header + 26)?)
+            + usize::from(u16_at(archive, local_header + 28)?);
+        let data = archive
+            .get(start..start + compressed_size)
+            .ok_or_else(|| invalid("truncated entry"))?;
+        entries.push(Entry {
+            name,
+            encrypted: flags & 1 != 0,
+            method,
+            crc32,
+            size,
+            data,
+        });
+    }
+    Ok(entries)
+}
+
+fn u16_at(archive: &[u8], offset: usize) -> Result<u16, Electrum2DescriptorError> {
+    archive
+        .get(offset..offset + 2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| Electrum2DescriptorError::InvalidZip("truncated archive".to_string()))
+}
+
+fn u32_at(archive: &[u8], offset: usize) -> Result<u32, Electrum2DescriptorError> {
+    archive
+        .get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or_else(|| Electrum2DescriptorError::InvalidZip("truncated archive".to_string()))
+}
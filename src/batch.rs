@@ -0,0 +1,135 @@
+use crate::electrum_wallet_file::ParseOptions;
+use crate::{Descriptors, Electrum2DescriptorError, ElectrumWalletFile, WalletSummary};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// The result of converting a directory: each wallet file with its descriptors or the reason it failed
+pub type Converted = Mapped<Descriptors>;
+
+/// The result of summarizing a directory: each wallet file with its summary or the reason it failed
+pub type Summarized = Mapped<WalletSummary>;
+
+/// The result of [`map_dir`]: each wallet file with the result of the function
+pub type Mapped<T> = Vec<(PathBuf, Result<T, Electrum2DescriptorError>)>;
+
+/// Options for converting a directory of wallet files
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatchOptions {
+    pub parse: ParseOptions,
+    /// Don't descend into subdirectories
+    pub no_recursion: bool,
+}
+
+/// The descriptors of each wallet file in a directory, sorted by path.
+/// Files that aren't electrum wallets are skipped, as are hidden files such as interrupted writes, but a file that
+/// starts like a json object and then fails to parse, e.g. a truncated wallet, is reported as failed.
+/// With the `parallel` feature the files are converted in parallel, each one read only when it's converted.
+pub fn convert_dir(
+    dir: &Path,
+    options: &BatchOptions,
+) -> Result<Converted, Electrum2DescriptorError> {
+    map_dir(dir, options, ElectrumWalletFile::to_descriptors)
+}
+
+/// The summary of each wallet file in a directory, sorted by path, e.g. to audit a directory of backups.
+/// Which files are read is the same as for [`convert_dir`].
+pub fn summarize_dir(
+    dir: &Path,
+    options: &BatchOptions,
+) -> Result<Summarized, Electrum2DescriptorError> {
+    map_dir(dir, options, ElectrumWalletFile::summary)
+}
+
+/// Apply `f` to each wallet file in a directory, sorted by path, when more than the descriptors or the summary is
+/// needed. Which files are read and how is the same as for [`convert_dir`].
+pub fn map_dir<T, F>(
+    dir: &Path,
+    options: &BatchOptions,
+    f: F,
+) -> Result<Mapped<T>, Electrum2DescriptorError>
+where
+    T: Send,
+    F: Fn(&ElectrumWalletFile) -> Result<T, Electrum2DescriptorError> + Sync,
+{
+    let mut files = Vec::new();
+    list_files(dir, options, &mut files)?;
+    files.sort();
+
+    #[cfg(feature = "parallel")]
+    let files = files.into_par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let files = files.into_iter();
+    let mapped = files
+        .filter_map(|file| {
+            let result = read_file(&file, options)
+                .and_then(|wallet| wallet.as_ref().map(&f).transpose())
+                .transpose()?;
+            Some((file, result))
+        })
+        .collect();
+    Ok(mapped)
+}
+
+/// Collect the paths of the files in the directory
+fn list_files(
+    dir: &Path,
+    options: &BatchOptions,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), Electrum2DescriptorError> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() && !options.no_recursion {
+            list_files(&entry.path(), options, files)?;
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// Parse one file, None if it's not an electrum wallet.
+/// A file that starts like a json object but can't be peeked, e.g. a truncated wallet, is an error.
+fn read_file(
+    file: &Path,
+    options: &BatchOptions,
+) -> Result<Option<ElectrumWalletFile>, Electrum2DescriptorError> {
+    let peeked = match ElectrumWalletFile::peek(file) {
+        Ok(peeked) => peeked,
+        Err(Electrum2DescriptorError::Serde(_)) if !file_starts_like_json(file)? => {
+            return Ok(None)
+        }
+        Err(e) => return Err(e),
+    };
+    if peeked.encrypted {
+        return Err(Electrum2DescriptorError::EncryptedWallet);
+    }
+    if peeked.wallet_type.is_none() {
+        return Ok(None);
+    }
+    ElectrumWalletFile::from_file_with_options(file, &options.parse).map(Some)
+}
+
+/// Whether the first bytes of the file are utf-8 and, after whitespace, open a json object
+fn file_starts_like_json(file: &Path) -> Result<bool, Electrum2DescriptorError> {
+    let mut head = Vec::with_capacity(4096);
+    std::fs::File::open(file)?
+        .take(4096)
+        .read_to_end(&mut head)?;
+    Ok(starts_like_json(&head))
+}
+
+/// Whether the bytes are utf-8 and, after whitespace, open a json object. The bytes may be the start of a longer
+/// text, cut in the middle of a character.
+pub(crate) fn starts_like_json(head: &[u8]) -> bool {
+    let utf8 = match std::str::from_utf8(head) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    };
+    utf8 && head.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{')
+}
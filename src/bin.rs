@@ -1,35 +1,1632 @@
 #[cfg(feature = "wallet_file")]
-use libelectrum2descriptors::ElectrumWalletFile;
+use bitcoin::bip32::{DerivationPath, Fingerprint};
+use bitcoin::Network;
+#[cfg(feature = "qr")]
+use libelectrum2descriptors::qr;
+#[cfg(feature = "wallet_file")]
 use libelectrum2descriptors::{
-    Electrum2DescriptorError, ElectrumExtendedKey, ElectrumExtendedPrivKey, ElectrumExtendedPubKey,
+    batch::BatchOptions,
+    electrum_wallet_file::{
+        DescriptorOptions, Keystore, ParseOptions, RepairAction, RepairPolicy, WalletType,
+        WriteOptions,
+    },
+    kind_from_address_str, summarize_dir, ConversionTarget, ElectrumWalletFile, PrivateKeyExport,
+    ReportFormat, ReportOptions, WalletSummary,
 };
-#[cfg(feature = "wallet_file")]
+use libelectrum2descriptors::{
+    recover, Descriptors, Electrum2DescriptorError, ElectrumExtendedKey, ElectrumExtendedPrivKey,
+    ElectrumExtendedPubKey, ScriptKind,
+};
+#[cfg(any(feature = "wallet_file", feature = "qr"))]
 use std::path::Path;
 use std::str::FromStr;
+#[cfg(feature = "wallet_file")]
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const USAGE: &str = "You must specify an extended public or private key or an electrum wallet file as first argument";
+const CONVERT_OPTIONS: &[&str] = &[
+    "--output",
+    "--label",
+    "--assume-network",
+    "--expected-fingerprint",
+    "--descriptor-file",
+    "--output-dir",
+    "--password-env",
+    "--password-fd",
+    "--password-file",
+    "--format",
+    "--origin",
+    "--watch-interval",
+    "--loss-report",
+    "--fresh-wallet",
+    "--scrub-history",
+    "--kind",
+    "--addresses",
+    "--qr-file",
+];
+/// The sources of the wallet password, passwords on the command line would leak through `ps`
+#[cfg(feature = "wallet_file")]
+const PASSWORD_OPTIONS: &[&str] = &["--password-env", "--password-fd", "--password-file"];
+const CONVERT_FLAGS: &[&str] = &[
+    "--force",
+    "--check",
+    "--quiet",
+    "--external-only",
+    "--change-only",
+    "--json",
+    "--with-checksum",
+    "--watch",
+    "--repair",
+    "--acknowledge-loss",
+    "--drop-labels",
+    "--qr",
+];
 
 fn main() -> Result<(), Electrum2DescriptorError> {
-    let mut args = std::env::args();
-    args.next(); // first is program name
-    let err_msg =
-        "You must specify an extended public or private key or an electrum wallet file as first argument";
+    let args = std::env::args().skip(1).collect::<Vec<_>>(); // first is program name
+    match args.first().map(String::as_str) {
+        #[cfg(feature = "wallet_file")]
+        Some("multisig") => multisig(Args::parse(
+            &args[1..],
+            &["--threshold", "--cosigner", "--output"],
+            &["--force"],
+        )?),
+        #[cfg(feature = "wallet_file")]
+        Some("verify") => verify(Args::parse(
+            &args[1..],
+            &["--descriptor", "--descriptor-file"],
+            &[],
+        )?),
+        #[cfg(feature = "wallet_file")]
+        Some("addresses") => addresses(Args::parse(
+            &args[1..],
+            &["--keychain", "--limit", "--format", "--network", "--kind"],
+            &["--verify"],
+        )?),
+        #[cfg(feature = "wallet_file")]
+        Some("inspect") => inspect(Args::parse(
+            &args[1..],
+            &["--filter", "--sort", "--report"],
+            &["--all", "--json", "--restore-command", "--private"],
+        )?),
+        #[cfg(feature = "wallet_file")]
+        Some("salvage") => salvage(Args::parse(&args[1..], &[], &[])?),
+        #[cfg(feature = "wallet_file")]
+        Some("edit") => edit(Args::parse(
+            &args[1..],
+            &[
+                "--add-cosigner-file",
+                "--remove-cosigner",
+                "--set-threshold",
+                "--set-label",
+            ],
+            &[
+                "--no-backup",
+                "--dry-run",
+                "--scrub-history",
+                "--drop-labels",
+            ],
+        )?),
+        Some("recover") => recover(Args::parse(&args[1..], &[], &["--show-secrets"])?),
+        Some("convert") => convert(Args::parse(&args[1..], CONVERT_OPTIONS, CONVERT_FLAGS)?),
+        _ => convert(Args::parse(&args, CONVERT_OPTIONS, CONVERT_FLAGS)?),
+    }
+}
+
+/// Convert an extended key or an electrum wallet file to descriptors, or a descriptor to an electrum wallet file
+fn convert(args: Args) -> Result<(), Electrum2DescriptorError> {
+    #[cfg(feature = "wallet_file")]
+    if let Some(descriptor_file) = args.value("--descriptor-file") {
+        return convert_descriptor_file(Path::new(descriptor_file), &args);
+    }
     let electrum_x = args
-        .next()
-        .ok_or_else(|| Electrum2DescriptorError::GenericBorrow(err_msg))?;
-    let descriptor = ElectrumExtendedPrivKey::from_str(&electrum_x)
-        .map(|e| e.to_descriptors())
-        .or_else(|_| ElectrumExtendedPubKey::from_str(&electrum_x).map(|e| e.to_descriptors()));
+        .positional
+        .first()
+        .ok_or(Electrum2DescriptorError::GenericBorrow(USAGE))?;
+    #[cfg(feature = "wallet_file")]
+    let expected_fingerprints = args
+        .values("--expected-fingerprint")
+        .into_iter()
+        .map(Fingerprint::from_str)
+        .collect::<Result<Vec<_>, _>>()?;
+    #[cfg(feature = "wallet_file")]
+    let origins = args
+        .values("--origin")
+        .into_iter()
+        .map(parse_origin)
+        .collect::<Result<Vec<_>, _>>()?;
+    #[cfg(not(feature = "wallet_file"))]
+    if args.value("--expected-fingerprint").is_some() {
+        return Err(Electrum2DescriptorError::GenericBorrow(
+            "--expected-fingerprint needs the wallet_file feature",
+        ));
+    }
+    #[cfg(not(feature = "wallet_file"))]
+    if args.value("--origin").is_some() {
+        return Err(Electrum2DescriptorError::GenericBorrow(
+            "--origin needs the wallet_file feature",
+        ));
+    }
+
+    #[cfg(feature = "wallet_file")]
+    if args.value("--loss-report").is_some() || args.value("--fresh-wallet").is_some() {
+        return loss(Path::new(electrum_x), &args);
+    }
+    #[cfg(not(feature = "wallet_file"))]
+    if args.value("--loss-report").is_some() || args.value("--fresh-wallet").is_some() {
+        return Err(Electrum2DescriptorError::GenericBorrow(
+            "--loss-report and --fresh-wallet need the wallet_file feature",
+        ));
+    }
+
+    #[cfg(feature = "wallet_file")]
+    if electrum_x.contains('(') {
+        let options = DescriptorOptions {
+            expected_fingerprints,
+            ..DescriptorOptions::default()
+        };
+        let mut warnings = Vec::new();
+        let mut wallet =
+            ElectrumWalletFile::from_descriptor_with_warnings(electrum_x, &options, &mut warnings)?;
+        for warning in warnings {
+            eprintln!("warning: {}", warning);
+        }
+        if let Some(label) = args.value("--label") {
+            wallet.set_label(label)?;
+        }
+        fill_addresses(&mut wallet, &args)?;
+        return write_wallet(&wallet, &args);
+    }
+
+    #[cfg(feature = "wallet_file")]
+    if args.flag("--check") {
+        return check(Path::new(electrum_x), args.flag("--repair"));
+    }
+
+    #[cfg(feature = "wallet_file")]
+    if let Some(output) = args.value("--scrub-history") {
+        let wallet = ElectrumWalletFile::from_file(Path::new(electrum_x))?;
+        return wallet
+            .scrub_history(!args.flag("--drop-labels"))
+            .to_file(Path::new(output));
+    }
+
+    #[cfg(feature = "wallet_file")]
+    if Path::new(electrum_x).is_dir() {
+        return convert_dir(
+            Path::new(electrum_x),
+            args.value("--format").unwrap_or("plain"),
+        );
+    }
+
+    #[cfg(feature = "wallet_file")]
+    let is_zip = Path::new(electrum_x).extension() == Some("zip".as_ref())
+        && Path::new(electrum_x).is_file();
+    #[cfg(feature = "zip")]
+    if is_zip {
+        return convert_zip(Path::new(electrum_x), password(&args)?.as_deref());
+    }
+    #[cfg(all(feature = "wallet_file", not(feature = "zip")))]
+    if is_zip {
+        return Err(Electrum2DescriptorError::GenericBorrow(
+            "reading zip backups needs the zip feature",
+        ));
+    }
+
+    // opt-in only, for keys exported with the prefix of the wrong network
+    let network = args
+        .value("--assume-network")
+        .map(parse_network)
+        .transpose()?;
+    let warn_network = |encoded: Network| match network {
+        Some(network) if network != encoded => {
+            eprintln!(
+                "WARNING: the prefix of {} is for {}, but it is used for {} as requested",
+                electrum_x, encoded, network
+            );
+            network
+        }
+        _ => encoded,
+    };
+    // for plain xpub/xprv keys, whose prefix doesn't tell the script kind
+    let kind = args.value("--kind").map(parse_kind).transpose()?;
+    #[cfg(feature = "wallet_file")]
+    if !expected_fingerprints.is_empty() {
+        if let Ok(keystore) = Keystore::from_electrum_xkey(electrum_x) {
+            ElectrumWalletFile::new(&[keystore], 1)?.check_fingerprints(&expected_fingerprints)?;
+        }
+    }
+    #[cfg(feature = "wallet_file")]
+    if !origins.is_empty() {
+        if let Ok(keystore) = Keystore::from_electrum_xkey(electrum_x) {
+            let mut wallet = ElectrumWalletFile::new(&[keystore], 1)?;
+            if let Some(kind) = kind {
+                wallet.assume_kind(kind)?;
+            }
+            return print_descriptors(&wallet.to_descriptors_with_origin(&origins)?, &args);
+        }
+    }
+    #[cfg(feature = "wallet_file")]
+    if args.flag("--watch") {
+        return watch(
+            Path::new(electrum_x),
+            network,
+            &expected_fingerprints,
+            &args,
+        );
+    }
+    let descriptor = ElectrumExtendedPrivKey::from_str(electrum_x)
+        .map(|e| {
+            let network = warn_network(e.xprv().network);
+            let e = e.with_network(network);
+            match kind {
+                Some(kind) => e.with_kind(kind).map(|e| e.to_descriptors()),
+                None => Ok(e.to_descriptors()),
+            }
+        })
+        .or_else(|_| {
+            ElectrumExtendedPubKey::from_str(electrum_x).map(|e| {
+                let network = warn_network(e.xpub().network);
+                let e = e.with_network(network);
+                match kind {
+                    Some(kind) => e.with_kind(kind).map(|e| e.to_descriptors()),
+                    None => Ok(e.to_descriptors()),
+                }
+            })
+        });
+    // a key whose prefix conflicts with --kind fails, it isn't a wallet file either
+    let descriptor = match descriptor {
+        Ok(descriptor) => Ok(descriptor?),
+        Err(e) => Err(e),
+    };
+    #[cfg(feature = "wallet_file")]
+    if descriptor.is_err() {
+        let export = std::fs::read_to_string(electrum_x).unwrap_or_default();
+        if PrivateKeyExport::is_export(&export) {
+            return convert_private_key_export(&export, &args);
+        }
+    }
     #[cfg(feature = "wallet_file")]
     let descriptor = descriptor.or_else(|_| {
-        let wallet_file = Path::new(&electrum_x)
+        let wallet_file = Path::new(electrum_x)
             .canonicalize()
-            .map_err(|_| Electrum2DescriptorError::GenericBorrow(err_msg))?;
+            .map_err(|_| Electrum2DescriptorError::GenericBorrow(USAGE))?;
         if !wallet_file.exists() {
-            return Err(Electrum2DescriptorError::GenericBorrow(err_msg));
+            return Err(Electrum2DescriptorError::GenericBorrow(USAGE));
         }
-        let wallet = ElectrumWalletFile::from_file(wallet_file.as_path())?;
-        wallet.to_descriptors()
+        let encrypted = ElectrumWalletFile::peek(&wallet_file).is_ok_and(|peeked| peeked.encrypted);
+        let mut wallet = match password(&args)? {
+            Some(password) => ElectrumWalletFile::from_file_with_password(
+                &wallet_file,
+                &password,
+                &ParseOptions::default(),
+            )?,
+            None if encrypted => return Err(Electrum2DescriptorError::EncryptedWallet),
+            None => ElectrumWalletFile::from_file(wallet_file.as_path())?,
+        };
+        if let Some(network) = network {
+            for warning in wallet.assume_network(network)? {
+                eprintln!("WARNING: {}", warning);
+            }
+        }
+        if let Some(kind) = kind {
+            wallet.assume_kind(kind)?;
+        }
+        wallet.check_fingerprints(&expected_fingerprints)?;
+        let mut warnings = Vec::new();
+        let descriptor = wallet.to_descriptors_with_warnings(&mut warnings)?;
+        let descriptor = if origins.is_empty() {
+            descriptor
+        } else {
+            wallet.to_descriptors_with_origin(&origins)?
+        };
+        if !args.flag("--quiet") {
+            for warning in warnings {
+                eprintln!("warning: {}", warning);
+            }
+        }
+        Ok(descriptor)
     });
 
-    println!("{:?}", descriptor?);
+    print_descriptors(&descriptor?, &args)
+}
+
+/// Set on SIGINT, so that `--watch` stops at its next poll
+#[cfg(feature = "wallet_file")]
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Stop `--watch` cleanly on Ctrl-C, with the printed descriptors flushed and exit code 0. Elsewhere than on unix
+/// Ctrl-C terminates the process as usual.
+#[cfg(all(feature = "wallet_file", unix))]
+fn handle_interrupt() {
+    extern "C" fn on_interrupt(_signal: libc::c_int) {
+        INTERRUPTED.store(true, Ordering::SeqCst);
+    }
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+    unsafe {
+        libc::signal(
+            libc::SIGINT,
+            on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        );
+    }
+}
+
+#[cfg(all(feature = "wallet_file", not(unix)))]
+fn handle_interrupt() {}
+
+/// Print the descriptors of the wallet file again whenever they change, until interrupted with Ctrl-C. The file is
+/// polled by name every `--watch-interval` milliseconds, as network drives don't deliver file system notifications and
+/// electrum replaces the file by renaming a new one over it. It is only read once it didn't change for an interval
+/// since electrum saves often. Unreadable versions are reported on stderr.
+#[cfg(feature = "wallet_file")]
+fn watch(
+    wallet_file: &Path,
+    network: Option<Network>,
+    expected_fingerprints: &[Fingerprint],
+    args: &Args,
+) -> Result<(), Electrum2DescriptorError> {
+    if !wallet_file.is_file() {
+        return Err(Electrum2DescriptorError::GenericBorrow(
+            "--watch needs a wallet file",
+        ));
+    }
+    let interval = match args.value("--watch-interval") {
+        Some(interval) => interval.parse().map_err(|_| {
+            Electrum2DescriptorError::InvalidArguments(format!(
+                "invalid watch interval: {}",
+                interval
+            ))
+        })?,
+        None => 500,
+    };
+    let convert = || {
+        let mut wallet = ElectrumWalletFile::from_file(wallet_file)?;
+        if let Some(network) = network {
+            wallet.assume_network(network)?;
+        }
+        wallet.check_fingerprints(expected_fingerprints)?;
+        wallet.to_descriptors()
+    };
+    // when the file was last seen to change, by its modification time and length
+    let stamp = || {
+        std::fs::metadata(wallet_file)
+            .ok()
+            .map(|metadata| (metadata.modified().ok(), metadata.len()))
+    };
+    let mut seen = stamp();
+    let mut changed = true;
+    let mut printed = None;
+    handle_interrupt();
+    while !INTERRUPTED.load(Ordering::SeqCst) {
+        let current = stamp();
+        if current != seen {
+            seen = current;
+            changed = true;
+        } else if changed && seen.is_some() {
+            changed = false;
+            match convert() {
+                Ok(descriptors) if printed.as_ref() != Some(&descriptors) => {
+                    print_descriptors(&descriptors, args)?;
+                    printed = Some(descriptors);
+                }
+                Ok(_) => (),
+                Err(e) => eprintln!(
+                    "warning: {} can't be converted: {}",
+                    wallet_file.display(),
+                    e
+                ),
+            }
+        }
+        std::thread::sleep(std::time::Duration::from_millis(interval));
+    }
+    Ok(())
+}
+
+/// Print the descriptors, with `--external-only` or `--change-only` just one of them on a line of its own.
+/// `--json` prints them as json object and `--with-checksum` appends the checksums.
+fn print_descriptors(
+    descriptors: &Descriptors,
+    args: &Args,
+) -> Result<(), Electrum2DescriptorError> {
+    let chain = match (args.flag("--external-only"), args.flag("--change-only")) {
+        (true, true) => {
+            return Err(Electrum2DescriptorError::GenericBorrow(
+                "--external-only and --change-only are mutually exclusive",
+            ))
+        }
+        (true, false) => Some(false),
+        (false, true) => Some(true),
+        (false, false) => None,
+    };
+    let descriptors = if args.flag("--with-checksum") {
+        descriptors.with_checksums()?
+    } else {
+        descriptors.clone()
+    };
+    if args.flag("--qr") || args.value("--qr-file").is_some() {
+        #[cfg(feature = "qr")]
+        {
+            write_qr(descriptors.get(chain.unwrap_or(false)), args)?;
+            if args.flag("--qr") {
+                return Ok(());
+            }
+        }
+        #[cfg(not(feature = "qr"))]
+        return Err(Electrum2DescriptorError::GenericBorrow(
+            "--qr and --qr-file need the qr feature",
+        ));
+    }
+    if args.flag("--json") {
+        #[cfg(feature = "wallet_file")]
+        {
+            let json = match chain {
+                Some(true) => serde_json::json!({ "change": descriptors.change }).to_string(),
+                Some(false) => serde_json::json!({ "external": descriptors.external }).to_string(),
+                None => serde_json::to_string(&descriptors)?,
+            };
+            println!("{}", json);
+            return Ok(());
+        }
+        #[cfg(not(feature = "wallet_file"))]
+        return Err(Electrum2DescriptorError::GenericBorrow(
+            "--json needs the wallet_file feature",
+        ));
+    }
+    match chain {
+        Some(change) => println!("{}", descriptors.get(change)),
+        None => println!("{:?}", descriptors),
+    }
+    Ok(())
+}
+
+/// Show a descriptor as QR codes instead of printing it with `--qr`, drawn with half-block characters for a terminal
+/// with a dark background, or write them as png images to `--qr-file`. A descriptor too long for one code is split
+/// over several, to be scanned in order, and the images get the number of their part, e.g. `out-1.png`. No image is
+/// written if one of them exists.
+#[cfg(feature = "qr")]
+fn write_qr(descriptor: &str, args: &Args) -> Result<(), Electrum2DescriptorError> {
+    let codes = qr::qr_codes(descriptor);
+    if let Some(qr_file) = args.value("--qr-file") {
+        let qr_file = Path::new(qr_file);
+        let files = (1..=codes.len())
+            .map(|part| match codes.len() {
+                1 => qr_file.to_path_buf(),
+                _ => {
+                    let stem = qr_file.file_stem().unwrap_or_default().to_string_lossy();
+                    let name = match qr_file.extension() {
+                        Some(extension) => {
+                            format!("{}-{}.{}", stem, part, extension.to_string_lossy())
+                        }
+                        None => format!("{}-{}", stem, part),
+                    };
+                    qr_file.with_file_name(name)
+                }
+            })
+            .collect::<Vec<_>>();
+        if let Some(file) = files.iter().find(|file| file.exists()) {
+            return Err(Electrum2DescriptorError::AlreadyExists(
+                file.display().to_string(),
+            ));
+        }
+        for (code, file) in codes.iter().zip(&files) {
+            std::fs::write(file, code.to_png())?;
+        }
+    }
+    if args.flag("--qr") {
+        for (part, code) in codes.iter().enumerate() {
+            if codes.len() > 1 {
+                println!("part {} of {}", part + 1, codes.len());
+            }
+            print!("{}", code.to_terminal());
+        }
+    }
+    Ok(())
+}
+
+/// Print the descriptors of the keys of an electrum private key export, or write the imported wallet of the keys
+/// to `--output`
+#[cfg(feature = "wallet_file")]
+fn convert_private_key_export(text: &str, args: &Args) -> Result<(), Electrum2DescriptorError> {
+    match args.value("--output") {
+        Some(output) => {
+            ElectrumWalletFile::from_private_key_export(text)?.to_file(Path::new(output))?
+        }
+        None => {
+            for descriptor in PrivateKeyExport::from_str(text)?.to_descriptors()? {
+                println!("{}", descriptor);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write a wallet file into `--output-dir` for each wallet of a descriptor file, named after the label of its line
+/// or else the fingerprints of its keys. Nothing is written if two wallets get the same name or a file exists already.
+#[cfg(feature = "wallet_file")]
+fn convert_descriptor_file(
+    descriptor_file: &Path,
+    args: &Args,
+) -> Result<(), Electrum2DescriptorError> {
+    let output_dir = Path::new(args.value("--output-dir").ok_or(
+        Electrum2DescriptorError::GenericBorrow("--descriptor-file needs --output-dir"),
+    )?);
+    let mut wallets = ElectrumWalletFile::from_descriptor_file(descriptor_file)?;
+    for (_label, wallet) in &mut wallets {
+        fill_addresses(wallet, args)?;
+    }
+    let mut files = Vec::new();
+    for (label, wallet) in &wallets {
+        let file = match label {
+            Some(label) => output_dir.join(check_file_name(label)?),
+            None => output_dir.join(fingerprints_name(wallet)?),
+        };
+        if files.contains(&file) {
+            return Err(Electrum2DescriptorError::InvalidArguments(format!(
+                "more than one wallet would be written to {}",
+                file.display()
+            )));
+        }
+        if file.exists() {
+            return Err(Electrum2DescriptorError::AlreadyExists(
+                file.display().to_string(),
+            ));
+        }
+        files.push(file);
+    }
+    for ((_label, wallet), file) in wallets.iter().zip(files) {
+        wallet.to_file(&file)?;
+        println!("{}", file.display());
+    }
+    Ok(())
+}
+
+/// Derive the first `--addresses` addresses of a wallet generated from descriptors, for the network of its keys
+#[cfg(feature = "wallet_file")]
+fn fill_addresses(
+    wallet: &mut ElectrumWalletFile,
+    args: &Args,
+) -> Result<(), Electrum2DescriptorError> {
+    if let Some(count) = args.value("--addresses") {
+        let count = count.parse().map_err(|_| {
+            Electrum2DescriptorError::InvalidArguments(format!(
+                "invalid number of addresses: {}",
+                count
+            ))
+        })?;
+        let network = wallet.network()?;
+        wallet.fill_addresses(count, network)?;
+    }
     Ok(())
 }
+
+/// A label used as file name must not lead out of the output directory
+#[cfg(feature = "wallet_file")]
+fn check_file_name(label: &str) -> Result<&str, Electrum2DescriptorError> {
+    let valid = label
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "-_. ".contains(c));
+    if label.is_empty() || label.starts_with('.') || !valid {
+        return Err(Electrum2DescriptorError::InvalidArguments(format!(
+            "the label {:?} can't be used as file name",
+            label
+        )));
+    }
+    Ok(label)
+}
+
+/// The root fingerprints of the keys, or else the fingerprints of the keys themselves, joined by `-`
+#[cfg(feature = "wallet_file")]
+fn fingerprints_name(wallet: &ElectrumWalletFile) -> Result<String, Electrum2DescriptorError> {
+    let fingerprints = wallet
+        .keystores()
+        .iter()
+        .zip(wallet.fingerprints())
+        .map(|(ks, (_, fingerprint))| {
+            let fingerprint = match fingerprint {
+                Some(fingerprint) => fingerprint,
+                None => ks.xpub()?.0.fingerprint(),
+            };
+            Ok(fingerprint.to_string())
+        })
+        .collect::<Result<Vec<_>, Electrum2DescriptorError>>()?;
+    Ok(fingerprints.join("-"))
+}
+
+/// The wallet password from one of `--password-env <variable>`, `--password-fd <fd>` (one line read from an inherited
+/// file descriptor, as gpg does) or `--password-file <file>`, which decrypts the wallet file and the secrets of its
+/// keystores. Only one of them may be given. A trailing newline is trimmed, the rest of the password is kept as is.
+#[cfg(feature = "wallet_file")]
+fn password(args: &Args) -> Result<Option<String>, Electrum2DescriptorError> {
+    let sources = PASSWORD_OPTIONS
+        .iter()
+        .filter_map(|option| args.value(option).map(|value| (*option, value)))
+        .collect::<Vec<_>>();
+    let password = match sources.as_slice() {
+        [] => return Ok(None),
+        [("--password-env", variable)] => std::env::var(variable).map_err(|_| {
+            Electrum2DescriptorError::InvalidArguments(format!(
+                "the environment variable {} is not set",
+                variable
+            ))
+        })?,
+        [("--password-fd", fd)] => {
+            let fd = fd.parse::<u32>().map_err(|_| {
+                Electrum2DescriptorError::InvalidArguments(format!(
+                    "invalid file descriptor: {}",
+                    fd
+                ))
+            })?;
+            let mut line = String::new();
+            std::io::BufRead::read_line(
+                &mut std::io::BufReader::new(std::fs::File::open(format!("/dev/fd/{}", fd))?),
+                &mut line,
+            )?;
+            line
+        }
+        [(_, file)] => std::fs::read_to_string(file)?,
+        _ => {
+            return Err(Electrum2DescriptorError::InvalidArguments(format!(
+                "only one of {} can be given",
+                PASSWORD_OPTIONS.join(", ")
+            )))
+        }
+    };
+    let trimmed = password
+        .strip_suffix('\n')
+        .map(|p| p.strip_suffix('\r').unwrap_or(p));
+    Ok(Some(trimmed.unwrap_or(&password).to_string()))
+}
+
+/// Parse the network of `--assume-network` or `--network`
+fn parse_network(network: &str) -> Result<Network, Electrum2DescriptorError> {
+    match network {
+        "mainnet" => Ok(Network::Bitcoin),
+        "testnet" => Ok(Network::Testnet),
+        "regtest" => Ok(Network::Regtest),
+        "signet" => Ok(Network::Signet),
+        _ => Err(Electrum2DescriptorError::InvalidArguments(format!(
+            "unknown network {}, expected mainnet, testnet, regtest or signet",
+            network
+        ))),
+    }
+}
+
+/// Parse the script kind of `--kind`
+fn parse_kind(kind: &str) -> Result<ScriptKind, Electrum2DescriptorError> {
+    match kind {
+        "p2pkh" | "p2wpkh-p2sh" | "p2wpkh" | "p2tr" => ScriptKind::from_str(kind),
+        _ => Err(Electrum2DescriptorError::InvalidArguments(format!(
+            "unknown kind {}, expected p2pkh, p2wpkh-p2sh, p2wpkh or p2tr",
+            kind
+        ))),
+    }
+}
+
+/// Parse an origin given as `fp=<fingerprint>,path=<derivation>`, e.g. `fp=aabbccdd,path=m/84h/0h/0h`
+#[cfg(feature = "wallet_file")]
+fn parse_origin(origin: &str) -> Result<(Fingerprint, DerivationPath), Electrum2DescriptorError> {
+    let invalid = || {
+        Electrum2DescriptorError::InvalidArguments(format!(
+            "invalid origin {}, expected fp=<fingerprint>,path=<derivation>",
+            origin
+        ))
+    };
+    let (mut fingerprint, mut derivation) = (None, None);
+    for part in origin.split(',') {
+        match part.split_once('=').ok_or_else(invalid)? {
+            ("fp", fp) => fingerprint = Some(Fingerprint::from_str(fp).map_err(|_| invalid())?),
+            ("path", path) => {
+                derivation = Some(DerivationPath::from_str(path).map_err(|_| invalid())?)
+            }
+            _ => return Err(invalid()),
+        }
+    }
+    match (fingerprint, derivation) {
+        (Some(fingerprint), Some(derivation)) => Ok((fingerprint, derivation)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Convert all the wallet files in a directory, reporting the ones that fail on stderr.
+/// `--format csv|tsv` prints a table with a row for each wallet file instead, including the failed ones.
+#[cfg(feature = "wallet_file")]
+fn convert_dir(dir: &Path, format: &str) -> Result<(), Electrum2DescriptorError> {
+    let separator = match format {
+        "plain" => None,
+        "csv" => Some(','),
+        "tsv" => Some('\t'),
+        _ => {
+            return Err(Electrum2DescriptorError::InvalidArguments(format!(
+                "unknown format {}, expected plain, csv or tsv",
+                format
+            )))
+        }
+    };
+    if let Some(separator) = separator {
+        let converted =
+            libelectrum2descriptors::batch::map_dir(dir, &BatchOptions::default(), |wallet| {
+                Ok((wallet.summary()?, wallet.to_descriptors()?))
+            })?;
+        let header = [
+            "file",
+            "wallet_type",
+            "script_kind",
+            "network",
+            "watch_only",
+            "external",
+            "change",
+            "fingerprints",
+            "error",
+        ];
+        print_row(&header.map(String::from), separator);
+        for (file, converted) in converted {
+            let file = file
+                .strip_prefix(dir)
+                .unwrap_or(&file)
+                .display()
+                .to_string();
+            let row = match converted {
+                Ok((summary, descriptors)) => [
+                    file,
+                    serde_json::to_value(&summary.wallet_type)?
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    summary.script_kind.to_string(),
+                    summary.network.to_string(),
+                    summary.watch_only.to_string(),
+                    descriptors.external,
+                    descriptors.change,
+                    summary
+                        .cosigners
+                        .iter()
+                        .map(|c| c.fingerprint.map(|f| f.to_string()).unwrap_or_default())
+                        .collect::<Vec<_>>()
+                        .join(";"),
+                    String::new(),
+                ],
+                Err(e) => {
+                    let mut row: [String; 9] = Default::default();
+                    row[0] = file;
+                    row[8] = e.to_string();
+                    row
+                }
+            };
+            print_row(&row, separator);
+        }
+        return Ok(());
+    }
+
+    let mut failed = false;
+    for (file, descriptor) in libelectrum2descriptors::convert_dir(dir, &BatchOptions::default())? {
+        match descriptor {
+            Ok(descriptor) => println!("{}: {:?}", file.display(), descriptor),
+            Err(e) => {
+                eprintln!("{}: {}", file.display(), e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        return Err(Electrum2DescriptorError::GenericBorrow(
+            "some wallet files could not be converted",
+        ));
+    }
+    Ok(())
+}
+
+/// Convert the wallets of a zip backup, reporting the ones that fail, e.g. encrypted ones without password, on stderr
+#[cfg(feature = "zip")]
+fn convert_zip(backup: &Path, password: Option<&str>) -> Result<(), Electrum2DescriptorError> {
+    let mut failed = false;
+    for (name, wallet) in ElectrumWalletFile::from_backup_zip(backup, password)? {
+        match wallet.and_then(|wallet| wallet.to_descriptors()) {
+            Ok(descriptor) => println!("{}: {:?}", name, descriptor),
+            Err(e) => {
+                eprintln!("{}: {}", name, e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        return Err(Electrum2DescriptorError::GenericBorrow(
+            "some wallets of the backup could not be converted",
+        ));
+    }
+    Ok(())
+}
+
+/// Print a row of a csv table, quoted as RFC 4180 says, or of a tsv table, which has no quoting: tabs and line
+/// breaks in the fields become spaces
+#[cfg(feature = "wallet_file")]
+fn print_row(fields: &[String], separator: char) {
+    let fields = fields
+        .iter()
+        .map(|field| {
+            if separator == '\t' {
+                field.replace(['\t', '\n', '\r'], " ")
+            } else if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect::<Vec<_>>();
+    println!("{}", fields.join(&separator.to_string()));
+}
+
+/// Check that a wallet file, or the ones in a directory, convert cleanly without printing the descriptors.
+/// Exits with 0 when all pass, 1 when there are warnings and 2 when some fail.
+#[cfg(feature = "wallet_file")]
+fn check(path: &Path, repair: bool) -> Result<(), Electrum2DescriptorError> {
+    let converted = if path.is_dir() {
+        libelectrum2descriptors::convert_dir(path, &BatchOptions::default())?
+    } else {
+        let descriptor = ElectrumWalletFile::from_file(path).and_then(|w| w.to_descriptors());
+        vec![(path.to_path_buf(), descriptor)]
+    };
+    let mut severity = 0;
+    for (file, descriptor) in &converted {
+        if let Err(e) = descriptor {
+            println!("FAIL {}: {}", file.display(), e);
+            severity = 2;
+            continue;
+        }
+        println!("PASS {}", file.display());
+        let strict = ParseOptions {
+            strict: true,
+            ..ParseOptions::default()
+        };
+        if let Err(e) = ElectrumWalletFile::from_file_with_options(file, &strict) {
+            println!("  warning: not as electrum writes it: {}", e);
+            severity = severity.max(1);
+        }
+    }
+    if repair {
+        for (file, _descriptor) in &converted {
+            repair_file(file)?;
+        }
+    }
+    std::process::exit(severity)
+}
+
+/// Repair a wallet file, writing the fixed wallet to `<file>.repaired` if anything was fixed
+#[cfg(feature = "wallet_file")]
+fn repair_file(file: &Path) -> Result<(), Electrum2DescriptorError> {
+    let options = ParseOptions {
+        unvalidated: true,
+        ..ParseOptions::default()
+    };
+    let mut wallet = match ElectrumWalletFile::from_file_with_options(file, &options) {
+        Ok(wallet) => wallet,
+        Err(e) => {
+            println!("REPAIR {}: needs attention: {}", file.display(), e);
+            return Ok(());
+        }
+    };
+    let actions = wallet.repair(RepairPolicy::default());
+    for action in &actions {
+        println!("REPAIR {}: {}", file.display(), action);
+    }
+    if actions
+        .iter()
+        .any(|action| !matches!(action, RepairAction::NeedsAttention(_)))
+    {
+        let mut repaired = file.as_os_str().to_owned();
+        repaired.push(".repaired");
+        wallet.to_file(Path::new(&repaired))?;
+    }
+    Ok(())
+}
+
+/// `--loss-report descriptors|core|wallet` prints what converting the wallet file to descriptors, a bitcoin core
+/// import or a fresh wallet file drops. `--fresh-wallet <file>` writes the fresh wallet file, printing its loss report
+/// on stderr unless `--quiet`, and refuses to unless `--acknowledge-loss` when something is lost.
+#[cfg(feature = "wallet_file")]
+fn loss(path: &Path, args: &Args) -> Result<(), Electrum2DescriptorError> {
+    // keys, descriptors and directories have nothing a fresh wallet file could lose
+    if !path.is_file() {
+        return Err(Electrum2DescriptorError::InvalidArguments(format!(
+            "--loss-report and --fresh-wallet need an electrum wallet file, {} isn't one",
+            path.display()
+        )));
+    }
+    let wallet = ElectrumWalletFile::from_file(path)?;
+    if let Some(target) = args.value("--loss-report") {
+        let target = match target {
+            "descriptors" => ConversionTarget::Descriptors,
+            "core" => ConversionTarget::CoreImport,
+            "wallet" => ConversionTarget::FreshWalletFile,
+            _ => {
+                return Err(Electrum2DescriptorError::InvalidArguments(format!(
+                    "unknown --loss-report {}, expected descriptors, core or wallet",
+                    target
+                )))
+            }
+        };
+        print!("{}", wallet.loss_report(target));
+    }
+    if let Some(output) = args.value("--fresh-wallet") {
+        let report = wallet.loss_report(ConversionTarget::FreshWalletFile);
+        if !args.flag("--quiet") {
+            eprint!("{}", report);
+        }
+        if !report.is_empty() && !args.flag("--acknowledge-loss") {
+            return Err(Electrum2DescriptorError::GenericBorrow(
+                "the fresh wallet file loses the data listed, confirm with --acknowledge-loss",
+            ));
+        }
+        wallet.to_fresh_wallet()?.to_file(Path::new(output))?;
+    }
+    Ok(())
+}
+
+/// Verify that a wallet file and a descriptor refer to the same wallet, listing the differences otherwise.
+/// The descriptor is given with `--descriptor`, or read with `--descriptor-file` from a file or `-` for stdin
+#[cfg(feature = "wallet_file")]
+fn verify(args: Args) -> Result<(), Electrum2DescriptorError> {
+    let wallet_file = args
+        .positional
+        .first()
+        .ok_or(Electrum2DescriptorError::GenericBorrow(
+            "verify needs a wallet file",
+        ))?;
+    let descriptor = match (args.value("--descriptor"), args.value("--descriptor-file")) {
+        (Some(descriptor), None) => descriptor.to_string(),
+        (None, Some("-")) => std::io::read_to_string(std::io::stdin())?,
+        (None, Some(file)) => std::fs::read_to_string(file)?,
+        _ => {
+            return Err(Electrum2DescriptorError::GenericBorrow(
+                "verify needs either --descriptor or --descriptor-file",
+            ))
+        }
+    };
+    let wallet = ElectrumWalletFile::from_file(Path::new(wallet_file))?;
+    // the checksum, if any, is verified
+    let expected = ElectrumWalletFile::from_descriptor(descriptor.trim())?;
+    let differences = wallet.diff(&expected)?;
+    if differences.is_empty() {
+        println!("{} matches the descriptor", wallet_file);
+        return Ok(());
+    }
+    println!("--- {}\n+++ descriptor", wallet_file);
+    for difference in differences {
+        println!("{}", difference);
+    }
+    Err(Electrum2DescriptorError::GenericBorrow(
+        "the wallet file and the descriptor differ",
+    ))
+}
+
+/// How many addresses are derived when the wallet file stores none and there is no `--limit`
+#[cfg(feature = "wallet_file")]
+const DERIVED_ADDRESSES: u32 = 20;
+
+/// Print the addresses of a wallet file with their keychain and index, e.g. to reconcile it against a withdrawal
+/// history. The stored addresses are printed, or derived ones when the wallet file stores none for a keychain.
+/// `--keychain receiving|change` limits them to one keychain, `--limit` to the first ones, `--format json|plain|csv`
+/// selects the output. `--verify` compares the stored addresses with the derived ones and fails on mismatches.
+/// `--network regtest|signet` derives the addresses for a test network, which the keys of testnet don't tell.
+#[cfg(feature = "wallet_file")]
+fn addresses(args: Args) -> Result<(), Electrum2DescriptorError> {
+    let wallet_file = args
+        .positional
+        .first()
+        .ok_or(Electrum2DescriptorError::GenericBorrow(
+            "addresses needs a wallet file",
+        ))?;
+    let keychains = match args.value("--keychain") {
+        None => vec![false, true],
+        Some("receiving") => vec![false],
+        Some("change") => vec![true],
+        Some(keychain) => {
+            return Err(Electrum2DescriptorError::InvalidArguments(format!(
+                "unknown keychain {}, expected receiving or change",
+                keychain
+            )))
+        }
+    };
+    let limit = args
+        .value("--limit")
+        .map(|limit| {
+            limit.parse::<u32>().map_err(|_| {
+                Electrum2DescriptorError::InvalidArguments(format!("invalid limit: {}", limit))
+            })
+        })
+        .transpose()?;
+    let format = args.value("--format").unwrap_or("plain");
+    if !["json", "plain", "csv"].contains(&format) {
+        return Err(Electrum2DescriptorError::InvalidArguments(format!(
+            "unknown format {}, expected json, plain or csv",
+            format
+        )));
+    }
+    let verify = args.flag("--verify");
+
+    let mut wallet = ElectrumWalletFile::from_file(Path::new(wallet_file))?;
+    if let Some(network) = args.value("--network") {
+        wallet.set_network(parse_network(network)?)?;
+    }
+    if let Some(kind) = args.value("--kind") {
+        wallet.assume_kind(parse_kind(kind)?)?;
+    }
+    let mut entries = Vec::new();
+    for change in keychains {
+        let keychain = if change { "change" } else { "receiving" };
+        let stored = if change {
+            &wallet.addresses().change
+        } else {
+            &wallet.addresses().receiving
+        };
+        let (source, addresses) = if stored.is_empty() {
+            let count = limit.unwrap_or(DERIVED_ADDRESSES);
+            let derived = (0..count)
+                .map(|index| wallet.address(change, index).map(|a| a.to_string()))
+                .collect::<Result<Vec<_>, _>>()?;
+            ("derived", derived)
+        } else {
+            let count = limit.map_or(stored.len(), |limit| stored.len().min(limit as usize));
+            ("stored", stored[..count].to_vec())
+        };
+        if format == "plain" {
+            eprintln!(
+                "note: {} {} addresses {}",
+                addresses.len(),
+                keychain,
+                if source == "stored" {
+                    "from the wallet file"
+                } else {
+                    "derived from the keys, the wallet file stores none"
+                }
+            );
+        }
+        for (index, address) in addresses.into_iter().enumerate() {
+            let derived = match (verify, source) {
+                (true, "stored") => Some(wallet.address(change, index as u32)?.to_string()),
+                _ => None,
+            };
+            entries.push((keychain, index, address, source, derived));
+        }
+    }
+
+    let mismatch = entries
+        .iter()
+        .any(|(_, _, address, _, derived)| derived.iter().any(|d| d != address));
+    match format {
+        "json" => {
+            let json = entries
+                .iter()
+                .map(|(keychain, index, address, source, derived)| {
+                    let mut entry = serde_json::json!({
+                        "keychain": keychain,
+                        "index": index,
+                        "address": address,
+                        "source": source,
+                    });
+                    if verify {
+                        entry["matches"] = derived.iter().all(|d| d == address).into();
+                        entry["derived"] = derived.clone().into();
+                    }
+                    entry
+                })
+                .collect::<Vec<_>>();
+            println!("{}", serde_json::Value::Array(json));
+        }
+        "csv" => {
+            let header = if verify {
+                "keychain,index,address,source,matches,derived"
+            } else {
+                "keychain,index,address,source"
+            };
+            println!("{}", header);
+            for (keychain, index, address, source, derived) in &entries {
+                print!("{},{},{},{}", keychain, index, address, source);
+                if verify {
+                    let matches = derived.iter().all(|d| d == address);
+                    print!(",{},{}", matches, derived.as_deref().unwrap_or_default());
+                }
+                println!();
+            }
+        }
+        _ => {
+            for (keychain, index, address, _source, derived) in &entries {
+                match derived {
+                    Some(derived) if derived != address => println!(
+                        "{} {} {} MISMATCH, derived {}",
+                        keychain, index, address, derived
+                    ),
+                    _ => println!("{} {} {}", keychain, index, address),
+                }
+            }
+        }
+    }
+    if mismatch {
+        return Err(Electrum2DescriptorError::GenericBorrow(
+            "some stored addresses don't match the derived ones",
+        ));
+    }
+    Ok(())
+}
+
+/// Print the summary of a wallet file, or with `--all <dir>` a table with a row for each wallet file in the
+/// directory. The rows are sorted by file, or by `--sort file|type|kind|network`, and `--filter
+/// watch-only|multisig|encrypted` only keeps the matching wallets. `--json` prints json instead.
+/// `--restore-command` prints the `electrum restore` command of the wallet instead, with its xprvs with `--private`.
+/// `--report text|md` prints the report of the wallet for signing ceremonies, named by the file.
+#[cfg(feature = "wallet_file")]
+fn inspect(args: Args) -> Result<(), Electrum2DescriptorError> {
+    let path = Path::new(args.positional.first().ok_or(
+        Electrum2DescriptorError::GenericBorrow(
+            "inspect needs a wallet file, or a directory with --all",
+        ),
+    )?);
+    if let Some(format) = args.value("--report") {
+        let format = match format {
+            "text" => ReportFormat::Text,
+            "md" => ReportFormat::Markdown,
+            format => {
+                return Err(Electrum2DescriptorError::InvalidArguments(format!(
+                    "unknown report format {}, expected text or md",
+                    format
+                )))
+            }
+        };
+        let options = ReportOptions {
+            format,
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string()),
+        };
+        print!(
+            "{}",
+            ElectrumWalletFile::from_file(path)?.to_report(&options)?
+        );
+        return Ok(());
+    }
+    if args.flag("--restore-command") {
+        let wallet = ElectrumWalletFile::from_file(path)?;
+        println!("{}", wallet.to_restore_command(args.flag("--private"))?);
+        return Ok(());
+    }
+    if !args.flag("--all") {
+        let summary = ElectrumWalletFile::from_file(path)?.summary()?;
+        if args.flag("--json") {
+            println!("{}", serde_json::to_string(&summary)?);
+        } else {
+            print!("{}", summary);
+        }
+        return Ok(());
+    }
+
+    let filter = args.value("--filter");
+    let keep = |summary: &Result<WalletSummary, Electrum2DescriptorError>| match (filter, summary) {
+        (None, _) => Ok(true),
+        (Some("watch-only"), summary) => Ok(matches!(summary, Ok(s) if s.watch_only)),
+        (Some("multisig"), summary) => Ok(matches!(
+            summary,
+            Ok(WalletSummary {
+                wallet_type: WalletType::Multisig(_),
+                ..
+            })
+        )),
+        (Some("encrypted"), summary) => Ok(encryption(summary) != "no"),
+        (Some(filter), _) => Err(Electrum2DescriptorError::InvalidArguments(format!(
+            "unknown filter {}, expected watch-only, multisig or encrypted",
+            filter
+        ))),
+    };
+    let mut rows = Vec::new();
+    for (file, summary) in summarize_dir(path, &BatchOptions::default())? {
+        if keep(&summary)? {
+            let file = file
+                .strip_prefix(path)
+                .unwrap_or(&file)
+                .display()
+                .to_string();
+            rows.push(inspect_row(file, &summary));
+        }
+    }
+    let column = match args.value("--sort").unwrap_or("file") {
+        "file" => "file",
+        "type" => "wallet_type",
+        "kind" => "script_kind",
+        "network" => "network",
+        sort => {
+            return Err(Electrum2DescriptorError::InvalidArguments(format!(
+                "unknown sort {}, expected file, type, kind or network",
+                sort
+            )))
+        }
+    };
+    // stable, so that the rows stay sorted by file within a column value
+    rows.sort_by_key(|row| row.get(column).map(ToString::to_string));
+
+    if args.flag("--json") {
+        println!("{}", serde_json::Value::Array(rows));
+        return Ok(());
+    }
+    let columns = [
+        ("file", "file"),
+        ("wallet_type", "type"),
+        ("script_kind", "kind"),
+        ("network", "network"),
+        ("watch_only", "watch-only"),
+        ("fingerprints", "fingerprints"),
+        ("encrypted", "encrypted"),
+    ];
+    let cell = |row: &serde_json::Value, column: &str| match &row[column] {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(values) if values.is_empty() => "-".to_string(),
+        serde_json::Value::Array(values) => values
+            .iter()
+            .map(|v| v.as_str().unwrap_or("unknown"))
+            .collect::<Vec<_>>()
+            .join(","),
+        serde_json::Value::Null => "-".to_string(),
+        value => value.to_string(),
+    };
+    let widths = columns
+        .iter()
+        .map(|(column, header)| {
+            rows.iter()
+                .map(|row| cell(row, column).len())
+                .chain(std::iter::once(header.len()))
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+    let line = |cells: Vec<String>| {
+        let padded = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect::<Vec<_>>();
+        println!("{}", padded.join("  ").trim_end());
+    };
+    line(columns.iter().map(|(_, h)| h.to_string()).collect());
+    for row in &rows {
+        match row.get("error") {
+            Some(error) => line(vec![
+                cell(row, "file"),
+                "error".to_string(),
+                error.as_str().unwrap_or_default().to_string(),
+            ]),
+            None => line(columns.iter().map(|(c, _)| cell(row, c)).collect()),
+        }
+    }
+    Ok(())
+}
+
+/// Whether the wallet file is encrypted: `file` for the whole file, `keystore` for some keystore secrets
+#[cfg(feature = "wallet_file")]
+fn encryption(summary: &Result<WalletSummary, Electrum2DescriptorError>) -> &'static str {
+    match summary {
+        Err(Electrum2DescriptorError::EncryptedWallet) => "file",
+        Ok(summary) if summary.encrypted => "keystore",
+        _ => "no",
+    }
+}
+
+/// The json row of a wallet file for `inspect --all`
+#[cfg(feature = "wallet_file")]
+fn inspect_row(
+    file: String,
+    summary: &Result<WalletSummary, Electrum2DescriptorError>,
+) -> serde_json::Value {
+    match summary {
+        Ok(s) => serde_json::json!({
+            "file": file,
+            "wallet_type": s.wallet_type,
+            "script_kind": s.script_kind.to_string(),
+            "network": s.network.to_string(),
+            "watch_only": s.watch_only,
+            "fingerprints": s.cosigners.iter().map(|c| c.fingerprint.map(|f| f.to_string())).collect::<Vec<_>>(),
+            "encrypted": encryption(summary),
+        }),
+        // the whole file is encrypted, nothing else can be known
+        Err(Electrum2DescriptorError::EncryptedWallet) => serde_json::json!({
+            "file": file,
+            "wallet_type": null,
+            "script_kind": null,
+            "network": null,
+            "watch_only": null,
+            "fingerprints": [],
+            "encrypted": encryption(summary),
+        }),
+        Err(e) => serde_json::json!({ "file": file, "error": e.to_string() }),
+    }
+}
+
+/// Scan a file for extended keys and WIFs with `recover scan <file>`, e.g. a damaged wallet file or notes.
+/// Private keys are only printed with `--show-secrets`
+fn recover(args: Args) -> Result<(), Electrum2DescriptorError> {
+    let file = match args.positional.as_slice() {
+        [command, file] if command == "scan" => file,
+        _ => {
+            return Err(Electrum2DescriptorError::GenericBorrow(
+                "usage: recover scan <file>",
+            ))
+        }
+    };
+    let text = String::from_utf8_lossy(&std::fs::read(file)?).into_owned();
+    let show_secrets = args.flag("--show-secrets");
+    for found in recover::scan_text(&text) {
+        let kind = found
+            .script_kind
+            .map_or_else(|| "unknown".to_string(), |k| k.to_string());
+        let key = if found.is_secret() && !show_secrets {
+            "<redacted>"
+        } else {
+            found.as_str()
+        };
+        println!(
+            "{}: {} {} {} {}",
+            found.offset, found.key_type, found.network, kind, key
+        );
+        if let Some(descriptors) = found.to_descriptors() {
+            if !found.is_secret() || show_secrets {
+                println!("  {:?}", descriptors);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print the descriptors of a wallet file whose keystores may be damaged.
+/// If some can't be parsed, the `addr()` descriptors of the listed addresses are printed instead.
+#[cfg(feature = "wallet_file")]
+fn salvage(args: Args) -> Result<(), Electrum2DescriptorError> {
+    let wallet_file = args
+        .positional
+        .first()
+        .ok_or(Electrum2DescriptorError::GenericBorrow(
+            "salvage needs a wallet file",
+        ))?;
+    let options = ParseOptions {
+        partial: true,
+        ..ParseOptions::default()
+    };
+    let wallet = ElectrumWalletFile::from_file_with_options(Path::new(wallet_file), &options)?;
+    if !wallet.is_incomplete() {
+        println!("{:?}", wallet.to_descriptors()?);
+        return Ok(());
+    }
+    if !wallet.unfinished_slots().is_empty() {
+        eprintln!(
+            "warning: {}",
+            Electrum2DescriptorError::UnfinishedSetup(wallet.unfinished_slots().to_vec())
+        );
+        if let Ok(summary) = wallet.summary() {
+            eprint!("{}", summary);
+        }
+    }
+    for e in wallet.keystore_errors() {
+        eprintln!("warning: left out a keystore: {}", e);
+    }
+    if let Some(Ok((kind, network))) = wallet
+        .addresses()
+        .receiving
+        .first()
+        .map(|a| kind_from_address_str(a))
+    {
+        eprintln!("the addresses are {} addresses on {}", kind, network);
+    }
+    for descriptor in wallet.addr_descriptors() {
+        println!("{}", descriptor);
+    }
+    Ok(())
+}
+
+/// Edit a wallet file in place, keeping its previous content as `<name>.bak` unless `--no-backup`.
+/// A threshold is lowered with `--set-threshold` before `--remove-cosigner <slot number>` removes a cosigner, then
+/// `--add-cosigner-file <keystore json>` adds one, a threshold is raised, and `--set-label` labels the keystore of a
+/// standard wallet. `--scrub-history` removes the transaction history, and the labels too with `--drop-labels`.
+/// `--dry-run` prints the summary and descriptors of the result instead of writing it.
+#[cfg(feature = "wallet_file")]
+fn edit(args: Args) -> Result<(), Electrum2DescriptorError> {
+    let wallet_file = Path::new(args.positional.first().ok_or(
+        Electrum2DescriptorError::GenericBorrow("edit needs a wallet file"),
+    )?);
+    // the crate doesn't encrypt, the edited wallet would be written back decrypted
+    if ElectrumWalletFile::peek(wallet_file)?.encrypted {
+        return Err(Electrum2DescriptorError::GenericBorrow(
+            "edit can't write encrypted wallet files back, remove the password in electrum first",
+        ));
+    }
+    let mut wallet = ElectrumWalletFile::from_file(wallet_file)?;
+    let threshold = args
+        .value("--set-threshold")
+        .map(|threshold| {
+            threshold.parse::<u8>().map_err(|_| {
+                Electrum2DescriptorError::InvalidArguments(format!(
+                    "invalid threshold: {}",
+                    threshold
+                ))
+            })
+        })
+        .transpose()?;
+    let lowers = |wallet: &ElectrumWalletFile, threshold: u8| matches!(wallet.wallet_type(), WalletType::Multisig(multisig) if threshold < multisig.threshold());
+
+    if let Some(threshold) = threshold.filter(|threshold| lowers(&wallet, *threshold)) {
+        wallet.set_threshold(threshold)?;
+    }
+    if let Some(slot) = args.value("--remove-cosigner") {
+        let index = slot
+            .parse::<usize>()
+            .ok()
+            .and_then(|slot| slot.checked_sub(1))
+            .ok_or_else(|| {
+                Electrum2DescriptorError::InvalidArguments(format!(
+                    "invalid cosigner {}, expected the number of its slot like 3 for x3/",
+                    slot
+                ))
+            })?;
+        wallet.remove_cosigner(index)?;
+    }
+    if let Some(file) = args.value("--add-cosigner-file") {
+        let keystore: Keystore = serde_json::from_str(&std::fs::read_to_string(file)?)?;
+        wallet.add_cosigner(keystore)?;
+    }
+    if let Some(threshold) = threshold.filter(|threshold| !lowers(&wallet, *threshold)) {
+        wallet.set_threshold(threshold)?;
+    }
+    if let Some(label) = args.value("--set-label") {
+        wallet.set_label(label)?;
+    }
+    if args.flag("--scrub-history") {
+        wallet = wallet.scrub_history(!args.flag("--drop-labels"));
+    }
+    // the descriptors check that the edited wallet is still consistent
+    let descriptors = wallet.to_descriptors()?;
+
+    if args.flag("--dry-run") {
+        print!("{}", wallet.summary()?);
+        println!("{:?}", descriptors);
+        return Ok(());
+    }
+    let options = WriteOptions {
+        overwrite: true,
+        no_backup: args.flag("--no-backup"),
+        ..WriteOptions::default()
+    };
+    wallet.to_file_with_options(wallet_file, &options)
+}
+
+/// Build a multisig electrum wallet file from the cosigners' extended keys.
+/// Each cosigner is given as `--cosigner <xkey>[,label=<label>][,fingerprint=<fingerprint>,derivation=<derivation>]`
+#[cfg(feature = "wallet_file")]
+fn multisig(args: Args) -> Result<(), Electrum2DescriptorError> {
+    let threshold = args
+        .value("--threshold")
+        .ok_or(Electrum2DescriptorError::GenericBorrow(
+            "--threshold is required for multisig",
+        ))?;
+    let threshold = threshold.parse().map_err(|_| {
+        Electrum2DescriptorError::InvalidArguments(format!("invalid threshold: {}", threshold))
+    })?;
+    let keystores = args
+        .values("--cosigner")
+        .into_iter()
+        .map(parse_cosigner)
+        .collect::<Result<Vec<_>, _>>()?;
+    let wallet = ElectrumWalletFile::new(&keystores, threshold)?;
+    write_wallet(&wallet, &args)
+}
+
+/// Parse a `--cosigner <xkey>[,label=<label>][,fingerprint=<fingerprint>,derivation=<derivation>]` argument
+#[cfg(feature = "wallet_file")]
+fn parse_cosigner(arg: &str) -> Result<Keystore, Electrum2DescriptorError> {
+    let mut parts = arg.split(',');
+    let xkey = parts.next().unwrap_or_default();
+    let mut keystore = Keystore::from_electrum_xkey(xkey)?;
+    let (mut fingerprint, mut derivation) = (None, None);
+    for option in parts {
+        match option.split_once('=') {
+            Some(("label", label)) => keystore = keystore.with_label(label),
+            Some(("fingerprint", value)) => fingerprint = Some(Fingerprint::from_str(value)?),
+            Some(("derivation", value)) => derivation = Some(DerivationPath::from_str(value)?),
+            _ => {
+                return Err(Electrum2DescriptorError::InvalidArguments(format!(
+                    "unknown cosigner option: {}",
+                    option
+                )))
+            }
+        }
+    }
+    match (fingerprint, derivation) {
+        (Some(fingerprint), Some(derivation)) => {
+            keystore = keystore.with_origin(fingerprint, &derivation)
+        }
+        (None, None) => (),
+        _ => {
+            return Err(Electrum2DescriptorError::InvalidArguments(format!(
+                "a cosigner needs both fingerprint and derivation: {}",
+                arg
+            )))
+        }
+    }
+    Ok(keystore)
+}
+
+/// Write the wallet to the `--output` file, or to stdout if there is none.
+/// An existing file is only replaced with `--force`, keeping its content as `<name>.bak`
+#[cfg(feature = "wallet_file")]
+fn write_wallet(wallet: &ElectrumWalletFile, args: &Args) -> Result<(), Electrum2DescriptorError> {
+    match args.value("--output") {
+        Some(output) if args.flag("--force") => wallet.to_file_overwrite(Path::new(output)),
+        Some(output) => wallet.to_file(Path::new(output)).map_err(|e| match e {
+            Electrum2DescriptorError::AlreadyExists(file) => {
+                Electrum2DescriptorError::InvalidArguments(format!(
+                    "{} already exists, use --force to replace it",
+                    file
+                ))
+            }
+            e => e,
+        }),
+        None => {
+            println!("{}", wallet);
+            Ok(())
+        }
+    }
+}
+
+/// Command line arguments split into positional arguments, options with a value and flags
+struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+    flags: Vec<String>,
+}
+
+#[cfg_attr(not(feature = "wallet_file"), allow(dead_code))]
+impl Args {
+    /// Split the arguments, `with_value` and `flags` list the accepted options
+    fn parse(
+        args: &[String],
+        with_value: &[&str],
+        flags: &[&str],
+    ) -> Result<Self, Electrum2DescriptorError> {
+        let mut parsed = Args {
+            positional: Vec::new(),
+            options: Vec::new(),
+            flags: Vec::new(),
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if with_value.contains(&arg.as_str()) {
+                let value = args.next().ok_or_else(|| {
+                    Electrum2DescriptorError::InvalidArguments(format!("{} requires a value", arg))
+                })?;
+                parsed.options.push((arg.clone(), value.clone()));
+            } else if flags.contains(&arg.as_str()) {
+                parsed.flags.push(arg.clone());
+            } else if arg.starts_with("--") {
+                return Err(Electrum2DescriptorError::InvalidArguments(format!(
+                    "unknown option: {}",
+                    arg
+                )));
+            } else {
+                parsed.positional.push(arg.clone());
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// The value of the last occurrence of an option
+    fn value(&self, name: &str) -> Option<&str> {
+        self.values(name).pop()
+    }
+
+    /// Whether a flag was given
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|f| f == name)
+    }
+
+    /// The values of all occurrences of an option
+    fn values(&self, name: &str) -> Vec<&str> {
+        self.options
+            .iter()
+            .filter(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+            .collect()
+    }
+}
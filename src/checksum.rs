@@ -0,0 +1,107 @@
+use crate::Electrum2DescriptorError;
+
+const INPUT_CHARSET: &str =
+    "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u64; 5] = [
+    0xf5dee51989,
+    0xa9fdca3312,
+    0x1bab10e32d,
+    0x3706b1677a,
+    0x644d626ffd,
+];
+
+fn polymod(c: u64, value: u64) -> u64 {
+    let c0 = c >> 35;
+    let mut c = ((c & 0x7ffffffff) << 5) ^ value;
+    for (i, generator) in GENERATOR.iter().enumerate() {
+        if c0 & (1 << i) != 0 {
+            c ^= generator;
+        }
+    }
+    c
+}
+
+/// The 8 character checksum of a descriptor without checksum, as specified by
+/// [BIP-380](https://github.com/bitcoin/bips/blob/master/bip-0380.mediawiki)
+pub fn descriptor_checksum(descriptor: &str) -> Result<String, Electrum2DescriptorError> {
+    let mut c = 1;
+    let mut class = 0;
+    let mut class_count = 0;
+    for ch in descriptor.chars() {
+        let position = INPUT_CHARSET.find(ch).ok_or_else(|| {
+            Electrum2DescriptorError::UnknownDescriptorFormat(format!(
+                "invalid character {:?} in {}",
+                ch, descriptor
+            ))
+        })? as u64;
+        c = polymod(c, position & 31);
+        class = class * 3 + (position >> 5);
+        class_count += 1;
+        if class_count == 3 {
+            c = polymod(c, class);
+            class = 0;
+            class_count = 0;
+        }
+    }
+    if class_count > 0 {
+        c = polymod(c, class);
+    }
+    for _ in 0..8 {
+        c = polymod(c, 0);
+    }
+    c ^= 1;
+    Ok((0..8)
+        .map(|j| CHECKSUM_CHARSET[((c >> (5 * (7 - j))) & 31) as usize] as char)
+        .collect())
+}
+
+/// Append the checksum to a descriptor without checksum
+pub fn with_checksum(descriptor: &str) -> Result<String, Electrum2DescriptorError> {
+    Ok(format!(
+        "{}#{}",
+        descriptor,
+        descriptor_checksum(descriptor)?
+    ))
+}
+
+/// The descriptor without its checksum, failing if the checksum doesn't match. Descriptors without one are
+/// returned as they are.
+pub fn strip_checksum(descriptor: &str) -> Result<&str, Electrum2DescriptorError> {
+    match descriptor.split_once('#') {
+        Some((descriptor, expected)) => {
+            if descriptor_checksum(descriptor)? != expected {
+                return Err(Electrum2DescriptorError::UnknownDescriptorFormat(format!(
+                    "invalid checksum {}",
+                    expected
+                )));
+            }
+            Ok(descriptor)
+        }
+        None => Ok(descriptor),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bip380_vectors() {
+        assert_eq!(
+            with_checksum("raw(deadbeef)").unwrap(),
+            "raw(deadbeef)#89f8spxm"
+        );
+        assert_eq!(
+            with_checksum("pkh([d34db33f/44'/0'/0']xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL/1/*)").unwrap(),
+            "pkh([d34db33f/44'/0'/0']xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL/1/*)#ml40v0wf"
+        );
+        assert!(descriptor_checksum("raw(deadbeef)\u{e9}").is_err());
+        assert_eq!(
+            strip_checksum("raw(deadbeef)#89f8spxm").unwrap(),
+            "raw(deadbeef)"
+        );
+        assert_eq!(strip_checksum("raw(deadbeef)").unwrap(), "raw(deadbeef)");
+        assert!(strip_checksum("raw(deadbeef)#89f8spxn").is_err());
+    }
+}
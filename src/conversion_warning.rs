@@ -0,0 +1,134 @@
+use crate::electrum_wallet_file::{KeyOrder, WalletType, SECRET_FIELDS};
+use crate::{Descriptors, Electrum2DescriptorError, ElectrumWalletFile, ScriptKind};
+use bitcoin::Network;
+use std::fmt;
+
+/// Information lost by a conversion
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConversionWarning {
+    /// A wallet file field that isn't converted, with the number of its entries
+    DroppedField(String, usize),
+    /// Descriptors hold keys, the seed a keystore was created from is lost
+    SeedNotRepresentableInDescriptor,
+    /// A key was re-interpreted for another network than its prefix is for: the key, its network and the assumed one
+    NetworkAssumed(String, Network, Network),
+    /// The change descriptor was given, its keys were used as the account keys
+    ChangeDescriptor(String),
+    /// An address listed more than once: the address, the position of the dropped duplicate and of the one kept
+    DuplicateAddress(String, String, String),
+    /// A field of the wallet file was given more than once, the last one was used
+    DuplicateField(String),
+    /// The seed of the keystore has an extension, restoring from the seed needs it too. The descriptors are from the
+    /// keys, which are derived with the extension already.
+    SeedExtension(String),
+    /// The keys of the multisig scripts are in keystore order instead of sorted, the addresses only match wallets
+    /// that use the same order
+    UnsortedKeys,
+    /// The plain xpub of a keystore was used for the script kind given, see [`ElectrumWalletFile::assume_kind`]
+    KindAssumed(String, ScriptKind),
+}
+
+/// Fields with the state of electrum's user interface, nothing is lost without them
+pub(crate) const GUI_FIELDS: &[&str] = &["qt-console-history", "winpos-qt"];
+
+impl ElectrumWalletFile {
+    /// Generate output descriptors matching the electrum wallet, noting in `warnings` what they don't represent
+    pub fn to_descriptors_with_warnings(
+        &self,
+        warnings: &mut Vec<ConversionWarning>,
+    ) -> Result<Descriptors, Electrum2DescriptorError> {
+        let descriptors = self.to_descriptors()?;
+        if self.keystores().iter().any(|ks| ks.seed.is_some()) {
+            warnings.push(ConversionWarning::SeedNotRepresentableInDescriptor);
+        }
+        for (i, keystore) in self.keystores().iter().enumerate() {
+            if keystore.seed.is_some() && keystore.has_seed_extension() {
+                warnings.push(ConversionWarning::SeedExtension(self.keystore_name(i)));
+            }
+        }
+        if self.key_order() == KeyOrder::AsStored && self.wallet_type() != &WalletType::Standard {
+            warnings.push(ConversionWarning::UnsortedKeys);
+        }
+        warnings.extend(self.parse_warnings().iter().cloned());
+        warnings.extend(self.dropped_fields());
+        Ok(descriptors)
+    }
+
+    /// The unmodeled fields with content
+    fn dropped_fields(&self) -> impl Iterator<Item = ConversionWarning> + '_ {
+        self.extra()
+            .iter()
+            .filter(|(name, _)| !GUI_FIELDS.contains(&name.as_str()))
+            .filter_map(|(name, value)| {
+                let entries = match value {
+                    serde_json::Value::Object(entries) => {
+                        entries.values().filter(|v| has_content(v)).count()
+                    }
+                    serde_json::Value::Array(entries) => {
+                        entries.iter().filter(|v| has_content(v)).count()
+                    }
+                    value if SECRET_FIELDS.contains(&name.as_str()) && has_content(value) => 1,
+                    _ => 0,
+                };
+                (entries > 0).then(|| ConversionWarning::DroppedField(name.clone(), entries))
+            })
+    }
+}
+
+/// Whether a json value is not null or empty
+pub(crate) fn has_content(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Null => false,
+        serde_json::Value::String(s) => !s.is_empty(),
+        serde_json::Value::Array(a) => !a.is_empty(),
+        serde_json::Value::Object(o) => !o.is_empty(),
+        _ => true,
+    }
+}
+
+impl fmt::Display for ConversionWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConversionWarning::DroppedField(name, entries) => {
+                write!(f, "{} with {} entries is not converted", name, entries)
+            }
+            ConversionWarning::SeedNotRepresentableInDescriptor => {
+                write!(f, "the seed is not part of the descriptors")
+            }
+            ConversionWarning::NetworkAssumed(key, encoded, assumed) => write!(
+                f,
+                "the prefix of {} is for {}, but it is used for {} as requested",
+                key, encoded, assumed
+            ),
+            ConversionWarning::ChangeDescriptor(descriptor) => write!(
+                f,
+                "{} is the change descriptor, the wallet is built as if the receiving descriptor /0/* was given",
+                descriptor
+            ),
+            ConversionWarning::DuplicateAddress(address, position, first) => write!(
+                f,
+                "the address {} is listed as {} and already as {}, the duplicate was dropped",
+                address, position, first
+            ),
+            ConversionWarning::DuplicateField(name) => write!(
+                f,
+                "the field {} is given more than once, the last one was used",
+                name
+            ),
+            ConversionWarning::SeedExtension(keystore) => write!(
+                f,
+                "the seed of {} has an extension, it restores to other keys without it",
+                keystore
+            ),
+            ConversionWarning::UnsortedKeys => write!(
+                f,
+                "the multisig keys are in keystore order with multi(), the addresses only match wallets using the same order"
+            ),
+            ConversionWarning::KindAssumed(keystore, kind) => write!(
+                f,
+                "the key of {} is used for {} as requested, its prefix doesn't tell the script kind",
+                keystore, kind
+            ),
+        }
+    }
+}
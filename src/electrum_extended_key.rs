@@ -0,0 +1,110 @@
+use crate::{
+    utils::secp, Descriptors, ElectrumExtendedKey, ElectrumExtendedPrivKey, ElectrumExtendedPubKey,
+};
+use bitcoin::bip32::ExtendedPubKey;
+
+/// An electrum extended key that is either public or private. Unlike a `Box<dyn ElectrumExtendedKey>` it needs no
+/// allocation and tells which one it is.
+pub enum ElectrumExtendedKeyEnum {
+    Pub(ElectrumExtendedPubKey),
+    Priv(ElectrumExtendedPrivKey),
+}
+
+impl ElectrumExtendedKeyEnum {
+    /// Whether the key is an xprv
+    pub fn is_private(&self) -> bool {
+        matches!(self, ElectrumExtendedKeyEnum::Priv(_))
+    }
+
+    /// The xpub of the key, derived from the xprv of private keys
+    pub fn as_public(&self) -> ElectrumExtendedPubKey {
+        match self {
+            ElectrumExtendedKeyEnum::Pub(xpub) => {
+                ElectrumExtendedPubKey::new(*xpub.xpub(), xpub.kind().to_string())
+            }
+            ElectrumExtendedKeyEnum::Priv(xprv) => {
+                let secp = secp();
+                ElectrumExtendedPubKey::new(
+                    ExtendedPubKey::from_priv(secp, xprv.xprv()),
+                    xprv.kind().to_string(),
+                )
+            }
+        }
+    }
+}
+
+impl ElectrumExtendedKey for ElectrumExtendedKeyEnum {
+    /// Returns the kind
+    fn kind(&self) -> &str {
+        match self {
+            ElectrumExtendedKeyEnum::Pub(xpub) => xpub.kind(),
+            ElectrumExtendedKeyEnum::Priv(xprv) => xprv.kind(),
+        }
+    }
+
+    /// Returns the xpub or xprv as String
+    fn xkey_str(&self) -> String {
+        match self {
+            ElectrumExtendedKeyEnum::Pub(xpub) => xpub.xkey_str(),
+            ElectrumExtendedKeyEnum::Priv(xprv) => xprv.xkey_str(),
+        }
+    }
+
+    /// Returns internal and external descriptor
+    fn to_descriptors(&self) -> Descriptors {
+        match self {
+            ElectrumExtendedKeyEnum::Pub(xpub) => xpub.to_descriptors(),
+            ElectrumExtendedKeyEnum::Priv(xprv) => xprv.to_descriptors(),
+        }
+    }
+}
+
+impl From<ElectrumExtendedPubKey> for ElectrumExtendedKeyEnum {
+    fn from(xpub: ElectrumExtendedPubKey) -> Self {
+        ElectrumExtendedKeyEnum::Pub(xpub)
+    }
+}
+
+impl From<ElectrumExtendedPrivKey> for ElectrumExtendedKeyEnum {
+    fn from(xprv: ElectrumExtendedPrivKey) -> Self {
+        ElectrumExtendedKeyEnum::Priv(xprv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_same_as_trait_objects() {
+        let elxprv = "yprvAHwhK6RbpuS3dgCYHM5jc2ZvEKd7Bi61u9FVhYMpgMSuZS613T1xxQeKTffhrHY79hZ5PsskBjcc6C2V7DrnsMsNaGDaWev3GLRQRgV7hxF";
+        let elxpub = "vpub5VXaSncXqxLbdmvrC4Y8z9CszPwuEscADoetWhfrxDFzPUbL5nbVtanYDkrVEutkv9n5A5aCcvRC9swbjDKgHjCZ2tAeae8VsBuPbS8KpXv";
+        let boxed: [Box<dyn ElectrumExtendedKey>; 2] = [
+            Box::new(ElectrumExtendedPrivKey::from_str(elxprv).unwrap()),
+            Box::new(ElectrumExtendedPubKey::from_str(elxpub).unwrap()),
+        ];
+        let keys = [
+            ElectrumExtendedKeyEnum::from(ElectrumExtendedPrivKey::from_str(elxprv).unwrap()),
+            ElectrumExtendedKeyEnum::from(ElectrumExtendedPubKey::from_str(elxpub).unwrap()),
+        ];
+        for (boxed, key) in boxed.iter().zip(&keys) {
+            assert_eq!(boxed.kind(), key.kind());
+            assert_eq!(boxed.xkey_str(), key.xkey_str());
+            assert_eq!(boxed.to_descriptors(), key.to_descriptors());
+        }
+        assert!(keys[0].is_private());
+        assert!(!keys[1].is_private());
+    }
+
+    #[test]
+    fn test_as_public() {
+        let xprv = ElectrumExtendedKeyEnum::from(ElectrumExtendedPrivKey::from_str("yprvAHwhK6RbpuS3dgCYHM5jc2ZvEKd7Bi61u9FVhYMpgMSuZS613T1xxQeKTffhrHY79hZ5PsskBjcc6C2V7DrnsMsNaGDaWev3GLRQRgV7hxF").unwrap());
+        let xpub = xprv.as_public();
+        assert_eq!(xpub.kind(), "sh(wpkh");
+        assert_eq!(xpub.xpub().to_string(), "xpub6C6nQwHaWbSrzs5tZ1q7m5R9cPK9eYpNMFesiXsYrgc1P8bvLLAet9JfHjYXKjToD8cBRswJXXbbFpXgwsswVPAZzKMa1jUp2kVkGVUaJa7");
+        let elxpub = "vpub5VXaSncXqxLbdmvrC4Y8z9CszPwuEscADoetWhfrxDFzPUbL5nbVtanYDkrVEutkv9n5A5aCcvRC9swbjDKgHjCZ2tAeae8VsBuPbS8KpXv";
+        let key = ElectrumExtendedKeyEnum::from(ElectrumExtendedPubKey::from_str(elxpub).unwrap());
+        assert_eq!(key.as_public().electrum_xpub().unwrap(), elxpub);
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{Descriptors, Electrum2DescriptorError, ElectrumExtendedKey};
+use crate::{
+    script_kind::override_kind, utils::clean_key, Descriptors, Electrum2DescriptorError,
+    ElectrumExtendedKey, ScriptKind,
+};
 use bitcoin::base58;
 use bitcoin::bip32::{ChainCode, ChildNumber, ExtendedPubKey, Fingerprint};
 use bitcoin::secp256k1;
@@ -90,7 +93,7 @@ impl FromStr for ElectrumExtendedPubKey {
     type Err = Electrum2DescriptorError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let data = base58::decode_check(s)?;
+        let data = base58::decode_check(&clean_key(s)?)?;
 
         if data.len() != 78 {
             return Err(Electrum2DescriptorError::Base58Error(
@@ -146,13 +149,26 @@ impl ElectrumExtendedPubKey {
         &self.xpub
     }
 
+    /// Re-interpret the key material for another network, regardless of the prefix it was encoded with
+    pub fn with_network(mut self, network: Network) -> Self {
+        self.xpub.network = network;
+        self
+    }
+
+    /// Use the key for `kind`, for plain xpub keys whose prefix doesn't tell the script kind, e.g. the keys of
+    /// segwit wallets restored from bip-0039 phrases. Fails if the slip-0132 prefix is for another kind.
+    pub fn with_kind(mut self, kind: ScriptKind) -> Result<Self, Electrum2DescriptorError> {
+        self.kind = override_kind(&self.xkey_str(), &self.kind, kind)?.to_string();
+        Ok(self)
+    }
+
     /// converts to electrum format
     pub fn electrum_xpub(&self) -> Result<String, Electrum2DescriptorError> {
         let sentinels = initialize_sentinels();
         let sentinel = sentinels
             .iter()
             .find(|sent| sent.1 == self.xpub.network && sent.2 == self.kind)
-            .ok_or_else(|| Electrum2DescriptorError::UnknownType)?;
+            .ok_or(Electrum2DescriptorError::UnknownType)?;
         let mut data = Vec::from(&sentinel.0[..]);
         data.push(self.xpub.depth);
         data.extend(self.xpub.parent_fingerprint.as_bytes());
@@ -237,6 +253,7 @@ mod tests {
         let secp = Secp256k1::verification_only();
         let first_address = descriptor
             .at_derivation_index(0)
+            .unwrap()
             .derived_descriptor(&secp)
             .unwrap()
             .address(miniscript::bitcoin::Network::Bitcoin)
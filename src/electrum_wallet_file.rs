@@ -1,11 +1,33 @@
 use crate::{
-    Descriptors, Electrum2DescriptorError, ElectrumExtendedKey, ElectrumExtendedPrivKey,
-    ElectrumExtendedPubKey,
+    checksum,
+    conversion_warning::has_content,
+    encryption,
+    seed::{self, SeedType},
+    utils::{clean_key, clean_text, kind_from_address_str, secp, AddressKind},
+    ConversionWarning, Descriptors, Electrum2DescriptorError, ElectrumExtendedKey,
+    ElectrumExtendedKeyEnum, ElectrumExtendedPrivKey, ElectrumExtendedPubKey, ScriptKind,
+};
+use bitcoin::{
+    bip32::{ChildNumber, DerivationPath, ExtendedPrivKey, ExtendedPubKey, Fingerprint},
+    opcodes::all::OP_CHECKMULTISIG,
+    script::Builder,
+    Address, Network, PublicKey,
 };
-use bitcoin::bip32::{ExtendedPrivKey, ExtendedPubKey};
 use regex::Regex;
-use serde::{de, ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
-use std::{fmt, io::BufReader, path::Path, str::FromStr, string::ToString};
+use serde::{de, ser, ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    convert::TryFrom,
+    ffi::{OsStr, OsString},
+    fmt,
+    fs::OpenOptions,
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    ops::Range,
+    path::Path,
+    str::FromStr,
+    string::ToString,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 /// Representation of an electrum wallet file. Has custom serialization and de-serialization routines to more accurately represent what we need, and the electrum wallet file format.
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -13,8 +35,80 @@ pub struct ElectrumWalletFile {
     addresses: Addresses,
     wallet_type: WalletType,
     keystores: Vec<Keystore>,
+    seed_version: Option<u32>,
+    /// Parsed watch-only, the secrets of the wallet file were skipped
+    watch_only_copy: bool,
+    /// Why keystores were left out when parsing partially, the wallet is incomplete if there are any
+    keystore_errors: Vec<String>,
+    /// The keystore slots of a multisig wallet without xpub, like `x3/`: its setup wasn't finished
+    unfinished_slots: Vec<String>,
+    /// What was repaired when parsing leniently, e.g. the duplicate addresses that were dropped
+    parse_warnings: Vec<ConversionWarning>,
+    /// The fields we don't model, kept verbatim so that rewriting a wallet doesn't lose e.g. lightning channels
+    extra: BTreeMap<String, serde_json::Value>,
+    /// The network of the addresses when the keys don't tell: regtest and signet use the keys of testnet.
+    /// Not stored by electrum, the addresses of a regtest wallet file reveal it.
+    network: Option<Network>,
+    /// The order of the keys of multisig scripts, electrum always sorts them
+    key_order: KeyOrder,
+}
+
+/// The order of the keys in the scripts of a multisig wallet
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyOrder {
+    /// Sorted as bip-0067 says, `sortedmulti()`, like electrum does
+    #[default]
+    Sorted,
+    /// In the order of the keystores, `multi()`. The addresses only match wallets that used the same order.
+    AsStored,
 }
 
+/// How many addresses beyond the stored ones are derived to find where deleted addresses shifted the later ones
+const DELETION_GAP: usize = 20;
+
+/// Numbers the temporary files of the writes of this process
+static TMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Top level fields holding secret material, in addition to the keystores: the lightning keys, the channels and
+/// channel backups with their private keys and the payment preimages
+pub const SECRET_FIELDS: &[&str] = &[
+    "channel_backups",
+    "channels",
+    "imported_channel_backups",
+    "lightning_preimages",
+    "lightning_privkey2",
+    "lightning_xprv",
+    "onchain_channel_backups",
+];
+
+/// Keystore fields holding secret material
+pub const SECRET_KEYSTORE_FIELDS: &[&str] = &["keypairs", "passphrase", "seed", "xprv"];
+
+/// The type of the keystore of an imported wallet
+pub(crate) const IMPORTED_KEYSTORE: &str = "imported";
+
+/// The fields electrum rebuilds from the transactions
+pub(crate) const HISTORY_FIELDS: &[&str] = &[
+    "addr_history",
+    "prevouts_by_scripthash",
+    "spent_outpoints",
+    "transactions",
+    "tx_fees",
+    "txi",
+    "txo",
+    "verified_tx3",
+];
+
+/// The fields of the payments made and requested, removed with the history
+const PAYMENT_FIELDS: &[&str] = &["fiat_value", "invoices", "payment_requests"];
+
+/// A key of a descriptor: the optional origin `[fingerprint/derivation]`, the extended key and the derivation after it.
+/// It is matched right after the `(` or `,` before it, see [`KEY_DELIMITER`].
+const KEY_EXPRESSION: &str = r#"(?:\[([^\]]*)\])?([txyzuvYZUV]p(?:ub|rv)[0-9A-Za-z]+)([^,)]*)"#;
+
+/// What comes before a key of a descriptor, so that no key is matched inside another argument
+const KEY_DELIMITER: &str = "[(,]";
+
 impl ElectrumWalletFile {
     /// Construct a wallet
     pub fn new(
@@ -26,20 +120,152 @@ impl ElectrumWalletFile {
                 addresses: Addresses::new(),
                 wallet_type: WalletType::Standard,
                 keystores: keystores.to_vec(),
+                seed_version: None,
+                extra: BTreeMap::new(),
+                watch_only_copy: false,
+                keystore_errors: Vec::new(),
+                unfinished_slots: Vec::new(),
+                parse_warnings: Vec::new(),
+                network: None,
+                key_order: KeyOrder::default(),
             }
-        } else if keystores.len() >= 255 {
-            return Err(Electrum2DescriptorError::TooManyKeyStores(keystores.len()));
         } else {
             ElectrumWalletFile {
                 addresses: Addresses::new(),
-                wallet_type: WalletType::Multisig(min_signatures, keystores.len() as u8),
+                wallet_type: WalletType::multisig(min_signatures.into(), keystores.len())?,
                 keystores: keystores.to_vec(),
+                seed_version: None,
+                extra: BTreeMap::new(),
+                watch_only_copy: false,
+                keystore_errors: Vec::new(),
+                unfinished_slots: Vec::new(),
+                parse_warnings: Vec::new(),
+                network: None,
+                key_order: KeyOrder::default(),
             }
         };
         wallet.validate()?;
+        for keystore in &wallet.keystores {
+            keystore.check_derivation()?;
+        }
         Ok(wallet)
     }
 
+    /// Construct an imported wallet from its keystore, which holds the key pairs, and its addresses, as electrum maps
+    /// each one to its public key and script type
+    pub(crate) fn new_imported(keystore: Keystore, addresses: serde_json::Value) -> Self {
+        ElectrumWalletFile {
+            addresses: Addresses::new(),
+            wallet_type: WalletType::Imported,
+            keystores: vec![keystore],
+            seed_version: None,
+            extra: BTreeMap::from([("addresses".to_string(), addresses)]),
+            watch_only_copy: false,
+            keystore_errors: Vec::new(),
+            unfinished_slots: Vec::new(),
+            parse_warnings: Vec::new(),
+            network: None,
+            key_order: KeyOrder::default(),
+        }
+    }
+
+    /// Construct a multisig wallet from the slip-0132 extended keys of the cosigners, like Zpub or Vprv.
+    /// The script kind is inferred from the prefixes, so all keys need the same multisig prefix and network.
+    /// For plain xpub/tpub keys build the keystores with an explicit kind and use [`ElectrumWalletFile::new`].
+    pub fn new_multisig_from_xpubs(
+        threshold: u8,
+        xpubs: &[&str],
+    ) -> Result<Self, Electrum2DescriptorError> {
+        let keystores = xpubs
+            .iter()
+            .map(|xkey| Keystore::from_electrum_xkey(xkey))
+            .collect::<Result<Vec<_>, _>>()?;
+        let keys = keystores
+            .iter()
+            .map(|ks| ks.xpub().map(|(_xpub, kind, network)| (kind, network)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.len() < 2 {
+            return Err(Electrum2DescriptorError::MultisigFewSigners);
+        }
+        let (kind, network) = keys[0];
+        if !kind.is_multisig() {
+            return Err(Electrum2DescriptorError::AmbiguousScriptKind(
+                xpubs[0].to_string(),
+            ));
+        }
+        if let Some((i, _)) = keys.iter().enumerate().find(|(_, k)| k.0 != kind) {
+            return Err(Electrum2DescriptorError::MixedScriptKinds(
+                xpubs[0].to_string(),
+                xpubs[i].to_string(),
+            ));
+        }
+        if let Some((i, _)) = keys.iter().enumerate().find(|(_, k)| k.1 != network) {
+            return Err(Electrum2DescriptorError::MixedNetworks(
+                xpubs[0].to_string(),
+                xpubs[i].to_string(),
+            ));
+        }
+
+        ElectrumWalletFile::new(&keystores, threshold)
+    }
+
+    /// Construct a standard wallet from a slip-0132 extended key like zpub or yprv, the same as
+    /// [`ElectrumWalletFile::from_descriptor`] would from the equivalent descriptor. The script kind is inferred from
+    /// the prefix; plain xpub/tpub and xprv/tprv keys need `kind`, for the others it must agree with the prefix.
+    /// Keys with the prefixes of multisig cosigners like Zpub are refused.
+    pub fn from_xpub(
+        xkey: &str,
+        kind: Option<ScriptKind>,
+    ) -> Result<Self, Electrum2DescriptorError> {
+        let xkey = &clean_key(xkey)?;
+        let key = match ElectrumExtendedPrivKey::from_str(xkey) {
+            Ok(exprv) => ElectrumExtendedKeyEnum::Priv(exprv),
+            Err(_) => ElectrumExtendedKeyEnum::Pub(
+                ElectrumExtendedPubKey::from_str(xkey).map_err(|e| match e {
+                    Electrum2DescriptorError::Base58Error(
+                        bitcoin::base58::Error::InvalidExtendedKeyVersion(_),
+                    ) => Electrum2DescriptorError::UnknownKeyPrefix(xkey.to_string()),
+                    e => e,
+                })?,
+            ),
+        };
+        let prefix_kind = ScriptKind::from_str(key.kind())?;
+        let kind = match (prefix_kind, kind) {
+            (ScriptKind::Standard, None) => {
+                return Err(Electrum2DescriptorError::ScriptKindRequired(
+                    xkey.to_string(),
+                ))
+            }
+            (ScriptKind::Standard, Some(kind)) => kind,
+            (prefix_kind, Some(kind)) if kind != prefix_kind => {
+                return Err(Electrum2DescriptorError::ScriptKindConflict(
+                    xkey.to_string(),
+                    prefix_kind.to_string(),
+                    kind.to_string(),
+                ))
+            }
+            (prefix_kind, _) => prefix_kind,
+        };
+        if kind.is_multisig() {
+            return Err(Electrum2DescriptorError::ScriptKindConflict(
+                xkey.to_string(),
+                kind.to_string(),
+                "a standard wallet".to_string(),
+            ));
+        }
+        let descriptor = match key {
+            ElectrumExtendedKeyEnum::Priv(exprv) => {
+                ElectrumExtendedPrivKey::new(*exprv.xprv(), kind.as_kind_str().to_string())
+                    .to_descriptors()
+            }
+            ElectrumExtendedKeyEnum::Pub(expub) => {
+                ElectrumExtendedPubKey::new(*expub.xpub(), kind.as_kind_str().to_string())
+                    .to_descriptors()
+            }
+        };
+        ElectrumWalletFile::from_descriptor(&descriptor.external)
+    }
+
     /// Getter for addresses
     pub fn addresses(&self) -> &Addresses {
         &self.addresses
@@ -55,160 +281,1843 @@ impl ElectrumWalletFile {
         &self.keystores
     }
 
-    /// Parse an electrum wallet file
-    pub fn from_file(wallet_file: &Path) -> Result<Self, Electrum2DescriptorError> {
-        let file = std::fs::File::open(wallet_file)?;
-        let reader = BufReader::new(file);
-        let wallet = serde_json::from_reader(reader)?;
-        Ok(wallet)
+    /// Set the label of the keystore of a standard wallet. The cosigners of multisig wallets are labelled individually.
+    pub fn set_label(&mut self, label: &str) -> Result<(), Electrum2DescriptorError> {
+        self.check_complete()?;
+        match self.wallet_type {
+            WalletType::Standard | WalletType::Imported => {
+                self.keystores[0].label = label.into();
+                Ok(())
+            }
+            WalletType::Multisig(_) => Err(Electrum2DescriptorError::GenericBorrow(
+                "Only the keystore of a standard wallet can be labelled, label the cosigners of a multisig wallet individually",
+            )),
+        }
+    }
+
+    /// Whether the wallet was parsed watch-only, so that it lacks the secrets of its wallet file
+    pub fn is_watch_only_copy(&self) -> bool {
+        self.watch_only_copy
+    }
+
+    /// Whether keystores were left out because they could not be parsed, see [`ParseOptions::partial`], or because
+    /// the setup of the multisig wallet wasn't finished, see [`ElectrumWalletFile::unfinished_slots`].
+    /// Only the addresses and the wallet_type of an incomplete wallet can be relied upon.
+    pub fn is_incomplete(&self) -> bool {
+        !self.keystore_errors.is_empty() || !self.unfinished_slots.is_empty()
+    }
+
+    /// The keystore slots, like `x3/`, that are missing or have an empty xpub. Electrum's wizard leaves them when
+    /// the creation of a multisig wallet is interrupted before all cosigners were added. The keystores of the
+    /// wallet are the finished ones.
+    pub fn unfinished_slots(&self) -> &[String] {
+        &self.unfinished_slots
+    }
+
+    /// Why keystores were left out when parsing partially
+    pub fn keystore_errors(&self) -> &[String] {
+        &self.keystore_errors
+    }
+
+    /// What was repaired when parsing leniently, see [`ParseOptions::strict`], and the script kinds assumed with
+    /// [`ElectrumWalletFile::assume_kind`]
+    pub fn parse_warnings(&self) -> &[ConversionWarning] {
+        &self.parse_warnings
+    }
+
+    /// Fail for an incomplete wallet, whose keystores are needed
+    pub(crate) fn check_complete(&self) -> Result<(), Electrum2DescriptorError> {
+        self.check_parsed()?;
+        if self.unfinished_slots.is_empty() {
+            Ok(())
+        } else {
+            Err(Electrum2DescriptorError::UnfinishedSetup(
+                self.unfinished_slots.clone(),
+            ))
+        }
+    }
+
+    /// Fail unless the keystores could be parsed and at least one is finished, enough for what the first keystore
+    /// tells, like the network and script kind
+    pub(crate) fn check_parsed(&self) -> Result<(), Electrum2DescriptorError> {
+        if let Some(e) = self.keystore_errors.first() {
+            return Err(Electrum2DescriptorError::IncompleteWallet(e.clone()));
+        }
+        if self.keystores.is_empty() {
+            return Err(Electrum2DescriptorError::UnfinishedSetup(
+                self.unfinished_slots.clone(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The `addr()` descriptors of the receiving and then the change addresses listed in the wallet file.
+    /// Unlike the other descriptors, they are available for incomplete wallets.
+    pub fn addr_descriptors(&self) -> Vec<String> {
+        self.addresses
+            .receiving
+            .iter()
+            .chain(&self.addresses.change)
+            .map(|address| format!("addr({})", address))
+            .collect()
+    }
+
+    /// Getter for the fields that are kept verbatim
+    pub fn extra(&self) -> &BTreeMap<String, serde_json::Value> {
+        &self.extra
+    }
+
+    /// A field that is kept verbatim, e.g. `stored_height`
+    pub fn raw_field(&self, key: &str) -> Option<&serde_json::Value> {
+        self.extra.get(key)
+    }
+
+    /// Set a field that is kept verbatim, e.g. clear `transactions` to make electrum rescan. Returns the previous value.
+    /// The fields the crate models can only be changed with their typed api.
+    pub fn set_raw_field(
+        &mut self,
+        key: &str,
+        value: serde_json::Value,
+    ) -> Result<Option<serde_json::Value>, Electrum2DescriptorError> {
+        let api = match key {
+            "addresses" => Some("use extend_addresses or repair_addresses"),
+            "wallet_type" | "keystore" => Some("build the wallet with ElectrumWalletFile::new"),
+            "seed_version" => Some("electrum sets it when upgrading the wallet file"),
+            key if Regex::new(r"^x\d+/$")?.is_match(key) => {
+                Some("build the wallet with ElectrumWalletFile::new")
+            }
+            _ => None,
+        };
+        if let Some(api) = api {
+            return Err(Electrum2DescriptorError::ModeledField(key.to_string(), api));
+        }
+        Ok(self.extra.insert(key.to_string(), value))
+    }
+
+    /// A copy without the transaction history, the invoices and the payment requests, e.g. to share the wallet with
+    /// an accountant. The addresses stay in `addr_history` with an empty history, so that electrum rescans them.
+    /// The keys, the addresses and the settings are kept, the labels unless `keep_labels` is false.
+    pub fn scrub_history(&self, keep_labels: bool) -> ElectrumWalletFile {
+        let mut wallet = self.clone();
+        for field in HISTORY_FIELDS.iter().chain(PAYMENT_FIELDS) {
+            let value = wallet.extra.remove(*field);
+            if let (&"addr_history", Some(serde_json::Value::Object(history))) = (field, value) {
+                let stubs = history
+                    .into_iter()
+                    .map(|(address, _)| (address, serde_json::Value::Array(Vec::new())))
+                    .collect();
+                wallet
+                    .extra
+                    .insert("addr_history".to_string(), serde_json::Value::Object(stubs));
+            }
+        }
+        if !keep_labels {
+            wallet.extra.remove("labels");
+        }
+        wallet
+    }
+
+    /// Getter for the seed_version, which tells electrum how to upgrade the wallet file
+    pub fn seed_version(&self) -> Option<u32> {
+        self.seed_version
+    }
+
+    /// Parse an electrum wallet file
+    pub fn from_file(wallet_file: &Path) -> Result<Self, Electrum2DescriptorError> {
+        log_debug!("reading the wallet file {}", wallet_file.display());
+        ElectrumWalletFile::from_file_with_options(wallet_file, &ParseOptions::default())
+    }
+
+    /// Parse an electrum wallet file without ever reading its secrets into memory.
+    /// As the result lacks them, it refuses to replace existing files.
+    pub fn from_file_watch_only(wallet_file: &Path) -> Result<Self, Electrum2DescriptorError> {
+        let file = std::fs::File::open(wallet_file)?;
+        ElectrumWalletFile::from_reader_watch_only(BufReader::new(file))
+    }
+
+    /// Parse an electrum wallet without ever reading its secrets into memory
+    pub fn from_reader_watch_only<R: Read>(reader: R) -> Result<Self, Electrum2DescriptorError> {
+        let options = ParseOptions {
+            watch_only: true,
+            ..ParseOptions::default()
+        };
+        ElectrumWalletFile::from_reader_with_options(reader, &options)
+    }
+
+    /// Parse an electrum wallet file with non default options
+    pub fn from_file_with_options(
+        wallet_file: &Path,
+        options: &ParseOptions,
+    ) -> Result<Self, Electrum2DescriptorError> {
+        let file = std::fs::File::open(wallet_file)?;
+        ElectrumWalletFile::from_reader_with_options(BufReader::new(file), options)
+    }
+
+    /// Parse an electrum wallet with non default options
+    pub fn from_reader_with_options<R: Read>(
+        reader: R,
+        options: &ParseOptions,
+    ) -> Result<Self, Electrum2DescriptorError> {
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+        let wallet = de::DeserializeSeed::deserialize(WalletSeed { options }, &mut deserializer)?;
+        deserializer.end()?;
+        Ok(wallet)
+    }
+
+    /// Parse an electrum wallet file from string with non default options
+    pub fn from_str_with_options(
+        wallet_file: &str,
+        options: &ParseOptions,
+    ) -> Result<Self, Electrum2DescriptorError> {
+        let mut deserializer = serde_json::Deserializer::from_str(wallet_file);
+        let wallet = de::DeserializeSeed::deserialize(WalletSeed { options }, &mut deserializer)?;
+        deserializer.end()?;
+        Ok(wallet)
+    }
+
+    /// Parse an electrum wallet file protected by a password, see [`ElectrumWalletFile::from_slice_with_password`]
+    pub fn from_file_with_password(
+        wallet_file: &Path,
+        password: &str,
+        options: &ParseOptions,
+    ) -> Result<Self, Electrum2DescriptorError> {
+        let data = std::fs::read(wallet_file)?;
+        ElectrumWalletFile::from_slice_with_password(&data, password, options)
+    }
+
+    /// Parse an electrum wallet protected by a password. The file is decrypted if electrum encrypted all of it,
+    /// then the secrets of the keystores are, see [`ElectrumWalletFile::decrypt_secrets`]. Wallets without
+    /// password parse as they do without one. A wrong password fails with
+    /// [`Electrum2DescriptorError::InvalidPassword`].
+    pub fn from_slice_with_password(
+        data: &[u8],
+        password: &str,
+        options: &ParseOptions,
+    ) -> Result<Self, Electrum2DescriptorError> {
+        log_debug!("decrypting the wallet");
+        let decrypted;
+        let json = if is_encrypted_file(data) {
+            decrypted = encryption::decrypt_wallet_file(data, password)?;
+            &decrypted[..]
+        } else {
+            data
+        };
+        let mut wallet = ElectrumWalletFile::from_reader_with_options(json, options)?;
+        if !options.watch_only {
+            wallet.decrypt_secrets(password)?;
+        }
+        Ok(wallet)
+    }
+
+    /// Decrypt the secrets that electrum encrypted with the wallet password: the xprv, seed, seed extension and
+    /// imported private keys of the keystores. The wallet is then marked as not using encryption, as when the
+    /// password is removed in electrum. A wrong password fails with [`Electrum2DescriptorError::InvalidPassword`]
+    /// and leaves the wallet as it was.
+    pub fn decrypt_secrets(&mut self, password: &str) -> Result<(), Electrum2DescriptorError> {
+        let use_encryption =
+            self.extra.get("use_encryption") == Some(&serde_json::Value::Bool(true));
+        let mut keystores = self.keystores.clone();
+        for keystore in keystores
+            .iter_mut()
+            .filter(|ks| use_encryption || ks.has_encrypted_secrets())
+        {
+            keystore.decrypt_secrets(password)?;
+        }
+        self.keystores = keystores;
+        if use_encryption {
+            self.extra
+                .insert("use_encryption".to_string(), serde_json::Value::Bool(false));
+        }
+        Ok(())
+    }
+
+    /// Parse an electrum wallet from json, e.g. one embedded in a larger document. The same as parsing its string.
+    pub fn from_value(value: serde_json::Value) -> Result<Self, Electrum2DescriptorError> {
+        ElectrumWalletFile::from_value_with_options(value, &ParseOptions::default())
+    }
+
+    /// Parse an electrum wallet from json with non default options
+    pub fn from_value_with_options(
+        value: serde_json::Value,
+        options: &ParseOptions,
+    ) -> Result<Self, Electrum2DescriptorError> {
+        Ok(de::DeserializeSeed::deserialize(
+            WalletSeed { options },
+            value,
+        )?)
+    }
+
+    /// The wallet as json, as written to a wallet file, e.g. to embed it in a larger document.
+    /// The keys of the json objects are sorted.
+    pub fn to_value(&self) -> Result<serde_json::Value, Electrum2DescriptorError> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    /// Find out the type of a wallet file, and whether it is encrypted, without parsing it completely.
+    /// Only the top level keys are read, the other values are skipped over without parsing them, and reading stops
+    /// as soon as the wallet_type is found.
+    pub fn peek(wallet_file: &Path) -> Result<WalletPeek, Electrum2DescriptorError> {
+        let file = std::fs::File::open(wallet_file)?;
+        ElectrumWalletFile::peek_reader(BufReader::with_capacity(1 << 16, file))
+    }
+
+    /// [`ElectrumWalletFile::peek`] a wallet that isn't a file of its own, e.g. an entry of an archive
+    pub fn peek_reader<R: BufRead>(mut reader: R) -> Result<WalletPeek, Electrum2DescriptorError> {
+        let start = reader.fill_buf()?;
+        if is_encrypted_file(start) {
+            return Ok(WalletPeek {
+                wallet_type: None,
+                encrypted: true,
+            });
+        }
+
+        Ok(WalletPeek {
+            wallet_type: skim_wallet_type(reader)?,
+            encrypted: false,
+        })
+    }
+
+    /// Write as json in the given style
+    pub fn to_json_with(&self, style: JsonStyle) -> Result<String, Electrum2DescriptorError> {
+        let mut json = Vec::new();
+        self.to_writer_with(&mut json, style)?;
+        Ok(String::from_utf8(json).expect("json is utf-8"))
+    }
+
+    /// Write as json in the given style
+    pub fn to_writer_with<W: Write>(
+        &self,
+        writer: W,
+        style: JsonStyle,
+    ) -> Result<(), Electrum2DescriptorError> {
+        if style.sort_keys {
+            // the maps of json values are sorted
+            write_json(writer, &serde_json::to_value(self)?, style.indent)
+        } else {
+            write_json(writer, self, style.indent)
+        }
+    }
+
+    /// Write to a new electrum wallet file, fails if the file already exists
+    pub fn to_file(&self, wallet_file: &Path) -> Result<(), Electrum2DescriptorError> {
+        self.to_file_with_options(wallet_file, &WriteOptions::default())
+    }
+
+    /// Write to an electrum wallet file, replacing an existing one. The previous content is kept as `<name>.bak`,
+    /// or `<name>.bak.1` and so on when earlier backups exist
+    pub fn to_file_overwrite(&self, wallet_file: &Path) -> Result<(), Electrum2DescriptorError> {
+        let options = WriteOptions {
+            overwrite: true,
+            ..WriteOptions::default()
+        };
+        self.to_file_with_options(wallet_file, &options)
+    }
+
+    /// Write to an electrum wallet file with non default options.
+    /// The wallet is written next to the target and atomically moved into place. Without
+    /// [`WriteOptions::overwrite`], a file created at the target in the meantime is never replaced.
+    pub fn to_file_with_options(
+        &self,
+        wallet_file: &Path,
+        options: &WriteOptions,
+    ) -> Result<(), Electrum2DescriptorError> {
+        log_span!("write", "{}", wallet_file.display());
+        // writing would lose the keystores that could not be parsed
+        self.check_complete()?;
+        let name = wallet_file.file_name().ok_or_else(|| {
+            Electrum2DescriptorError::InvalidArguments(format!(
+                "not a file name: {}",
+                wallet_file.display()
+            ))
+        })?;
+        let dir = wallet_file
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        // unique per process and write, concurrent writers to the same target don't share it
+        let mut tmp_name = OsString::from(".");
+        tmp_name.push(name);
+        tmp_name.push(format!(
+            ".{}.{}.tmp",
+            std::process::id(),
+            TMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let tmp_file = dir.join(tmp_name);
+
+        let already_exists =
+            || Electrum2DescriptorError::AlreadyExists(wallet_file.display().to_string());
+        if wallet_file.exists() {
+            if self.watch_only_copy {
+                return Err(Electrum2DescriptorError::WatchOnlyOverwrite(
+                    wallet_file.display().to_string(),
+                ));
+            }
+            if !options.overwrite {
+                return Err(already_exists());
+            }
+        }
+        // a left over from an interrupted write with a reused pid would keep its permissions
+        let _ = std::fs::remove_file(&tmp_file);
+        let written = self.write_new_file(&tmp_file, options).and_then(|_| {
+            if !options.overwrite {
+                // linking fails if the target exists, unlike renaming
+                return match std::fs::hard_link(&tmp_file, wallet_file) {
+                    Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                        Err(already_exists())
+                    }
+                    // e.g. file systems without hard links, creating the target is exclusive as well
+                    Err(_) => self.write_new_file(wallet_file, options),
+                    Ok(()) => Ok(()),
+                };
+            }
+            if !options.no_backup {
+                backup(wallet_file, dir, name)?;
+            }
+            Ok(std::fs::rename(&tmp_file, wallet_file)?)
+        });
+        let _ = std::fs::remove_file(&tmp_file);
+        written?;
+        #[cfg(unix)]
+        if !options.skip_sync {
+            std::fs::File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+
+    /// Write the wallet to a new file, failing if it exists. Wallets with secrets are only readable by the owner on
+    /// unix, elsewhere the file inherits the permissions of its directory.
+    fn write_new_file(
+        &self,
+        path: &Path,
+        options: &WriteOptions,
+    ) -> Result<(), Electrum2DescriptorError> {
+        let mut open_options = OpenOptions::new();
+        open_options.write(true).create_new(true);
+        #[cfg(unix)]
+        if self.has_secrets() {
+            use std::os::unix::fs::OpenOptionsExt;
+            open_options.mode(0o600);
+        }
+        let file = open_options.open(path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::AlreadyExists => {
+                Electrum2DescriptorError::AlreadyExists(path.display().to_string())
+            }
+            _ => e.into(),
+        })?;
+        let mut writer = BufWriter::new(file);
+        self.to_writer_with(&mut writer, options.style)?;
+        let file = writer.into_inner().map_err(|e| e.into_error())?;
+        if !options.skip_sync {
+            file.sync_all()?;
+        }
+        Ok(())
+    }
+
+    /// Whether the wallet holds private material, encrypted or not
+    pub fn has_secrets(&self) -> bool {
+        self.keystores.iter().any(|keystore| {
+            keystore.xprv.is_some()
+                || keystore.seed.is_some()
+                || keystore.extra.get("keypairs").is_some_and(has_content)
+        }) || SECRET_FIELDS
+            .iter()
+            .any(|field| self.extra.get(*field).is_some_and(has_content))
+    }
+
+    /// Construct from an output descriptor. Only the external descriptor is needed, the change descriptor is implied.
+    /// The change descriptor is accepted as well, see [`ElectrumWalletFile::from_descriptor_with_warnings`]. A
+    /// checksum, if present, must match.
+    pub fn from_descriptor(desc: &str) -> Result<Self, Electrum2DescriptorError> {
+        ElectrumWalletFile::from_descriptor_with_opts(desc, &DescriptorOptions::default())
+    }
+
+    /// Construct from an output descriptor with non default options
+    pub fn from_descriptor_with_opts(
+        desc: &str,
+        options: &DescriptorOptions,
+    ) -> Result<Self, Electrum2DescriptorError> {
+        ElectrumWalletFile::from_descriptor_with_warnings(desc, options, &mut Vec::new())
+    }
+
+    /// Construct from an output descriptor, noting in `warnings` how it was interpreted. When given the change
+    /// descriptor `/1/*` instead of the external one, its keys are used as the account keys all the same, unless
+    /// [`DescriptorOptions::strict`]. The keys must not mix the two chains.
+    pub fn from_descriptor_with_warnings(
+        desc: &str,
+        options: &DescriptorOptions,
+        warnings: &mut Vec<ConversionWarning>,
+    ) -> Result<Self, Electrum2DescriptorError> {
+        log_span!("parse_descriptor", "strict: {}", options.strict);
+        let desc = &clean_text(desc);
+        let desc = checksum::strip_checksum(desc)?;
+        let (wallet, change) = ElectrumWalletFile::from_descriptor_of_chain(desc)?;
+        if change {
+            if options.strict {
+                return Err(Electrum2DescriptorError::ChangeDescriptor(desc.to_string()));
+            }
+            warnings.push(ConversionWarning::ChangeDescriptor(desc.to_string()));
+        }
+        let re = Regex::new(r#"\[([0-9a-fA-F]{8})[/\]]"#)?;
+        let mut found = re
+            .captures_iter(desc)
+            .map(|cap| Fingerprint::from_str(&cap[1]))
+            .collect::<Result<Vec<_>, _>>()?;
+        found.extend(wallet.fingerprints().into_iter().filter_map(|(_, fp)| fp));
+        check_expected_fingerprints(&options.expected_fingerprints, &found)?;
+        Ok(wallet)
+    }
+
+    /// Construct from the external or the change output descriptor, as told by the chains of its keys. Returns
+    /// whether it is the change descriptor.
+    fn from_descriptor_of_chain(desc: &str) -> Result<(Self, bool), Electrum2DescriptorError> {
+        let (wallet, change) = if desc.contains("(sortedmulti(") || desc.contains("(multi(") {
+            log_debug!("parsing a multisig descriptor");
+            ElectrumWalletFile::from_descriptor_multisig(desc)
+        } else {
+            log_debug!("parsing a singlesig descriptor");
+            ElectrumWalletFile::from_descriptor_singlesig(desc)
+        }?;
+        wallet.validate()?;
+        Ok((wallet, change))
+    }
+
+    /// Construct the wallets of a file listing descriptors, one per line, optionally labelled as `<label>: <descriptor>`.
+    /// Empty lines and lines starting with `#` are skipped, checksums are verified. The change descriptor of a wallet
+    /// listed next to its external one is for the same wallet, so each wallet is returned once along with its label.
+    /// Fails for a wallet listed twice.
+    pub fn from_descriptor_file(
+        path: &Path,
+    ) -> Result<Vec<(Option<String>, Self)>, Electrum2DescriptorError> {
+        let file = std::fs::File::open(path)?;
+        let mut wallets: Vec<(usize, [bool; 2], Option<String>, Self)> = Vec::new();
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let number = i + 1;
+            let (label, descriptor) = match line.split_once(':') {
+                Some((label, descriptor)) if !label.contains('(') => {
+                    (Some(label.trim().to_string()), descriptor.trim())
+                }
+                _ => (None, line),
+            };
+            let (wallet, change) = parse_descriptor_line(descriptor).map_err(|e| {
+                Electrum2DescriptorError::InvalidDescriptorLine(number, Box::new(e))
+            })?;
+            match wallets.iter_mut().find(|(_, _, _, w)| *w == wallet) {
+                Some((first, listed, _, _)) if listed[change as usize] => {
+                    return Err(Electrum2DescriptorError::DuplicateDescriptor(
+                        number, *first,
+                    ))
+                }
+                Some((_, listed, known_label, _)) => {
+                    listed[change as usize] = true;
+                    if known_label.is_none() {
+                        *known_label = label;
+                    }
+                }
+                None => {
+                    let mut listed = [false; 2];
+                    listed[change as usize] = true;
+                    wallets.push((number, listed, label, wallet));
+                }
+            }
+        }
+        Ok(wallets
+            .into_iter()
+            .map(|(_, _, label, wallet)| (label, wallet))
+            .collect())
+    }
+
+    /// Check that each of the `expected` fingerprints is the root fingerprint of a keystore, to make sure the wallet
+    /// is made of the right master keys. Fails listing the fingerprints found otherwise.
+    pub fn check_fingerprints(
+        &self,
+        expected: &[Fingerprint],
+    ) -> Result<(), Electrum2DescriptorError> {
+        let found = self
+            .fingerprints()
+            .into_iter()
+            .filter_map(|(_, fp)| fp)
+            .collect::<Vec<_>>();
+        check_expected_fingerprints(expected, &found)
+    }
+
+    /// The master fingerprint of each keystore slot, see [`Keystore::fingerprint`]: the stored root fingerprint or
+    /// the computed one of a master key, None when it can't be known
+    pub fn fingerprints(&self) -> Vec<(usize, Option<Fingerprint>)> {
+        self.keystores
+            .iter()
+            .map(Keystore::fingerprint)
+            .enumerate()
+            .collect()
+    }
+
+    /// The keystore whose keys a fingerprint from a psbt or a coordinator refers to. The master fingerprints of
+    /// [`ElectrumWalletFile::fingerprints`] are matched first, a stored root fingerprint taking precedence over
+    /// the one computed from a master key. With `account_fallback`, the fingerprint of the account xpub itself
+    /// is tried last, as some tools put it in origins instead of the master fingerprint.
+    pub fn keystore_for_fingerprint(
+        &self,
+        fingerprint: Fingerprint,
+        account_fallback: bool,
+    ) -> Option<(usize, &Keystore)> {
+        let master = self
+            .fingerprints()
+            .into_iter()
+            .find(|(_, fp)| *fp == Some(fingerprint))
+            .map(|(i, _)| i);
+        let account = || {
+            self.keystores.iter().position(|ks| {
+                ks.xpub()
+                    .map(|(xpub, _, _)| xpub.fingerprint() == fingerprint)
+                    .unwrap_or(false)
+            })
+        };
+        let index = master.or_else(|| account_fallback.then(account).flatten())?;
+        Some((index, &self.keystores[index]))
+    }
+
+    /// The indices of the keystores that can sign without external help: they hold an unencrypted xprv and
+    /// aren't hardware wallets
+    pub fn signing_keystores(&self) -> Vec<usize> {
+        self.keystores
+            .iter()
+            .enumerate()
+            .filter(|(_, ks)| ks.can_sign() && !ks.is_hardware())
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Whether any keystore of the wallet can sign, see [`ElectrumWalletFile::signing_keystores`]
+    pub fn can_sign(&self) -> bool {
+        !self.signing_keystores().is_empty()
+    }
+
+    /// The signing keystores, failing when there are none. The error tells encrypted keys apart from watch-only
+    /// wallets, naming the keystores to decrypt.
+    pub(crate) fn check_can_sign(&self) -> Result<Vec<usize>, Electrum2DescriptorError> {
+        let signing = self.signing_keystores();
+        if !signing.is_empty() {
+            return Ok(signing);
+        }
+        let encrypted = self
+            .keystores
+            .iter()
+            .enumerate()
+            .filter(|(_, ks)| ks.has_encrypted_secrets())
+            .map(|(i, _)| self.keystore_name(i))
+            .collect::<Vec<_>>();
+        if encrypted.is_empty() {
+            Err(Electrum2DescriptorError::NoPrivateKeys)
+        } else {
+            Err(Electrum2DescriptorError::SecretsEncrypted(
+                encrypted.join(", "),
+            ))
+        }
+    }
+
+    /// The name of a keystore slot in the wallet file: `keystore` for singlesig, `x1/`, `x2/`... for multisig
+    pub(crate) fn keystore_name(&self, index: usize) -> String {
+        match self.wallet_type {
+            WalletType::Standard | WalletType::Imported => "keystore".to_string(),
+            WalletType::Multisig(_) => format!("x{}/", index + 1),
+        }
+    }
+
+    /// Construct from a single signature output descriptor. Only the external descriptor is needed, the change descriptor is implied.
+    fn from_descriptor_singlesig(desc: &str) -> Result<(Self, bool), Electrum2DescriptorError> {
+        let re = Regex::new(&format!(
+            r#"^(pkh|sh\(wpkh|sh\(wsh|wpkh|wsh|tr){}{}\)+$"#,
+            KEY_DELIMITER, KEY_EXPRESSION
+        ))?;
+        let (keystore, change) = match re.captures(desc) {
+            Some(cap) => {
+                let (keystore, change) =
+                    parse_key_expression(&cap[1], cap.get(2), &cap[3], &cap[4])?;
+                if keystore.chains.is_some() {
+                    return Err(Electrum2DescriptorError::UnsupportedKeyPath(
+                        cap[3].to_string(),
+                        format!("{:?}", &cap[4]),
+                        "singlesig keys derive /0/* or /<0;1>/*",
+                    ));
+                }
+                (keystore, is_change_descriptor(desc, &[change])?)
+            }
+            None => {
+                return Err(Electrum2DescriptorError::UnknownDescriptorFormat(
+                    desc.to_string(),
+                ))
+            }
+        };
+
+        let wallet = ElectrumWalletFile {
+            addresses: Addresses::new(),
+            keystores: vec![keystore],
+            wallet_type: WalletType::Standard,
+            seed_version: None,
+            extra: BTreeMap::new(),
+            watch_only_copy: false,
+            keystore_errors: Vec::new(),
+            unfinished_slots: Vec::new(),
+            parse_warnings: Vec::new(),
+            network: None,
+            key_order: KeyOrder::default(),
+        };
+        Ok((wallet, change))
+    }
+
+    /// Construct from a multisig output descriptor. Only the external descriptor is needed, the change descriptor is implied.
+    fn from_descriptor_multisig(desc: &str) -> Result<(Self, bool), Electrum2DescriptorError> {
+        let re = Regex::new(
+            r#"^(sh|sh\(wsh|wsh)\((?:sorted)?multi\((\d+),((?:\[[^\]]*\])?[txyzuvYZUV]p(ub|rv)[0-9A-Za-z]+[^,)]*,?)+\)+$"#,
+        )?;
+        let balanced = desc.matches('(').count() == desc.matches(')').count();
+        let captures = re.captures(desc).filter(|_| balanced).map(|captures| {
+            captures
+                .iter()
+                .skip(1)
+                .take(2)
+                .flatten()
+                .map(|c| c.as_str())
+                .collect::<Vec<_>>()
+        });
+        if let Some([kind, x]) = captures.as_deref() {
+            let kind = match *kind {
+                "wsh" => "wsh",
+                "sh" => "pkh",
+                "sh(wsh" => "sh(wsh",
+                _ => {
+                    return Err(Electrum2DescriptorError::UnknownScriptKind(
+                        kind.to_string(),
+                    ))
+                }
+            };
+            let re = Regex::new(&format!("{}{}", KEY_DELIMITER, KEY_EXPRESSION))?;
+            let (keystores, changes): (Vec<_>, Vec<_>) = re
+                .captures_iter(desc)
+                .map(|cap| parse_key_expression(kind, cap.get(1), &cap[2], &cap[3]))
+                .collect::<Result<Vec<_>, Electrum2DescriptorError>>()?
+                .into_iter()
+                .unzip();
+            // each argument after the threshold is a key
+            if keystores.len() != desc.matches(',').count() {
+                return Err(Electrum2DescriptorError::UnknownDescriptorFormat(
+                    desc.to_string(),
+                ));
+            }
+            for (i, keystore) in keystores.iter().enumerate() {
+                if keystores[..i].iter().any(|other| other.same_key(keystore)) {
+                    return Err(Electrum2DescriptorError::DuplicateCosigner(
+                        keystore.xpub.clone(),
+                    ));
+                }
+            }
+            let change = is_change_descriptor(desc, &changes)?;
+            if keystores.len() < 2 {
+                return Err(Electrum2DescriptorError::MultisigFewSigners);
+            }
+            let x = x
+                .parse()
+                .map_err(|_| Electrum2DescriptorError::UnknownDescriptorFormat(desc.to_string()))?;
+            let wallet_type = WalletType::multisig(x, keystores.len())?;
+            let key_order = if desc.contains("sortedmulti(") {
+                KeyOrder::Sorted
+            } else {
+                KeyOrder::AsStored
+            };
+
+            let wallet = ElectrumWalletFile {
+                addresses: Addresses::new(),
+                keystores,
+                wallet_type,
+                seed_version: None,
+                extra: BTreeMap::new(),
+                watch_only_copy: false,
+                keystore_errors: Vec::new(),
+                unfinished_slots: Vec::new(),
+                parse_warnings: Vec::new(),
+                network: None,
+                key_order,
+            };
+            Ok((wallet, change))
+        } else {
+            Err(Electrum2DescriptorError::UnknownDescriptorFormat(format!(
+                "{:?}",
+                captures
+            )))
+        }
+    }
+
+    /// Re-interpret the keys for `network`, for keys that were exported with the prefix of the wrong network.
+    /// The addresses are derived again for the new network. Returns a warning for each key whose prefix disagreed.
+    pub fn assume_network(
+        &mut self,
+        network: Network,
+    ) -> Result<Vec<ConversionWarning>, Electrum2DescriptorError> {
+        self.check_complete()?;
+        let mut warnings = Vec::new();
+        for keystore in &mut self.keystores {
+            let (_xpub, _kind, encoded) = keystore.xpub()?;
+            if !shares_keys(encoded, network) {
+                warnings.push(ConversionWarning::NetworkAssumed(
+                    keystore.xpub.clone(),
+                    encoded,
+                    network,
+                ));
+                *keystore = keystore.with_network(network)?;
+            }
+        }
+        if !warnings.is_empty() {
+            let addresses = Addresses {
+                change: self.derive_addresses(true, 0, self.addresses.change.len() as u32)?,
+                receiving: self.derive_addresses(
+                    false,
+                    0,
+                    self.addresses.receiving.len() as u32,
+                )?,
+            };
+            self.replace_addresses(addresses);
+        }
+        if self.network()? != network {
+            self.set_network(network)?;
+        }
+        Ok(warnings)
+    }
+
+    /// Use the keys for `kind`, for wallets whose plain xpub keys don't tell the script kind, e.g. segwit wallets
+    /// restored from bip-0039 phrases. Singlesig kinds stand for their multisig counterparts in multisig wallets.
+    /// The addresses are derived again. Each overridden keystore is noted in the [`ElectrumWalletFile::parse_warnings`].
+    /// Fails if a slip-0132 prefix is for another kind.
+    pub fn assume_kind(&mut self, kind: ScriptKind) -> Result<(), Electrum2DescriptorError> {
+        self.check_complete()?;
+        let kind = match self.wallet_type {
+            WalletType::Standard if kind.is_multisig() => {
+                return Err(Electrum2DescriptorError::ScriptKindConflict(
+                    self.keystores[0].xpub.clone(),
+                    kind.to_string(),
+                    "a standard wallet".to_string(),
+                ))
+            }
+            WalletType::Standard => kind,
+            WalletType::Multisig(_) if kind == ScriptKind::P2tr => {
+                return Err(Electrum2DescriptorError::TaprootMultisig)
+            }
+            WalletType::Multisig(_) => multisig_kind(kind),
+            WalletType::Imported => return Err(Electrum2DescriptorError::ImportedKeys),
+        };
+        let mut warnings = Vec::new();
+        for (i, keystore) in self.keystores.iter().enumerate() {
+            if keystore.xpub()?.1 != kind {
+                warnings.push(ConversionWarning::KindAssumed(self.keystore_name(i), kind));
+            }
+        }
+        self.keystores = self
+            .keystores
+            .iter()
+            .map(|keystore| keystore.with_kind(kind))
+            .collect::<Result<Vec<_>, _>>()?;
+        if !warnings.is_empty() {
+            let addresses = Addresses {
+                receiving: self.derive_addresses(
+                    false,
+                    0,
+                    self.addresses.receiving.len() as u32,
+                )?,
+                change: self.derive_addresses(true, 0, self.addresses.change.len() as u32)?,
+            };
+            self.replace_addresses(addresses);
+        }
+        self.parse_warnings.extend(warnings);
+        Ok(())
+    }
+
+    /// The network of the addresses: the one of the keys, unless it was set with [`ElectrumWalletFile::set_network`]
+    /// or the stored addresses are for regtest
+    pub fn network(&self) -> Result<Network, Electrum2DescriptorError> {
+        self.check_parsed()?;
+        match self.network {
+            Some(network) => Ok(network),
+            None => self.keystores[0]
+                .xpub()
+                .map(|(_xpub, _kind, network)| network),
+        }
+    }
+
+    /// Derive the addresses for another test network than the keys are encoded for, e.g. `bcrt1` addresses for
+    /// regtest: the test networks share the keys. The stored addresses are derived again.
+    /// Keys of mainnet and the test networks differ, see [`ElectrumWalletFile::assume_network`] to re-interpret them.
+    pub fn set_network(&mut self, network: Network) -> Result<(), Electrum2DescriptorError> {
+        self.check_complete()?;
+        let (_xpub, _kind, encoded) = self.keystores[0].xpub()?;
+        if !shares_keys(encoded, network) {
+            return Err(Electrum2DescriptorError::MixedNetworks(
+                format!("the keys for {}", encoded),
+                network.to_string(),
+            ));
+        }
+        self.network = Some(network);
+        let addresses = Addresses {
+            receiving: self.derive_addresses(false, 0, self.addresses.receiving.len() as u32)?,
+            change: self.derive_addresses(true, 0, self.addresses.change.len() as u32)?,
+        };
+        self.replace_addresses(addresses);
+        Ok(())
+    }
+
+    /// The order of the keys in the scripts of a multisig wallet
+    pub fn key_order(&self) -> KeyOrder {
+        self.key_order
+    }
+
+    /// Set the order of the keys in the scripts of a multisig wallet. The stored addresses are derived again.
+    ///
+    /// **Warning:** with [`KeyOrder::AsStored`] the addresses only match wallets that put the keys in the same fixed
+    /// order, electrum itself always sorts them. Funds sent to the addresses of the wrong order can't be found by
+    /// the wallet.
+    pub fn set_key_order(&mut self, order: KeyOrder) -> Result<(), Electrum2DescriptorError> {
+        self.key_order = order;
+        if self.wallet_type != WalletType::Standard {
+            let addresses = Addresses {
+                receiving: self.derive_addresses(
+                    false,
+                    0,
+                    self.addresses.receiving.len() as u32,
+                )?,
+                change: self.derive_addresses(true, 0, self.addresses.change.len() as u32)?,
+            };
+            self.replace_addresses(addresses);
+        }
+        Ok(())
+    }
+
+    /// Add a cosigner to a multisig wallet, in the next slot. Its keys must be of the script kind and network of the
+    /// other cosigners, a singlesig prefix standing for its multisig counterpart. The threshold stays, raise it
+    /// afterwards if needed. The stored addresses and their history are cleared, as they are of the old keys.
+    pub fn add_cosigner(&mut self, keystore: Keystore) -> Result<(), Electrum2DescriptorError> {
+        self.check_complete()?;
+        let threshold = match self.wallet_type {
+            WalletType::Standard | WalletType::Imported => {
+                return Err(Electrum2DescriptorError::NotMultisig)
+            }
+            WalletType::Multisig(multisig) => multisig.threshold(),
+        };
+        let (_xpub, kind, network) = self.keystores[0].xpub()?;
+        let (_xpub, added_kind, added_network) = keystore.xpub()?;
+        if multisig_kind(added_kind) != multisig_kind(kind) {
+            return Err(Electrum2DescriptorError::MixedScriptKinds(
+                self.keystores[0].xpub.clone(),
+                keystore.xpub,
+            ));
+        }
+        if added_network != network {
+            return Err(Electrum2DescriptorError::MixedNetworks(
+                self.keystores[0].xpub.clone(),
+                keystore.xpub,
+            ));
+        }
+        keystore.check_derivation()?;
+        self.wallet_type = WalletType::multisig(threshold.into(), self.keystores.len() + 1)?;
+        self.keystores.push(keystore);
+        self.clear_addresses();
+        Ok(())
+    }
+
+    /// Remove the cosigner at `index` from a multisig wallet and return its keystore. The later cosigners move up a
+    /// slot, so that the wallet file has the slots `x1/` to `xN/` again. The threshold must still fit the remaining
+    /// cosigners, lower it first otherwise. The stored addresses and their history are cleared, as they are of the old
+    /// keys.
+    pub fn remove_cosigner(&mut self, index: usize) -> Result<Keystore, Electrum2DescriptorError> {
+        self.check_complete()?;
+        let threshold = match self.wallet_type {
+            WalletType::Standard | WalletType::Imported => {
+                return Err(Electrum2DescriptorError::NotMultisig)
+            }
+            WalletType::Multisig(multisig) => multisig.threshold(),
+        };
+        if index >= self.keystores.len() {
+            return Err(Electrum2DescriptorError::UnknownCosigner(
+                index,
+                self.keystores.len(),
+            ));
+        }
+        let cosigners = self.keystores.len() - 1;
+        if cosigners < 2 {
+            return Err(Electrum2DescriptorError::MultisigFewSigners);
+        }
+        if usize::from(threshold) > cosigners {
+            return Err(Electrum2DescriptorError::ThresholdAboveCosigners(
+                threshold, cosigners,
+            ));
+        }
+        self.wallet_type = WalletType::multisig(threshold.into(), cosigners)?;
+        self.clear_addresses();
+        Ok(self.keystores.remove(index))
+    }
+
+    /// Store re-derived `addresses`. If they differ from the stored ones, the history electrum built for the old ones
+    /// is dropped, electrum rebuilds it for the new ones when it syncs.
+    fn replace_addresses(&mut self, addresses: Addresses) {
+        if addresses != self.addresses {
+            self.clear_history();
+        }
+        self.addresses = addresses;
+    }
+
+    /// Clear the stored addresses after the keys or the scripts changed, with the history of the old addresses
+    fn clear_addresses(&mut self) {
+        self.addresses = Addresses::new();
+        self.clear_history();
+    }
+
+    fn clear_history(&mut self) {
+        for field in HISTORY_FIELDS {
+            self.extra.remove(*field);
+        }
+    }
+
+    /// Change the number of signatures a multisig wallet needs, from 1 to the number of cosigners.
+    ///
+    /// **This makes a different wallet:** the scripts and so all addresses change, coins received on the old addresses
+    /// are not found by the new wallet. The stored addresses and their history are cleared, as they are of the old
+    /// scripts.
+    pub fn set_threshold(&mut self, threshold: u8) -> Result<(), Electrum2DescriptorError> {
+        self.check_complete()?;
+        if self.wallet_type == WalletType::Standard {
+            return Err(Electrum2DescriptorError::NotMultisig);
+        }
+        self.wallet_type = WalletType::multisig(threshold.into(), self.keystores.len())?;
+        self.clear_addresses();
+        Ok(())
+    }
+
+    /// Normalize the wallet, so that wallets describing the same thing serialize byte-identically with
+    /// [`JsonStyle::ELECTRUM`], e.g. for content-addressed backups or reviewing diffs. For each keystore:
+    /// - the keys are re-encoded with the slip-0132 prefix of the kind of the wallet, e.g. `Vpub` instead of `vpub` for
+    ///   the cosigners of a p2wsh multisig wallet
+    /// - the hardened markers `h` and `H` of the derivation are written as `'`
+    /// - the root fingerprint and the derivation `m` of master keys are filled in
+    /// - the derivation, root fingerprint, label and script type are dropped if they are `null` or `""`
+    ///
+    /// The keystore type is always written. Imported wallets, whose address lists would be sorted, are not supported.
+    pub fn canonicalize(&mut self) -> Result<(), Electrum2DescriptorError> {
+        self.canonicalize_with(&CanonicalizeOptions::default())
+    }
+
+    /// [`ElectrumWalletFile::canonicalize`], with the cosigners sorted as well if the options say so
+    pub fn canonicalize_with(
+        &mut self,
+        options: &CanonicalizeOptions,
+    ) -> Result<(), Electrum2DescriptorError> {
+        self.check_complete()?;
+        let multisig = self.wallet_type != WalletType::Standard;
+        for keystore in &mut self.keystores {
+            keystore.canonicalize(multisig)?;
+        }
+        if options.sort_cosigners {
+            self.sort_cosigners()?;
+        }
+        Ok(())
+    }
+
+    /// Order the cosigners of a multisig wallet by their [`Keystore::key_id`], renumbering the slots `x1/` to `xN/`,
+    /// so that wallets assembled from the same keys in another order serialize the same. Nothing else changes: the
+    /// keys of `sortedmulti()` scripts are sorted anyway, so the addresses and the wallet id stay, the descriptors
+    /// only list the keys in the new order. Singlesig wallets are left as they are.
+    ///
+    /// Wallets with [`KeyOrder::AsStored`] are refused, the order of the cosigners makes their `multi()` addresses.
+    pub fn sort_cosigners(&mut self) -> Result<(), Electrum2DescriptorError> {
+        self.check_complete()?;
+        if self.wallet_type == WalletType::Standard {
+            return Ok(());
+        }
+        if self.key_order == KeyOrder::AsStored {
+            return Err(Electrum2DescriptorError::FixedKeyOrder);
+        }
+        self.keystores.sort_by_key(Keystore::key_id);
+        Ok(())
+    }
+
+    /// Check the stored addresses against the keys, to catch mismatched files: all of them must be of the script kind
+    /// and network of the keys, and each must be the derived one of the index of its position. As the index of a
+    /// stored address is its position, a deleted address attributes all later ones to the wrong index, which is
+    /// reported apart from a foreign address. See [`ElectrumWalletFile::repair_addresses`].
+    pub fn verify_addresses(&self) -> Result<(), Electrum2DescriptorError> {
+        self.check_complete()?;
+        let (_xpub, kind, _network) = self.keystores[0].xpub()?;
+        let network = self.network()?;
+        let expected = AddressKind::for_script_kind(kind, self.wallet_type != WalletType::Standard);
+        for stored in self
+            .addresses
+            .receiving
+            .iter()
+            .chain(&self.addresses.change)
+        {
+            let (found, found_network) = kind_from_address_str(stored)?;
+            if found != expected {
+                return Err(Electrum2DescriptorError::AddressKindMismatch(
+                    stored.clone(),
+                    found.to_string(),
+                    expected.to_string(),
+                ));
+            }
+            // only the bech32 addresses of regtest tell the test networks apart
+            if !shares_keys(found_network, network)
+                || ((found_network == Network::Regtest) != (network == Network::Regtest)
+                    && found_network != Network::Testnet)
+            {
+                return Err(Electrum2DescriptorError::MixedNetworks(
+                    stored.clone(),
+                    "the keys".to_string(),
+                ));
+            }
+        }
+
+        for (change, stored) in [
+            (false, &self.addresses.receiving),
+            (true, &self.addresses.change),
+        ] {
+            self.check_address_indices(change, stored)?;
+        }
+        Ok(())
+    }
+
+    /// Check that the stored addresses of a chain are the derived ones of their positions
+    fn check_address_indices(
+        &self,
+        change: bool,
+        stored: &[String],
+    ) -> Result<(), Electrum2DescriptorError> {
+        let derived = self.derive_addresses(change, 0, (stored.len() + DELETION_GAP) as u32)?;
+        let position = match stored.iter().zip(&derived).position(|(s, d)| s != d) {
+            Some(position) => position,
+            None => return Ok(()),
+        };
+        let chain = if change { "change" } else { "receiving" };
+        match derived.iter().position(|d| *d == stored[position]) {
+            None => Err(Electrum2DescriptorError::ForeignAddress(
+                chain,
+                position,
+                stored[position].clone(),
+            )),
+            Some(index)
+                if index > position && derived[index..].starts_with(&stored[position..]) =>
+            {
+                Err(Electrum2DescriptorError::AddressesDeleted(
+                    chain,
+                    index - position,
+                    position,
+                ))
+            }
+            Some(_) => Err(Electrum2DescriptorError::AddressMismatch(
+                position as u32,
+                stored[position].clone(),
+                derived[position].clone(),
+            )),
+        }
+    }
+
+    /// Derive the stored addresses again from the keys, after [`ElectrumWalletFile::verify_addresses`] found them
+    /// inconsistent, e.g. when the user confirms a repair. Each chain gets the addresses up to the highest index
+    /// of a stored address of the wallet, foreign addresses are dropped. Returns the addresses that were stored before.
+    pub fn repair_addresses(&mut self) -> Result<Addresses, Electrum2DescriptorError> {
+        self.check_complete()?;
+        let mut repaired = Addresses::new();
+        for (change, stored, repaired) in [
+            (false, &self.addresses.receiving, &mut repaired.receiving),
+            (true, &self.addresses.change, &mut repaired.change),
+        ] {
+            let derived = self.derive_addresses(change, 0, (stored.len() + DELETION_GAP) as u32)?;
+            let len = stored
+                .iter()
+                .filter_map(|s| derived.iter().position(|d| d == s))
+                .map(|index| index + 1)
+                .max()
+                .unwrap_or(0);
+            *repaired = derived[..len].to_vec();
+        }
+        Ok(std::mem::replace(&mut self.addresses, repaired))
+    }
+
+    /// Fix the inconsistencies support keeps seeing in wallet files, as far as `policy` allows, e.g. after parsing
+    /// with [`ParseOptions::unvalidated`]. Returns what was fixed and what needs attention:
+    /// - a wallet_type with fewer cosigners than keystores, or a multisig wallet_type of a singlesig keystore
+    /// - an xpub that doesn't match the xprv of its keystore
+    /// - prefixes of the wrong kind, like a Zpub in a standard wallet
+    ///
+    /// A fix is only applied when the stored addresses, if any, are the ones of the fixed wallet. A multisig
+    /// wallet_type with more cosigners than keystores is left alone, as it also is an unfinished setup.
+    pub fn repair(&mut self, policy: RepairPolicy) -> Vec<RepairAction> {
+        let mut actions = Vec::new();
+        if let Err(e) = self.check_complete() {
+            actions.push(RepairAction::NeedsAttention(e.to_string()));
+            return actions;
+        }
+
+        let keystores = self.keystores.len();
+        let wallet_type = match self.wallet_type {
+            WalletType::Standard if keystores > 1 => {
+                actions.push(RepairAction::NeedsAttention(format!(
+                    "the standard wallet has {} keystores, the threshold is unknown",
+                    keystores
+                )));
+                None
+            }
+            WalletType::Multisig(_) if keystores == 1 => Some(WalletType::Standard),
+            WalletType::Multisig(multisig) if keystores > multisig.cosigners().into() => {
+                WalletType::multisig(multisig.threshold().into(), keystores).ok()
+            }
+            _ => None,
+        };
+        if let Some(wallet_type) = wallet_type {
+            let action = RepairAction::WalletType(self.wallet_type.clone(), wallet_type.clone());
+            let mut fixed = self.clone();
+            fixed.wallet_type = wallet_type;
+            self.apply_repair(fixed, policy.wallet_type, action, &mut actions);
+        }
+
+        for i in 0..self.keystores.len() {
+            let keystore = &self.keystores[i];
+            let xprv = match keystore
+                .xprv
+                .as_deref()
+                .map(ElectrumExtendedPrivKey::from_str)
+            {
+                Some(Ok(xprv)) => xprv,
+                _ => continue,
+            };
+            let derived = ExtendedPubKey::from_priv(secp(), xprv.xprv());
+            if keystore.xpub().ok().map(|(xpub, _kind, _network)| xpub) == Some(derived) {
+                continue;
+            }
+            let mut fixed = self.clone();
+            match ElectrumExtendedPubKey::new(derived, xprv.kind().to_string()).electrum_xpub() {
+                Ok(xpub) => fixed.keystores[i].xpub = xpub,
+                Err(_) => continue,
+            }
+            let action = RepairAction::XpubFromXprv(self.keystore_name(i));
+            self.apply_repair(fixed, policy.xpub_from_xprv, action, &mut actions);
+        }
+
+        let multisig = self.wallet_type != WalletType::Standard;
+        for i in 0..self.keystores.len() {
+            let (xpub, kind, _network) = match self.keystores[i].xpub() {
+                Ok(xpub) => xpub,
+                Err(e) => {
+                    actions.push(RepairAction::NeedsAttention(format!(
+                        "{}: {}",
+                        self.keystore_name(i),
+                        e
+                    )));
+                    continue;
+                }
+            };
+            let target = match (kind, multisig) {
+                (ScriptKind::P2wpkh, true) => ScriptKind::P2wsh,
+                (ScriptKind::P2wpkhP2sh, true) => ScriptKind::P2wshP2sh,
+                (ScriptKind::P2wsh, false) => ScriptKind::P2wpkh,
+                (ScriptKind::P2wshP2sh, false) => ScriptKind::P2wpkhP2sh,
+                _ => continue,
+            };
+            let mut fixed = self.clone();
+            let keystore = &mut fixed.keystores[i];
+            let kind_str = target.as_kind_str().to_string();
+            match ElectrumExtendedPubKey::new(xpub, kind_str.clone()).electrum_xpub() {
+                Ok(xpub) => keystore.xpub = xpub,
+                Err(_) => continue,
+            }
+            // encrypted xprvs are kept as they are
+            if let Ok(Some(xprv)) = keystore.xprv() {
+                match ElectrumExtendedPrivKey::new(xprv, kind_str).electrum_xprv() {
+                    Ok(xprv) => keystore.xprv = Some(xprv),
+                    Err(_) => continue,
+                }
+            }
+            let action = RepairAction::Prefix(self.keystore_name(i), kind, target);
+            self.apply_repair(fixed, policy.prefixes, action, &mut actions);
+        }
+
+        if let Err(e) = self.validate() {
+            actions.push(RepairAction::NeedsAttention(e.to_string()));
+        }
+        actions
+    }
+
+    /// Replace the wallet with the `fixed` one if the policy `allows` it and the stored addresses agree with it
+    fn apply_repair(
+        &mut self,
+        fixed: ElectrumWalletFile,
+        allowed: bool,
+        action: RepairAction,
+        actions: &mut Vec<RepairAction>,
+    ) {
+        let stored = match (
+            self.addresses.receiving.first(),
+            self.addresses.change.first(),
+        ) {
+            (Some(address), _) => Some((false, address)),
+            (None, Some(address)) => Some((true, address)),
+            (None, None) => None,
+        };
+        let agrees = match stored {
+            Some((change, address)) => fixed
+                .derive_addresses(change, 0, 1)
+                .map(|derived| derived.first() == Some(address))
+                .unwrap_or(false),
+            None => true,
+        };
+        if !allowed {
+            actions.push(RepairAction::NeedsAttention(format!(
+                "not allowed to fix: {}",
+                action
+            )));
+        } else if !agrees {
+            actions.push(RepairAction::NeedsAttention(format!(
+                "the stored addresses don't agree with the fix: {}",
+                action
+            )));
+        } else {
+            *self = fixed;
+            actions.push(action);
+        }
+    }
+
+    /// Derive and append the missing addresses up to `receiving_target` and `change_target` addresses,
+    /// e.g. before going offline. The stored addresses are never changed, but they are checked against the
+    /// keys first with [`ElectrumWalletFile::verify_addresses`], to catch mismatched files. Returns how many receiving and change addresses were added.
+    pub fn extend_addresses(
+        &mut self,
+        receiving_target: u32,
+        change_target: u32,
+    ) -> Result<(usize, usize), Electrum2DescriptorError> {
+        self.verify_addresses()?;
+        let receiving =
+            self.derive_addresses(false, self.addresses.receiving.len(), receiving_target)?;
+        let change = self.derive_addresses(true, self.addresses.change.len(), change_target)?;
+        let mut extended = self.addresses.clone();
+        extended.receiving.extend(receiving.iter().cloned());
+        extended.change.extend(change.iter().cloned());
+        if let Some(duplicate) = extended.duplicates().first() {
+            return Err(duplicate.to_error());
+        }
+        let added = (receiving.len(), change.len());
+        self.addresses.receiving.extend(receiving);
+        self.addresses.change.extend(change);
+        Ok(added)
+    }
+
+    /// Derive the first `count` receiving and change addresses for `network`, e.g. for a wallet file generated from a
+    /// descriptor, so that electrum shows them without syncing first. Like [`ElectrumWalletFile::extend_addresses`],
+    /// stored addresses are kept, and `network` must share the keys, see [`ElectrumWalletFile::set_network`].
+    pub fn fill_addresses(
+        &mut self,
+        count: usize,
+        network: Network,
+    ) -> Result<(), Electrum2DescriptorError> {
+        let count = u32::try_from(count).map_err(|_| {
+            Electrum2DescriptorError::InvalidArguments(format!("{} addresses are too many", count))
+        })?;
+        if self.network()? != network {
+            self.set_network(network)?;
+        }
+        self.extend_addresses(count, count)?;
+        Ok(())
+    }
+
+    /// Derive the addresses from index `start` up to `end` of the receiving or change chain
+    fn derive_addresses(
+        &self,
+        change: bool,
+        start: usize,
+        end: u32,
+    ) -> Result<Vec<String>, Electrum2DescriptorError> {
+        (start as u32..end)
+            .map(|index| self.address(change, index).map(|a| a.to_string()))
+            .collect()
+    }
+
+    /// Where a stored address is: whether it is a change address, and its index
+    pub fn find_address(&self, address: &str) -> Option<(bool, u32)> {
+        [
+            (false, &self.addresses.receiving),
+            (true, &self.addresses.change),
+        ]
+        .iter()
+        .find_map(|(change, addresses)| {
+            let index = addresses.iter().position(|a| a == address)?;
+            Some((*change, index as u32))
+        })
+    }
+
+    /// Derive the address at `index` of the receiving or change chain, the way electrum does
+    pub fn address(&self, change: bool, index: u32) -> Result<Address, Electrum2DescriptorError> {
+        self.check_complete()?;
+        let secp = secp();
+        let xpubs = self
+            .keystores
+            .iter()
+            .map(|ks| ks.xpub())
+            .collect::<Result<Vec<_>, _>>()?;
+        let (_xpub, kind, _network) = xpubs[0];
+        let network = self.network()?;
+        let mut pubkeys = xpubs
+            .iter()
+            .zip(&self.keystores)
+            .map(|((xpub, _kind, _network), keystore)| {
+                let path = [
+                    ChildNumber::from_normal_idx(keystore.chain(change))?,
+                    ChildNumber::from_normal_idx(index)?,
+                ];
+                Ok(PublicKey::new(xpub.derive_pub(secp, &path)?.public_key))
+            })
+            .collect::<Result<Vec<_>, Electrum2DescriptorError>>()?;
+
+        let address = match (&self.wallet_type, kind) {
+            (WalletType::Standard, ScriptKind::Standard) => Address::p2pkh(&pubkeys[0], network),
+            (WalletType::Standard, ScriptKind::P2wpkhP2sh) => {
+                Address::p2shwpkh(&pubkeys[0], network)?
+            }
+            (WalletType::Standard, ScriptKind::P2wpkh) => Address::p2wpkh(&pubkeys[0], network)?,
+            (WalletType::Standard, ScriptKind::P2tr) => {
+                Address::p2tr(secp, pubkeys[0].inner.into(), None, network)
+            }
+            (WalletType::Multisig(_), ScriptKind::P2tr) => {
+                return Err(Electrum2DescriptorError::TaprootMultisig)
+            }
+            (WalletType::Multisig(multisig), kind) => {
+                if self.key_order == KeyOrder::Sorted {
+                    pubkeys.sort_by_key(|pk| pk.to_bytes());
+                }
+                let script = pubkeys
+                    .iter()
+                    .fold(
+                        Builder::new().push_int(multisig.threshold().into()),
+                        |builder, pk| builder.push_key(pk),
+                    )
+                    .push_int(pubkeys.len() as i64)
+                    .push_opcode(OP_CHECKMULTISIG)
+                    .into_script();
+                match kind {
+                    ScriptKind::Standard => Address::p2sh(&script, network)?,
+                    ScriptKind::P2wshP2sh | ScriptKind::P2wpkhP2sh => {
+                        Address::p2shwsh(&script, network)
+                    }
+                    ScriptKind::P2wsh | ScriptKind::P2wpkh => Address::p2wsh(&script, network),
+                    _ => {
+                        return Err(Electrum2DescriptorError::UnknownScriptKind(
+                            kind.to_string(),
+                        ))
+                    }
+                }
+            }
+            (WalletType::Standard, kind) => {
+                return Err(Electrum2DescriptorError::UnknownScriptKind(
+                    kind.to_string(),
+                ))
+            }
+            (WalletType::Imported, _) => return Err(Electrum2DescriptorError::ImportedKeys),
+        };
+        Ok(address)
+    }
+
+    /// Generate output descriptors matching the electrum wallet, with the origins of the keystores that have one.
+    /// With the `miniscript` feature, the descriptors are rendered from [`crate::typed_descriptors`], with the xprvs of
+    /// hot wallets in place of their xpubs, so that both can't diverge.
+    pub fn to_descriptors(&self) -> Result<Descriptors, Electrum2DescriptorError> {
+        log_span!("convert", "{} wallet", self.wallet_type);
+        #[cfg(feature = "miniscript")]
+        {
+            log_debug!(
+                "descriptors of the {} wallet built with miniscript",
+                self.wallet_type
+            );
+            crate::typed_descriptors::to_descriptors(self)
+        }
+        #[cfg(not(feature = "miniscript"))]
+        {
+            log_debug!(
+                "descriptors of the {} wallet built as strings",
+                self.wallet_type
+            );
+            self.descriptor_strings()
+        }
     }
 
-    /// Write to an electrum wallet file
-    pub fn to_file(&self, wallet_file: &Path) -> Result<(), Electrum2DescriptorError> {
-        let file = std::fs::File::create(wallet_file)?;
-        Ok(serde_json::to_writer_pretty(file, self)?)
+    /// The descriptors built as strings, with the xprvs of hot wallets. With the `miniscript` feature only the tests
+    /// compare against them.
+    #[cfg_attr(all(feature = "miniscript", not(test)), allow(dead_code))]
+    pub(crate) fn descriptor_strings(&self) -> Result<Descriptors, Electrum2DescriptorError> {
+        self.check_complete()?;
+        let keys = self
+            .keystores
+            .iter()
+            .map(|keystore| {
+                let origin = match keystore.descriptor_origin() {
+                    Some((fingerprint, derivation)) => format!(
+                        "[{}{}]",
+                        fingerprint,
+                        derivation.to_string().trim_start_matches('m')
+                    ),
+                    None => String::new(),
+                };
+                Ok(format!("{}{}", origin, keystore.get_xkey()?.xkey_str()))
+            })
+            .collect::<Result<Vec<_>, Electrum2DescriptorError>>()?;
+        let (_xpub, kind, _network) = self.keystores[0].xpub()?;
+        let [external, change] = [false, true].map(|change| {
+            let keys = keys
+                .iter()
+                .zip(&self.keystores)
+                .map(|(key, keystore)| format!("{}/{}/*", key, keystore.chain(change)))
+                .collect::<Vec<_>>();
+            match self.wallet_type {
+                WalletType::Standard => singlesig_descriptor(kind, &keys[0]),
+                WalletType::Multisig(multisig) => {
+                    multisig_descriptor(kind, multisig.threshold(), &keys, self.key_order)
+                }
+                WalletType::Imported => Err(Electrum2DescriptorError::ImportedKeys),
+            }
+        });
+        Ok(Descriptors {
+            external: external?,
+            change: change?,
+        })
     }
 
-    /// Construct from an output descriptor. Only the external descriptor is needed, the change descriptor is implied.
-    pub fn from_descriptor(desc: &str) -> Result<Self, Electrum2DescriptorError> {
-        let wallet = if desc.contains("(sortedmulti(") {
-            ElectrumWalletFile::from_descriptor_multisig(desc)
+    /// The command that restores the wallet on a headless electrum, e.g. a daemon on a server. With `private` the
+    /// xprvs of the keystores that hold one are used instead of their xpubs. The keys are written with their
+    /// slip-0132 prefix, which tells electrum the script kind, and test networks get their option like `--testnet`.
+    ///
+    /// Standard wallets give `electrum restore <key>`. Electrum 3.0 and later read the script kind from the prefix,
+    /// older versions only accept `xpub` and `xprv` keys for legacy wallets. Electrum's `restore` doesn't create
+    /// multisig wallets: for them the text is the wallet type followed by the keys in slot order, like
+    /// `"2of3 Zpub... Zpub... Zpub..."`, the input the multisig wizard asks for, which scripts driving the daemon
+    /// have to split up. Taproot wallets can't be restored, electrum has none.
+    pub fn to_restore_command(&self, private: bool) -> Result<String, Electrum2DescriptorError> {
+        self.check_complete()?;
+        let (_xpub, kind, _network) = self.keystores[0].xpub()?;
+        if kind == ScriptKind::P2tr {
+            return Err(Electrum2DescriptorError::NotRestorable(
+                "electrum has no taproot wallets".to_string(),
+            ));
+        }
+        if self
+            .keystores
+            .iter()
+            .any(|keystore| keystore.chains.is_some())
+        {
+            return Err(Electrum2DescriptorError::NotRestorable(
+                "electrum derives the chains 0 and 1 only".to_string(),
+            ));
+        }
+        let signing = if private {
+            self.check_can_sign()?
         } else {
-            ElectrumWalletFile::from_descriptor_singlesig(desc)
-        }?;
-        wallet.validate()?;
-        Ok(wallet)
-    }
-
-    /// Construct from a single signature output descriptor. Only the external descriptor is needed, the change descriptor is implied.
-    fn from_descriptor_singlesig(desc: &str) -> Result<Self, Electrum2DescriptorError> {
-        let re =
-            Regex::new(r#"(pkh|sh\(wpkh|sh\(wsh|wpkh|wsh)\((([tx]p(ub|rv)[0-9A-Za-z]+)/0/\*)\)+"#)?;
-        let captures = re.captures(desc).map(|captures| {
-            captures
-                .iter()
-                .skip(1)
-                .take(3)
-                .flatten()
-                .map(|c| c.as_str())
-                .collect::<Vec<_>>()
-        });
-        let keystore = match captures.as_deref() {
-            Some([kind, _, xkey]) => Keystore::new(kind, xkey)?,
-            _ => {
-                return Err(Electrum2DescriptorError::UnknownDescriptorFormat(format!(
-                    "{:?}",
-                    captures
+            Vec::new()
+        };
+        let mut wallet = self.clone();
+        wallet.canonicalize()?;
+        let keys = wallet
+            .keystores
+            .iter()
+            .enumerate()
+            .map(|(i, keystore)| match &keystore.xprv {
+                Some(xprv) if signing.contains(&i) => xprv.as_str(),
+                _ => keystore.xpub.as_str(),
+            })
+            .collect::<Vec<_>>();
+        let network = match self.network()? {
+            Network::Bitcoin => "",
+            Network::Testnet => " --testnet",
+            Network::Signet => " --signet",
+            Network::Regtest => " --regtest",
+            network => {
+                return Err(Electrum2DescriptorError::NotRestorable(format!(
+                    "electrum doesn't know {}",
+                    network
                 )))
             }
         };
-
-        Ok(ElectrumWalletFile {
-            addresses: Addresses::new(),
-            keystores: vec![keystore],
-            wallet_type: WalletType::Standard,
-        })
+        let text = match self.wallet_type {
+            WalletType::Standard => keys[0].to_string(),
+            WalletType::Multisig(multisig) => format!(
+                "\"{}of{} {}\"",
+                multisig.threshold(),
+                multisig.cosigners(),
+                keys.join(" ")
+            ),
+            WalletType::Imported => return Err(Electrum2DescriptorError::ImportedKeys),
+        };
+        Ok(format!("electrum{} restore {}", network, text))
     }
 
-    /// Construct from a multisig output descriptor. Only the external descriptor is needed, the change descriptor is implied.
-    fn from_descriptor_multisig(desc: &str) -> Result<Self, Electrum2DescriptorError> {
-        let re = Regex::new(
-            r#"(sh|sh\(wsh|wsh)\(sortedmulti\((\d),([tx]p(ub|rv)[0-9A-Za-z]+/0/\*,?)+\)+"#,
-        )?;
-        let captures = re.captures(desc).map(|captures| {
-            captures
-                .iter()
-                .skip(1)
-                .take(2)
-                .flatten()
-                .map(|c| c.as_str())
-                .collect::<Vec<_>>()
-        });
-        if let Some([kind, x]) = captures.as_deref() {
-            let kind = match *kind {
-                "wsh" => "wsh",
-                "sh" => "pkh",
-                "sh(wsh" => "sh(wsh",
-                _ => {
-                    return Err(Electrum2DescriptorError::UnknownScriptKind(
-                        kind.to_string(),
-                    ))
+    /// The spending policy of the wallet in miniscript policy syntax, `pk(A)` or `thresh(2,pk(A),pk(B),pk(C))` with
+    /// the cosigners in slot order. Keys are named by their label, else their master fingerprint, else their slot.
+    pub fn to_policy(&self) -> Result<String, Electrum2DescriptorError> {
+        self.check_complete()?;
+        if self.wallet_type == WalletType::Imported {
+            return Err(Electrum2DescriptorError::ImportedKeys);
+        }
+        let names = self
+            .keystores
+            .iter()
+            .enumerate()
+            .map(|(i, keystore)| {
+                let label = keystore
+                    .label
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|label| !label.is_empty() && !label.contains(['(', ')', ',']));
+                match (label, keystore.fingerprint()) {
+                    (Some(label), _) => label.to_string(),
+                    (None, Some(fingerprint)) => fingerprint.to_string(),
+                    (None, None) => self.keystore_name(i),
                 }
-            };
-            let re = Regex::new(r#"[tx]p[ur][bv][0-9A-Za-z]+"#)?;
-            let keystores = re
-                .captures_iter(desc)
-                .map(|cap| Keystore::new(kind, &cap[0]))
-                .collect::<Result<Vec<Keystore>, _>>()?;
-            let y = keystores.len();
-            if y < 2 {
-                return Err(Electrum2DescriptorError::MultisigFewSigners);
-            }
+            })
+            .collect::<Vec<_>>();
+        // cosigners sharing a name are told apart by their slots
+        let names = names
+            .iter()
+            .enumerate()
+            .map(
+                |(i, name)| match names.iter().filter(|n| *n == name).count() {
+                    1 => name.clone(),
+                    _ => self.keystore_name(i),
+                },
+            )
+            .collect::<Vec<_>>();
+        Ok(self.policy_of(&names))
+    }
 
-            Ok(ElectrumWalletFile {
-                addresses: Addresses::new(),
-                keystores,
-                wallet_type: WalletType::Multisig(x.parse().unwrap(), y as u8),
+    /// The spending policy of [`ElectrumWalletFile::to_policy`] with the xpubs written out as in descriptors,
+    /// along with their origin when it is known. Private keys are never included.
+    pub fn to_policy_with_keys(&self) -> Result<String, Electrum2DescriptorError> {
+        self.check_complete()?;
+        let keys = self
+            .keystores
+            .iter()
+            .map(|keystore| {
+                let (xpub, _kind, _network) = keystore.xpub()?;
+                let xkey = xpub.to_string();
+                Ok(match keystore.origin()? {
+                    Some((fingerprint, derivation)) => format!(
+                        "[{}{}]{}",
+                        fingerprint,
+                        derivation.to_string().trim_start_matches('m'),
+                        xkey
+                    ),
+                    None => xkey,
+                })
             })
-        } else {
-            Err(Electrum2DescriptorError::UnknownDescriptorFormat(format!(
-                "{:?}",
-                captures
-            )))
-        }
+            .collect::<Result<Vec<_>, Electrum2DescriptorError>>()?;
+        Ok(self.policy_of(&keys))
     }
 
-    /// Generate output descriptors matching the electrum wallet
-    pub fn to_descriptors(&self) -> Result<Descriptors, Electrum2DescriptorError> {
+    /// The policy of the wallet type over the keys of the keystores
+    fn policy_of(&self, keys: &[String]) -> String {
+        let pks = keys.iter().map(|key| format!("pk({})", key));
         match self.wallet_type {
-            WalletType::Standard => {
-                let exkey = self.keystores[0].get_xkey()?;
-                let desc_ext = exkey.kind().to_string() + "(" + &exkey.xkey_str() + "/0/*)";
-                let desc_chg = exkey.kind().to_string() + "(" + &exkey.xkey_str() + "/1/*)";
-
-                Ok(Descriptors {
-                    external: desc_ext,
-                    change: desc_chg,
-                })
+            WalletType::Standard | WalletType::Imported => pks.collect::<Vec<_>>().join(","),
+            WalletType::Multisig(multisig) => {
+                format!(
+                    "thresh({},{})",
+                    multisig.threshold(),
+                    pks.collect::<Vec<_>>().join(",")
+                )
             }
-            WalletType::Multisig(x, _y) => {
-                let xkeys = self
-                    .keystores
-                    .iter()
-                    .map(|ks| ks.get_xkey())
-                    .collect::<Result<Vec<Box<dyn ElectrumExtendedKey>>, _>>()?;
-                let prefix = match xkeys[0].kind() as &str {
-                    "pkh" => "sh",
-                    kind => kind,
+        }
+    }
+
+    /// The descriptors with the origins of the keys given by the caller instead of the stored ones, e.g. when the wallet
+    /// file doesn't store them.
+    /// The origins are paired with the keystores in order and each derivation must be as deep as its key.
+    pub fn to_descriptors_with_origin(
+        &self,
+        origins: &[(Fingerprint, DerivationPath)],
+    ) -> Result<Descriptors, Electrum2DescriptorError> {
+        self.check_complete()?;
+        if origins.len() != self.keystores.len() {
+            return Err(Electrum2DescriptorError::WrongNumberOfOrigins(
+                origins.len(),
+                self.keystores.len(),
+            ));
+        }
+        let keys = self
+            .keystores
+            .iter()
+            .zip(origins)
+            .map(|(keystore, (fingerprint, derivation))| {
+                let (xpub, _kind, _network) = keystore.xpub()?;
+                if derivation.len() != xpub.depth as usize {
+                    return Err(Electrum2DescriptorError::OriginDepthMismatch(
+                        derivation.to_string(),
+                        derivation.len(),
+                        xpub.depth,
+                    ));
+                }
+                Ok(format!(
+                    "[{}{}]{}",
+                    fingerprint,
+                    derivation.to_string().trim_start_matches('m'),
+                    keystore.get_xkey()?.xkey_str()
+                ))
+            })
+            .collect::<Result<Vec<_>, Electrum2DescriptorError>>()?;
+        let (_xpub, kind, _network) = self.keystores[0].xpub()?;
+        let [external, change] = [false, true].map(|change| {
+            let keys = keys
+                .iter()
+                .zip(&self.keystores)
+                .map(|(key, keystore)| format!("{}/{}/*", key, keystore.chain(change)))
+                .collect::<Vec<_>>();
+            match self.wallet_type {
+                WalletType::Standard => singlesig_descriptor(kind, &keys[0]),
+                WalletType::Multisig(multisig) => {
+                    multisig_descriptor(kind, multisig.threshold(), &keys, self.key_order)
                 }
-                .to_string();
-                let prefix = format!("{}(sortedmulti({}", prefix, x);
+                WalletType::Imported => Err(Electrum2DescriptorError::ImportedKeys),
+            }
+        });
+        Ok(Descriptors {
+            external: external?,
+            change: change?,
+        })
+    }
 
-                let mut desc = xkeys.iter().fold(prefix, |acc, exkey| {
-                    acc + &(",".to_string() + &exkey.xkey_str() + "/0/*")
+    /// The descriptors of the accounts `m/purpose'/coin'/account'` of the master xprv of a standard wallet, for each
+    /// account of the range, e.g. to find funds on other accounts after restoring a master key into electrum.
+    /// The purpose is the one of bip-0044, 49, 84 or 86 for `kind` and the keys have their origin. Fails for keys that
+    /// aren't master keys, as their accounts were derived already, and for watch-only wallets.
+    pub fn account_descriptors(
+        &self,
+        kind: ScriptKind,
+        accounts: Range<u32>,
+    ) -> Result<Vec<(u32, Descriptors)>, Electrum2DescriptorError> {
+        self.check_complete()?;
+        if self.wallet_type != WalletType::Standard {
+            return Err(Electrum2DescriptorError::GenericBorrow(
+                "accounts can only be derived for standard wallets",
+            ));
+        }
+        let purpose = match kind {
+            ScriptKind::Standard => 44,
+            ScriptKind::P2wpkhP2sh => 49,
+            ScriptKind::P2wpkh => 84,
+            ScriptKind::P2tr => 86,
+            kind => {
+                return Err(Electrum2DescriptorError::UnknownScriptKind(
+                    kind.to_string(),
+                ))
+            }
+        };
+        let master = self.keystores[0]
+            .xprv()?
+            .ok_or(Electrum2DescriptorError::NoPrivateKeys)?;
+        if master.depth != 0 {
+            return Err(Electrum2DescriptorError::NotMasterKey(master.depth));
+        }
+        let coin = if master.network == Network::Bitcoin {
+            0
+        } else {
+            1
+        };
+        let secp = secp();
+        let fingerprint = master.fingerprint(secp);
+        let closing = if kind.as_kind_str().contains('(') {
+            ")"
+        } else {
+            ""
+        };
+        accounts
+            .map(|account| {
+                let path = DerivationPath::from(vec![
+                    ChildNumber::from_hardened_idx(purpose)?,
+                    ChildNumber::from_hardened_idx(coin)?,
+                    ChildNumber::from_hardened_idx(account)?,
+                ]);
+                let xpub = ExtendedPubKey::from_priv(secp, &master.derive_priv(secp, &path)?);
+                let origin = format!(
+                    "[{}{}]{}",
+                    fingerprint,
+                    path.to_string().trim_start_matches('m'),
+                    xpub
+                );
+                let [external, change] = [0, 1].map(|chain| {
+                    format!("{}({}/{}/*){}", kind.as_kind_str(), origin, chain, closing)
                 });
-                desc += "))";
-                let opening = desc.matches('(').count();
-                let closing = desc.matches(')').count();
-                if opening > closing {
-                    desc += ")"
+                Ok((account, Descriptors { external, change }))
+            })
+            .collect()
+    }
+
+    /// The definite descriptor of the address at `index` of the receiving or change chain, with checksum.
+    /// The keys are derived to the child, and their origin is extended with the final derivation steps,
+    /// so that a signer can check the address independently.
+    pub fn descriptor_at(
+        &self,
+        change: bool,
+        index: u32,
+    ) -> Result<String, Electrum2DescriptorError> {
+        self.check_complete()?;
+        let secp = secp();
+        let mut kind = ScriptKind::Standard;
+        let keys = self
+            .keystores
+            .iter()
+            .map(|keystore| {
+                let (xpub, script_kind, _network) = keystore.xpub()?;
+                kind = script_kind;
+                let chain = keystore.chain(change);
+                let path = [
+                    ChildNumber::from_normal_idx(chain)?,
+                    ChildNumber::from_normal_idx(index)?,
+                ];
+                let child = xpub.derive_pub(secp, &path)?;
+                // without a known origin, the xpub itself is the origin
+                let origin = match keystore.origin()? {
+                    Some((fingerprint, derivation)) => format!(
+                        "{}{}",
+                        fingerprint,
+                        derivation.to_string().trim_start_matches('m')
+                    ),
+                    None => xpub.fingerprint().to_string(),
                 };
-                let desc_chg = desc.replace("/0/*", "/1/*");
+                Ok(format!("[{}/{}/{}]{}", origin, chain, index, child))
+            })
+            .collect::<Result<Vec<_>, Electrum2DescriptorError>>()?;
 
-                Ok(Descriptors {
-                    external: desc,
-                    change: desc_chg,
-                })
+        let descriptor = match self.wallet_type {
+            WalletType::Standard => singlesig_descriptor(kind, &keys[0])?,
+            WalletType::Multisig(multisig) => {
+                { multisig_descriptor(kind, multisig.threshold(), &keys, self.key_order) }?
             }
-        }
+            WalletType::Imported => return Err(Electrum2DescriptorError::ImportedKeys),
+        };
+        crate::checksum::with_checksum(&descriptor)
+    }
+
+    /// The definite descriptors of a range of addresses, see [`ElectrumWalletFile::descriptor_at`]
+    pub fn descriptors_at(
+        &self,
+        change: bool,
+        indexes: std::ops::Range<u32>,
+    ) -> Result<Vec<String>, Electrum2DescriptorError> {
+        indexes
+            .map(|index| self.descriptor_at(change, index))
+            .collect()
     }
 
     /// validate the internal structure
     fn validate(&self) -> Result<(), Electrum2DescriptorError> {
-        let expected_keystores: usize = match self.wallet_type {
-            WalletType::Standard => 1,
-            WalletType::Multisig(_x, y) => y.into(),
-        };
+        log_span!("validate", "{} keystores", self.keystores.len());
+        log_debug!(
+            "validating the {} keystores of a {} wallet",
+            self.keystores.len(),
+            self.wallet_type
+        );
+        let expected_keystores = usize::from(self.wallet_type.cosigners());
 
         if self.keystores.len() != expected_keystores {
             return Err(Electrum2DescriptorError::WrongNumberOfKeyStores(
@@ -217,12 +2126,24 @@ impl ElectrumWalletFile {
             ));
         }
 
-        if let WalletType::Multisig(x, _y) = self.wallet_type {
-            if x as usize > expected_keystores {
-                return Err(Electrum2DescriptorError::NumberSignaturesKeyStores(
-                    x,
-                    expected_keystores,
-                ));
+        // a private key must be for the network of the cosigners, the keys that don't decode fail when used
+        let networks = self
+            .keystores
+            .iter()
+            .filter_map(|ks| {
+                ks.xpub()
+                    .ok()
+                    .map(|(_xpub, _kind, network)| (&ks.xpub, network))
+            })
+            .collect::<Vec<_>>();
+        for keystore in &self.keystores {
+            if let Ok(Some(xprv)) = keystore.xprv() {
+                if let Some((xpub, _network)) = networks.iter().find(|(_, n)| *n != xprv.network) {
+                    return Err(Electrum2DescriptorError::MixedNetworks(
+                        format!("the xprv of {}", keystore.xpub),
+                        xpub.to_string(),
+                    ));
+                }
             }
         }
 
@@ -235,7 +2156,7 @@ impl FromStr for ElectrumWalletFile {
 
     /// Parse an electrum wallet file from string
     fn from_str(wallet_file: &str) -> Result<Self, Electrum2DescriptorError> {
-        Ok(serde_json::from_str(wallet_file)?)
+        ElectrumWalletFile::from_str_with_options(wallet_file, &ParseOptions::default())
     }
 }
 
@@ -254,22 +2175,42 @@ impl Serialize for ElectrumWalletFile {
     {
         // We don't know the length of the map at this point, so it's None
         let mut map = serializer.serialize_map(None)?;
-        map.serialize_entry("addresses", &self.addresses)?;
+        // the addresses of an imported wallet are kept verbatim with the other fields
+        if self.wallet_type != WalletType::Imported {
+            map.serialize_entry("addresses", &self.addresses)?;
+        }
+        if let Some(seed_version) = self.seed_version {
+            map.serialize_entry("seed_version", &seed_version)?;
+        }
         map.serialize_entry("wallet_type", &self.wallet_type)?;
         match self.wallet_type {
             WalletType::Standard => {
-                map.serialize_entry("keystore", &self.keystores[0])?;
+                // missing from an incomplete wallet
+                if let Some(keystore) = self.keystores.first() {
+                    map.serialize_entry("keystore", keystore)?;
+                }
             }
-            WalletType::Multisig(_x, _y) => {
+            WalletType::Multisig(_) => {
                 self.keystores
                     .iter()
                     .enumerate()
-                    .map(|(i, keystore)| {
-                        let key = format!("x{}/", i + 1);
-                        map.serialize_entry(&key, &keystore)
-                    })
+                    .map(|(i, keystore)| map.serialize_entry(&self.keystore_name(i), &keystore))
                     .collect::<Result<Vec<_>, _>>()?;
             }
+            WalletType::Imported => {
+                // the key pairs take the place of the extended keys
+                if let Some(keystore) = self.keystores.first() {
+                    let mut value = serde_json::to_value(keystore).map_err(ser::Error::custom)?;
+                    if let Some(keystore) = value.as_object_mut() {
+                        keystore.remove("xprv");
+                        keystore.remove("xpub");
+                    }
+                    map.serialize_entry("keystore", &value)?;
+                }
+            }
+        }
+        for (key, value) in &self.extra {
+            map.serialize_entry(key, value)?;
         }
         map.end()
     }
@@ -280,11 +2221,513 @@ impl<'de> Deserialize<'de> for ElectrumWalletFile {
     where
         D: Deserializer<'de>,
     {
+        de::DeserializeSeed::deserialize(
+            WalletSeed {
+                options: &ParseOptions::default(),
+            },
+            deserializer,
+        )
+    }
+}
+
+/// What peek found out about a wallet file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WalletPeek {
+    /// The wallet_type as written by electrum, e.g. `standard`, `2of3` or `imported`. Unknown when encrypted.
+    pub wallet_type: Option<String>,
+    /// The file is encrypted with the wallet password
+    pub encrypted: bool,
+}
+
+/// Skim the json object of a wallet file for the top level wallet_type. Only the strings and brackets are
+/// followed to know where the top level keys are, all other values are skipped without parsing them.
+fn skim_wallet_type<R: BufRead>(mut reader: R) -> Result<Option<String>, serde_json::Error> {
+    let invalid = |reason: &str| <serde_json::Error as de::Error>::custom(reason);
+    let mut depth = 0usize;
+    let (mut in_string, mut escaped) = (false, false);
+    // whether the next top level string is a key, and whether the last key was the wallet_type
+    let (mut is_key, mut wallet_type_next) = (false, false);
+    // the top level key or the wallet_type being read, with its quotes
+    let mut captured: Option<Vec<u8>> = None;
+    loop {
+        let buf = reader.fill_buf().map_err(serde_json::Error::io)?;
+        if buf.is_empty() {
+            return Err(invalid("the json ends before the end of the wallet"));
+        }
+        let len = buf.len();
+        let mut i = 0;
+        while i < len {
+            if in_string && !escaped && captured.is_none() {
+                // most of a wallet file is in strings, like the transactions in hex
+                match memchr::memchr2(b'"', b'\\', &buf[i..]) {
+                    Some(skipped) => i += skipped,
+                    None => break,
+                }
+            } else if !in_string && depth > 1 {
+                // below the top level only the brackets and strings matter
+                match buf[i..]
+                    .iter()
+                    .position(|b| matches!(b, b'"' | b'{' | b'}' | b'[' | b']'))
+                {
+                    Some(skipped) => i += skipped,
+                    None => break,
+                }
+            }
+            let byte = buf[i];
+            i += 1;
+            if in_string {
+                if let Some(captured) = captured.as_mut() {
+                    captured.push(byte);
+                }
+                if escaped {
+                    escaped = false;
+                } else if byte == b'\\' {
+                    escaped = true;
+                } else if byte == b'"' {
+                    in_string = false;
+                    if let Some(text) = captured.take() {
+                        let text = serde_json::from_slice::<String>(&text)?;
+                        if wallet_type_next {
+                            return Ok(Some(text));
+                        }
+                        wallet_type_next = text == "wallet_type";
+                    }
+                }
+                continue;
+            }
+            match byte {
+                b' ' | b'\n' | b'\r' | b'\t' => {}
+                b'{' | b'[' if depth > 0 || byte == b'{' => {
+                    if depth == 1 && wallet_type_next {
+                        return Err(invalid("the wallet_type isn't a string"));
+                    }
+                    depth += 1;
+                    is_key = depth == 1;
+                }
+                b'}' | b']' if depth > 0 => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(None);
+                    }
+                }
+                b'"' if depth > 0 => {
+                    in_string = true;
+                    if depth == 1 && (is_key || wallet_type_next) {
+                        captured = Some(vec![byte]);
+                    }
+                    is_key = false;
+                }
+                b',' if depth == 1 => {
+                    is_key = true;
+                    wallet_type_next = false;
+                }
+                _ if depth == 0 => return Err(invalid("a wallet file is a json object")),
+                b':' | b',' => {}
+                _ if depth == 1 && wallet_type_next => {
+                    return Err(invalid("the wallet_type isn't a string"))
+                }
+                _ => {}
+            }
+        }
+        reader.consume(len);
+    }
+}
+
+/// Options for parsing electrum wallet files
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Only accept what electrum itself writes: reject unknown fields and numbers encoded as strings.
+    /// By default parsing is lenient, as older electrum versions and third party tools deviate.
+    pub strict: bool,
+    /// Skip all secrets while parsing, so they never get into memory. See [`ElectrumWalletFile::from_file_watch_only`]
+    pub watch_only: bool,
+    /// Leave out the keystores that can't be parsed instead of failing, to salvage the addresses of a damaged
+    /// wallet file. See [`ElectrumWalletFile::is_incomplete`]
+    pub partial: bool,
+    /// Don't check the wallet_type against the keystores and the networks of the keys against each other, to
+    /// fix them with [`ElectrumWalletFile::repair`]
+    pub unvalidated: bool,
+}
+
+/// Which fixes [`ElectrumWalletFile::repair`] may apply, all of them by default
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RepairPolicy {
+    /// Derive the xpub of a keystore again from its xprv when they don't match
+    pub xpub_from_xprv: bool,
+    /// Re-encode the keys of multisig cosigners with singlesig prefixes like zpub, and the other way around
+    pub prefixes: bool,
+    /// Change the wallet_type to the keystores the file has
+    pub wallet_type: bool,
+}
+
+impl Default for RepairPolicy {
+    fn default() -> Self {
+        RepairPolicy {
+            xpub_from_xprv: true,
+            prefixes: true,
+            wallet_type: true,
+        }
+    }
+}
+
+/// A fix [`ElectrumWalletFile::repair`] applied, or an inconsistency it left alone
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RepairAction {
+    /// The xpub of the keystore was derived again from its xprv
+    XpubFromXprv(String),
+    /// The keys of the keystore were re-encoded from the prefix of the first script kind to the second
+    Prefix(String, ScriptKind, ScriptKind),
+    /// The wallet_type was changed from the first to the second
+    WalletType(WalletType, WalletType),
+    /// Something that can't be fixed safely and needs a look
+    NeedsAttention(String),
+}
+
+impl fmt::Display for RepairAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RepairAction::XpubFromXprv(slot) => {
+                write!(f, "{}: derived the xpub again from the xprv", slot)
+            }
+            RepairAction::Prefix(slot, from, to) => {
+                write!(f, "{}: re-encoded the keys from {} to {}", slot, from, to)
+            }
+            RepairAction::WalletType(from, to) => write!(
+                f,
+                "changed the wallet_type from {} to {}",
+                serde_json::to_value(from).map_err(|_| fmt::Error)?,
+                serde_json::to_value(to).map_err(|_| fmt::Error)?
+            ),
+            RepairAction::NeedsAttention(reason) => write!(f, "needs attention: {}", reason),
+        }
+    }
+}
+
+/// Options for constructing electrum wallet files from descriptors
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DescriptorOptions {
+    /// Fail unless each of them is the fingerprint of a key origin in the descriptor or of a master key,
+    /// e.g. to not restore the wrong cosigners after a device reset
+    pub expected_fingerprints: Vec<Fingerprint>,
+    /// Fail for the change descriptor instead of using its keys as the account keys
+    pub strict: bool,
+}
+
+/// Options for [`ElectrumWalletFile::canonicalize_with`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CanonicalizeOptions {
+    /// Order the cosigners of multisig wallets, see [`ElectrumWalletFile::sort_cosigners`]
+    pub sort_cosigners: bool,
+}
+
+/// Fail if one of the `expected` fingerprints wasn't `found`
+fn check_expected_fingerprints(
+    expected: &[Fingerprint],
+    found: &[Fingerprint],
+) -> Result<(), Electrum2DescriptorError> {
+    match expected
+        .iter()
+        .find(|fingerprint| !found.contains(fingerprint))
+    {
+        Some(missing) => {
+            let found = if found.is_empty() {
+                "no fingerprints".to_string()
+            } else {
+                found
+                    .iter()
+                    .map(|fingerprint| fingerprint.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            Err(Electrum2DescriptorError::UnexpectedFingerprint(
+                missing.to_string(),
+                found,
+            ))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Parse a descriptor of a descriptor file, which may be the change descriptor. Returns whether it is.
+fn parse_descriptor_line(
+    descriptor: &str,
+) -> Result<(ElectrumWalletFile, bool), Electrum2DescriptorError> {
+    let mut warnings = Vec::new();
+    let wallet = ElectrumWalletFile::from_descriptor_with_warnings(
+        descriptor,
+        &DescriptorOptions::default(),
+        &mut warnings,
+    )?;
+    let change = warnings
+        .iter()
+        .any(|warning| matches!(warning, ConversionWarning::ChangeDescriptor(_)));
+    Ok((wallet, change))
+}
+
+/// Keep the content of `wallet_file` as `<name>.bak`, or `<name>.bak.1` and so on: backups are never replaced
+fn backup(wallet_file: &Path, dir: &Path, name: &OsStr) -> Result<(), Electrum2DescriptorError> {
+    let mut original = match std::fs::File::open(wallet_file) {
+        Ok(original) => original,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut open_options = OpenOptions::new();
+    open_options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        open_options.mode(original.metadata()?.permissions().mode() & 0o777);
+    }
+    let mut number = 0;
+    loop {
+        let mut backup_name = name.to_os_string();
+        backup_name.push(".bak");
+        if number > 0 {
+            backup_name.push(format!(".{}", number));
+        }
+        match open_options.open(dir.join(backup_name)) {
+            Ok(mut backup) => {
+                std::io::copy(&mut original, &mut backup)?;
+                return Ok(());
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => number += 1,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Whether a descriptor is the change descriptor, from whether each of its keys derives the change chain, see
+/// [`parse_chains`]. Fails if some keys derive the change chain and others don't.
+fn is_change_descriptor(
+    descriptor: &str,
+    changes: &[Option<bool>],
+) -> Result<bool, Electrum2DescriptorError> {
+    let change = changes.contains(&Some(true));
+    if change && changes.iter().any(|change| *change != Some(true)) {
+        return Err(Electrum2DescriptorError::MixedDescriptorChains(
+            descriptor.to_string(),
+        ));
+    }
+    Ok(change)
+}
+
+/// The receiving and change chains of a key
+type Chains = (u32, u32);
+
+/// The receiving and change chains of a key of a descriptor from the derivation after it, None for electrum's 0 and
+/// 1, and whether the descriptor derives the change chain. A single even chain `/<chain>/*` is the receiving one and
+/// the next one the change chain, like electrum's `/0/*` and `/1/*`. Of the odd chains only `/1/*` is taken as the
+/// change chain, as the receiving chain of any other would be a guess. The bip-0389 multipath gives both chains, it
+/// must be `/<0;1>/*`.
+fn parse_chains(
+    key: &str,
+    suffix: &str,
+) -> Result<(Option<Chains>, Option<bool>), Electrum2DescriptorError> {
+    let unsupported = |reason| {
+        Electrum2DescriptorError::UnsupportedKeyPath(
+            key.to_string(),
+            format!("{:?}", suffix),
+            reason,
+        )
+    };
+    let chain = suffix
+        .strip_prefix('/')
+        .and_then(|suffix| suffix.strip_suffix("/*"))
+        .ok_or_else(|| unsupported("expected one unhardened chain like /0/* or /<0;1>/*"))?;
+    if chain.starts_with('<') {
+        return match chain {
+            "<0;1>" => Ok((None, None)),
+            _ => Err(unsupported("the only multipath is /<0;1>/*")),
+        };
+    }
+    let chain = match ChildNumber::from_str(chain) {
+        Ok(ChildNumber::Normal { index }) if chain.chars().all(|c| c.is_ascii_digit()) => index,
+        _ => {
+            return Err(unsupported(
+                "expected one unhardened chain like /0/* or /<0;1>/*",
+            ))
+        }
+    };
+    match chain {
+        0 => Ok((None, Some(false))),
+        1 => Ok((None, Some(true))),
+        chain if chain % 2 == 0 => Ok((Some((chain, chain + 1)), Some(false))),
+        _ => Err(unsupported(
+            "of the odd chains only /1/* is a change chain, give the receiving chain instead",
+        )),
+    }
+}
+
+/// The keystore of a key expression matched by [`KEY_EXPRESSION`], and whether it derives the change chain, see
+/// [`parse_chains`]. The origin sets the root fingerprint and the derivation, hardened steps are written `'` or `h`.
+/// Its derivation must be as deep as the key. Taproot keys have the standard prefix and are marked, see
+/// [`Keystore::is_taproot`].
+fn parse_key_expression(
+    kind: &str,
+    origin: Option<regex::Match>,
+    key: &str,
+    suffix: &str,
+) -> Result<(Keystore, Option<bool>), Electrum2DescriptorError> {
+    let mut keystore = match kind {
+        "tr" => {
+            Keystore::new(ScriptKind::Standard.as_kind_str(), key)?.with_kind(ScriptKind::P2tr)?
+        }
+        kind => Keystore::new(kind, key)?,
+    };
+    if let Some(origin) = origin {
+        let (fingerprint, derivation) = match origin.as_str().split_once('/') {
+            Some((fingerprint, derivation)) => (fingerprint, format!("m/{}", derivation)),
+            None => (origin.as_str(), "m".to_string()),
+        };
+        let derivation = DerivationPath::from_str(&derivation)?;
+        let (xpub, _kind, _network) = keystore.xpub()?;
+        if derivation.len() != xpub.depth as usize {
+            return Err(Electrum2DescriptorError::OriginDepthMismatch(
+                derivation.to_string(),
+                derivation.len(),
+                xpub.depth,
+            ));
+        }
+        keystore = keystore.with_origin(Fingerprint::from_str(fingerprint)?, &derivation);
+    }
+    let (chains, change) = parse_chains(key, suffix)?;
+    keystore.chains = chains;
+    Ok((keystore, change))
+}
+
+/// How to format the json of a wallet file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JsonStyle {
+    pub indent: JsonIndent,
+    /// Sort the keys of all objects
+    pub sort_keys: bool,
+}
+
+/// The indentation of json
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JsonIndent {
+    /// All on one line
+    Compact,
+    /// Pretty printed with 2 spaces
+    Pretty2,
+    /// Pretty printed with 4 spaces
+    Pretty4,
+}
+
+impl JsonStyle {
+    /// As electrum writes wallet files: pretty printed with 4 spaces and sorted keys
+    pub const ELECTRUM: JsonStyle = JsonStyle {
+        indent: JsonIndent::Pretty4,
+        sort_keys: true,
+    };
+
+    /// All on one line, e.g. to store wallets in a database
+    pub const COMPACT: JsonStyle = JsonStyle {
+        indent: JsonIndent::Compact,
+        sort_keys: false,
+    };
+}
+
+impl Default for JsonStyle {
+    fn default() -> Self {
+        JsonStyle::ELECTRUM
+    }
+}
+
+fn write_json<W: Write, T: Serialize + ?Sized>(
+    writer: W,
+    value: &T,
+    indent: JsonIndent,
+) -> Result<(), Electrum2DescriptorError> {
+    match indent {
+        JsonIndent::Compact => serde_json::to_writer(writer, value)?,
+        JsonIndent::Pretty2 => serde_json::to_writer_pretty(writer, value)?,
+        JsonIndent::Pretty4 => {
+            let formatter = serde_json::ser::PrettyFormatter::with_indent(b"    ");
+            value.serialize(&mut serde_json::Serializer::with_formatter(
+                writer, formatter,
+            ))?
+        }
+    }
+    Ok(())
+}
+
+/// Options for writing electrum wallet files
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Replace an existing file
+    pub overwrite: bool,
+    /// Don't keep the content of a replaced file as `<name>.bak`, see [`ElectrumWalletFile::to_file_overwrite`]
+    pub no_backup: bool,
+    /// Don't fsync the file and its directory, e.g. when writing to tmpfs
+    pub skip_sync: bool,
+    /// Electrum's by default
+    pub style: JsonStyle,
+}
+
+/// The fields of a wallet file that electrum writes, in addition to the keystores
+const KNOWN_FIELDS: &[&str] = &[
+    "addr_history",
+    "addresses",
+    "channel_backups",
+    "channels",
+    "contacts",
+    "fiat_value",
+    "frozen_addresses",
+    "frozen_coins",
+    "imported_channel_backups",
+    "invoices",
+    "labels",
+    "lightning_payments",
+    "lightning_preimages",
+    "lightning_privkey2",
+    "lightning_xprv",
+    "onchain_channel_backups",
+    "payment_requests",
+    "prevouts_by_scripthash",
+    "qt-console-history",
+    "seed_type",
+    "seed_version",
+    "spent_outpoints",
+    "stored_height",
+    "submarine_swaps",
+    "transactions",
+    "tx_fees",
+    "txi",
+    "txo",
+    "use_change",
+    "use_encryption",
+    "verified_tx3",
+    "wallet_type",
+    "winpos-qt",
+];
+
+/// Whether the start of a wallet file is encrypted. Electrum encrypts the whole file with the password, as base64
+/// starting with the magic "BIE1".
+fn is_encrypted_file(start: &[u8]) -> bool {
+    start[start.iter().take_while(|b| b.is_ascii_whitespace()).count()..].starts_with(b"QklFM")
+}
+
+/// Deserializes an ElectrumWalletFile according to the ParseOptions
+struct WalletSeed<'a> {
+    options: &'a ParseOptions,
+}
+
+impl<'de, 'a> de::DeserializeSeed<'de> for WalletSeed<'a> {
+    type Value = ElectrumWalletFile;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        log_span!("parse", "watch-only: {}", self.options.watch_only);
         enum Field {
             Addrs,
-            Keyst,
+            /// A keystore, with the name of its slot
+            Keyst(String),
             WalTyp,
-            Ignore,
+            SeedVersion,
+            Other(String),
         }
 
         impl<'de> Deserialize<'de> for Field {
@@ -317,11 +2760,12 @@ impl<'de> Deserialize<'de> for ElectrumWalletFile {
                                 .collect::<Vec<_>>()
                         });
                         match captures.as_deref() {
-                            Some(["x", _i, "/"]) => Ok(Field::Keyst),
-                            Some(["keystore"]) => Ok(Field::Keyst),
+                            Some(["x", _i, "/"]) => Ok(Field::Keyst(value.to_string())),
+                            Some(["keystore"]) => Ok(Field::Keyst(value.to_string())),
                             Some(["addresses"]) => Ok(Field::Addrs),
                             Some(["wallet_type"]) => Ok(Field::WalTyp),
-                            _ => Ok(Field::Ignore),
+                            Some(["seed_version"]) => Ok(Field::SeedVersion),
+                            _ => Ok(Field::Other(value.to_string())),
                         }
                     }
                 }
@@ -330,9 +2774,11 @@ impl<'de> Deserialize<'de> for ElectrumWalletFile {
             }
         }
 
-        struct ElectrumWalletFileVisitor;
+        struct ElectrumWalletFileVisitor<'a> {
+            options: &'a ParseOptions,
+        }
 
-        impl<'de> de::Visitor<'de> for ElectrumWalletFileVisitor {
+        impl<'de, 'a> de::Visitor<'de> for ElectrumWalletFileVisitor<'a> {
             type Value = ElectrumWalletFile;
 
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
@@ -343,33 +2789,165 @@ impl<'de> Deserialize<'de> for ElectrumWalletFile {
             where
                 V: de::MapAccess<'de>,
             {
-                let mut addresses = Addresses::new();
+                let strict = self.options.strict;
+                let mut addresses = None;
                 let mut keystores = Vec::new();
                 let mut wallet_type = WalletType::Standard;
+                let mut seed_version = None;
+                let mut extra = BTreeMap::new();
+                let mut keystore_errors = Vec::new();
+                let mut unfinished_slots = Vec::new();
+                let mut parse_warnings = Vec::new();
+                let mut seen = HashSet::new();
 
                 while let Some(key) = map.next_key()? {
+                    // like python's json module, which electrum uses, the last duplicate wins,
+                    // but two keystores for the same slot can't be told apart
+                    let name = match &key {
+                        Field::Addrs => "addresses",
+                        Field::Keyst(slot) => slot.as_str(),
+                        Field::WalTyp => "wallet_type",
+                        Field::SeedVersion => "seed_version",
+                        Field::Other(name) => name.as_str(),
+                    }
+                    .to_string();
+                    if !seen.insert(name.clone()) {
+                        if let Field::Keyst(_) = key {
+                            return Err(de::Error::custom(format!(
+                                "duplicate keystore `{}`",
+                                name
+                            )));
+                        }
+                        parse_warnings.push(ConversionWarning::DuplicateField(name));
+                    }
                     match key {
                         Field::Addrs => {
-                            addresses = map.next_value()?;
+                            addresses = Some(map.next_value::<serde_json::Value>()?);
                         }
-                        Field::Keyst => {
-                            keystores.push(map.next_value()?);
+                        Field::Keyst(slot) => {
+                            let value = map.next_value_seed(KeystoreSeed {
+                                watch_only: self.options.watch_only,
+                            })?;
+                            if slot != "keystore" && is_placeholder(&value) {
+                                unfinished_slots.push(slot);
+                                continue;
+                            }
+                            match parse_keystore(value, self.options) {
+                                Ok(keystore) => keystores.push(keystore),
+                                Err(e) if self.options.partial => keystore_errors.push(e),
+                                Err(e) => return Err(de::Error::custom(e)),
+                            }
                         }
                         Field::WalTyp => {
                             wallet_type = map.next_value()?;
                         }
-                        Field::Ignore => {
-                            let _ignore = map.next_value::<de::IgnoredAny>()?;
+                        Field::SeedVersion => {
+                            let value = map.next_value::<serde_json::Value>()?;
+                            seed_version = Some(
+                                number_from_value("seed_version", &value, strict)
+                                    .map_err(de::Error::custom)?,
+                            );
+                        }
+                        Field::Other(name) => {
+                            if strict && !KNOWN_FIELDS.contains(&name.as_str()) {
+                                return Err(de::Error::custom(format!("unknown field `{}`", name)));
+                            }
+                            if self.options.watch_only && SECRET_FIELDS.contains(&name.as_str()) {
+                                map.next_value::<de::IgnoredAny>()?;
+                                continue;
+                            }
+                            let value = map.next_value::<serde_json::Value>()?;
+                            if strict {
+                                check_numbers(&name, &value).map_err(de::Error::custom)?;
+                            }
+                            extra.insert(name, value);
                         }
                     }
                 }
 
+                // imported wallets map each address to its public key and script type instead
+                let mut addresses = match addresses {
+                    Some(addresses) if wallet_type == WalletType::Imported => {
+                        extra.insert("addresses".to_string(), addresses);
+                        Addresses::new()
+                    }
+                    Some(addresses) => {
+                        Addresses::deserialize(addresses).map_err(de::Error::custom)?
+                    }
+                    None => Addresses::new(),
+                };
+
+                // the wizard only writes the slots of the cosigners added so far
+                if let WalletType::Multisig(multisig) = wallet_type {
+                    let y = multisig.cosigners();
+                    let missing = keystores.len() < y.into() && !seen.contains("keystore");
+                    if !unfinished_slots.is_empty() || missing {
+                        unfinished_slots.extend(
+                            (1..=y)
+                                .map(|i| format!("x{}/", i))
+                                .filter(|slot| !seen.contains(slot)),
+                        );
+                        unfinished_slots.sort_by_key(|slot| slot_number(slot));
+                    }
+                }
+
+                for duplicate in addresses.duplicates() {
+                    if strict {
+                        return Err(de::Error::custom(duplicate.to_error()));
+                    }
+                    parse_warnings.push(duplicate.to_warning());
+                }
+                addresses.remove_duplicates();
+
+                // base58 addresses and the `tb1` ones of signet look like testnet ones
+                let network = addresses
+                    .receiving
+                    .iter()
+                    .chain(&addresses.change)
+                    .next()
+                    .and_then(|address| kind_from_address_str(address).ok())
+                    .map(|(_kind, network)| network)
+                    .filter(|network| *network == Network::Regtest);
                 let wallet = ElectrumWalletFile {
                     addresses,
                     keystores,
                     wallet_type,
+                    seed_version,
+                    watch_only_copy: self.options.watch_only,
+                    keystore_errors,
+                    unfinished_slots,
+                    parse_warnings,
+                    extra,
+                    network,
+                    key_order: KeyOrder::default(),
                 };
-                wallet.validate().map_err(de::Error::custom)?;
+                #[cfg(feature = "log")]
+                {
+                    let mut fields = seen.iter().collect::<Vec<_>>();
+                    fields.sort();
+                    log_debug!(
+                        "parsed a {} wallet with {} keystores, fields {:?}, watch-only: {}",
+                        wallet.wallet_type,
+                        wallet.keystores.len(),
+                        fields,
+                        wallet.watch_only_copy
+                    );
+                    for (i, keystore) in wallet.keystores.iter().enumerate() {
+                        log_debug!(
+                            "keystore {}: {}, xprv: {}, seed: {}",
+                            wallet.keystore_name(i),
+                            keystore
+                                .xpub()
+                                .map(|(_xpub, kind, network)| format!("{} on {}", kind, network))
+                                .unwrap_or_else(|_| "undecodable xpub".to_string()),
+                            keystore.xprv.is_some(),
+                            keystore.seed.is_some()
+                        );
+                    }
+                }
+                if !wallet.is_incomplete() && !self.options.unvalidated {
+                    wallet.validate().map_err(de::Error::custom)?;
+                }
                 Ok(wallet)
             }
         }
@@ -379,13 +2957,164 @@ impl<'de> Deserialize<'de> for ElectrumWalletFile {
             "addr_history",
             "channel_backups",
             "keystore",
+            "seed_version",
             "wallet_type",
             "x1/",
             "x2/",
             "x3/",
         ];
-        deserializer.deserialize_struct("ElectrumWalletFile", FIELDS, ElectrumWalletFileVisitor)
+        deserializer.deserialize_struct(
+            "ElectrumWalletFile",
+            FIELDS,
+            ElectrumWalletFileVisitor {
+                options: self.options,
+            },
+        )
+    }
+}
+
+/// Deserializes a keystore as json object, skipping the secrets for watch-only parsing
+struct KeystoreSeed {
+    watch_only: bool,
+}
+
+impl<'de> de::DeserializeSeed<'de> for KeystoreSeed {
+    type Value = serde_json::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> de::Visitor<'de> for KeystoreSeed {
+    type Value = serde_json::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a keystore")
+    }
+
+    fn visit_map<V>(self, mut map: V) -> Result<Self::Value, V::Error>
+    where
+        V: de::MapAccess<'de>,
+    {
+        let mut keystore = serde_json::Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            if self.watch_only && SECRET_KEYSTORE_FIELDS.contains(&key.as_str()) {
+                map.next_value::<de::IgnoredAny>()?;
+            } else {
+                keystore.insert(key, map.next_value()?);
+            }
+        }
+        Ok(serde_json::Value::Object(keystore))
+    }
+}
+
+/// Whether a keystore is a slot the multisig wizard wrote before the cosigner was added: without xpub or an empty one
+fn is_placeholder(value: &serde_json::Value) -> bool {
+    let no_xprv = value
+        .get("xprv")
+        .unwrap_or(&serde_json::Value::Null)
+        .is_null();
+    let no_xpub = match value.get("xpub") {
+        None | Some(serde_json::Value::Null) => true,
+        Some(serde_json::Value::String(xpub)) => xpub.trim().is_empty(),
+        Some(_) => false,
+    };
+    no_xprv && no_xpub
+}
+
+/// The number of a multisig keystore slot, like 3 for `x3/`
+fn slot_number(slot: &str) -> Option<u8> {
+    slot.strip_prefix('x')?.strip_suffix('/')?.parse().ok()
+}
+
+/// Build a keystore from its json object. When parsing partially, its xpub is checked too, so that a garbled
+/// keystore is left out rather than failing later.
+fn parse_keystore(
+    mut value: serde_json::Value,
+    options: &ParseOptions,
+) -> Result<Keystore, String> {
+    // imported keystores hold key pairs instead of an extended key
+    if let Some(keystore) = value.as_object_mut() {
+        if keystore.get("type") == Some(&serde_json::Value::from(IMPORTED_KEYSTORE)) {
+            keystore
+                .entry("xpub")
+                .or_insert_with(|| serde_json::Value::from(""));
+        }
+    }
+    if let Some(version) = value.get("pw_hash_version") {
+        number_from_value::<u32>("pw_hash_version", version, options.strict)?;
     }
+    let mut keystore = Keystore::deserialize(value).map_err(|e| e.to_string())?;
+    // older coldcard plugins stored the fingerprint as little endian integer instead
+    if keystore.root_fingerprint.is_none() {
+        if let Some(xfp) = keystore.extra.get("ckcc_xfp") {
+            let xfp = number_from_value::<u32>("ckcc_xfp", xfp, options.strict)?;
+            keystore.root_fingerprint = Fingerprint::from(xfp.to_le_bytes())
+                .to_string()
+                .as_str()
+                .into();
+        }
+    }
+    if options.partial && !keystore.is_imported() {
+        keystore.xpub().map_err(|e| e.to_string())?;
+    }
+    Ok(keystore)
+}
+
+/// Get a number from a json value. Older electrum versions and third party tools sometimes write numbers as strings,
+/// which is only accepted when not strict.
+fn number_from_value<T>(field: &str, value: &serde_json::Value, strict: bool) -> Result<T, String>
+where
+    T: FromStr + de::DeserializeOwned,
+{
+    match value {
+        serde_json::Value::Number(_) => {
+            T::deserialize(value).map_err(|_| format!("{}: invalid number {}", field, value))
+        }
+        serde_json::Value::String(s) if strict => Err(format!(
+            "{}: expected a number, found the string {:?}",
+            field, s
+        )),
+        serde_json::Value::String(s) => s
+            .trim()
+            .parse()
+            .map_err(|_| format!("{}: invalid number {:?}", field, s)),
+        _ => Err(format!("{}: expected a number, found {}", field, value)),
+    }
+}
+
+/// Strictly check the numbers in the known fields which we otherwise ignore
+fn check_numbers(field: &str, value: &serde_json::Value) -> Result<(), String> {
+    match field {
+        "stored_height" => number_from_value::<i64>(field, value, true).map(|_| ()),
+        "addr_history" => value
+            .as_object()
+            .into_iter()
+            .flat_map(|history| history.values())
+            .filter_map(|txs| txs.as_array())
+            .flatten()
+            .filter_map(|tx| tx.get(1))
+            .try_for_each(|height| {
+                number_from_value::<i64>("addr_history height", height, true).map(|_| ())
+            }),
+        _ => Ok(()),
+    }
+}
+
+/// Deserialize a number that may be encoded as string
+fn lenient_number<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr + de::DeserializeOwned,
+{
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    value
+        .map(|value| number_from_value("number", &value, false).map_err(de::Error::custom))
+        .transpose()
 }
 
 /// Representation of the addresses section of an electrum wallet file
@@ -402,51 +3131,614 @@ impl Addresses {
             receiving: Vec::new(),
         }
     }
+
+    /// The receiving then the change addresses, with whether they are change addresses and their index
+    fn positions(&self) -> impl Iterator<Item = (bool, usize, &String)> {
+        let receiving = self
+            .receiving
+            .iter()
+            .enumerate()
+            .map(|(i, a)| (false, i, a));
+        let change = self.change.iter().enumerate().map(|(i, a)| (true, i, a));
+        receiving.chain(change)
+    }
+
+    /// The addresses that are listed again, by receiving or change, after their first occurrence
+    fn duplicates(&self) -> Vec<DuplicateAddress> {
+        let mut first = HashMap::new();
+        let mut duplicates = Vec::new();
+        for (change, index, address) in self.positions() {
+            match first.get(address) {
+                Some(&position) => duplicates.push(DuplicateAddress {
+                    address: address.clone(),
+                    position: (change, index),
+                    first: position,
+                }),
+                None => {
+                    first.insert(address, (change, index));
+                }
+            }
+        }
+        duplicates
+    }
+
+    /// Keep only the first occurrence of each address
+    fn remove_duplicates(&mut self) {
+        let mut seen = HashSet::new();
+        self.receiving.retain(|a| seen.insert(a.clone()));
+        self.change.retain(|a| seen.insert(a.clone()));
+    }
+}
+
+/// An address listed more than once, which breaks telling the index of an address
+struct DuplicateAddress {
+    address: String,
+    /// Whether it is listed as change address, and the index it is listed at
+    position: (bool, usize),
+    first: (bool, usize),
+}
+
+impl DuplicateAddress {
+    fn to_error(&self) -> Electrum2DescriptorError {
+        Electrum2DescriptorError::DuplicateAddress(
+            self.address.clone(),
+            position_name(self.position),
+            position_name(self.first),
+        )
+    }
+
+    fn to_warning(&self) -> ConversionWarning {
+        ConversionWarning::DuplicateAddress(
+            self.address.clone(),
+            position_name(self.position),
+            position_name(self.first),
+        )
+    }
+}
+
+/// Whether the extended keys of the networks are encoded the same: the test networks use the ones of testnet
+fn shares_keys(a: Network, b: Network) -> bool {
+    (a == Network::Bitcoin) == (b == Network::Bitcoin)
+}
+
+/// The descriptor of the key of a standard wallet, for the script kind of the key
+fn singlesig_descriptor(kind: ScriptKind, key: &str) -> Result<String, Electrum2DescriptorError> {
+    match kind {
+        ScriptKind::Standard => Ok(format!("pkh({})", key)),
+        ScriptKind::P2wpkhP2sh => Ok(format!("sh(wpkh({}))", key)),
+        ScriptKind::P2wpkh => Ok(format!("wpkh({})", key)),
+        ScriptKind::P2tr => Ok(format!("tr({})", key)),
+        kind => Err(Electrum2DescriptorError::UnknownScriptKind(
+            kind.to_string(),
+        )),
+    }
+}
+
+/// The multisig script kind a singlesig prefix of a cosigner stands for
+pub(crate) fn multisig_kind(kind: ScriptKind) -> ScriptKind {
+    match kind {
+        ScriptKind::P2wpkh => ScriptKind::P2wsh,
+        ScriptKind::P2wpkhP2sh => ScriptKind::P2wshP2sh,
+        kind => kind,
+    }
+}
+
+/// Decode an extended key with the bip32 prefix or a slip-0132 one like zprv or Vpub, which must agree with `kind`.
+/// Singlesig prefixes stand for their multisig counterparts, as some coordinators write them for cosigners.
+fn decode_xkey(
+    kind: &str,
+    xkey: &str,
+) -> Result<(Option<ExtendedPrivKey>, ExtendedPubKey), Electrum2DescriptorError> {
+    let xkey = &clean_key(xkey)?;
+    if let Ok(xprv) = ExtendedPrivKey::from_str(xkey) {
+        return Ok((Some(xprv), ExtendedPubKey::from_priv(secp(), &xprv)));
+    }
+    if let Ok(xpub) = ExtendedPubKey::from_str(xkey) {
+        return Ok((None, xpub));
+    }
+    let (xprv, xpub, prefix) = match ElectrumExtendedPrivKey::from_str(xkey) {
+        Ok(exprv) => (
+            Some(*exprv.xprv()),
+            ExtendedPubKey::from_priv(secp(), exprv.xprv()),
+            exprv.kind().to_string(),
+        ),
+        Err(_) => {
+            let expub = ElectrumExtendedPubKey::from_str(xkey)?;
+            (None, *expub.xpub(), expub.kind().to_string())
+        }
+    };
+    let prefix = ScriptKind::from_str(&prefix)?;
+    let given = ScriptKind::from_str(kind)?;
+    if prefix != given && multisig_kind(prefix) != given {
+        return Err(Electrum2DescriptorError::ScriptKindConflict(
+            xkey.to_string(),
+            prefix.to_string(),
+            given.to_string(),
+        ));
+    }
+    Ok((xprv, xpub))
+}
+
+/// The sortedmulti, or multi for [`KeyOrder::AsStored`], descriptor of the keys of a multisig wallet, wrapped as the
+/// script kind of the keys says. Singlesig segwit prefixes stand for their multisig counterparts, as they do in
+/// [`Keystore::canonicalize`].
+fn multisig_descriptor(
+    kind: ScriptKind,
+    threshold: u8,
+    keys: &[String],
+    order: KeyOrder,
+) -> Result<String, Electrum2DescriptorError> {
+    let multi = match order {
+        KeyOrder::Sorted => "sortedmulti",
+        KeyOrder::AsStored => "multi",
+    };
+    let multi = format!("{}({},{})", multi, threshold, keys.join(","));
+    match kind {
+        ScriptKind::Standard => Ok(format!("sh({})", multi)),
+        ScriptKind::P2wsh | ScriptKind::P2wpkh => Ok(format!("wsh({})", multi)),
+        ScriptKind::P2wshP2sh | ScriptKind::P2wpkhP2sh => Ok(format!("sh(wsh({}))", multi)),
+        ScriptKind::P2tr => Err(Electrum2DescriptorError::TaprootMultisig),
+    }
+}
+
+/// Name a position in the addresses, e.g. "change address 3"
+fn position_name((change, index): (bool, usize)) -> String {
+    let chain = if change { "change" } else { "receiving" };
+    format!("{} address {}", chain, index)
+}
+
+/// The identity of the key of a keystore, see [`Keystore::key_id`]: the bip32 serialization of the xpub, which holds
+/// the network and key material but not the script kind, or the stored string if it doesn't decode. Keystores compare
+/// as a whole, labels and all, their key ids compare the keys only.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct KeyId(Vec<u8>);
+
+impl KeyId {
+    /// The bip32 serialization of the xpub
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
 }
 
 /// Representation of a keystore section of an electrum wallet file. Can be single sig "keystore" or multisig "x1/" "x2/" ...
+/// Whether two keystores hold the same key, regardless of labels, derivation, the xprv and the xpub's slip-0132
+/// prefix, is [`Keystore::same_key`]; [`Keystore::key_id`] is the key to hash and sort them by.
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 pub struct Keystore {
     #[serde(default = "Keystore::default_type")]
     pub r#type: String,
     pub xprv: Option<String>,
     pub xpub: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<String>,
+    /// The extension ("passphrase") the seed was created with, the seed alone restores to other keys.
+    /// Encrypted with the wallet password like the seed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passphrase: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "lenient_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pw_hash_version: Option<u32>,
+    #[serde(default, skip_serializing_if = "OptionalString::is_missing")]
+    pub derivation: OptionalString,
+    #[serde(default, skip_serializing_if = "OptionalString::is_missing")]
+    pub root_fingerprint: OptionalString,
+    #[serde(default, skip_serializing_if = "OptionalString::is_missing")]
+    pub label: OptionalString,
+    /// Written by tools that mark taproot keystores explicitly, e.g. `p2tr`
+    #[serde(default, skip_serializing_if = "OptionalString::is_missing")]
+    pub script_type: OptionalString,
+    /// The receiving and change chains under the key, if not electrum's 0 and 1, for cosigners of descriptors like
+    /// `wsh(sortedmulti(2,xpubA/0/*,xpubB/2/*))`. Electrum doesn't know the field and derives 0 and 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chains: Option<(u32, u32)>,
+    /// The fields we don't model, e.g. the `hw_type` of hardware keystores, kept verbatim
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 impl Keystore {
-    /// Construct a Keystore from script kind and xpub or xprv
-    fn new(kind: &str, xkey: &str) -> Result<Self, Electrum2DescriptorError> {
-        let xprv = ExtendedPrivKey::from_str(xkey);
-        let exprv = if let Ok(xprv) = xprv {
-            Some(ElectrumExtendedPrivKey::new(xprv, kind.to_string()).electrum_xprv()?)
-        } else {
-            None
+    /// Construct a Keystore from script kind and xpub or xprv, with the bip32 prefix or the slip-0132 one of the kind
+    pub fn new(kind: &str, xkey: &str) -> Result<Self, Electrum2DescriptorError> {
+        let (xprv, xpub) = decode_xkey(kind, xkey)?;
+        let exprv = match xprv {
+            Some(xprv) => {
+                Some(ElectrumExtendedPrivKey::new(xprv, kind.to_string()).electrum_xprv()?)
+            }
+            None => None,
         };
-
-        let expub = if let Ok(xprv) = xprv {
-            let secp = bitcoin::secp256k1::Secp256k1::new();
-            ElectrumExtendedPubKey::new(ExtendedPubKey::from_priv(&secp, &xprv), kind.to_string())
-        } else {
-            ElectrumExtendedPubKey::new(ExtendedPubKey::from_str(xkey)?, kind.to_string())
-        }
-        .electrum_xpub()?;
+        let expub = ElectrumExtendedPubKey::new(xpub, kind.to_string()).electrum_xpub()?;
 
         Ok(Keystore {
             r#type: Keystore::default_type(),
             xprv: exprv,
             xpub: expub,
+            seed: None,
+            passphrase: None,
+            pw_hash_version: None,
+            derivation: OptionalString::default(),
+            root_fingerprint: OptionalString::default(),
+            label: OptionalString::default(),
+            script_type: OptionalString::default(),
+            chains: None,
+            extra: BTreeMap::new(),
         })
     }
 
-    /// Get the xprv if available or else the xpub.
-    fn get_xkey(&self) -> Result<Box<dyn ElectrumExtendedKey>, Electrum2DescriptorError> {
+    /// Construct a Keystore from an electrum standard or segwit seed and its extension, empty if it has none.
+    /// The keys are derived like electrum does, the seed and the extension are kept.
+    pub fn from_seed(
+        seed: &str,
+        extension: &str,
+        network: Network,
+    ) -> Result<Self, Electrum2DescriptorError> {
+        let seed = seed::normalize(seed);
+        let (kind, derivation) = match SeedType::of(&seed)? {
+            SeedType::Standard => (ScriptKind::Standard, DerivationPath::master()),
+            SeedType::Segwit => (
+                ScriptKind::P2wpkh,
+                DerivationPath::from(vec![ChildNumber::from_hardened_idx(0)?]),
+            ),
+        };
+        let secp = secp();
+        let mut bip32_seed = seed::bip32_seed(&seed, extension);
+        let master = ExtendedPrivKey::new_master(network, &bip32_seed);
+        seed::wipe(&mut bip32_seed);
+        let mut master = master?;
+        let xprv = master.derive_priv(secp, &derivation);
+        let fingerprint = master.fingerprint(secp);
+        master.private_key.non_secure_erase();
+        let mut xprv = xprv?;
+        let mut xprv_str = xprv.to_string();
+        xprv.private_key.non_secure_erase();
+        let keystore = Keystore::new(kind.as_kind_str(), &xprv_str);
+        seed::wipe_string(&mut xprv_str);
+        let mut keystore = keystore?.with_origin(fingerprint, &derivation);
+        keystore.seed = Some(seed);
+        if !extension.is_empty() {
+            keystore.passphrase = Some(extension.to_string());
+        }
+        Ok(keystore)
+    }
+
+    /// Whether the seed has an extension, which is needed along with the seed to restore the keys
+    pub fn has_seed_extension(&self) -> bool {
+        self.passphrase.as_deref().is_some_and(|p| !p.is_empty())
+    }
+
+    /// Construct a Keystore from a slip-0132 extended key like zpub or Vprv, the script kind is implied by the prefix
+    pub fn from_electrum_xkey(xkey: &str) -> Result<Self, Electrum2DescriptorError> {
+        match ElectrumExtendedPrivKey::from_str(xkey) {
+            Ok(exprv) => Keystore::new(exprv.kind(), &exprv.xkey_str()),
+            Err(_) => {
+                let expub = ElectrumExtendedPubKey::from_str(xkey)?;
+                Keystore::new(expub.kind(), &expub.xkey_str())
+            }
+        }
+    }
+
+    /// Set where the key comes from: the fingerprint of its master key and its derivation from there.
+    /// Each cosigner of a multisig wallet has its own.
+    pub fn with_origin(mut self, fingerprint: Fingerprint, derivation: &DerivationPath) -> Self {
+        self.root_fingerprint = fingerprint.to_string().as_str().into();
+        self.derivation = derivation.to_string().as_str().into();
+        self
+    }
+
+    /// The fingerprint of the master key and the derivation from there, if both are known
+    pub fn origin(
+        &self,
+    ) -> Result<Option<(Fingerprint, DerivationPath)>, Electrum2DescriptorError> {
+        match (self.fingerprint(), self.derivation.as_deref()) {
+            (Some(fingerprint), Some(derivation)) => {
+                Ok(Some((fingerprint, DerivationPath::from_str(derivation)?)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// The origin written before the key in descriptors: the stored one if it is valid and as deep as the key.
+    /// Master keys have none, their fingerprint is their own.
+    pub(crate) fn descriptor_origin(&self) -> Option<(Fingerprint, DerivationPath)> {
+        let (xpub, _kind, _network) = self.xpub().ok()?;
+        self.origin().ok().flatten().filter(|(_, derivation)| {
+            !derivation.is_master() && derivation.len() == xpub.depth as usize
+        })
+    }
+
+    /// Check that a derivation following the bip of a script kind agrees with the kind of the key.
+    /// The derivations of cosigners may differ otherwise.
+    fn check_derivation(&self) -> Result<(), Electrum2DescriptorError> {
+        let (_xpub, kind, _network) = self.xpub()?;
+        let derivation = match self.derivation.as_deref() {
+            Some(derivation) => DerivationPath::from_str(derivation)?,
+            None => return Ok(()),
+        };
+        match ScriptKind::from_derivation(&derivation) {
+            Some(implied) if implied != kind => Err(Electrum2DescriptorError::DerivationMismatch(
+                derivation.to_string(),
+                implied.to_string(),
+                kind.to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// See [`ElectrumWalletFile::canonicalize`]
+    fn canonicalize(&mut self, multisig: bool) -> Result<(), Electrum2DescriptorError> {
+        let (xpub, kind, _network) = self.xpub()?;
+        let kind = match (kind, multisig) {
+            (ScriptKind::P2wpkh, true) => ScriptKind::P2wsh,
+            (ScriptKind::P2wpkhP2sh, true) => ScriptKind::P2wshP2sh,
+            (ScriptKind::P2wsh, false) => ScriptKind::P2wpkh,
+            (ScriptKind::P2wshP2sh, false) => ScriptKind::P2wpkhP2sh,
+            // taproot keys have the standard prefix
+            (ScriptKind::P2tr, _) => ScriptKind::Standard,
+            (kind, _) => kind,
+        };
+        let kind = kind.as_kind_str().to_string();
+        self.xpub = ElectrumExtendedPubKey::new(xpub, kind.clone()).electrum_xpub()?;
+        // encrypted xprvs are kept as they are
+        if let Ok(Some(xprv)) = self.xprv() {
+            self.xprv = Some(ElectrumExtendedPrivKey::new(xprv, kind).electrum_xprv()?);
+        }
+
+        if let Some(derivation) = self.derivation.as_deref() {
+            if let Ok(derivation) = DerivationPath::from_str(derivation) {
+                self.derivation = derivation.to_string().as_str().into();
+            }
+        }
+        if self.root_fingerprint.is_none() {
+            self.root_fingerprint = self
+                .compute_root_fingerprint()
+                .map(|fingerprint| fingerprint.to_string())
+                .into();
+        }
+        if self.derivation.is_none() && xpub.depth == 0 {
+            self.derivation = "m".into();
+        }
+        for field in [
+            &mut self.derivation,
+            &mut self.root_fingerprint,
+            &mut self.label,
+            &mut self.script_type,
+        ] {
+            if field.is_none() {
+                *field = OptionalString::default();
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the label, which is how electrum tells cosigners apart
+    pub fn with_label(mut self, label: &str) -> Self {
+        self.label = label.into();
+        self
+    }
+
+    /// Whether the keys are held by a hardware wallet
+    pub fn is_hardware(&self) -> bool {
+        self.r#type == "hardware"
+    }
+
+    /// Whether the keystore holds the key pairs of imported private keys rather than an extended key
+    pub fn is_imported(&self) -> bool {
+        self.r#type == IMPORTED_KEYSTORE
+    }
+
+    /// Whether the keystore holds an xprv or seed that is encrypted with the wallet password.
+    /// Electrum stores them base64 encoded then, so the xprv doesn't parse as extended key and the seed has no spaces.
+    pub fn has_encrypted_secrets(&self) -> bool {
+        let xprv_encrypted = self.xprv().is_err();
+        let seed_encrypted = self
+            .seed
+            .as_deref()
+            .map(|seed| !seed.contains(' '))
+            .unwrap_or(false);
+        xprv_encrypted || seed_encrypted
+    }
+
+    /// Decrypt the xprv, seed, seed extension and imported keys, failing unless the xprv decrypts to a key
+    fn decrypt_secrets(&mut self, password: &str) -> Result<(), Electrum2DescriptorError> {
+        let version = self.pw_hash_version;
+        let decrypt = |secret: &mut String| -> Result<(), Electrum2DescriptorError> {
+            *secret = encryption::decrypt_secret(secret, password, version)?;
+            Ok(())
+        };
+        if let Some(xprv) = self.xprv.as_mut() {
+            decrypt(xprv)?;
+            if self.xprv().is_err() {
+                return Err(Electrum2DescriptorError::InvalidPassword);
+            }
+        }
+        if let Some(seed) = self.seed.as_mut() {
+            decrypt(seed)?;
+        }
+        if let Some(passphrase) = self.passphrase.as_mut().filter(|p| !p.is_empty()) {
+            decrypt(passphrase)?;
+        }
+        if let Some(serde_json::Value::Object(keypairs)) = self.extra.get_mut("keypairs") {
+            for value in keypairs.values_mut() {
+                if let serde_json::Value::String(key) = value {
+                    decrypt(key)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the keystore can sign without external help, that is it holds an unencrypted xprv
+    pub fn can_sign(&self) -> bool {
+        matches!(self.xprv(), Ok(Some(_)))
+    }
+
+    /// The xpub as stored by electrum, with its slip-0132 prefix
+    pub fn electrum_xpub(&self) -> &str {
+        &self.xpub
+    }
+
+    /// Decode the xpub, along with the script kind and network its prefix stands for.
+    /// Keys with the standard prefix are taproot if [`Keystore::is_taproot`].
+    pub fn xpub(&self) -> Result<(ExtendedPubKey, ScriptKind, Network), Electrum2DescriptorError> {
+        if self.is_imported() {
+            return Err(Electrum2DescriptorError::ImportedKeys);
+        }
+        let expub = ElectrumExtendedPubKey::from_str(&self.xpub)
+            .map_err(|e| Electrum2DescriptorError::InvalidKeystoreField("xpub", Box::new(e)))?;
+        let kind = match ScriptKind::from_str(expub.kind())? {
+            ScriptKind::Standard if self.is_taproot() => ScriptKind::P2tr,
+            kind => kind,
+        };
+        Ok((*expub.xpub(), kind, expub.xpub().network))
+    }
+
+    /// The chain the receiving or the change addresses are derived from, see [`Keystore::chains`]
+    pub fn chain(&self, change: bool) -> u32 {
+        match self.chains {
+            Some((receiving, _change)) if !change => receiving,
+            Some((_receiving, change)) => change,
+            None => change as u32,
+        }
+    }
+
+    /// Whether the keystore is marked as taproot, as there is no slip-0132 prefix for it:
+    /// by a `script_type` of `p2tr` or by the bip-0086 derivation m/86'
+    pub fn is_taproot(&self) -> bool {
+        self.script_type.as_deref() == Some("p2tr")
+            || ["m/86'", "m/86h"].iter().any(|purpose| {
+                self.derivation.as_deref().is_some_and(|derivation| {
+                    derivation == *purpose || derivation.starts_with(&format!("{}/", purpose))
+                })
+            })
+    }
+
+    /// The identity of the key, to deduplicate and sort keystores by, e.g. as key of a `HashSet` or `BTreeMap`
+    pub fn key_id(&self) -> KeyId {
+        match self.xpub() {
+            Ok((xpub, _kind, _network)) => KeyId(xpub.encode().to_vec()),
+            Err(_) => KeyId(self.xpub.as_bytes().to_vec()),
+        }
+    }
+
+    /// Whether both keystores hold the same key, i.e. have the same [`Keystore::key_id`]
+    pub fn same_key(&self, other: &Self) -> bool {
+        self.key_id() == other.key_id()
+    }
+
+    /// The keystore with its keys re-encoded for another network, regardless of the prefix they were encoded with.
+    /// Meant to recover keys exported with the wrong prefix.
+    pub fn with_network(&self, network: Network) -> Result<Self, Electrum2DescriptorError> {
+        let mut keystore = self.clone();
+        keystore.xpub = ElectrumExtendedPubKey::from_str(&self.xpub)?
+            .with_network(network)
+            .electrum_xpub()?;
+        if let Some(xprv) = &self.xprv {
+            keystore.xprv = Some(
+                ElectrumExtendedPrivKey::from_str(xprv)?
+                    .with_network(network)
+                    .electrum_xprv()?,
+            );
+        }
+        Ok(keystore)
+    }
+
+    /// The keystore with its keys used for `kind`, for plain xpub/xprv keys whose prefix doesn't tell the script kind.
+    /// The keys are re-encoded with the slip-0132 prefix of the kind, taproot keystores are marked by `script_type`.
+    /// Fails if the prefix or the taproot mark is for another kind.
+    pub fn with_kind(&self, kind: ScriptKind) -> Result<Self, Electrum2DescriptorError> {
+        let (_xpub, current, _network) = self.xpub()?;
+        if current == kind {
+            return Ok(self.clone());
+        }
+        if current == ScriptKind::P2tr {
+            return Err(Electrum2DescriptorError::ScriptKindConflict(
+                self.xpub.clone(),
+                current.to_string(),
+                kind.to_string(),
+            ));
+        }
+        // taproot keys have the standard prefix
+        let encoded = match kind {
+            ScriptKind::P2tr => ScriptKind::Standard,
+            kind => kind,
+        };
+        let mut keystore = self.clone();
+        let expub = ElectrumExtendedPubKey::from_str(&self.xpub)?.with_kind(kind)?;
+        keystore.xpub =
+            ElectrumExtendedPubKey::new(*expub.xpub(), encoded.as_kind_str().to_string())
+                .electrum_xpub()?;
         if let Some(xprv) = &self.xprv {
-            let exprv = ElectrumExtendedPrivKey::from_str(xprv)?;
-            return Ok(Box::new(exprv));
+            let exprv = ElectrumExtendedPrivKey::from_str(xprv)?.with_kind(kind)?;
+            keystore.xprv = Some(
+                ElectrumExtendedPrivKey::new(*exprv.xprv(), encoded.as_kind_str().to_string())
+                    .electrum_xprv()?,
+            );
+        }
+        if kind == ScriptKind::P2tr {
+            keystore.script_type = "p2tr".into();
+        }
+        Ok(keystore)
+    }
+
+    /// Decode the xprv if there is one
+    pub fn xprv(&self) -> Result<Option<ExtendedPrivKey>, Electrum2DescriptorError> {
+        self.xprv
+            .as_deref()
+            .map(|xprv| {
+                let exprv = ElectrumExtendedPrivKey::from_str(xprv).map_err(|e| {
+                    Electrum2DescriptorError::InvalidKeystoreField("xprv", Box::new(e))
+                })?;
+                Ok(*exprv.xprv())
+            })
+            .transpose()
+    }
+
+    /// Get the xprv if available or else the xpub.
+    pub fn get_xkey(&self) -> Result<ElectrumExtendedKeyEnum, Electrum2DescriptorError> {
+        let (xpub, kind, _network) = self.xpub()?;
+        let kind = kind.as_kind_str().to_string();
+        match self.xprv()? {
+            Some(xprv) => Ok(ElectrumExtendedPrivKey::new(xprv, kind).into()),
+            None => Ok(ElectrumExtendedPubKey::new(xpub, kind).into()),
+        }
+    }
+
+    /// Computes the fingerprint of the master key, if the keystore holds the master key itself.
+    /// That is the case for a master xprv, a depth 0 xpub or a derivation of "m".
+    /// For account level keys without a stored root_fingerprint this returns None, as the master can't be known.
+    pub fn compute_root_fingerprint(&self) -> Option<Fingerprint> {
+        let (xpub, _kind, _network) = self.xpub().ok()?;
+        let xpub = match self.xprv().ok()? {
+            Some(xprv) => {
+                let secp = secp();
+                ExtendedPubKey::from_priv(secp, &xprv)
+            }
+            None => xpub,
+        };
+        if xpub.depth == 0 || self.derivation.as_deref() == Some("m") {
+            Some(xpub.fingerprint())
+        } else {
+            None
+        }
+    }
+
+    /// The fingerprint of the master key. The stored root_fingerprint takes precedence, if there is none it is computed when possible.
+    pub fn root_fingerprint(&self) -> Result<Option<Fingerprint>, Electrum2DescriptorError> {
+        match self.root_fingerprint.as_deref() {
+            Some(fingerprint) => Ok(Some(Fingerprint::from_str(fingerprint)?)),
+            None => Ok(self.compute_root_fingerprint()),
         }
+    }
 
-        let expub = ElectrumExtendedPubKey::from_str(&self.xpub)?;
-        Ok(Box::new(expub))
+    /// The fingerprint of the master key: the stored root_fingerprint if it is valid, else the computed one when
+    /// possible. Unlike [`Keystore::root_fingerprint`] it doesn't fail for an invalid stored root_fingerprint.
+    pub fn fingerprint(&self) -> Option<Fingerprint> {
+        self.root_fingerprint
+            .as_deref()
+            .and_then(|fingerprint| Fingerprint::from_str(fingerprint).ok())
+            .or_else(|| self.compute_root_fingerprint())
     }
 
     /// Default keystore type to use if nothing else was specified
@@ -455,11 +3747,179 @@ impl Keystore {
     }
 }
 
+/// An optional string field of a keystore. Electrum writes missing values as `null`, as `""` or omits the field entirely.
+/// All of these read as `None`, but the original form is remembered so that serialization writes back what was read.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OptionalString {
+    value: Option<String>,
+    absent: Absent,
+}
+
+/// The form in which an absent optional string was found in the wallet file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Absent {
+    #[default]
+    Missing,
+    Null,
+    Empty,
+}
+
+impl OptionalString {
+    /// Returns the value, if there is one
+    pub fn as_deref(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+
+    /// Returns true if there is a value
+    pub fn is_some(&self) -> bool {
+        self.value.is_some()
+    }
+
+    /// Returns true if there is no value
+    pub fn is_none(&self) -> bool {
+        self.value.is_none()
+    }
+
+    /// Returns true if the field was missing in the wallet file, so it should not be serialized
+    fn is_missing(&self) -> bool {
+        self.value.is_none() && self.absent == Absent::Missing
+    }
+}
+
+impl From<Option<String>> for OptionalString {
+    fn from(value: Option<String>) -> Self {
+        let value = value.filter(|v| !v.is_empty());
+        OptionalString {
+            value,
+            absent: Absent::Missing,
+        }
+    }
+}
+
+impl From<&str> for OptionalString {
+    fn from(value: &str) -> Self {
+        Some(value.to_string()).into()
+    }
+}
+
+impl<'de> Deserialize<'de> for OptionalString {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Option::<String>::deserialize(deserializer)?;
+        Ok(match value {
+            None => OptionalString {
+                value: None,
+                absent: Absent::Null,
+            },
+            Some(v) if v.is_empty() => OptionalString {
+                value: None,
+                absent: Absent::Empty,
+            },
+            Some(v) => OptionalString {
+                value: Some(v),
+                absent: Absent::Missing,
+            },
+        })
+    }
+}
+
+impl Serialize for OptionalString {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match (&self.value, self.absent) {
+            (Some(v), _) => serializer.serialize_str(v),
+            (None, Absent::Empty) => serializer.serialize_str(""),
+            (None, _) => serializer.serialize_none(),
+        }
+    }
+}
+
 /// Representation of the wallet_type section of an electrum wallet file. Has custom serialization and de-serialization implementatoin.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum WalletType {
     Standard,
-    Multisig(u8, u8),
+    Multisig(Multisig),
+    /// Imported private keys, each with its own address, see [`ElectrumWalletFile::from_private_key_export`]
+    Imported,
+}
+
+impl WalletType {
+    /// A multisig wallet type, failing unless the threshold is within the number of cosigners as
+    /// [`validate_threshold`] checks
+    pub fn multisig(threshold: usize, cosigners: usize) -> Result<Self, Electrum2DescriptorError> {
+        Ok(WalletType::Multisig(Multisig::new(threshold, cosigners)?))
+    }
+
+    /// The number of signatures needed to spend, 1 for a standard or an imported wallet
+    pub fn threshold(&self) -> u8 {
+        match self {
+            WalletType::Standard | WalletType::Imported => 1,
+            WalletType::Multisig(multisig) => multisig.threshold(),
+        }
+    }
+
+    /// The number of keystores, 1 for a standard or an imported wallet
+    pub fn cosigners(&self) -> u8 {
+        match self {
+            WalletType::Standard | WalletType::Imported => 1,
+            WalletType::Multisig(multisig) => multisig.cosigners(),
+        }
+    }
+}
+
+/// The threshold and the number of cosigners of a multisig wallet, only built through the checks of
+/// [`validate_threshold`]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Multisig {
+    threshold: u8,
+    cosigners: u8,
+}
+
+impl Multisig {
+    pub fn new(threshold: usize, cosigners: usize) -> Result<Self, Electrum2DescriptorError> {
+        let (threshold, cosigners) = validate_threshold(threshold, cosigners)?;
+        Ok(Multisig {
+            threshold,
+            cosigners,
+        })
+    }
+
+    /// The number of signatures needed to spend
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// The number of cosigners, and so of keystores
+    pub fn cosigners(&self) -> u8 {
+        self.cosigners
+    }
+}
+
+/// Debugs as `2, 3` so that a [`WalletType`] debugs as `Multisig(2, 3)`, as in the log messages
+impl fmt::Debug for Multisig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, {}", self.threshold, self.cosigners)
+    }
+}
+
+/// Check the threshold of a multisig wallet: at least one signature and at most one per cosigner, of which there can
+/// be 255 at most. Returns both as they are stored in [`Multisig`].
+pub fn validate_threshold(
+    threshold: usize,
+    cosigners: usize,
+) -> Result<(u8, u8), Electrum2DescriptorError> {
+    let cosigners_u8 = u8::try_from(cosigners)
+        .map_err(|_| Electrum2DescriptorError::TooManyKeyStores(cosigners))?;
+    if threshold == 0 || threshold > cosigners {
+        return Err(Electrum2DescriptorError::NumberSignaturesKeyStores(
+            threshold, cosigners,
+        ));
+    }
+    Ok((threshold as u8, cosigners_u8))
 }
 
 impl fmt::Display for WalletType {
@@ -473,7 +3933,7 @@ impl FromStr for WalletType {
 
     /// Parse WalletType from a string representation
     fn from_str(wallet_type: &str) -> Result<Self, Self::Err> {
-        let re = Regex::new(r#"(standard)|(\d+)(of)(\d+)"#)?;
+        let re = Regex::new(r#"(standard)|(imported)|(\d+)(of)(\d+)"#)?;
         let captures = re.captures(wallet_type).map(|captures| {
             captures
                 .iter()
@@ -484,7 +3944,13 @@ impl FromStr for WalletType {
         });
         match captures.as_deref() {
             Some(["standard"]) => Ok(WalletType::Standard),
-            Some([x, "of", y]) => Ok(WalletType::Multisig(x.parse().unwrap(), y.parse().unwrap())),
+            Some(["imported"]) => Ok(WalletType::Imported),
+            Some([x, "of", y]) => match (x.parse(), y.parse()) {
+                (Ok(x), Ok(y)) => WalletType::multisig(x, y),
+                _ => Err(Electrum2DescriptorError::UnknownWalletType(
+                    wallet_type.to_string(),
+                )),
+            },
             _ => Err(Electrum2DescriptorError::UnknownWalletType(
                 wallet_type.to_string(),
             )),
@@ -509,7 +3975,10 @@ impl Serialize for WalletType {
     {
         let s = match *self {
             WalletType::Standard => "standard".to_string(),
-            WalletType::Multisig(x, y) => format!("{}of{}", x, y),
+            WalletType::Multisig(multisig) => {
+                format!("{}of{}", multisig.threshold, multisig.cosigners)
+            }
+            WalletType::Imported => "imported".to_string(),
         };
         serializer.serialize_str(&s)
     }
@@ -0,0 +1,208 @@
+//! Decrypting what electrum encrypts with the wallet password: the whole wallet file, as an ecies message to a key
+//! derived from the password, and the secrets of the keystores, with aes keyed by the hash of the password.
+use crate::{
+    aes::Aes, inflate::inflate, seed::pbkdf2_hmac_sha512, utils::secp, Electrum2DescriptorError,
+};
+use bitcoin::{
+    base64,
+    hashes::{hmac, sha256, sha256d, sha512, Hash, HashEngine},
+    secp256k1::{constants::CURVE_ORDER, PublicKey, Scalar, SecretKey},
+};
+
+/// The magic of the messages encrypted to the key of the password, other magics are for hardware wallets
+const MAGIC: &[u8] = b"BIE1";
+
+/// The rounds of pbkdf2 electrum derives the key of the wallet file from the password with
+const PBKDF2_ROUNDS: usize = 1024;
+
+/// The largest wallet file decompressed, a guard against compressed data that expands without bounds
+const MAX_WALLET_SIZE: usize = 1 << 30;
+
+/// Decrypt a wallet file, base64 of the magic, the ephemeral public key, the aes-128-cbc ciphertext of the zlib
+/// compressed json and an hmac-sha256 of the rest
+pub(crate) fn decrypt_wallet_file(
+    data: &[u8],
+    password: &str,
+) -> Result<Vec<u8>, Electrum2DescriptorError> {
+    let text = std::str::from_utf8(data).map_err(|_| invalid("the wallet file isn't base64"))?;
+    let message =
+        base64::decode(text.trim()).map_err(|_| invalid("the wallet file isn't base64"))?;
+    if message.len() < 4 + 33 + 16 + 32 {
+        return Err(invalid("the wallet file is too short"));
+    }
+    if &message[..4] != MAGIC {
+        return Err(invalid(
+            "the wallet file isn't encrypted with a password but e.g. with a hardware wallet",
+        ));
+    }
+    let ephemeral =
+        PublicKey::from_slice(&message[4..37]).map_err(|_| invalid("invalid ephemeral key"))?;
+    let (signed, mac) = message.split_at(message.len() - 32);
+
+    let secret = password_key(password)?;
+    let ecdh = ephemeral.mul_tweak(
+        secp(),
+        &Scalar::from_be_bytes(secret.secret_bytes()).expect("a secret key is a scalar"),
+    )?;
+    let key = sha512::Hash::hash(&ecdh.serialize()).to_byte_array();
+    let mut engine = hmac::HmacEngine::<sha256::Hash>::new(&key[32..]);
+    engine.input(signed);
+    if hmac::Hmac::<sha256::Hash>::from_engine(engine).as_byte_array() != mac {
+        return Err(Electrum2DescriptorError::InvalidPassword);
+    }
+    let mut iv = [0u8; 16];
+    iv.copy_from_slice(&key[..16]);
+    let compressed = Aes::new(&key[16..32])
+        .expect("a 128 bit key")
+        .decrypt_cbc(&iv, &signed[37..])
+        .ok_or_else(|| invalid("invalid padding"))?;
+    zlib_decompress(&compressed)
+}
+
+/// Decrypt a secret of a keystore, e.g. its xprv or seed: base64 of the iv and the aes-256-cbc ciphertext, keyed
+/// by the double sha256 of the password. Only the version 1 of electrum's password hashing exists.
+pub(crate) fn decrypt_secret(
+    secret: &str,
+    password: &str,
+    version: Option<u32>,
+) -> Result<String, Electrum2DescriptorError> {
+    if let Some(version) = version.filter(|version| *version != 1) {
+        return Err(invalid(&format!(
+            "unknown password hash version {}",
+            version
+        )));
+    }
+    let data = base64::decode(secret).map_err(|_| Electrum2DescriptorError::InvalidPassword)?;
+    if data.len() < 32 {
+        return Err(Electrum2DescriptorError::InvalidPassword);
+    }
+    let mut iv = [0u8; 16];
+    iv.copy_from_slice(&data[..16]);
+    let key = sha256d::Hash::hash(password.as_bytes()).to_byte_array();
+    let plain = Aes::new(&key)
+        .expect("a 256 bit key")
+        .decrypt_cbc(&iv, &data[16..])
+        .ok_or(Electrum2DescriptorError::InvalidPassword)?;
+    String::from_utf8(plain).map_err(|_| Electrum2DescriptorError::InvalidPassword)
+}
+
+/// The private key the wallet file is encrypted to: pbkdf2 of the password, reduced modulo the curve order
+fn password_key(password: &str) -> Result<SecretKey, Electrum2DescriptorError> {
+    let stretched = pbkdf2_hmac_sha512(password.as_bytes(), b"", PBKDF2_ROUNDS);
+    let mut high = [0u8; 32];
+    let mut low = [0u8; 32];
+    high.copy_from_slice(&stretched[..32]);
+    low.copy_from_slice(&stretched[32..]);
+    // high * 2^256 + low, where 2^256 is congruent to 2^256 - n
+    let low = Scalar::from_be_bytes(reduce(low)).expect("reduced");
+    let shift = Scalar::from_be_bytes(subtract([0; 32], CURVE_ORDER)).expect("below the order");
+    let key = match SecretKey::from_slice(&reduce(high)) {
+        Ok(high) => high.mul_tweak(&shift)?.add_tweak(&low)?,
+        // the high half is a multiple of the order
+        Err(_) => SecretKey::from_slice(&low.to_be_bytes())?,
+    };
+    Ok(key)
+}
+
+/// Reduce a 256 bit number modulo the curve order, which is above 2^255 so one subtraction is enough
+fn reduce(number: [u8; 32]) -> [u8; 32] {
+    if number >= CURVE_ORDER {
+        subtract(number, CURVE_ORDER)
+    } else {
+        number
+    }
+}
+
+/// Subtract big endian 256 bit numbers, wrapping around
+fn subtract(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+    let mut difference = [0u8; 32];
+    let mut borrow = 0;
+    for i in (0..32).rev() {
+        let d = i16::from(a[i]) - i16::from(b[i]) - borrow;
+        borrow = i16::from(d < 0);
+        difference[i] = d.rem_euclid(256) as u8;
+    }
+    difference
+}
+
+/// Decompress zlib data: a two byte header, the deflate data and the adler-32 checksum of the content
+fn zlib_decompress(data: &[u8]) -> Result<Vec<u8>, Electrum2DescriptorError> {
+    if data.len() < 6
+        || data[0] & 0x0f != 8
+        || data[1] & 0x20 != 0
+        || !(u16::from(data[0]) << 8 | u16::from(data[1])).is_multiple_of(31)
+    {
+        return Err(invalid("the decrypted wallet isn't zlib compressed"));
+    }
+    let content =
+        inflate(&data[2..data.len() - 4], MAX_WALLET_SIZE).map_err(|e| invalid(&e.to_string()))?;
+    if adler32(&content).to_be_bytes() != data[data.len() - 4..] {
+        return Err(invalid("the decrypted wallet is corrupt"));
+    }
+    Ok(content)
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += u32::from(*byte);
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    b << 16 | a
+}
+
+fn invalid(reason: &str) -> Electrum2DescriptorError {
+    Electrum2DescriptorError::InvalidEncryptedData(reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // computed with python's hashlib and cryptography, independently of the crate
+    const PASSWORD_PUBKEY: &str =
+        "02ea3ff2a7da5fedd8cdee3de2c536639a46959fdd20b12531104bbb8d1eaefc01";
+    const ENCRYPTED_SECRET: &str = "AqZvVCIYdRV6AZ0ilZX6Kt4KTWwsPs+YT1eMeqRNd1w=";
+
+    #[test]
+    fn test_password_key() {
+        // the key of electrum's storage for the password, ECPrivkey.from_arbitrary_size_secret of the pbkdf2
+        assert_eq!(
+            PublicKey::from_secret_key(secp(), &password_key("password").unwrap()).to_string(),
+            PASSWORD_PUBKEY
+        );
+    }
+
+    #[test]
+    fn test_zlib() {
+        // zlib.compress(b"hello") in python
+        let compressed = [
+            0x78, 0x9c, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00, 0x06, 0x2c, 0x02, 0x15,
+        ];
+        assert_eq!(zlib_decompress(&compressed).unwrap(), b"hello");
+        let mut corrupt = compressed;
+        corrupt[12] ^= 1;
+        assert!(zlib_decompress(&corrupt).is_err());
+        assert!(zlib_decompress(&compressed[2..]).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_secret() {
+        assert_eq!(
+            decrypt_secret(ENCRYPTED_SECRET, "password", Some(1)).unwrap(),
+            "hello"
+        );
+        assert!(matches!(
+            decrypt_secret(ENCRYPTED_SECRET, "wrong", None),
+            Err(Electrum2DescriptorError::InvalidPassword)
+        ));
+        assert!(matches!(
+            decrypt_secret(ENCRYPTED_SECRET, "password", Some(2)),
+            Err(Electrum2DescriptorError::InvalidEncryptedData(_))
+        ));
+    }
+}
@@ -1,4 +1,4 @@
-use bitcoin::{base58, bip32, secp256k1};
+use bitcoin::{address, base58, bip32, hashes::hex, key, secp256k1};
 #[cfg(feature = "wallet_file")]
 use serde_json::Error as SerdeError;
 use std::io;
@@ -19,9 +19,18 @@ pub enum Electrum2DescriptorError {
     Secp256k1Error(#[from] secp256k1::Error),
     #[error(transparent)]
     Bip32Error(#[from] bip32::Error),
+    #[error(transparent)]
+    HexError(#[from] hex::Error),
+    #[error(transparent)]
+    AddressError(#[from] address::Error),
+    #[error(transparent)]
+    KeyError(#[from] key::Error),
     #[cfg(feature = "wallet_file")]
     #[error(transparent)]
     RegexError(#[from] regex::Error),
+    #[cfg(feature = "miniscript")]
+    #[error(transparent)]
+    MiniscriptError(#[from] miniscript::Error),
 
     #[error("Unknown type")]
     UnknownType,
@@ -29,16 +38,138 @@ pub enum Electrum2DescriptorError {
     UnknownWalletType(String),
     #[error("Multisig with less than two signers doesn't make a lot of sense")]
     MultisigFewSigners,
-    #[error("Unknown multisig descriptor format: {0}")]
+    #[error("Unknown descriptor format: {0}")]
     UnknownDescriptorFormat(String),
     #[error("Wrong number of keystores: {0}; expected: {1}")]
     WrongNumberOfKeyStores(usize, usize),
-    #[error("Minimum number of signatures {0} must not be greater than keystores {1}")]
-    NumberSignaturesKeyStores(u8, usize),
+    #[error("Threshold {0} is out of range: it must be at least 1 and at most the number of keystores {1}")]
+    NumberSignaturesKeyStores(usize, usize),
+    #[error(
+        "The threshold {0} is above the {1} cosigners that would remain, lower the threshold first"
+    )]
+    ThresholdAboveCosigners(u8, usize),
+    #[error("There is no cosigner {0}, the wallet has {1}")]
+    UnknownCosigner(usize, usize),
+    #[error("Electrum can't restore the wallet: {0}")]
+    NotRestorable(String),
+    #[error("The wallet isn't a multisig wallet")]
+    NotMultisig,
     #[error("keystore sizes above 255 are not currently supported. {0}")]
     TooManyKeyStores(usize),
+    #[error("The script kind of {0} can't be inferred from its prefix, use keystores with an explicit kind and ElectrumWalletFile::new")]
+    AmbiguousScriptKind(String),
+    #[error("{0} has no known slip-0132 prefix")]
+    UnknownKeyPrefix(String),
+    #[error("The script kind of {0} can't be inferred from its prefix, it must be given")]
+    ScriptKindRequired(String),
+    #[error("The prefix of {0} is for {1} but {2} was given")]
+    ScriptKindConflict(String, String, String),
+    #[error("{0} and {1} have different script kinds, use keystores with an explicit kind and ElectrumWalletFile::new")]
+    MixedScriptKinds(String, String),
+    #[error("{0} and {1} are for different networks")]
+    MixedNetworks(String, String),
     #[error("Unknown script kind: {0}")]
     UnknownScriptKind(String),
     #[error("{0}")]
     GenericBorrow(&'static str),
+    #[error("Invalid keystore {0}: {1}")]
+    InvalidKeystoreField(&'static str, Box<Electrum2DescriptorError>),
+    #[error("The wallet has no private keys")]
+    NoPrivateKeys,
+    #[error("The key is not a master key but at depth {0}, its accounts were derived already")]
+    NotMasterKey(u8),
+    #[error("{0} origins were given for {1} keystores")]
+    WrongNumberOfOrigins(usize, usize),
+    #[error("The derivation {0} has {1} steps, but the key is at depth {2}")]
+    OriginDepthMismatch(String, usize, u8),
+    #[error("Unsupported seed: {0} can't be imported")]
+    UnsupportedSeed(String),
+    #[error("The field {0} is modeled by the crate, {1}")]
+    ModeledField(String, &'static str),
+    #[error("The address {0} is listed as {1} and already as {2}")]
+    DuplicateAddress(String, String, String),
+    #[error("The private keys of {0} are encrypted, decrypt the wallet first")]
+    SecretsEncrypted(String),
+    #[error("Signing the psbt failed: {0}")]
+    PsbtSigning(String),
+    #[error("The wallet file is incomplete, a keystore could not be parsed: {0}")]
+    IncompleteWallet(String),
+    #[error("{} — wallet setup was not finished", unfinished_slots(.0))]
+    UnfinishedSetup(Vec<String>),
+    #[error(
+        "The stored address {1} at index {0} doesn't match the address {2} derived from the keys"
+    )]
+    AddressMismatch(u32, String, String),
+    #[error("The {0} address {2} at position {1} isn't derived from the keys")]
+    ForeignAddress(&'static str, usize, String),
+    #[error("{1} {0} addresses are missing before position {2}, the later ones are stored at the wrong index")]
+    AddressesDeleted(&'static str, usize, usize),
+    #[error("The stored address {0} is {1}, but the keys are for {2} addresses")]
+    AddressKindMismatch(String, String, String),
+    #[error("Invalid descriptor on line {0}: {1}")]
+    InvalidDescriptorLine(usize, Box<Electrum2DescriptorError>),
+    #[error("The descriptor on line {0} is for the same wallet as line {1}")]
+    DuplicateDescriptor(usize, usize),
+    #[error("{0} is the change descriptor, pass the receiving descriptor ending in /0/* instead")]
+    ChangeDescriptor(String),
+    #[error("The keys of {0} mix the receiving chain /0/* and the change chain /1/*")]
+    MixedDescriptorChains(String),
+    #[error("The address {0} is not one of the wallet's")]
+    AddressNotInWallet(String),
+    #[error("Messages can't be signed with {0}")]
+    MessageSigning(&'static str),
+    #[error("Invalid message signature: {0}")]
+    InvalidMessageSignature(String),
+    #[error("The derivation {0} is for {1} scripts, but the key is for {2}")]
+    DerivationMismatch(String, String, String),
+    #[error("Expected the fingerprint {0}, but found {1}")]
+    UnexpectedFingerprint(String, String),
+    #[error("Taproot multisig wallets can't be represented as descriptors yet")]
+    TaprootMultisig,
+    #[error("The wallet file is encrypted")]
+    EncryptedWallet,
+    #[error("{0} already exists, a wallet parsed watch-only must not replace it")]
+    WatchOnlyOverwrite(String),
+    #[error("{0} already exists, refusing to overwrite it")]
+    AlreadyExists(String),
+    #[error("Invalid multisig setup file on line {0}: {1}")]
+    InvalidSetupFile(usize, String),
+    #[error("The policy {0} of {1} doesn't match the {2} cosigners listed")]
+    PolicyMismatch(u8, usize, usize),
+    #[error("{0} has no root fingerprint and derivation")]
+    MissingOrigin(String),
+    #[error("Invalid arguments: {0}")]
+    InvalidArguments(String),
+    #[error("{0} bytes don't fit a QR code, which holds {1} at most")]
+    QrCodeTooLong(usize, usize),
+    #[error(
+        "The cosigners of a multi() wallet can't be reordered, their order makes the addresses"
+    )]
+    FixedKeyOrder,
+    #[error("Invalid character {0:?} at position {1} of the key")]
+    InvalidKeyCharacter(char, usize),
+    #[error("Invalid private key export: {0}")]
+    InvalidPrivateKeyExport(String),
+    #[error("{0}: the private key isn't the key of the address")]
+    PrivateKeyMismatch(String),
+    #[error("The derivation {1} after {0} isn't supported, {2}")]
+    UnsupportedKeyPath(String, String, &'static str),
+    #[error("Invalid zip backup: {0}")]
+    InvalidZip(String),
+    #[error("Imported keys have no extended key, convert them with a descriptor per key")]
+    ImportedKeys,
+    #[error("The key {0} is a cosigner more than once")]
+    DuplicateCosigner(String),
+    #[error("Invalid password")]
+    InvalidPassword,
+    #[error("Invalid encrypted data: {0}")]
+    InvalidEncryptedData(String),
+}
+
+fn unfinished_slots(slots: &[String]) -> String {
+    slots
+        .iter()
+        .map(|slot| format!("{} has no xpub", slot))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
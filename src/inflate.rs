@@ -0,0 +1,299 @@
+//! A decoder of raw deflate data as specified by [RFC 1951](https://www.rfc-editor.org/rfc/rfc1951), enough to
+//! read the entries of zip backups and the zlib compressed content of encrypted wallet files. It favours simplicity
+//! over speed, backups are small.
+use crate::Electrum2DescriptorError;
+
+/// The base lengths of the length symbols 257..285 and their extra bits
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// The base distances of the distance symbols 0..29 and their extra bits
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// The order in which the code lengths of the code length alphabet are stored in dynamic blocks
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// The longest huffman code
+const MAX_BITS: usize = 15;
+
+/// Decompress raw deflate data, failing if it would decompress to more than `limit` bytes
+pub(crate) fn inflate(data: &[u8], limit: usize) -> Result<Vec<u8>, Electrum2DescriptorError> {
+    let mut bits = Bits {
+        data,
+        position: 0,
+        buffer: 0,
+        count: 0,
+    };
+    let mut out = Vec::new();
+    loop {
+        let last = bits.take(1)? == 1;
+        match bits.take(2)? {
+            0 => bits.stored(&mut out)?,
+            1 => {
+                let (lengths, distances) = fixed_codes()?;
+                codes(&mut bits, &mut out, &lengths, &distances, limit)?;
+            }
+            2 => {
+                let (lengths, distances) = dynamic_codes(&mut bits)?;
+                codes(&mut bits, &mut out, &lengths, &distances, limit)?;
+            }
+            _ => return Err(invalid("reserved block type")),
+        }
+        if out.len() > limit {
+            return Err(invalid("more data than the entry size"));
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+fn invalid(reason: &str) -> Electrum2DescriptorError {
+    Electrum2DescriptorError::InvalidZip(format!("invalid deflate data, {}", reason))
+}
+
+/// The input, read least significant bit first
+struct Bits<'a> {
+    data: &'a [u8],
+    position: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl Bits<'_> {
+    /// The next `need` bits, at most 16
+    fn take(&mut self, need: u32) -> Result<u32, Electrum2DescriptorError> {
+        while self.count < need {
+            let byte = *self
+                .data
+                .get(self.position)
+                .ok_or_else(|| invalid("unexpected end"))?;
+            self.buffer |= u32::from(byte) << self.count;
+            self.position += 1;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1 << need) - 1);
+        self.buffer >>= need;
+        self.count -= need;
+        Ok(value)
+    }
+
+    /// Copy a stored block, which starts at the next byte
+    fn stored(&mut self, out: &mut Vec<u8>) -> Result<(), Electrum2DescriptorError> {
+        self.buffer = 0;
+        self.count = 0;
+        let header = self
+            .data
+            .get(self.position..self.position + 4)
+            .ok_or_else(|| invalid("unexpected end"))?;
+        let len = u16::from_le_bytes([header[0], header[1]]);
+        let nlen = u16::from_le_bytes([header[2], header[3]]);
+        if len != !nlen {
+            return Err(invalid("stored block length doesn't match its complement"));
+        }
+        self.position += 4;
+        let block = self
+            .data
+            .get(self.position..self.position + usize::from(len))
+            .ok_or_else(|| invalid("unexpected end"))?;
+        out.extend_from_slice(block);
+        self.position += usize::from(len);
+        Ok(())
+    }
+}
+
+/// A canonical huffman code: how many codes there are of each length and the symbols ordered by code
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, Electrum2DescriptorError> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for length in lengths {
+            counts[usize::from(*length)] += 1;
+        }
+        counts[0] = 0;
+        let mut left = 1i32;
+        for count in &counts[1..] {
+            left = (left << 1) - i32::from(*count);
+            if left < 0 {
+                return Err(invalid("over-subscribed huffman code"));
+            }
+        }
+        let mut offsets = [0u16; MAX_BITS + 1];
+        for length in 1..MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; usize::from(offsets[MAX_BITS] + counts[MAX_BITS])];
+        for (symbol, length) in lengths.iter().enumerate() {
+            if *length != 0 {
+                let offset = &mut offsets[usize::from(*length)];
+                symbols[usize::from(*offset)] = symbol as u16;
+                *offset += 1;
+            }
+        }
+        Ok(Huffman { counts, symbols })
+    }
+
+    fn decode(&self, bits: &mut Bits) -> Result<u16, Electrum2DescriptorError> {
+        // the codes of a length are consecutive and follow the ones of the shorter lengths
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+        for count in &self.counts[1..] {
+            code |= bits.take(1)? as i32;
+            let count = i32::from(*count);
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("invalid huffman code"))
+    }
+}
+
+fn fixed_codes() -> Result<(Huffman, Huffman), Electrum2DescriptorError> {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5; 30])?))
+}
+
+fn dynamic_codes(bits: &mut Bits) -> Result<(Huffman, Huffman), Electrum2DescriptorError> {
+    let nlen = bits.take(5)? as usize + 257;
+    let ndist = bits.take(5)? as usize + 1;
+    let ncode = bits.take(4)? as usize + 4;
+    if nlen > 286 || ndist > 30 {
+        return Err(invalid("too many length or distance codes"));
+    }
+    let mut code_lengths = [0u8; 19];
+    for index in &CODE_LENGTH_ORDER[..ncode] {
+        code_lengths[*index] = bits.take(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths)?;
+
+    let mut lengths = vec![0u8; nlen + ndist];
+    let mut index = 0;
+    while index < nlen + ndist {
+        let symbol = code_lengths.decode(bits)?;
+        let (length, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *index
+                    .checked_sub(1)
+                    .and_then(|previous| lengths.get(previous))
+                    .ok_or_else(|| invalid("repeated length without a first one"))?;
+                (previous, 3 + bits.take(2)? as usize)
+            }
+            17 => (0, 3 + bits.take(3)? as usize),
+            _ => (0, 11 + bits.take(7)? as usize),
+        };
+        if index + repeat > nlen + ndist {
+            return Err(invalid("too many code lengths"));
+        }
+        lengths[index..index + repeat].fill(length);
+        index += repeat;
+    }
+    if lengths[256] == 0 {
+        return Err(invalid("no end of block code"));
+    }
+    Ok((
+        Huffman::new(&lengths[..nlen])?,
+        Huffman::new(&lengths[nlen..])?,
+    ))
+}
+
+/// Decode the literals and back references of a compressed block
+fn codes(
+    bits: &mut Bits,
+    out: &mut Vec<u8>,
+    lengths: &Huffman,
+    distances: &Huffman,
+    limit: usize,
+) -> Result<(), Electrum2DescriptorError> {
+    loop {
+        let symbol = usize::from(lengths.decode(bits)?);
+        if symbol < 256 {
+            if out.len() == limit {
+                return Err(invalid("more data than the entry size"));
+            }
+            out.push(symbol as u8);
+            continue;
+        }
+        if symbol == 256 {
+            return Ok(());
+        }
+        let symbol = symbol - 257;
+        if symbol >= LENGTH_BASE.len() {
+            return Err(invalid("invalid length symbol"));
+        }
+        let length =
+            usize::from(LENGTH_BASE[symbol]) + bits.take(u32::from(LENGTH_EXTRA[symbol]))? as usize;
+        let symbol = usize::from(distances.decode(bits)?);
+        if symbol >= DISTANCE_BASE.len() {
+            return Err(invalid("invalid distance symbol"));
+        }
+        let distance = usize::from(DISTANCE_BASE[symbol])
+            + bits.take(u32::from(DISTANCE_EXTRA[symbol]))? as usize;
+        if distance > out.len() {
+            return Err(invalid("distance too far back"));
+        }
+        if out.len() + length > limit {
+            return Err(invalid("more data than the entry size"));
+        }
+        // the copy may overlap what it appends
+        for _ in 0..length {
+            out.push(out[out.len() - distance]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_block_types() {
+        // a stored, a fixed and a dynamic block, compressed with python's zlib
+        assert_eq!(inflate(&hex("010500faff68656c6c6f"), 5).unwrap(), b"hello");
+        let fixed = hex("cb48cdc9c957c8409000");
+        assert_eq!(inflate(&fixed, 17).unwrap(), b"hello hello hello");
+        let dynamic =
+            hex("258a810900300cc26e4df4ff1bd6762028314a9189648b3f0aa9ddc7e355c74c4f299107");
+        assert_eq!(
+            inflate(&dynamic, 60).unwrap(),
+            b"bbadabaababacaabaaabacaadaacdbdbaabbcaabadbbbdabcdbaaabdacba"
+        );
+
+        assert!(inflate(&fixed, 16).is_err());
+        assert!(inflate(&fixed[..6], 17).is_err());
+        assert!(inflate(&[0x07], 17).is_err());
+    }
+}
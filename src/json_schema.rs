@@ -0,0 +1,99 @@
+use crate::ElectrumWalletFile;
+use serde_json::{json, Value};
+
+impl ElectrumWalletFile {
+    /// A JSON schema (draft 2020-12) of the wallet files the crate reads and writes, for validating them elsewhere.
+    /// Standard wallets have a `keystore`, multisig wallets one `x1/`, `x2/`... keystore per cosigner and a
+    /// `wallet_type` like `2of3`. Imported wallets have a `keystore` of key pairs and map each address to its public
+    /// key and script type. Fields the crate doesn't model are allowed, as they are kept verbatim, and
+    /// numbers may be stringified like the parser accepts them.
+    pub fn json_schema() -> Value {
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "Electrum wallet file",
+            "type": "object",
+            "properties": {
+                "addresses": {
+                    "anyOf": [
+                        { "$ref": "#/$defs/addresses" },
+                        { "$ref": "#/$defs/imported_addresses" }
+                    ]
+                },
+                "seed_version": { "type": ["integer", "string"] },
+                "wallet_type": { "$ref": "#/$defs/wallet_type" },
+                "keystore": {
+                    "anyOf": [
+                        { "$ref": "#/$defs/keystore" },
+                        { "$ref": "#/$defs/imported_keystore" }
+                    ]
+                }
+            },
+            "patternProperties": {
+                "^x[1-9][0-9]*/$": { "$ref": "#/$defs/keystore" }
+            },
+            "$defs": {
+                "addresses": {
+                    "type": "object",
+                    "properties": {
+                        "change": { "type": "array", "items": { "type": "string" } },
+                        "receiving": { "type": "array", "items": { "type": "string" } }
+                    },
+                    "required": ["change", "receiving"]
+                },
+                "imported_addresses": {
+                    "type": "object",
+                    "patternProperties": {
+                        "": {
+                            "type": "object",
+                            "properties": {
+                                "pubkey": { "type": "string" },
+                                "type": { "type": "string" }
+                            }
+                        }
+                    }
+                },
+                "wallet_type": {
+                    "type": "string",
+                    "anyOf": [
+                        { "enum": ["standard", "imported"] },
+                        { "pattern": "^[1-9][0-9]*of[1-9][0-9]*$" }
+                    ]
+                },
+                "keystore": {
+                    "type": "object",
+                    "properties": {
+                        "type": { "type": "string" },
+                        "xprv": { "type": ["string", "null"] },
+                        "xpub": { "type": "string" },
+                        "seed": { "type": "string" },
+                        "passphrase": { "type": "string" },
+                        "pw_hash_version": { "type": ["integer", "string", "null"] },
+                        "derivation": { "type": ["string", "null"] },
+                        "root_fingerprint": { "type": ["string", "null"] },
+                        "label": { "type": ["string", "null"] },
+                        "script_type": { "type": ["string", "null"] },
+                        "chains": {
+                            "type": "array",
+                            "items": { "type": "integer", "minimum": 0 },
+                            "minItems": 2,
+                            "maxItems": 2
+                        }
+                    },
+                    "required": ["xpub"]
+                },
+                "imported_keystore": {
+                    "type": "object",
+                    "properties": {
+                        "type": { "enum": ["imported"] },
+                        "keypairs": {
+                            "type": "object",
+                            "patternProperties": { "": { "type": "string" } }
+                        },
+                        "pw_hash_version": { "type": ["integer", "string", "null"] }
+                    },
+                    "required": ["type"]
+                }
+            }
+        })
+    }
+}
@@ -1,16 +1,96 @@
+/// A debug record of the `log` feature, compiled out without it. Only names, kinds and counts are logged, never
+/// keys, seeds or addresses. The record carries the path of the open spans in its `span` key.
+#[cfg_attr(not(feature = "wallet_file"), allow(unused_macros))]
+macro_rules! log_debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "log")]
+        log::debug!(target: crate::span::TARGET, span = crate::span::path().as_str(); $($arg)*);
+    };
+}
+
+/// Open a span of the `log` feature named `$name` until the end of the enclosing block, see [`span`]. The context
+/// follows the rules of [`log_debug`]. Compiled out without the feature.
+#[cfg_attr(not(feature = "wallet_file"), allow(unused_macros))]
+macro_rules! log_span {
+    ($name:literal, $($arg:tt)*) => {
+        #[cfg(feature = "log")]
+        let _span = crate::span::Span::enter($name, format_args!($($arg)*));
+    };
+}
+
+#[cfg(feature = "wallet_file")]
+mod aes;
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary;
+#[cfg(feature = "zip")]
+pub mod backup_zip;
+#[cfg(feature = "wallet_file")]
+pub mod batch;
+pub mod checksum;
+#[cfg(feature = "wallet_file")]
+pub mod conversion_warning;
+pub mod electrum_extended_key;
 pub mod electrum_extended_priv_key;
 pub mod electrum_extended_pub_key;
 #[cfg(feature = "wallet_file")]
 pub mod electrum_wallet_file;
+#[cfg(feature = "wallet_file")]
+mod encryption;
 pub mod errors;
+#[cfg(feature = "wallet_file")]
+mod inflate;
+#[cfg(feature = "wallet_file")]
+mod json_schema;
+#[cfg(feature = "wallet_file")]
+pub mod loss_report;
+pub mod message;
+#[cfg(feature = "wallet_file")]
+mod multisig_setup;
+#[cfg(feature = "wallet_file")]
+pub mod private_key_export;
+#[cfg(feature = "psbt")]
+pub mod psbt;
+#[cfg(feature = "qr")]
+pub mod qr;
+pub mod recover;
+#[cfg(feature = "wallet_file")]
+mod redact;
+pub mod script_kind;
+#[cfg(feature = "wallet_file")]
+mod seed;
+#[cfg(feature = "log")]
+mod span;
+#[cfg(feature = "miniscript")]
+pub mod typed_descriptors;
+pub mod utils;
+#[cfg(feature = "wallet_file")]
+pub mod wallet_diff;
+#[cfg(feature = "wallet_file")]
+pub mod wallet_summary;
 
+#[cfg(feature = "wallet_file")]
+pub use batch::{convert_dir, summarize_dir};
+#[cfg(feature = "wallet_file")]
+pub use conversion_warning::ConversionWarning;
+pub use electrum_extended_key::ElectrumExtendedKeyEnum;
 pub use electrum_extended_priv_key::ElectrumExtendedPrivKey;
 pub use electrum_extended_pub_key::ElectrumExtendedPubKey;
 #[cfg(feature = "wallet_file")]
 pub use electrum_wallet_file::ElectrumWalletFile;
 pub use errors::Electrum2DescriptorError;
+#[cfg(feature = "wallet_file")]
+pub use loss_report::{ConversionTarget, LossReport};
+#[cfg(feature = "wallet_file")]
+pub use private_key_export::PrivateKeyExport;
+pub use script_kind::ScriptKind;
+pub use utils::{kind_from_address, kind_from_address_str, AddressKind};
+#[cfg(feature = "wallet_file")]
+pub use wallet_diff::WalletDifference;
+#[cfg(feature = "wallet_file")]
+pub use wallet_summary::{ReportFormat, ReportOptions, WalletSummary};
 
-pub trait ElectrumExtendedKey {
+/// An electrum extended key, the keys are plain data that can be shared between threads
+pub trait ElectrumExtendedKey: Send + Sync {
     /// Returns internal and external descriptor
     fn to_descriptors(&self) -> Descriptors;
 
@@ -23,7 +103,27 @@ pub trait ElectrumExtendedKey {
 
 /// The two descriptors for external and change addresses
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Descriptors {
     pub external: String,
     pub change: String,
 }
+
+impl Descriptors {
+    /// The descriptor of the change addresses or else of the external ones
+    pub fn get(&self, change: bool) -> &str {
+        if change {
+            &self.change
+        } else {
+            &self.external
+        }
+    }
+
+    /// Both descriptors with their checksums appended
+    pub fn with_checksums(&self) -> Result<Descriptors, Electrum2DescriptorError> {
+        Ok(Descriptors {
+            external: checksum::with_checksum(&self.external)?,
+            change: checksum::with_checksum(&self.change)?,
+        })
+    }
+}
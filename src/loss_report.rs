@@ -0,0 +1,175 @@
+use crate::conversion_warning::{has_content, GUI_FIELDS};
+use crate::electrum_wallet_file::{Keystore, HISTORY_FIELDS, SECRET_FIELDS};
+use crate::{Electrum2DescriptorError, ElectrumWalletFile};
+use serde::Serialize;
+use std::fmt;
+
+/// What a wallet is converted to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversionTarget {
+    /// The descriptors alone, as printed by `convert`
+    Descriptors,
+    /// Descriptors imported into bitcoin core, which rescans the chain for the confirmed transactions
+    CoreImport,
+    /// A new electrum wallet file of the keys, see [`ElectrumWalletFile::to_fresh_wallet`]. Electrum syncs the
+    /// confirmed transactions again.
+    FreshWalletFile,
+}
+
+/// Data in a wallet file that doesn't survive a conversion, see [`ElectrumWalletFile::loss_report`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct LossReport {
+    pub target: ConversionTarget,
+    /// What is lost, with the number of entries and what they are, e.g. `("labels", 143, "entries")`
+    pub items: Vec<(String, usize, String)>,
+}
+
+/// The names of the fields users know from electrum's interface, with the unit of their entries
+const KNOWN_FIELDS: &[(&str, &str, &str)] = &[
+    ("labels", "labels", "entries"),
+    ("frozen_addresses", "frozen addresses", "addresses"),
+    ("frozen_coins", "frozen coins", "coins"),
+    ("channels", "lightning channels", "channels"),
+    ("channel_backups", "lightning channel backups", "backups"),
+    (
+        "imported_channel_backups",
+        "imported lightning channel backups",
+        "backups",
+    ),
+    (
+        "onchain_channel_backups",
+        "onchain lightning channel backups",
+        "backups",
+    ),
+    ("lightning_payments", "lightning payments", "payments"),
+    ("lightning_preimages", "lightning preimages", "preimages"),
+    ("submarine_swaps", "submarine swaps", "swaps"),
+    ("invoices", "invoices", "invoices"),
+    ("payment_requests", "payment requests", "requests"),
+    ("fiat_value", "fiat values", "entries"),
+    ("contacts", "contacts", "contacts"),
+];
+
+impl LossReport {
+    /// Whether nothing is lost
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl ElectrumWalletFile {
+    /// List what the wallet file holds that converting it to `target` doesn't carry over, with item counts
+    pub fn loss_report(&self, target: ConversionTarget) -> LossReport {
+        let mut items = Vec::new();
+        let mut push = |what: &str, count: usize, unit: &str| {
+            if count > 0 {
+                items.push((what.to_string(), count, unit.to_string()));
+            }
+        };
+
+        let transactions = self.extra().get("transactions").and_then(|t| t.as_object());
+        let verified = self.extra().get("verified_tx3").and_then(|t| t.as_object());
+        match (target, transactions) {
+            (ConversionTarget::Descriptors, Some(transactions)) => {
+                push("transaction history", transactions.len(), "transactions")
+            }
+            (_, Some(transactions)) => push(
+                "unconfirmed and local transactions",
+                transactions
+                    .keys()
+                    .filter(|txid| !verified.is_some_and(|verified| verified.contains_key(*txid)))
+                    .count(),
+                "transactions",
+            ),
+            (_, None) => {}
+        }
+
+        for (name, value) in self.extra() {
+            let name = name.as_str();
+            if GUI_FIELDS.contains(&name) || HISTORY_FIELDS.contains(&name) {
+                continue;
+            }
+            let entries = match value {
+                serde_json::Value::Object(entries) => {
+                    entries.values().filter(|v| has_content(v)).count()
+                }
+                serde_json::Value::Array(entries) => {
+                    entries.iter().filter(|v| has_content(v)).count()
+                }
+                value if SECRET_FIELDS.contains(&name) && has_content(value) => {
+                    push("lightning key", 1, "key");
+                    continue;
+                }
+                _ => continue,
+            };
+            match KNOWN_FIELDS.iter().find(|(field, _, _)| *field == name) {
+                Some((_, what, unit)) => push(what, entries, unit),
+                None => push(name, entries, "entries"),
+            }
+        }
+
+        let keystores = self.keystores();
+        let count =
+            |has: fn(&Keystore) -> bool| keystores.iter().filter(|keystore| has(keystore)).count();
+        push(
+            "seed phrases",
+            count(|keystore| keystore.seed.is_some()),
+            "keystores",
+        );
+        push(
+            "seed extensions",
+            count(|keystore| keystore.has_seed_extension()),
+            "keystores",
+        );
+        if target != ConversionTarget::FreshWalletFile {
+            push(
+                "keystore labels",
+                count(|keystore| keystore.label.as_deref().is_some_and(|l| !l.is_empty())),
+                "keystores",
+            );
+        }
+
+        LossReport { target, items }
+    }
+
+    /// A new wallet file with the keys, origins and labels of the keystores, without the seeds, the history and
+    /// everything else the [`ConversionTarget::FreshWalletFile`] loss report lists
+    pub fn to_fresh_wallet(&self) -> Result<ElectrumWalletFile, Electrum2DescriptorError> {
+        self.check_complete()?;
+        let keystores = self
+            .keystores()
+            .iter()
+            .map(|keystore| {
+                let mut keystore = keystore.clone();
+                keystore.seed = None;
+                keystore.passphrase = None;
+                keystore
+            })
+            .collect::<Vec<_>>();
+        let threshold = self.wallet_type().threshold();
+        let mut wallet = ElectrumWalletFile::new(&keystores, threshold)?;
+        wallet.set_key_order(self.key_order())?;
+        Ok(wallet)
+    }
+}
+
+impl fmt::Display for ConversionTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConversionTarget::Descriptors => write!(f, "descriptors"),
+            ConversionTarget::CoreImport => write!(f, "a bitcoin core import"),
+            ConversionTarget::FreshWalletFile => write!(f, "a fresh wallet file"),
+        }
+    }
+}
+
+/// One line per lost item, e.g. `labels: 143 entries`
+impl fmt::Display for LossReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (what, count, unit) in &self.items {
+            writeln!(f, "{}: {} {}", what, count, unit)?;
+        }
+        Ok(())
+    }
+}
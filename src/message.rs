@@ -0,0 +1,213 @@
+//! Signing and verifying messages with the keys of addresses, as electrum's signmessage and verifymessage do
+#[cfg(feature = "wallet_file")]
+use crate::{electrum_wallet_file::WalletType, ElectrumWalletFile, ScriptKind};
+use crate::{utils::secp, Electrum2DescriptorError};
+#[cfg(feature = "wallet_file")]
+use bitcoin::bip32::ChildNumber;
+use bitcoin::{
+    base64,
+    secp256k1::{
+        ecdsa::{RecoverableSignature, RecoveryId},
+        Message, Secp256k1, SecretKey, Signing,
+    },
+    sign_message::{signed_msg_hash, MessageSignature},
+    Address, PublicKey,
+};
+
+/// The address types a signature header stands for, as specified by
+/// [BIP-137](https://github.com/bitcoin/bips/blob/master/bip-0137.mediawiki)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Header {
+    Uncompressed,
+    /// Bitcoin core uses it for p2pkh, electrum for all the single key script kinds
+    Compressed,
+    P2wpkhP2sh,
+    P2wpkh,
+}
+
+#[cfg(feature = "wallet_file")]
+impl ElectrumWalletFile {
+    /// Sign a message with the key of one of the wallet's addresses, as electrum's signmessage does: a base64
+    /// encoded recoverable signature with the header of a compressed key, which is also used for segwit addresses.
+    /// Fails for watch-only and multisig wallets, and for addresses that aren't stored in the wallet file.
+    pub fn sign_message(
+        &self,
+        address: &Address,
+        message: &str,
+    ) -> Result<String, Electrum2DescriptorError> {
+        self.check_complete()?;
+        if let WalletType::Multisig(_) = self.wallet_type() {
+            return Err(Electrum2DescriptorError::MessageSigning(
+                "the addresses of multisig wallets",
+            ));
+        }
+        let keystore = &self.keystores()[0];
+        if let (_xpub, ScriptKind::P2tr, _network) = keystore.xpub()? {
+            return Err(Electrum2DescriptorError::MessageSigning(
+                "taproot addresses",
+            ));
+        }
+        let (change, index) = self
+            .find_address(&address.to_string())
+            .ok_or_else(|| Electrum2DescriptorError::AddressNotInWallet(address.to_string()))?;
+        self.check_can_sign()?;
+        let xprv = keystore
+            .xprv()?
+            .expect("the keystore of a singlesig wallet that can sign holds an xprv");
+
+        let secp = secp();
+        let path = [
+            ChildNumber::from_normal_idx(keystore.chain(change))?,
+            ChildNumber::from_normal_idx(index)?,
+        ];
+        let key = xprv.derive_priv(secp, &path)?.private_key;
+        Ok(sign(secp, &key, message).to_base64())
+    }
+
+    /// Verify a base64 encoded message signature with [`verify_message`], failing unless the address is one of the
+    /// wallet's stored addresses
+    pub fn verify_message(
+        &self,
+        address: &Address,
+        message: &str,
+        signature: &str,
+    ) -> Result<bool, Electrum2DescriptorError> {
+        if self.find_address(&address.to_string()).is_none() {
+            return Err(Electrum2DescriptorError::AddressNotInWallet(
+                address.to_string(),
+            ));
+        }
+        verify_message(address, message, signature)
+    }
+}
+
+/// Sign like electrum, with the deterministic nonce of RFC 6979
+#[cfg_attr(not(feature = "wallet_file"), allow(dead_code))]
+fn sign<C: Signing>(secp: &Secp256k1<C>, key: &SecretKey, message: &str) -> MessageSignature {
+    let hash = signed_msg_hash(message);
+    let msg = Message::from_slice(hash.as_ref()).expect("32 bytes");
+    MessageSignature::new(secp.sign_ecdsa_recoverable(&msg, key), true)
+}
+
+/// Whether a base64 encoded message signature was made with the key of a p2pkh, p2wpkh-p2sh or p2wpkh address.
+/// Accepts the headers of BIP-137 and electrum's, which uses the header of p2pkh for all of them.
+/// Fails for signatures that don't decode.
+pub fn verify_message(
+    address: &Address,
+    message: &str,
+    signature: &str,
+) -> Result<bool, Electrum2DescriptorError> {
+    let invalid =
+        |reason: &str| Electrum2DescriptorError::InvalidMessageSignature(reason.to_string());
+    let bytes = base64::decode(signature).map_err(|e| invalid(&e.to_string()))?;
+    if bytes.len() != 65 {
+        return Err(invalid("not 65 bytes"));
+    }
+    let header = match bytes[0] {
+        27..=30 => Header::Uncompressed,
+        31..=34 => Header::Compressed,
+        35..=38 => Header::P2wpkhP2sh,
+        39..=42 => Header::P2wpkh,
+        _ => return Err(invalid("unknown header")),
+    };
+    let recovery_id = RecoveryId::from_i32(((bytes[0] - 27) % 4) as i32)?;
+    let signature = RecoverableSignature::from_compact(&bytes[1..], recovery_id)?;
+
+    let secp = secp();
+    let hash = signed_msg_hash(message);
+    let msg = Message::from_slice(hash.as_ref()).expect("32 bytes");
+    let key = match secp.recover_ecdsa(&msg, &signature) {
+        Ok(key) => key,
+        Err(_) => return Ok(false),
+    };
+    let network = address.network;
+    let candidates = match header {
+        Header::Uncompressed => vec![Address::p2pkh(&PublicKey::new_uncompressed(key), network)],
+        Header::Compressed => {
+            let key = PublicKey::new(key);
+            vec![
+                Address::p2pkh(&key, network),
+                Address::p2shwpkh(&key, network)?,
+                Address::p2wpkh(&key, network)?,
+            ]
+        }
+        Header::P2wpkhP2sh => vec![Address::p2shwpkh(&PublicKey::new(key), network)?],
+        Header::P2wpkh => vec![Address::p2wpkh(&PublicKey::new(key), network)?],
+    };
+    Ok(candidates.contains(address))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::{Network, PrivateKey};
+    use std::str::FromStr;
+
+    fn address(address: &str) -> Address {
+        Address::from_str(address).unwrap().assume_checked()
+    }
+
+    /// Replace the header of a base64 encoded signature
+    fn with_header(signature: &str, header: u8) -> String {
+        let mut bytes = base64::decode(signature).unwrap();
+        bytes[0] = header + (bytes[0] - 27) % 4;
+        base64::encode(bytes)
+    }
+
+    #[test]
+    fn test_sign_like_electrum() {
+        // from electrum's test_bitcoin.py
+        let key =
+            PrivateKey::from_wif("L1TnU2zbNaAqMoVh65Cyvmcjzbrj41Gs9iTLcWbpJCMynXuap6UN").unwrap();
+        let signature = sign(
+            &Secp256k1::new(),
+            &key.inner,
+            "Chancellor on brink of second bailout for banks",
+        );
+        assert_eq!(
+            signature.to_base64(),
+            "H/9jMOnj4MFbH3d7t4yCQ9i7DgZU/VZ278w3+ySv2F4yIsdqjsc5ng3kmN8OZAThgyfCZOQxZCWza9V5XzlVY0Y="
+        );
+    }
+
+    #[test]
+    fn test_verify_electrum() {
+        // from electrum's test_bitcoin.py, the second one by an uncompressed key
+        let message = "Chancellor on brink of second bailout for banks";
+        let signature = "H/9jMOnj4MFbH3d7t4yCQ9i7DgZU/VZ278w3+ySv2F4yIsdqjsc5ng3kmN8OZAThgyfCZOQxZCWza9V5XzlVY0Y=";
+        let signer = address("15hETetDmcXm1mM4sEf7U2KXC9hDHFMSzz");
+        assert!(verify_message(&signer, message, signature).unwrap());
+        assert!(!verify_message(&signer, "Chancellor", signature).unwrap());
+        let uncompressed = address("1GPHVTY8UD9my6jyP4tb2TYJwUbDetyNC6");
+        assert!(!verify_message(&uncompressed, message, signature).unwrap());
+        let signature = "G84dmJ8TKIDKMT9qBRhpX2sNmR0y5t+POcYnFFJCs66lJmAs3T8A6Sbpx7KA6yTQ9djQMabwQXRrDomOkIKGn18=";
+        assert!(verify_message(&uncompressed, "Electrum", signature).unwrap());
+    }
+
+    #[test]
+    fn test_verify_bip137_headers() {
+        // from bitcoin core's rpc_signmessage.py
+        let key =
+            PrivateKey::from_wif("cUeKHd5orzT3mz8P9pxyREHfsWtVfgsfDjiZZBcjUBAaGk1BTj7N").unwrap();
+        let message = "This is just a test message";
+        let signature = "INbVnW4e6PeRmsv2Qgu8NuopvrVjkcxob+sX8OcZG0SALhWybUjzMLPdAsXI46YZGb0KQTRii+wWIQzRpG/U+S0=";
+        let public_key = key.public_key(&Secp256k1::new());
+        let p2pkh = Address::p2pkh(&public_key, Network::Testnet);
+        assert_eq!(p2pkh, address("mpLQjfK79b7CCV4VMJWEWAj5Mpx8Up5zxB"));
+        let p2wpkh_p2sh = Address::p2shwpkh(&public_key, Network::Testnet).unwrap();
+        let p2wpkh = Address::p2wpkh(&public_key, Network::Testnet).unwrap();
+
+        for address in [&p2pkh, &p2wpkh_p2sh, &p2wpkh] {
+            assert!(verify_message(address, message, signature).unwrap());
+        }
+        let segwit = with_header(signature, 35);
+        assert!(verify_message(&p2wpkh_p2sh, message, &segwit).unwrap());
+        assert!(!verify_message(&p2pkh, message, &segwit).unwrap());
+        let native = with_header(signature, 39);
+        assert!(verify_message(&p2wpkh, message, &native).unwrap());
+        assert!(!verify_message(&p2wpkh_p2sh, message, &native).unwrap());
+
+        assert!(verify_message(&p2pkh, message, &with_header(signature, 43)).is_err());
+        assert!(verify_message(&p2pkh, message, "bm90IGEgc2lnbmF0dXJl").is_err());
+    }
+}
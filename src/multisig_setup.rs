@@ -0,0 +1,287 @@
+use crate::{
+    electrum_wallet_file::{multisig_kind, Keystore, WalletType},
+    Electrum2DescriptorError, ElectrumExtendedKey, ElectrumExtendedPrivKey, ElectrumExtendedPubKey,
+    ElectrumWalletFile, ScriptKind,
+};
+use bitcoin::bip32::{DerivationPath, Fingerprint};
+use std::str::FromStr;
+
+/// A multisig wallet as listed by the setup text files of BlueWallet and Coldcard: header lines like
+/// `Policy: 2 of 3`, then a `<fingerprint>: <xpub>` line per cosigner
+pub(crate) struct MultisigSetup {
+    pub name: Option<String>,
+    pub threshold: u8,
+    pub kind: ScriptKind,
+    /// The root fingerprint, the derivation and the slip-0132 extended key of each cosigner
+    pub cosigners: Vec<(Fingerprint, DerivationPath, String)>,
+}
+
+impl MultisigSetup {
+    /// Parse a setup file. A `Derivation` line applies to the cosigners listed after it, BlueWallet also writes
+    /// them as `# derivation:` comments. Without a `Format` line the kind is told by the prefixes of the keys,
+    /// or for plain xpubs by a bip-0048 derivation, else it is P2SH.
+    pub fn parse(text: &str) -> Result<Self, Electrum2DescriptorError> {
+        let mut name = None;
+        let mut policy = None;
+        let mut format = None;
+        let mut derivation = None;
+        let mut cosigners = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let invalid =
+                |reason: String| Electrum2DescriptorError::InvalidSetupFile(i + 1, reason);
+            let line = line.trim();
+            let line = match line.strip_prefix('#') {
+                Some(comment) if comment.trim().to_lowercase().starts_with("derivation:") => {
+                    comment.trim()
+                }
+                Some(_) => continue,
+                None if line.is_empty() => continue,
+                None => line,
+            };
+            let (field, value) = line
+                .split_once(':')
+                .ok_or_else(|| invalid(format!("expected <field>: <value>, found {}", line)))?;
+            let value = value.trim();
+            match field.trim().to_lowercase().as_str() {
+                "name" => name = Some(value.to_string()),
+                "policy" => {
+                    policy = Some(parse_policy(value).ok_or_else(|| {
+                        invalid(format!("expected a policy like 2 of 3, found {}", value))
+                    })?)
+                }
+                "format" => {
+                    format = Some(
+                        parse_format(value)
+                            .ok_or_else(|| invalid(format!("unknown format {}", value)))?,
+                    )
+                }
+                "derivation" => derivation = Some(DerivationPath::from_str(value)?),
+                fingerprint if fingerprint.len() == 8 => {
+                    let fingerprint = Fingerprint::from_str(fingerprint)
+                        .map_err(|_| invalid(format!("unknown field {}", field)))?;
+                    let derivation = derivation
+                        .clone()
+                        .ok_or_else(|| invalid("no derivation for the key".to_string()))?;
+                    cosigners.push((fingerprint, derivation, value.to_string()));
+                }
+                _ => return Err(invalid(format!("unknown field {}", field))),
+            }
+        }
+        let (threshold, count) = policy.ok_or_else(|| {
+            Electrum2DescriptorError::InvalidSetupFile(0, "there is no Policy".to_string())
+        })?;
+        if count != cosigners.len() || threshold as usize > count {
+            return Err(Electrum2DescriptorError::PolicyMismatch(
+                threshold,
+                count,
+                cosigners.len(),
+            ));
+        }
+        let kind = match (format, cosigners.first()) {
+            (Some(kind), _) => kind,
+            (None, Some((_, derivation, xkey))) => {
+                match multisig_kind(Keystore::from_electrum_xkey(xkey)?.xpub()?.1) {
+                    ScriptKind::Standard => ScriptKind::from_derivation(derivation)
+                        .map(multisig_kind)
+                        .unwrap_or(ScriptKind::Standard),
+                    kind => kind,
+                }
+            }
+            (None, None) => ScriptKind::Standard,
+        };
+        Ok(MultisigSetup {
+            name,
+            threshold,
+            kind,
+            cosigners,
+        })
+    }
+
+    /// The setup of a multisig wallet, all cosigners need their root fingerprint and derivation
+    pub fn of_wallet(wallet: &ElectrumWalletFile) -> Result<Self, Electrum2DescriptorError> {
+        let threshold = match wallet.wallet_type() {
+            WalletType::Standard | WalletType::Imported => {
+                return Err(Electrum2DescriptorError::NotMultisig)
+            }
+            WalletType::Multisig(multisig) => multisig.threshold(),
+        };
+        let mut wallet = wallet.clone();
+        wallet.canonicalize()?;
+        let cosigners = wallet
+            .keystores()
+            .iter()
+            .enumerate()
+            .map(|(i, keystore)| match keystore.origin()? {
+                Some(_) if keystore.chains.is_some() => {
+                    Err(Electrum2DescriptorError::UnsupportedKeyPath(
+                        keystore.xpub.clone(),
+                        format!("/<{};{}>/*", keystore.chain(false), keystore.chain(true)),
+                        "setup files derive /0/* and /1/*",
+                    ))
+                }
+                Some((fingerprint, derivation)) => {
+                    Ok((fingerprint, derivation, keystore.xpub.clone()))
+                }
+                None => Err(Electrum2DescriptorError::MissingOrigin(format!(
+                    "x{}/",
+                    i + 1
+                ))),
+            })
+            .collect::<Result<Vec<_>, Electrum2DescriptorError>>()?;
+        let (_xpub, kind, _network) = wallet.keystores()[0].xpub()?;
+        Ok(MultisigSetup {
+            name: None,
+            threshold,
+            kind,
+            cosigners,
+        })
+    }
+
+    /// The multisig wallet, the prefixes of the keys must agree with the format. Singlesig prefixes stand for their
+    /// multisig counterparts.
+    pub fn to_wallet(&self) -> Result<ElectrumWalletFile, Electrum2DescriptorError> {
+        if self.cosigners.len() < 2 {
+            return Err(Electrum2DescriptorError::MultisigFewSigners);
+        }
+        let keystores = self
+            .cosigners
+            .iter()
+            .map(|(fingerprint, derivation, xkey)| {
+                let (key, prefix_kind) = match ElectrumExtendedPrivKey::from_str(xkey) {
+                    Ok(exprv) => (exprv.xkey_str(), ScriptKind::from_str(exprv.kind())?),
+                    Err(_) => {
+                        let expub = ElectrumExtendedPubKey::from_str(xkey)?;
+                        (expub.xkey_str(), ScriptKind::from_str(expub.kind())?)
+                    }
+                };
+                let prefix_kind = multisig_kind(prefix_kind);
+                if prefix_kind != ScriptKind::Standard && prefix_kind != self.kind {
+                    return Err(Electrum2DescriptorError::ScriptKindConflict(
+                        xkey.to_string(),
+                        prefix_kind.to_string(),
+                        self.kind.to_string(),
+                    ));
+                }
+                Ok(Keystore::new(self.kind.as_kind_str(), &key)?
+                    .with_origin(*fingerprint, derivation))
+            })
+            .collect::<Result<Vec<_>, Electrum2DescriptorError>>()?;
+        ElectrumWalletFile::new(&keystores, self.threshold)
+    }
+
+    /// The lines of the setup after the `comments`, with a `Derivation` line before each cosigner written by
+    /// `cosigner_derivation` unless all share a derivation
+    pub fn to_text(&self, comments: &[&str], cosigner_derivation: &str) -> String {
+        let mut lines = comments
+            .iter()
+            .map(|comment| format!("#{}", comment))
+            .collect::<Vec<_>>();
+        if let Some(name) = &self.name {
+            lines.push(format!("Name: {}", name));
+        }
+        lines.push(format!(
+            "Policy: {} of {}",
+            self.threshold,
+            self.cosigners.len()
+        ));
+        let shared = self
+            .cosigners
+            .iter()
+            .all(|(_, derivation, _)| Some(derivation) == self.cosigners.first().map(|c| &c.1));
+        if shared {
+            lines.push(format!("Derivation: {}", self.cosigners[0].1));
+        }
+        lines.push(format!("Format: {}", format_name(self.kind)));
+        lines.push(String::new());
+        for (fingerprint, derivation, xkey) in &self.cosigners {
+            if !shared {
+                lines.push(format!("{}{}", cosigner_derivation, derivation));
+            }
+            lines.push(format!(
+                "{}: {}",
+                fingerprint.to_string().to_uppercase(),
+                xkey
+            ));
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
+
+impl ElectrumWalletFile {
+    /// Construct a multisig wallet from a BlueWallet vault export
+    pub fn from_bluewallet_text(text: &str) -> Result<Self, Electrum2DescriptorError> {
+        MultisigSetup::parse(text)?.to_wallet()
+    }
+
+    /// The BlueWallet multisig setup file of a multisig wallet, importable as vault. Only the xpubs are exported,
+    /// each cosigner needs its root fingerprint and derivation.
+    pub fn to_bluewallet_text(&self) -> Result<String, Electrum2DescriptorError> {
+        Ok(MultisigSetup::of_wallet(self)?.to_text(
+            &[
+                " BlueWallet Multisig setup file",
+                " this file contains only public keys and is safe to",
+                " distribute among cosigners",
+                "",
+            ],
+            "# derivation: ",
+        ))
+    }
+}
+
+impl ElectrumWalletFile {
+    /// Construct a multisig wallet from a Coldcard multisig setup file, which Passport and Keystone read as well.
+    /// See [`ElectrumWalletFile::from_bluewallet_text`] for how the script kind is found without a `Format` line.
+    pub fn from_coldcard_multisig(text: &str) -> Result<Self, Electrum2DescriptorError> {
+        MultisigSetup::parse(text)?.to_wallet()
+    }
+
+    /// The Coldcard multisig setup file of a multisig wallet, named `name` on the device: at most 20 ascii
+    /// characters. The keys are written as plain xpubs, each cosigner needs its root fingerprint and derivation.
+    pub fn to_coldcard_multisig(&self, name: &str) -> Result<String, Electrum2DescriptorError> {
+        let valid = name.chars().all(|c| c.is_ascii_graphic() || c == ' ');
+        if name.trim().is_empty() || name.len() > 20 || !valid {
+            return Err(Electrum2DescriptorError::InvalidArguments(format!(
+                "the name {:?} must have 1 to 20 ascii characters",
+                name
+            )));
+        }
+        let mut setup = MultisigSetup::of_wallet(self)?;
+        setup.name = Some(name.to_string());
+        for (_fingerprint, _derivation, xkey) in &mut setup.cosigners {
+            *xkey = ElectrumExtendedPubKey::from_str(xkey)?.xkey_str();
+        }
+        Ok(setup.to_text(
+            &[
+                " Coldcard Multisig setup file (exported by electrum2descriptors)",
+                "",
+            ],
+            "# derivation: ",
+        ))
+    }
+}
+
+/// Parse a policy like `2 of 3` into the threshold and the number of cosigners
+fn parse_policy(policy: &str) -> Option<(u8, usize)> {
+    let (threshold, count) = policy.split_once(" of ")?;
+    Some((threshold.trim().parse().ok()?, count.trim().parse().ok()?))
+}
+
+/// The script kind of a `Format` value, case insensitive
+fn parse_format(format: &str) -> Option<ScriptKind> {
+    match format.to_uppercase().as_str() {
+        "P2SH" => Some(ScriptKind::Standard),
+        "P2SH-P2WSH" | "P2WSH-P2SH" => Some(ScriptKind::P2wshP2sh),
+        "P2WSH" => Some(ScriptKind::P2wsh),
+        _ => None,
+    }
+}
+
+/// The `Format` value of a multisig script kind
+fn format_name(kind: ScriptKind) -> &'static str {
+    match kind {
+        ScriptKind::P2wshP2sh | ScriptKind::P2wpkhP2sh => "P2SH-P2WSH",
+        ScriptKind::P2wsh | ScriptKind::P2wpkh => "P2WSH",
+        ScriptKind::Standard | ScriptKind::P2tr => "P2SH",
+    }
+}
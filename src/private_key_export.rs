@@ -0,0 +1,204 @@
+use crate::electrum_wallet_file::{Keystore, OptionalString, IMPORTED_KEYSTORE};
+use crate::{
+    checksum::with_checksum, redact::PLACEHOLDER, Electrum2DescriptorError, ElectrumWalletFile,
+    ScriptKind,
+};
+use bitcoin::{address::NetworkUnchecked, Address, PrivateKey};
+use serde_json::{json, Map, Value};
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+/// The header electrum writes above the rows of a csv export
+const CSV_HEADER: &str = "address,private_key";
+
+/// The keys of electrum's "Export private keys", as json mapping each address to its key or as csv with a row
+/// `<address>,<key>` per address. The keys are WIFs with the script prefix electrum gives them, e.g.
+/// `p2wpkh:cQ...`, a WIF without prefix is for p2pkh.
+///
+/// [`ElectrumWalletFile::from_private_key_export`] builds the imported wallet of the keys.
+#[derive(Clone, PartialEq, Eq)]
+pub struct PrivateKeyExport {
+    pub keys: Vec<ImportedKey>,
+}
+
+/// A private key of an export with its address
+#[derive(Clone, PartialEq, Eq)]
+pub struct ImportedKey {
+    pub address: Address,
+    /// p2pkh, p2wpkh-p2sh or p2wpkh
+    pub kind: ScriptKind,
+    pub key: PrivateKey,
+}
+
+impl PrivateKeyExport {
+    /// Whether the text looks like an export rather than a wallet file: a json object of strings or the csv header
+    pub fn is_export(text: &str) -> bool {
+        match serde_json::from_str::<Map<String, Value>>(text) {
+            Ok(map) => !map.is_empty() && map.values().all(Value::is_string),
+            Err(_) => text.lines().next().map(str::trim) == Some(CSV_HEADER),
+        }
+    }
+
+    /// A descriptor with the checksum for each key, e.g. `wpkh(cQ...)#...`, to sweep the keys with descriptor wallets
+    pub fn to_descriptors(&self) -> Result<Vec<String>, Electrum2DescriptorError> {
+        self.keys
+            .iter()
+            .map(|imported| {
+                let closing = ")".repeat(imported.kind.as_kind_str().matches('(').count() + 1);
+                with_checksum(&format!(
+                    "{}({}{}",
+                    imported.kind.as_kind_str(),
+                    imported.key,
+                    closing
+                ))
+            })
+            .collect()
+    }
+}
+
+impl ElectrumWalletFile {
+    /// The imported wallet of the keys of an electrum private key export, see [`PrivateKeyExport`], unencrypted.
+    /// Electrum maps each address to its public key and script type, and the keystore each public key to its WIF.
+    pub fn from_private_key_export(text: &str) -> Result<Self, Electrum2DescriptorError> {
+        let export = PrivateKeyExport::from_str(text)?;
+        let secp = crate::utils::secp();
+        let mut addresses = Map::new();
+        let mut keypairs = Map::new();
+        for imported in &export.keys {
+            let pubkey = imported.key.public_key(secp).to_string();
+            let kind = match imported.kind {
+                ScriptKind::Standard => "p2pkh".to_string(),
+                kind => kind.to_string(),
+            };
+            addresses.insert(
+                imported.address.to_string(),
+                json!({ "pubkey": pubkey, "type": kind }),
+            );
+            keypairs.insert(pubkey, Value::String(imported.key.to_wif()));
+        }
+        let keystore = Keystore {
+            r#type: IMPORTED_KEYSTORE.to_string(),
+            xprv: None,
+            xpub: String::new(),
+            seed: None,
+            passphrase: None,
+            pw_hash_version: Some(1),
+            derivation: OptionalString::default(),
+            root_fingerprint: OptionalString::default(),
+            label: OptionalString::default(),
+            script_type: OptionalString::default(),
+            chains: None,
+            extra: BTreeMap::from([("keypairs".to_string(), Value::Object(keypairs))]),
+        };
+        Ok(ElectrumWalletFile::new_imported(
+            keystore,
+            Value::Object(addresses),
+        ))
+    }
+}
+
+/// Parse a json or csv export. Each key must be the key of its address, a mismatch fails with the address and for
+/// csv the line.
+impl FromStr for PrivateKeyExport {
+    type Err = Electrum2DescriptorError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let rows = match serde_json::from_str::<Map<String, Value>>(text) {
+            Ok(map) => map
+                .into_iter()
+                .map(|(address, key)| match key {
+                    Value::String(key) => Ok((address.clone(), address, key)),
+                    _ => Err(Electrum2DescriptorError::InvalidPrivateKeyExport(format!(
+                        "the key of {} isn't a string",
+                        address
+                    ))),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            Err(_) => {
+                text.lines()
+                    .enumerate()
+                    .filter(|(_, line)| !line.trim().is_empty() && line.trim() != CSV_HEADER)
+                    .map(|(i, line)| {
+                        match line.split_once(',') {
+                            // electrum pads the addresses to 34 characters
+                            Some((address, key)) => Ok((
+                                format!("line {} ({})", i + 1, address.trim()),
+                                address.trim().to_string(),
+                                key.trim().to_string(),
+                            )),
+                            None => Err(Electrum2DescriptorError::InvalidPrivateKeyExport(
+                                format!("line {} isn't <address>,<private key>", i + 1),
+                            )),
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()?
+            }
+        };
+        if rows.is_empty() {
+            return Err(Electrum2DescriptorError::InvalidPrivateKeyExport(
+                "there are no keys".to_string(),
+            ));
+        }
+        let keys = rows
+            .into_iter()
+            .map(|(row, address, key)| ImportedKey::new(&row, &address, &key))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(PrivateKeyExport { keys })
+    }
+}
+
+impl ImportedKey {
+    /// Check the script prefixed WIF against the address of the `row`
+    fn new(row: &str, address: &str, key: &str) -> Result<Self, Electrum2DescriptorError> {
+        let (kind, wif) = match key.split_once(':') {
+            Some(("p2pkh", wif)) => (ScriptKind::Standard, wif),
+            Some(("p2wpkh-p2sh", wif)) => (ScriptKind::P2wpkhP2sh, wif),
+            Some(("p2wpkh", wif)) => (ScriptKind::P2wpkh, wif),
+            Some((prefix, _)) => {
+                return Err(Electrum2DescriptorError::InvalidPrivateKeyExport(format!(
+                    "{}: unknown script type {}",
+                    row, prefix
+                )))
+            }
+            None => (ScriptKind::Standard, key),
+        };
+        let key = PrivateKey::from_wif(wif)?;
+        let stated = Address::<NetworkUnchecked>::from_str(address)?;
+        let secp = crate::utils::secp();
+        let pubkey = key.public_key(secp);
+        let derived = match kind {
+            ScriptKind::P2wpkhP2sh => Address::p2shwpkh(&pubkey, key.network),
+            ScriptKind::P2wpkh => Address::p2wpkh(&pubkey, key.network),
+            _ => Ok(Address::p2pkh(&pubkey, key.network)),
+        }
+        .map_err(|_| Electrum2DescriptorError::PrivateKeyMismatch(row.to_string()))?;
+        if !stated.is_valid_for_network(key.network) || stated.clone().assume_checked() != derived {
+            return Err(Electrum2DescriptorError::PrivateKeyMismatch(
+                row.to_string(),
+            ));
+        }
+        Ok(ImportedKey {
+            address: derived,
+            kind,
+            key,
+        })
+    }
+}
+
+/// The keys are redacted, so that exports can be logged
+impl fmt::Debug for ImportedKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ImportedKey")
+            .field("address", &self.address)
+            .field("kind", &self.kind)
+            .field("key", &PLACEHOLDER)
+            .finish()
+    }
+}
+
+impl fmt::Debug for PrivateKeyExport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PrivateKeyExport")
+            .field("keys", &self.keys)
+            .finish()
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{utils::secp, Electrum2DescriptorError, ElectrumWalletFile};
+use bitcoin::bip32::{ChildNumber, DerivationPath, ExtendedPrivKey, Fingerprint, KeySource};
+use bitcoin::psbt::{GetKey, KeyRequest, PartiallySignedTransaction};
+use bitcoin::secp256k1::{Secp256k1, Signing};
+use bitcoin::PrivateKey;
+
+/// How many addresses beyond the wallet's own are tried to find keys of inputs with unknown fingerprints
+const TRIAL_GAP: u32 = 20;
+
+impl ElectrumWalletFile {
+    /// Sign the inputs whose bip32 derivations belong to the wallet's xprvs, returning how many inputs got signatures.
+    /// Keys are matched by the fingerprint of the xprv or the keystore's root fingerprint and derivation,
+    /// else by deriving the wallet's receiving and change keys. Multisig inputs get partial signatures.
+    pub fn sign_psbt(
+        &self,
+        psbt: &mut PartiallySignedTransaction,
+    ) -> Result<usize, Electrum2DescriptorError> {
+        let secp = secp();
+        let keys = self
+            .check_can_sign()?
+            .into_iter()
+            .map(|i| {
+                let ks = &self.keystores()[i];
+                Ok(SigningKey {
+                    xprv: ks.xprv()?.expect("signing keystores hold an xprv"),
+                    origin: ks.origin()?,
+                    chains: [ks.chain(false), ks.chain(true)],
+                })
+            })
+            .collect::<Result<Vec<_>, Electrum2DescriptorError>>()?;
+
+        let addresses = self.addresses();
+        let signer = WalletSigner {
+            keys,
+            trials: addresses.receiving.len().max(addresses.change.len()) as u32 + TRIAL_GAP,
+        };
+        let signed = psbt.sign(&signer, secp).map_err(|(_signed, errors)| {
+            Electrum2DescriptorError::PsbtSigning(format!("{:?}", errors))
+        })?;
+        Ok(signed.values().filter(|keys| !keys.is_empty()).count())
+    }
+}
+
+/// An xprv of the wallet with the origin of its keystore
+struct SigningKey {
+    xprv: ExtendedPrivKey,
+    origin: Option<(Fingerprint, DerivationPath)>,
+    /// The receiving and change chains, see [`crate::electrum_wallet_file::Keystore::chains`]
+    chains: [u32; 2],
+}
+
+/// Finds the private keys for the psbt inputs
+struct WalletSigner {
+    keys: Vec<SigningKey>,
+    /// The number of receiving and change keys to try
+    trials: u32,
+}
+
+impl SigningKey {
+    /// The private key for a bip32 key source, if it derives from this key
+    fn derive<C: Signing>(
+        &self,
+        (fingerprint, path): &KeySource,
+        secp: &Secp256k1<C>,
+    ) -> Result<Option<PrivateKey>, bitcoin::bip32::Error> {
+        let relative = if self.xprv.fingerprint(secp) == *fingerprint {
+            path.as_ref()
+        } else {
+            match &self.origin {
+                Some((origin_fingerprint, origin_path))
+                    if origin_fingerprint == fingerprint
+                        && path.as_ref().starts_with(origin_path.as_ref()) =>
+                {
+                    &path.as_ref()[origin_path.len()..]
+                }
+                _ => return Ok(None),
+            }
+        };
+        Ok(Some(self.xprv.derive_priv(secp, &relative)?.to_priv()))
+    }
+}
+
+impl GetKey for WalletSigner {
+    type Error = bitcoin::bip32::Error;
+
+    fn get_key<C: Signing>(
+        &self,
+        key_request: KeyRequest,
+        secp: &Secp256k1<C>,
+    ) -> Result<Option<PrivateKey>, Self::Error> {
+        match key_request {
+            KeyRequest::Bip32(key_source) => {
+                for key in &self.keys {
+                    if let Some(private_key) = key.derive(&key_source, secp)? {
+                        return Ok(Some(private_key));
+                    }
+                }
+                Ok(None)
+            }
+            KeyRequest::Pubkey(public_key) => {
+                for key in &self.keys {
+                    for change in key.chains {
+                        let chain = key
+                            .xprv
+                            .derive_priv(secp, &[ChildNumber::from_normal_idx(change)?])?;
+                        for index in 0..self.trials {
+                            let child =
+                                chain.derive_priv(secp, &[ChildNumber::from_normal_idx(index)?])?;
+                            let private_key = child.to_priv();
+                            if private_key.public_key(secp) == public_key {
+                                return Ok(Some(private_key));
+                            }
+                        }
+                    }
+                }
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+}
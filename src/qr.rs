@@ -0,0 +1,573 @@
+//! QR codes of descriptors, to carry them to air-gapped devices without retyping them. The codes are of byte mode
+//! and error correction level M, as specified by ISO/IEC 18004, and rendered for the terminal or as png images.
+use crate::Electrum2DescriptorError;
+
+/// The largest version used, 97 modules wide. Larger codes are hard to scan from a terminal, longer texts are
+/// split over several codes by [`qr_codes`].
+pub const MAX_VERSION: u8 = 20;
+
+/// The error correction codewords per block at level M, by version
+const ECC_CODEWORDS_PER_BLOCK: [usize; MAX_VERSION as usize + 1] = [
+    0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26,
+];
+
+/// The error correction blocks at level M, by version
+const ERROR_CORRECTION_BLOCKS: [usize; MAX_VERSION as usize + 1] = [
+    0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16,
+];
+
+/// The light modules around the code that scanners need
+const QUIET_ZONE: usize = 4;
+
+/// The pixels per module of the png images
+const PNG_SCALE: usize = 8;
+
+/// A QR code, a square of dark and light modules
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QrCode {
+    version: u8,
+    size: usize,
+    modules: Vec<bool>,
+}
+
+/// The QR codes of `text`, a single one if it fits a code of [`MAX_VERSION`], else the parts to scan in order and
+/// concatenate. The parts are about the same length and split between characters.
+pub fn qr_codes(text: &str) -> Vec<QrCode> {
+    let capacity = capacity(MAX_VERSION);
+    let parts = text.len().div_ceil(capacity);
+    let part_len = match parts {
+        0 | 1 => capacity,
+        parts => text.len().div_ceil(parts),
+    };
+    let mut codes = Vec::new();
+    let mut rest = text;
+    loop {
+        let mut end = part_len.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (part, tail) = rest.split_at(end);
+        codes.push(QrCode::new(part.as_bytes()).expect("the part fits a code"));
+        if tail.is_empty() {
+            return codes;
+        }
+        rest = tail;
+    }
+}
+
+/// The bytes a code of `version` holds
+pub fn capacity(version: u8) -> usize {
+    (data_codewords(version) * 8 - 4 - count_bits(version)) / 8
+}
+
+impl QrCode {
+    /// The smallest code holding `data`, failing if it doesn't fit a code of [`MAX_VERSION`]
+    pub fn new(data: &[u8]) -> Result<Self, Electrum2DescriptorError> {
+        let version = (1..=MAX_VERSION)
+            .find(|version| data.len() <= capacity(*version))
+            .ok_or(Electrum2DescriptorError::QrCodeTooLong(
+                data.len(),
+                capacity(MAX_VERSION),
+            ))?;
+        let mut code = QrCode {
+            version,
+            size: usize::from(version) * 4 + 17,
+            modules: Vec::new(),
+        };
+        let (template, function) = code.function_patterns();
+        let codewords = interleave(version, &encode(version, data));
+        // the mask with the lowest penalty makes the code easiest to scan
+        code.modules = (0..8)
+            .map(|mask| {
+                let mut modules = template.clone();
+                code.place(&mut modules, &function, &codewords, mask);
+                modules
+            })
+            .min_by_key(|modules| code.penalty(modules))
+            .expect("there are masks");
+        Ok(code)
+    }
+
+    /// The version, from 1 to [`MAX_VERSION`]
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// The number of modules of a side, without the quiet zone
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Whether the module at column `x` and row `y` is dark, the modules outside the code are light
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        x < self.size && y < self.size && self.modules[y * self.size + x]
+    }
+
+    /// The code for a terminal with a dark background, two rows of modules per line of half-block characters. The
+    /// light modules are drawn and the dark ones left blank, with the quiet zone around.
+    pub fn to_terminal(&self) -> String {
+        let light = |x: usize, y: usize| {
+            let inside = |i: usize| i >= QUIET_ZONE && i - QUIET_ZONE < self.size;
+            !(inside(x) && inside(y) && self.is_dark(x - QUIET_ZONE, y - QUIET_ZONE))
+        };
+        let width = self.size + 2 * QUIET_ZONE;
+        let mut text = String::new();
+        for y in (0..width).step_by(2) {
+            for x in 0..width {
+                text.push(match (light(x, y), light(x, y + 1)) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                });
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// The code as a black and white png image, with the quiet zone around
+    pub fn to_png(&self) -> Vec<u8> {
+        let width = (self.size + 2 * QUIET_ZONE) * PNG_SCALE;
+        let row_len = width.div_ceil(8);
+        // a filter type byte and 1 bit per pixel, set for white
+        let mut pixels = Vec::with_capacity((row_len + 1) * width);
+        for y in 0..width {
+            pixels.push(0);
+            let mut row = vec![0u8; row_len];
+            for x in 0..width {
+                let module = |i: usize| (i / PNG_SCALE).wrapping_sub(QUIET_ZONE);
+                if !self.is_dark(module(x), module(y)) {
+                    row[x / 8] |= 0x80 >> (x % 8);
+                }
+            }
+            pixels.extend(row);
+        }
+
+        let mut header = Vec::new();
+        header.extend((width as u32).to_be_bytes());
+        header.extend((width as u32).to_be_bytes());
+        // bit depth 1, grayscale, deflate, adaptive filtering, not interlaced
+        header.extend([1, 0, 0, 0, 0]);
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        png_chunk(&mut png, b"IHDR", &header);
+        png_chunk(&mut png, b"IDAT", &zlib_stored(&pixels));
+        png_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    /// The finder, timing and alignment patterns and the areas of the format and version information, with the
+    /// modules they take
+    fn function_patterns(&self) -> (Vec<bool>, Vec<bool>) {
+        let size = self.size;
+        let mut modules = vec![false; size * size];
+        let mut function = vec![false; size * size];
+        let mut set = |x: usize, y: usize, dark: bool| {
+            modules[y * size + x] = dark;
+            function[y * size + x] = true;
+        };
+        for i in 0..size {
+            set(6, i, i % 2 == 0);
+            set(i, 6, i % 2 == 0);
+        }
+        // the finders with their light separators, cut at the edges
+        for (cx, cy) in [(3, 3), (size - 4, 3), (3, size - 4)] {
+            for dy in -4i32..=4 {
+                for dx in -4i32..=4 {
+                    let (x, y) = (cx as i32 + dx, cy as i32 + dy);
+                    if (0..size as i32).contains(&x) && (0..size as i32).contains(&y) {
+                        let distance = dx.abs().max(dy.abs());
+                        set(x as usize, y as usize, distance != 2 && distance != 4);
+                    }
+                }
+            }
+        }
+        let positions = alignment_positions(self.version);
+        let last = positions.len().saturating_sub(1);
+        for (i, cx) in positions.iter().enumerate() {
+            for (j, cy) in positions.iter().enumerate() {
+                if (i, j) == (0, 0) || (i, j) == (0, last) || (i, j) == (last, 0) {
+                    continue;
+                }
+                for dy in -2i32..=2 {
+                    for dx in -2i32..=2 {
+                        let distance = dx.abs().max(dy.abs());
+                        set(
+                            (*cx as i32 + dx) as usize,
+                            (*cy as i32 + dy) as usize,
+                            distance != 1,
+                        );
+                    }
+                }
+            }
+        }
+        // reserved for the format information, written with the mask
+        for i in (0..9).filter(|i| *i != 6) {
+            set(8, i, false);
+            set(i, 8, false);
+        }
+        for i in 0..8 {
+            set(size - 1 - i, 8, false);
+            set(8, size - 1 - i, false);
+        }
+        if self.version >= 7 {
+            let bits = version_bits(self.version);
+            for i in 0..18 {
+                let dark = (bits >> i) & 1 == 1;
+                let (a, b) = (size - 11 + i % 3, i / 3);
+                set(a, b, dark);
+                set(b, a, dark);
+            }
+        }
+        (modules, function)
+    }
+
+    /// Place the codewords in the modules that aren't part of a pattern, in two module wide columns zigzagging up
+    /// and down from the bottom right, and apply the `mask`
+    fn place(&self, modules: &mut [bool], function: &[bool], codewords: &[u8], mask: u8) {
+        let size = self.size;
+        let mut bit = 0;
+        let mut right = size - 1;
+        loop {
+            // the vertical timing pattern takes a whole column
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vertical in 0..size {
+                let y = if upward {
+                    size - 1 - vertical
+                } else {
+                    vertical
+                };
+                for x in [right, right - 1] {
+                    let index = y * size + x;
+                    if function[index] {
+                        continue;
+                    }
+                    // the remainder bits after the codewords are light
+                    let dark = codewords
+                        .get(bit / 8)
+                        .is_some_and(|codeword| (codeword >> (7 - bit % 8)) & 1 == 1);
+                    modules[index] = dark ^ masked(mask, x, y);
+                    bit += 1;
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+
+        let bits = format_bits(mask);
+        let mut set = |x: usize, y: usize, i: usize| modules[y * size + x] = (bits >> i) & 1 == 1;
+        for i in 0..6 {
+            set(8, i, i);
+        }
+        set(8, 7, 6);
+        set(8, 8, 7);
+        set(7, 8, 8);
+        for i in 9..15 {
+            set(14 - i, 8, i);
+        }
+        for i in 0..8 {
+            set(size - 1 - i, 8, i);
+        }
+        for i in 8..15 {
+            set(8, size - 15 + i, i);
+        }
+        // always dark
+        modules[(size - 8) * size + 8] = true;
+    }
+
+    /// The penalty of the patterns that confuse scanners: runs of the same color, 2x2 blocks of the same color,
+    /// shapes of a finder and an unbalanced share of dark modules
+    fn penalty(&self, modules: &[bool]) -> usize {
+        let size = self.size;
+        let at = |x: usize, y: usize| modules[y * size + x];
+        let mut penalty = 0;
+        for transposed in [false, true] {
+            let line = |i: usize, j: usize| if transposed { at(i, j) } else { at(j, i) };
+            for i in 0..size {
+                let mut run = 1;
+                for j in 1..size {
+                    if line(i, j) == line(i, j - 1) {
+                        run += 1;
+                        if run == 5 {
+                            penalty += 3;
+                        } else if run > 5 {
+                            penalty += 1;
+                        }
+                    } else {
+                        run = 1;
+                    }
+                }
+                const FINDER: [bool; 11] = [
+                    true, false, true, true, true, false, true, false, false, false, false,
+                ];
+                for j in 0..=size.saturating_sub(FINDER.len()) {
+                    let forward = (0..FINDER.len()).all(|k| line(i, j + k) == FINDER[k]);
+                    let backward = (0..FINDER.len()).all(|k| line(i, j + k) == FINDER[10 - k]);
+                    penalty += 40 * (forward as usize + backward as usize);
+                }
+            }
+        }
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let color = at(x, y);
+                if at(x + 1, y) == color && at(x, y + 1) == color && at(x + 1, y + 1) == color {
+                    penalty += 3;
+                }
+            }
+        }
+        let dark = modules.iter().filter(|dark| **dark).count();
+        let percent = dark * 100 / modules.len();
+        penalty + 10 * ((percent.max(50) - percent.min(50)) / 5)
+    }
+}
+
+/// The codewords of the modules a code of `version` has for data, including the error correction
+fn raw_codewords(version: u8) -> usize {
+    let version = usize::from(version);
+    let mut modules = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let alignments = version / 7 + 2;
+        modules -= (25 * alignments - 10) * alignments - 55;
+        if version >= 7 {
+            modules -= 36;
+        }
+    }
+    modules / 8
+}
+
+fn data_codewords(version: u8) -> usize {
+    let version_index = usize::from(version);
+    raw_codewords(version)
+        - ECC_CODEWORDS_PER_BLOCK[version_index] * ERROR_CORRECTION_BLOCKS[version_index]
+}
+
+/// The bits of the byte count
+fn count_bits(version: u8) -> usize {
+    if version <= 9 {
+        8
+    } else {
+        16
+    }
+}
+
+/// The data codewords of `data` in byte mode, padded to the capacity of `version`
+fn encode(version: u8, data: &[u8]) -> Vec<u8> {
+    let mut bits = Vec::new();
+    let mut push = |value: usize, len: usize| {
+        bits.extend((0..len).rev().map(|i| (value >> i) & 1 == 1));
+    };
+    push(0b0100, 4);
+    push(data.len(), count_bits(version));
+    for byte in data {
+        push(usize::from(*byte), 8);
+    }
+    let capacity = data_codewords(version) * 8;
+    let terminator = (capacity - bits.len()).min(4);
+    bits.extend(std::iter::repeat_n(false, terminator));
+    bits.resize(bits.len().div_ceil(8) * 8, false);
+    let mut codewords = bits
+        .chunks(8)
+        .map(|byte| byte.iter().fold(0u8, |value, bit| value << 1 | *bit as u8))
+        .collect::<Vec<_>>();
+    for pad in [0xec, 0x11].iter().cycle() {
+        if codewords.len() == data_codewords(version) {
+            break;
+        }
+        codewords.push(*pad);
+    }
+    codewords
+}
+
+/// Split the data codewords in blocks, append their error correction and interleave the blocks. The first blocks
+/// are a codeword shorter when the codewords don't divide evenly.
+fn interleave(version: u8, data: &[u8]) -> Vec<u8> {
+    let version_index = usize::from(version);
+    let blocks = ERROR_CORRECTION_BLOCKS[version_index];
+    let ecc_len = ECC_CODEWORDS_PER_BLOCK[version_index];
+    let short_blocks = blocks - raw_codewords(version) % blocks;
+    let short_len = raw_codewords(version) / blocks - ecc_len;
+    let divisor = reed_solomon_divisor(ecc_len);
+    let mut offset = 0;
+    let mut split = Vec::with_capacity(blocks);
+    for block in 0..blocks {
+        let len = short_len + usize::from(block >= short_blocks);
+        let data = &data[offset..offset + len];
+        offset += len;
+        split.push((data, reed_solomon_remainder(data, &divisor)));
+    }
+    let mut codewords = Vec::with_capacity(raw_codewords(version));
+    for i in 0..=short_len {
+        codewords.extend(split.iter().filter_map(|(data, _ecc)| data.get(i)));
+    }
+    for i in 0..ecc_len {
+        codewords.extend(split.iter().map(|(_data, ecc)| ecc[i]));
+    }
+    codewords
+}
+
+/// Multiply in GF(2^8) modulo the polynomial x^8 + x^4 + x^3 + x^2 + 1
+fn gf_multiply(a: u8, b: u8) -> u8 {
+    let mut product = 0u16;
+    for i in (0..8).rev() {
+        product = (product << 1) ^ ((product >> 7) * 0x11d);
+        product ^= ((u16::from(b) >> i) & 1) * u16::from(a);
+    }
+    product as u8
+}
+
+/// The coefficients of the generator polynomial of `degree`, without the leading 1
+fn reed_solomon_divisor(degree: usize) -> Vec<u8> {
+    let mut divisor = vec![0u8; degree];
+    divisor[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            divisor[j] = gf_multiply(divisor[j], root);
+            if j + 1 < degree {
+                divisor[j] ^= divisor[j + 1];
+            }
+        }
+        root = gf_multiply(root, 2);
+    }
+    divisor
+}
+
+/// The error correction codewords of a block
+fn reed_solomon_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut remainder = vec![0u8; divisor.len()];
+    for byte in data {
+        let factor = byte ^ remainder.remove(0);
+        remainder.push(0);
+        for (r, d) in remainder.iter_mut().zip(divisor) {
+            *r ^= gf_multiply(*d, factor);
+        }
+    }
+    remainder
+}
+
+/// The centers of the alignment patterns on each axis
+fn alignment_positions(version: u8) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let version = usize::from(version);
+    let count = version / 7 + 2;
+    let step = (version * 4 + count * 2 + 1) / (count * 2 - 2) * 2;
+    let size = version * 4 + 17;
+    let mut positions = (0..count - 1)
+        .map(|i| size - 7 - i * step)
+        .collect::<Vec<_>>();
+    positions.push(6);
+    positions.reverse();
+    positions
+}
+
+/// The level M and the mask, with their BCH error correction and the fixed mask of the format information
+fn format_bits(mask: u8) -> u16 {
+    let data = u16::from(mask);
+    let mut remainder = data;
+    for _ in 0..10 {
+        remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+    }
+    (data << 10 | remainder) ^ 0x5412
+}
+
+/// The version with its BCH error correction, for versions 7 and up
+fn version_bits(version: u8) -> u32 {
+    let data = u32::from(version);
+    let mut remainder = data;
+    for _ in 0..12 {
+        remainder = (remainder << 1) ^ ((remainder >> 11) * 0x1f25);
+    }
+    data << 12 | remainder
+}
+
+/// Whether the mask flips the module at column `x` and row `y`
+fn masked(mask: u8, x: usize, y: usize) -> bool {
+    match mask {
+        0 => (x + y).is_multiple_of(2),
+        1 => y.is_multiple_of(2),
+        2 => x.is_multiple_of(3),
+        3 => (x + y).is_multiple_of(3),
+        4 => (x / 3 + y / 2).is_multiple_of(2),
+        5 => x * y % 2 + x * y % 3 == 0,
+        6 => (x * y % 2 + x * y % 3).is_multiple_of(2),
+        _ => ((x + y) % 2 + x * y % 3).is_multiple_of(2),
+    }
+}
+
+fn png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32fast::hash(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+/// A zlib stream of stored deflate blocks, the images compress well but are small anyway
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        zlib.push(blocks.peek().is_none() as u8);
+        let len = block.len() as u16;
+        zlib.extend(len.to_le_bytes());
+        zlib.extend((!len).to_le_bytes());
+        zlib.extend(block);
+    }
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + u32::from(*byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    zlib.extend((b << 16 | a).to_be_bytes());
+    zlib
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_correction() {
+        // the codewords of "HELLO WORLD" in a 1-M code, in alphanumeric mode
+        let data = [
+            32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17,
+        ];
+        assert_eq!(
+            reed_solomon_remainder(&data, &reed_solomon_divisor(10)),
+            vec![196, 35, 39, 119, 235, 215, 231, 226, 93, 23]
+        );
+    }
+
+    #[test]
+    fn test_tables() {
+        // the byte capacities at level M of the specification
+        let capacities = [
+            14, 26, 42, 62, 84, 106, 122, 152, 180, 213, 251, 287, 331, 362, 412, 450, 504, 560,
+            624, 666,
+        ];
+        for version in 1..=MAX_VERSION {
+            assert_eq!(
+                capacity(version),
+                capacities[usize::from(version) - 1],
+                "{}",
+                version
+            );
+        }
+        assert_eq!(format_bits(0), 0b101010000010010);
+        assert_eq!(version_bits(7), 0b000111110010010100);
+        assert_eq!(alignment_positions(2), vec![6, 18]);
+        assert_eq!(alignment_positions(7), vec![6, 22, 38]);
+        assert_eq!(alignment_positions(20), vec![6, 34, 62, 90]);
+    }
+}
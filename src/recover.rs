@@ -0,0 +1,214 @@
+use crate::{
+    Descriptors, ElectrumExtendedKey, ElectrumExtendedPrivKey, ElectrumExtendedPubKey, ScriptKind,
+};
+use bitcoin::{base58, Network, PrivateKey};
+use std::{fmt, str::FromStr};
+
+/// Lengths of the base58 encodings tried: extended keys, then compressed and uncompressed WIFs
+const CANDIDATE_LENGTHS: [usize; 4] = [112, 111, 52, 51];
+
+/// What kind of key was found
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FoundKeyType {
+    ExtendedPubKey,
+    ExtendedPrivKey,
+    /// A single private key in wallet import format
+    Wif,
+}
+
+impl fmt::Display for FoundKeyType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            FoundKeyType::ExtendedPubKey => "xpub",
+            FoundKeyType::ExtendedPrivKey => "xprv",
+            FoundKeyType::Wif => "wif",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A key found in a text by [`scan_text`]. Its `Debug` output hides private keys.
+#[derive(Clone, PartialEq, Eq)]
+pub struct FoundKey {
+    pub key_type: FoundKeyType,
+    pub network: Network,
+    /// From the slip-0132 prefix of extended keys, or from electrum's `<script kind>:` prefix of WIFs
+    pub script_kind: Option<ScriptKind>,
+    /// Byte offset of the key in the scanned text
+    pub offset: usize,
+    key: String,
+}
+
+impl FoundKey {
+    /// The key as it was found in the text
+    pub fn as_str(&self) -> &str {
+        &self.key
+    }
+
+    /// Whether the key is private material
+    pub fn is_secret(&self) -> bool {
+        self.key_type != FoundKeyType::ExtendedPubKey
+    }
+
+    /// The descriptors of an extended key, WIFs have no derivation and give none
+    pub fn to_descriptors(&self) -> Option<Descriptors> {
+        match self.key_type {
+            FoundKeyType::ExtendedPubKey => ElectrumExtendedPubKey::from_str(&self.key)
+                .ok()
+                .map(|k| k.to_descriptors()),
+            FoundKeyType::ExtendedPrivKey => ElectrumExtendedPrivKey::from_str(&self.key)
+                .ok()
+                .map(|k| k.to_descriptors()),
+            FoundKeyType::Wif => None,
+        }
+    }
+
+    /// A single signature wallet for an extended key. Multisig keys and WIFs give none.
+    #[cfg(feature = "wallet_file")]
+    pub fn to_wallet(&self) -> Option<crate::ElectrumWalletFile> {
+        if self.key_type == FoundKeyType::Wif || self.script_kind.is_some_and(|k| k.is_multisig()) {
+            return None;
+        }
+        let keystore = crate::electrum_wallet_file::Keystore::from_electrum_xkey(&self.key).ok()?;
+        crate::ElectrumWalletFile::new(&[keystore], 1).ok()
+    }
+}
+
+impl fmt::Debug for FoundKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let key = if self.is_secret() {
+            "<redacted>"
+        } else {
+            self.key.as_str()
+        };
+        f.debug_struct("FoundKey")
+            .field("key_type", &self.key_type)
+            .field("network", &self.network)
+            .field("script_kind", &self.script_kind)
+            .field("offset", &self.offset)
+            .field("key", &key)
+            .finish()
+    }
+}
+
+/// Find the extended keys and WIFs in an arbitrary text, e.g. notes or a corrupted wallet file.
+/// Only strings with a valid base58check checksum and a known prefix are reported, in the order of the text.
+pub fn scan_text(input: &str) -> Vec<FoundKey> {
+    let bytes = input.as_bytes();
+    let mut found = Vec::new();
+    let mut start = 0;
+    while start < bytes.len() {
+        if !is_base58(bytes[start]) {
+            start += 1;
+            continue;
+        }
+        let end = (start..bytes.len())
+            .find(|&i| !is_base58(bytes[i]))
+            .unwrap_or(bytes.len());
+        scan_run(input, start, end, &mut found);
+        start = end;
+    }
+    found
+}
+
+/// Look for keys in a run of base58 characters, which may be glued to other text
+fn scan_run(input: &str, start: usize, end: usize, found: &mut Vec<FoundKey>) {
+    let mut position = start;
+    while position < end {
+        let key = CANDIDATE_LENGTHS
+            .iter()
+            .filter(|&&len| position + len <= end)
+            .find_map(|&len| decode(input, position, len));
+        match key {
+            Some(key) => {
+                position += key.key.len();
+                found.push(key);
+            }
+            None => position += 1,
+        }
+    }
+}
+
+fn decode(input: &str, offset: usize, len: usize) -> Option<FoundKey> {
+    let key = &input[offset..offset + len];
+    let data = base58::decode_check(key).ok()?;
+    let (key_type, network, script_kind) = if data.len() == 78 {
+        if let Ok(xpub) = ElectrumExtendedPubKey::from_str(key) {
+            let kind = ScriptKind::from_str(xpub.kind()).ok();
+            (FoundKeyType::ExtendedPubKey, xpub.xpub().network, kind)
+        } else {
+            let xprv = ElectrumExtendedPrivKey::from_str(key).ok()?;
+            let kind = ScriptKind::from_str(xprv.kind()).ok();
+            (FoundKeyType::ExtendedPrivKey, xprv.xprv().network, kind)
+        }
+    } else {
+        let wif = PrivateKey::from_wif(key).ok()?;
+        (
+            FoundKeyType::Wif,
+            wif.network,
+            wif_script_kind(&input[..offset]),
+        )
+    };
+    Some(FoundKey {
+        key_type,
+        network,
+        script_kind,
+        offset,
+        key: key.to_string(),
+    })
+}
+
+/// The script kind of electrum's imported keys, written as `p2wpkh:<wif>`
+fn wif_script_kind(before: &str) -> Option<ScriptKind> {
+    let before = before.strip_suffix(':')?;
+    let name_start = before
+        .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+        .map_or(0, |i| i + 1);
+    ScriptKind::from_str(&before[name_start..]).ok()
+}
+
+fn is_base58(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() && !matches!(byte, b'0' | b'O' | b'I' | b'l')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VPUB: &str = "vpub5Vadaqpt7tkVaKgJ3tALYd9nk5q4NvzNgC5CJffpkG3gxcqrUrp1cNTWTLaQvXdgL9YPKjJ7btKrJHBW2DScYoFqaoysBhhqNoJXv15W6yr";
+    const WIF: &str = "cMcfH8sRgBgDMfpBNG6H3haaxLkaYXgqMRef8Nev6tWyBSNr6c3n";
+
+    #[test]
+    fn test_scan_noisy_text() {
+        let text = format!(
+            "my backup, xpub is {}! and the old key p2wpkh:{}\nxpub6BAD1234 not a key",
+            VPUB, WIF
+        );
+        let found = scan_text(&text);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].key_type, FoundKeyType::ExtendedPubKey);
+        assert_eq!(found[0].network, Network::Testnet);
+        assert_eq!(found[0].script_kind, Some(ScriptKind::P2wpkh));
+        assert_eq!(&text[found[0].offset..][..VPUB.len()], VPUB);
+        assert_eq!(found[1].key_type, FoundKeyType::Wif);
+        assert_eq!(found[1].script_kind, Some(ScriptKind::P2wpkh));
+        assert_eq!(found[1].as_str(), WIF);
+        assert!(!format!("{:?}", found[1]).contains(WIF));
+    }
+
+    #[test]
+    fn test_scan_rejects_bad_checksum() {
+        let mut corrupted = VPUB.to_string();
+        corrupted.replace_range(20..21, "x");
+        assert!(scan_text(&corrupted).is_empty());
+    }
+
+    #[test]
+    fn test_scan_glued_key() {
+        let text = format!("abc{}def", VPUB);
+        let found = scan_text(&text);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].offset, 3);
+        assert!(found[0].to_descriptors().is_some());
+    }
+}
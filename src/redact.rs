@@ -0,0 +1,211 @@
+use crate::electrum_wallet_file::{Keystore, SECRET_FIELDS, SECRET_KEYSTORE_FIELDS};
+use crate::{utils::secp, Electrum2DescriptorError, ElectrumWalletFile, ScriptKind};
+use bitcoin::{
+    bip32::{ExtendedPrivKey, ExtendedPubKey, Fingerprint},
+    hashes::{sha256, Hash, HashEngine},
+    Address, Network,
+};
+use serde_json::Value;
+use std::{convert::TryInto, str::FromStr};
+
+/// Replaces the secrets
+pub(crate) const PLACEHOLDER: &str = "<redacted>";
+
+/// How many characters of an address are kept
+const ADDRESS_PREFIX_LEN: usize = 12;
+
+impl ElectrumWalletFile {
+    /// The wallet file as json that is safe to share in bug reports. The secrets are replaced with placeholders,
+    /// the keys with dummy keys of the same prefix and the addresses are truncated. The dummy keys are derived from
+    /// a hash of the original ones: the same key always gets the same dummy, but it can't be linked to the original.
+    /// Everything else, including the fields we don't model, is kept so that the result still parses.
+    pub fn to_redacted_json(&self) -> Result<String, Electrum2DescriptorError> {
+        let mut json = serde_json::to_value(self)?;
+        let wallet = json
+            .as_object_mut()
+            .ok_or(Electrum2DescriptorError::GenericBorrow(
+                "a wallet serializes as json object",
+            ))?;
+        for field in SECRET_FIELDS {
+            if let Some(value) = wallet.get_mut(*field) {
+                redact_all(value);
+            }
+        }
+        for (i, keystore) in self.keystores().iter().enumerate() {
+            if let Some(Value::Object(json)) = wallet.get_mut(&self.keystore_name(i)) {
+                for field in SECRET_KEYSTORE_FIELDS {
+                    if let Some(value) = json.get_mut(*field) {
+                        redact(value);
+                    }
+                }
+                redact_keys(keystore, json)?;
+            }
+        }
+        for value in wallet.values_mut() {
+            truncate_addresses(value);
+        }
+        Ok(serde_json::to_string_pretty(&json)?)
+    }
+}
+
+/// Replace a secret with the placeholder, keeping the nulls electrum writes for missing secrets
+fn redact(value: &mut Value) {
+    if !value.is_null() {
+        *value = Value::String(PLACEHOLDER.to_string());
+    }
+}
+
+/// Replace every string in a secret field, e.g. the private keys of the channels, keeping the structure
+fn redact_all(value: &mut Value) {
+    match value {
+        Value::String(_) => redact(value),
+        Value::Array(values) => values.iter_mut().for_each(redact_all),
+        Value::Object(map) => map.values_mut().for_each(redact_all),
+        _ => (),
+    }
+}
+
+/// Replace the xpub and xprv of a keystore with a dummy key and its root fingerprint with a dummy one. The other
+/// fields are searched for key material too, like the `ckcc_xpub` and `ckcc_xfp` of coldcards or a fingerprint in
+/// the label: extended keys get a dummy of their own and the fingerprint is replaced wherever it shows up.
+fn redact_keys(
+    keystore: &Keystore,
+    json: &mut serde_json::Map<String, Value>,
+) -> Result<(), Electrum2DescriptorError> {
+    let (xpub, kind, network) = match keystore.xpub() {
+        Ok(xpub) => xpub,
+        Err(_) => {
+            json.insert("xpub".to_string(), Value::String(PLACEHOLDER.to_string()));
+            return Ok(());
+        }
+    };
+    let dummy = dummy_xprv(&xpub, network)?;
+    let dummy_xkey = if keystore.xprv.is_some() {
+        dummy.to_string()
+    } else {
+        ExtendedPubKey::from_priv(secp(), &dummy).to_string()
+    };
+    // taproot keys have the standard prefix
+    let kind = match kind {
+        ScriptKind::P2tr => ScriptKind::Standard,
+        kind => kind,
+    };
+    let dummy = Keystore::new(kind.as_kind_str(), &dummy_xkey)?;
+    json.insert("xpub".to_string(), Value::String(dummy.xpub));
+    if let Some(xprv) = dummy.xprv {
+        json.insert("xprv".to_string(), Value::String(xprv));
+    }
+
+    let fingerprint = keystore
+        .fingerprint()
+        .map(|fingerprint| fingerprint.to_string());
+    let dummy_fingerprint = fingerprint.as_deref().map(|fingerprint| {
+        Fingerprint::from(
+            &dummy_hash(fingerprint.as_bytes())[..4]
+                .try_into()
+                .expect("4 bytes"),
+        )
+    });
+    for (field, value) in json.iter_mut() {
+        match (field.as_str(), value) {
+            ("xpub" | "xprv", _) => (),
+            ("root_fingerprint", Value::String(root_fingerprint)) => {
+                if let Some(dummy) = dummy_fingerprint {
+                    *root_fingerprint = dummy.to_string();
+                }
+            }
+            ("ckcc_xfp", value) => {
+                *value = match dummy_fingerprint {
+                    Some(dummy) => Value::from(u32::from_le_bytes(dummy.to_bytes())),
+                    None => Value::String(PLACEHOLDER.to_string()),
+                }
+            }
+            (_, value) => redact_key_material(value, fingerprint.as_deref(), dummy_fingerprint)?,
+        }
+    }
+    Ok(())
+}
+
+/// Replace the extended keys in a value with dummies, and the fingerprint of the keystore with the dummy one
+fn redact_key_material(
+    value: &mut Value,
+    fingerprint: Option<&str>,
+    dummy_fingerprint: Option<Fingerprint>,
+) -> Result<(), Electrum2DescriptorError> {
+    match value {
+        Value::String(s) => {
+            if let Ok(xpub) = ExtendedPubKey::from_str(s) {
+                *s = ExtendedPubKey::from_priv(secp(), &dummy_xprv(&xpub, xpub.network)?)
+                    .to_string();
+            } else if ExtendedPrivKey::from_str(s).is_ok() {
+                *s = PLACEHOLDER.to_string();
+            } else if let (Some(fingerprint), Some(dummy)) = (fingerprint, dummy_fingerprint) {
+                // coldcards write the fingerprint in upper case, e.g. in labels
+                let starts = s
+                    .to_ascii_lowercase()
+                    .match_indices(fingerprint)
+                    .map(|(start, _)| start)
+                    .collect::<Vec<_>>();
+                for start in starts {
+                    s.replace_range(start..start + fingerprint.len(), &dummy.to_string());
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                redact_key_material(value, fingerprint, dummy_fingerprint)?;
+            }
+        }
+        Value::Object(map) => {
+            for value in map.values_mut() {
+                redact_key_material(value, fingerprint, dummy_fingerprint)?;
+            }
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+/// The dummy of an extended key: a master key from a hash of it, placed where the key is
+fn dummy_xprv(
+    xpub: &ExtendedPubKey,
+    network: Network,
+) -> Result<ExtendedPrivKey, Electrum2DescriptorError> {
+    let hash = dummy_hash(&xpub.encode());
+    let mut dummy = ExtendedPrivKey::new_master(network, hash.as_byte_array())?;
+    dummy.depth = xpub.depth;
+    dummy.child_number = xpub.child_number;
+    dummy.parent_fingerprint = Fingerprint::from(&hash[..4].try_into().expect("4 bytes"));
+    Ok(dummy)
+}
+
+fn dummy_hash(data: &[u8]) -> sha256::Hash {
+    let mut engine = sha256::Hash::engine();
+    engine.input(b"electrum2descriptors redaction");
+    engine.input(data);
+    sha256::Hash::from_engine(engine)
+}
+
+/// Truncate the addresses found anywhere in the json, both as values and as keys of objects
+fn truncate_addresses(value: &mut Value) {
+    match value {
+        Value::String(s) => *s = truncate_address(s),
+        Value::Array(values) => values.iter_mut().for_each(truncate_addresses),
+        Value::Object(map) => {
+            let entries = std::mem::take(map);
+            for (key, mut value) in entries {
+                truncate_addresses(&mut value);
+                map.insert(truncate_address(&key), value);
+            }
+        }
+        _ => (),
+    }
+}
+
+fn truncate_address(s: &str) -> String {
+    if s.len() > ADDRESS_PREFIX_LEN && Address::from_str(s).is_ok() {
+        format!("{}...", &s[..ADDRESS_PREFIX_LEN])
+    } else {
+        s.to_string()
+    }
+}
@@ -0,0 +1,157 @@
+use crate::Electrum2DescriptorError;
+use bitcoin::bip32::{ChildNumber, DerivationPath};
+use std::{fmt, str::FromStr};
+
+/// The script type an extended key is meant for, as encoded by its slip-0132 prefix.
+/// The names follow electrum's xpub header names.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ScriptKind {
+    /// xpub / tpub: p2pkh for single signature, p2sh for multisig
+    Standard,
+    /// ypub / upub
+    P2wpkhP2sh,
+    /// Ypub / Upub
+    P2wshP2sh,
+    /// zpub / vpub
+    P2wpkh,
+    /// Zpub / Vpub
+    P2wsh,
+    /// There is no taproot prefix, taproot keys use xpub / tpub and are recognized otherwise
+    P2tr,
+}
+
+impl ScriptKind {
+    /// All the script kinds
+    pub const ALL: [ScriptKind; 6] = [
+        ScriptKind::Standard,
+        ScriptKind::P2wpkhP2sh,
+        ScriptKind::P2wshP2sh,
+        ScriptKind::P2wpkh,
+        ScriptKind::P2wsh,
+        ScriptKind::P2tr,
+    ];
+
+    /// The kind string used by the electrum extended keys, which is the opening of the descriptor
+    pub fn as_kind_str(&self) -> &'static str {
+        match self {
+            ScriptKind::Standard => "pkh",
+            ScriptKind::P2wpkhP2sh => "sh(wpkh",
+            ScriptKind::P2wshP2sh => "sh(wsh",
+            ScriptKind::P2wpkh => "wpkh",
+            ScriptKind::P2wsh => "wsh",
+            ScriptKind::P2tr => "tr",
+        }
+    }
+
+    /// The kind a derivation stands for, if it follows bip-0044, 45, 48, 49, 84 or 86
+    pub fn from_derivation(derivation: &DerivationPath) -> Option<ScriptKind> {
+        let hardened = |index| ChildNumber::Hardened { index };
+        match derivation.as_ref() {
+            [purpose, ..] if *purpose == hardened(44) || *purpose == hardened(45) => {
+                Some(ScriptKind::Standard)
+            }
+            [purpose, ..] if *purpose == hardened(49) => Some(ScriptKind::P2wpkhP2sh),
+            [purpose, ..] if *purpose == hardened(84) => Some(ScriptKind::P2wpkh),
+            [purpose, ..] if *purpose == hardened(86) => Some(ScriptKind::P2tr),
+            [purpose, _coin, _account, script, ..] if *purpose == hardened(48) => match script {
+                script if *script == hardened(1) => Some(ScriptKind::P2wshP2sh),
+                script if *script == hardened(2) => Some(ScriptKind::P2wsh),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Whether the kind is meant for multisig scripts. Standard keys are used for both.
+    pub fn is_multisig(&self) -> bool {
+        matches!(self, ScriptKind::P2wshP2sh | ScriptKind::P2wsh)
+    }
+}
+
+/// The kind string of an extended key whose prefix tells `prefix_kind`, with `kind` overriding it. Only plain
+/// xpub/xprv prefixes can be overridden, a slip-0132 prefix must agree.
+pub(crate) fn override_kind(
+    xkey: &str,
+    prefix_kind: &str,
+    kind: ScriptKind,
+) -> Result<&'static str, Electrum2DescriptorError> {
+    match ScriptKind::from_str(prefix_kind)? {
+        ScriptKind::Standard => Ok(kind.as_kind_str()),
+        prefix_kind if prefix_kind == kind => Ok(kind.as_kind_str()),
+        prefix_kind => Err(Electrum2DescriptorError::ScriptKindConflict(
+            xkey.to_string(),
+            prefix_kind.to_string(),
+            kind.to_string(),
+        )),
+    }
+}
+
+impl fmt::Display for ScriptKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            ScriptKind::Standard => "standard",
+            ScriptKind::P2wpkhP2sh => "p2wpkh-p2sh",
+            ScriptKind::P2wshP2sh => "p2wsh-p2sh",
+            ScriptKind::P2wpkh => "p2wpkh",
+            ScriptKind::P2wsh => "p2wsh",
+            ScriptKind::P2tr => "p2tr",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for ScriptKind {
+    type Err = Electrum2DescriptorError;
+
+    /// Parse from electrum's names or from the kind strings of the electrum extended keys
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "standard" | "p2pkh" | "p2sh" | "pkh" => Ok(ScriptKind::Standard),
+            "p2wpkh-p2sh" | "sh(wpkh" => Ok(ScriptKind::P2wpkhP2sh),
+            "p2wsh-p2sh" | "sh(wsh" => Ok(ScriptKind::P2wshP2sh),
+            "p2wpkh" | "wpkh" => Ok(ScriptKind::P2wpkh),
+            "p2wsh" | "wsh" => Ok(ScriptKind::P2wsh),
+            "p2tr" | "tr" => Ok(ScriptKind::P2tr),
+            _ => Err(Electrum2DescriptorError::UnknownScriptKind(s.to_string())),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ScriptKind {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        for kind in ScriptKind::ALL {
+            assert_eq!(ScriptKind::from_str(&kind.to_string()).unwrap(), kind);
+            assert_eq!(ScriptKind::from_str(kind.as_kind_str()).unwrap(), kind);
+        }
+        assert!(ScriptKind::from_str("p2pk").is_err());
+    }
+
+    #[test]
+    fn test_from_derivation() {
+        let kind = |derivation| {
+            ScriptKind::from_derivation(&DerivationPath::from_str(derivation).unwrap())
+        };
+        assert_eq!(kind("m/84'/0'/0'"), Some(ScriptKind::P2wpkh));
+        assert_eq!(kind("m/49h/1h/0h"), Some(ScriptKind::P2wpkhP2sh));
+        assert_eq!(kind("m/48'/1'/0'/2'"), Some(ScriptKind::P2wsh));
+        assert_eq!(kind("m/48'/1'/0'/1'"), Some(ScriptKind::P2wshP2sh));
+        assert_eq!(kind("m/45'"), Some(ScriptKind::Standard));
+        assert_eq!(kind("m/48'/1'"), None);
+        assert_eq!(kind("m/0'"), None);
+        assert_eq!(kind("m"), None);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::Electrum2DescriptorError;
+use bitcoin::hashes::{hmac, sha512, Hash, HashEngine};
+use std::sync::atomic::{compiler_fence, Ordering};
+
+/// The rounds of pbkdf2 electrum stretches seeds with
+const PBKDF2_ROUNDS: usize = 2048;
+
+/// The types of electrum seeds, told apart by the prefix of their version hash
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SeedType {
+    Standard,
+    Segwit,
+}
+
+impl SeedType {
+    /// The type of a normalized seed phrase. Two factor seeds and bip-0039 or old electrum seeds aren't supported.
+    pub(crate) fn of(seed: &str) -> Result<SeedType, Electrum2DescriptorError> {
+        let version = hmac_sha512(b"Seed version", seed.as_bytes()).to_string();
+        if version.starts_with("01") {
+            Ok(SeedType::Standard)
+        } else if version.starts_with("100") {
+            Ok(SeedType::Segwit)
+        } else if version.starts_with("101") || version.starts_with("102") {
+            Err(Electrum2DescriptorError::UnsupportedSeed(
+                "two factor seeds".to_string(),
+            ))
+        } else {
+            Err(Electrum2DescriptorError::UnsupportedSeed(
+                "seeds that aren't electrum standard or segwit seeds".to_string(),
+            ))
+        }
+    }
+}
+
+/// Normalize a seed phrase the way electrum does for ascii seeds: lowercase words separated by single spaces.
+/// Electrum also strips accents after unicode normalization, seeds with other characters may not match.
+pub(crate) fn normalize(seed: &str) -> String {
+    seed.to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The bip-0032 seed of a normalized electrum seed phrase and its extension. The caller wipes it after use.
+pub(crate) fn bip32_seed(seed: &str, extension: &str) -> [u8; 64] {
+    let mut extension = normalize(extension);
+    let mut salt = format!("electrum{}", extension);
+    let bip32_seed = pbkdf2_hmac_sha512(seed.as_bytes(), salt.as_bytes(), PBKDF2_ROUNDS);
+    wipe_string(&mut extension);
+    wipe_string(&mut salt);
+    bip32_seed
+}
+
+/// The first block of pbkdf2 with hmac-sha512, as much as electrum derives from a password or seed
+pub(crate) fn pbkdf2_hmac_sha512(password: &[u8], salt: &[u8], rounds: usize) -> [u8; 64] {
+    let mut block = salt.to_vec();
+    block.extend_from_slice(&1u32.to_be_bytes());
+    let mut u = hmac_sha512(password, &block).to_byte_array();
+    let mut result = u;
+    for _ in 1..rounds {
+        u = hmac_sha512(password, &u).to_byte_array();
+        result.iter_mut().zip(u.iter()).for_each(|(r, u)| *r ^= u);
+    }
+    wipe(&mut u);
+    wipe(&mut block);
+    result
+}
+
+/// Overwrite secret bytes with zeros, in a way the compiler doesn't optimize away as a dead store. Copies the
+/// compiler made on its own, e.g. when moving values, aren't reached.
+pub(crate) fn wipe(bytes: &mut [u8]) {
+    for byte in bytes.iter_mut() {
+        // SAFETY: the pointer comes from a mutable reference, so it is valid and aligned
+        unsafe { std::ptr::write_volatile(byte, 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Wipe a string holding a secret, e.g. a seed phrase, leaving it empty
+pub(crate) fn wipe_string(string: &mut String) {
+    let mut bytes = std::mem::take(string).into_bytes();
+    wipe(&mut bytes);
+}
+
+fn hmac_sha512(key: &[u8], data: &[u8]) -> hmac::Hmac<sha512::Hash> {
+    let mut engine = hmac::HmacEngine::<sha512::Hash>::new(key);
+    engine.input(data);
+    hmac::Hmac::from_engine(engine)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::hex::FromHex;
+
+    #[test]
+    fn test_seed_type() {
+        let seed = "old desert genius anchor vessel kingdom mushroom put rail inspire file biology";
+        assert_eq!(SeedType::of(seed).unwrap(), SeedType::Segwit);
+        let seed =
+            "mail high accident nothing immune blanket suggest donor number gravity palm lamp";
+        assert_eq!(SeedType::of(seed).unwrap(), SeedType::Standard);
+        assert!(SeedType::of("not a seed").is_err());
+    }
+
+    #[test]
+    fn test_bip32_seed() {
+        // the english cases of electrum's test_mnemonic, the extension is normalized like the seed
+        let seed =
+            "wild father tree among universe such mobile favorite target dynamic credit identify";
+        for (extension, expected) in [
+            ("", "aac2a6302e48577ab4b46f23dbae0774e2e62c796f797d0a1b5faeb528301e3064342dafb79069e7c4c6b8c38ae11d7a973bec0d4f70626f8cc5184a8d0b0756"),
+            ("Did you ever hear the tragedy of Darth  Plagueis the Wise?", "4aa29f2aeb0127efb55138ab9e7be83b36750358751906f86c662b21a1ea1370f949e6d1a12fa56d3d93cadda93038c76ac8118597364e46f5156fde6183c82f"),
+        ] {
+            assert_eq!(
+                bip32_seed(seed, extension).to_vec(),
+                Vec::<u8>::from_hex(expected).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_wipe() {
+        let mut secret = "old desert genius".to_string();
+        wipe_string(&mut secret);
+        assert!(secret.is_empty());
+        let mut bytes = [1u8; 4];
+        wipe(&mut bytes);
+        assert_eq!(bytes, [0; 4]);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("  Old\tdesert \n genius "), "old desert genius");
+    }
+}
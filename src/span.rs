@@ -0,0 +1,46 @@
+//! The spans of the `log` feature, as the `tracing` crate isn't a dependency. A span records when a step such as
+//! parsing, converting or writing a wallet starts and ends, and the records in between carry the path of the open
+//! spans of their thread in their `span` key, e.g. `parse_descriptor/validate`. Like the records, their context
+//! only holds names, kinds and counts.
+use std::{cell::RefCell, fmt, time::Instant};
+
+pub(crate) const TARGET: &str = "electrum2descriptors";
+
+thread_local! {
+    static SPANS: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+}
+
+/// An open span, closed when dropped
+pub(crate) struct Span {
+    name: &'static str,
+    start: Instant,
+}
+
+impl Span {
+    pub(crate) fn enter(name: &'static str, context: fmt::Arguments) -> Span {
+        SPANS.with(|spans| spans.borrow_mut().push(name));
+        log::debug!(target: TARGET, span = path().as_str(); "{} {}", name, context);
+        Span {
+            name,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        log::debug!(
+            target: TARGET,
+            span = path().as_str();
+            "{} done in {:?}",
+            self.name,
+            self.start.elapsed()
+        );
+        SPANS.with(|spans| spans.borrow_mut().pop());
+    }
+}
+
+/// The names of the open spans, outermost first
+pub(crate) fn path() -> String {
+    SPANS.with(|spans| spans.borrow().join("/"))
+}
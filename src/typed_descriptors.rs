@@ -0,0 +1,198 @@
+use crate::electrum_wallet_file::{KeyOrder, Keystore, WalletType};
+use crate::{Descriptors, Electrum2DescriptorError, ElectrumWalletFile, ScriptKind};
+use bitcoin::bip32::{ChildNumber, DerivationPath};
+use miniscript::descriptor::{
+    Descriptor, DescriptorPublicKey, DescriptorSecretKey, DescriptorXKey, KeyMap, Wildcard,
+};
+use miniscript::{Miniscript, ScriptContext, Terminal};
+use std::convert::TryFrom;
+
+/// The receiving and the change descriptor of a wallet
+pub type TypedDescriptors = (
+    Descriptor<DescriptorPublicKey>,
+    Descriptor<DescriptorPublicKey>,
+);
+
+impl TryFrom<&ElectrumWalletFile> for TypedDescriptors {
+    type Error = Electrum2DescriptorError;
+
+    /// Build the typed receiving and change descriptors from the xpubs of the keystores, the same ones
+    /// [`ElectrumWalletFile::to_descriptors`] renders
+    fn try_from(wallet: &ElectrumWalletFile) -> Result<Self, Self::Error> {
+        Ok((descriptor(wallet, false)?, descriptor(wallet, true)?))
+    }
+}
+
+/// The descriptors of a wallet as strings, with the xprvs of the keystores that hold one, see
+/// [`ElectrumWalletFile::to_descriptors`]
+pub(crate) fn to_descriptors(
+    wallet: &ElectrumWalletFile,
+) -> Result<Descriptors, Electrum2DescriptorError> {
+    let (external, change) = TypedDescriptors::try_from(wallet)?;
+    Ok(Descriptors {
+        external: with_secrets(&external, &key_map(wallet, false)?),
+        change: with_secrets(&change, &key_map(wallet, true)?),
+    })
+}
+
+/// The descriptor with the secret keys of the key map in place of their public keys, without checksum
+fn with_secrets(descriptor: &Descriptor<DescriptorPublicKey>, key_map: &KeyMap) -> String {
+    if key_map.is_empty() {
+        // the alternate form leaves out the checksum
+        return format!("{:#}", descriptor);
+    }
+    let descriptor = descriptor.to_string_with_secret(key_map);
+    match descriptor.split_once('#') {
+        Some((descriptor, _checksum)) => descriptor.to_string(),
+        None => descriptor,
+    }
+}
+
+/// The xprvs of the hot keystores, by the public keys of the receiving or change descriptor
+fn key_map(wallet: &ElectrumWalletFile, change: bool) -> Result<KeyMap, Electrum2DescriptorError> {
+    let mut key_map = KeyMap::new();
+    for keystore in wallet.keystores() {
+        if let Some(xprv) = keystore.xprv()? {
+            let public = descriptor_key(keystore, change)?;
+            let secret = DescriptorSecretKey::XPrv(DescriptorXKey {
+                origin: public.origin.clone(),
+                xkey: xprv,
+                derivation_path: public.derivation_path.clone(),
+                wildcard: public.wildcard,
+            });
+            key_map.insert(DescriptorPublicKey::XPub(public), secret);
+        }
+    }
+    Ok(key_map)
+}
+
+/// The xpub of the keystore with its origin and the receiving or change chain
+fn descriptor_key(
+    keystore: &Keystore,
+    change: bool,
+) -> Result<DescriptorXKey<bitcoin::bip32::ExtendedPubKey>, Electrum2DescriptorError> {
+    let (xpub, _kind, _network) = keystore.xpub()?;
+    Ok(DescriptorXKey {
+        origin: keystore.descriptor_origin(),
+        xkey: xpub,
+        derivation_path: DerivationPath::from(vec![ChildNumber::from_normal_idx(
+            keystore.chain(change),
+        )?]),
+        wildcard: Wildcard::Unhardened,
+    })
+}
+
+/// The descriptor of the receiving or change chain
+fn descriptor(
+    wallet: &ElectrumWalletFile,
+    change: bool,
+) -> Result<Descriptor<DescriptorPublicKey>, Electrum2DescriptorError> {
+    wallet.check_complete()?;
+    let mut kind = ScriptKind::Standard;
+    let keys = wallet
+        .keystores()
+        .iter()
+        .enumerate()
+        .map(|(i, keystore)| {
+            let (_xpub, script_kind, _network) = keystore.xpub()?;
+            // like electrum, the first keystore tells the script kind
+            if i == 0 {
+                kind = script_kind;
+            }
+            Ok(DescriptorPublicKey::XPub(descriptor_key(keystore, change)?))
+        })
+        .collect::<Result<Vec<_>, Electrum2DescriptorError>>()?;
+
+    let descriptor = match (wallet.wallet_type(), kind) {
+        (WalletType::Standard, ScriptKind::Standard) => Descriptor::new_pkh(keys[0].clone())?,
+        (WalletType::Standard, ScriptKind::P2wpkhP2sh) => Descriptor::new_sh_wpkh(keys[0].clone())?,
+        (WalletType::Standard, ScriptKind::P2wpkh) => Descriptor::new_wpkh(keys[0].clone())?,
+        (WalletType::Standard, ScriptKind::P2tr) => Descriptor::new_tr(keys[0].clone(), None)?,
+        (WalletType::Multisig(_), ScriptKind::P2tr) => {
+            return Err(Electrum2DescriptorError::TaprootMultisig)
+        }
+        (WalletType::Multisig(multisig), kind) if wallet.key_order() == KeyOrder::AsStored => {
+            match kind {
+                ScriptKind::Standard => Descriptor::new_sh(multi(multisig.threshold(), keys)?)?,
+                ScriptKind::P2wshP2sh | ScriptKind::P2wpkhP2sh => {
+                    Descriptor::new_sh_wsh(multi(multisig.threshold(), keys)?)?
+                }
+                ScriptKind::P2wsh | ScriptKind::P2wpkh => {
+                    Descriptor::new_wsh(multi(multisig.threshold(), keys)?)?
+                }
+                kind => {
+                    return Err(Electrum2DescriptorError::UnknownScriptKind(
+                        kind.to_string(),
+                    ))
+                }
+            }
+        }
+        (WalletType::Multisig(multisig), ScriptKind::Standard) => {
+            Descriptor::new_sh_sortedmulti(multisig.threshold().into(), keys)?
+        }
+        (WalletType::Multisig(multisig), ScriptKind::P2wshP2sh | ScriptKind::P2wpkhP2sh) => {
+            Descriptor::new_sh_wsh_sortedmulti(multisig.threshold().into(), keys)?
+        }
+        (WalletType::Multisig(multisig), ScriptKind::P2wsh | ScriptKind::P2wpkh) => {
+            Descriptor::new_wsh_sortedmulti(multisig.threshold().into(), keys)?
+        }
+        (_, kind) => {
+            return Err(Electrum2DescriptorError::UnknownScriptKind(
+                kind.to_string(),
+            ))
+        }
+    };
+    Ok(descriptor)
+}
+
+/// The `multi()` of the keys in keystore order, see [`KeyOrder::AsStored`]
+fn multi<Ctx: ScriptContext>(
+    threshold: u8,
+    keys: Vec<DescriptorPublicKey>,
+) -> Result<Miniscript<DescriptorPublicKey, Ctx>, Electrum2DescriptorError> {
+    Ok(Miniscript::from_ast(Terminal::Multi(
+        threshold as usize,
+        keys,
+    ))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_typed_matches_strings() {
+        let wallets = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/wallets");
+        let mut compared = 0;
+        let mut hot = 0;
+        for entry in std::fs::read_dir(wallets).unwrap() {
+            let path = entry.unwrap().path();
+            let wallet = match ElectrumWalletFile::from_file(&path) {
+                Ok(wallet) => wallet,
+                Err(_) => continue,
+            };
+            let strings = match wallet.descriptor_strings() {
+                Ok(strings) => strings,
+                Err(_) => continue,
+            };
+            match TypedDescriptors::try_from(&wallet) {
+                Ok(_) => {
+                    assert_eq!(to_descriptors(&wallet).unwrap(), strings, "{:?}", path);
+                    compared += 1;
+                    if wallet.keystores().iter().any(|ks| ks.xprv.is_some()) {
+                        hot += 1;
+                    }
+                }
+                // the string builder makes invalid descriptors of keys whose prefix doesn't fit the wallet type
+                Err(_) => assert!(
+                    Descriptor::parse_descriptor(crate::utils::secp(), &strings.external).is_err(),
+                    "{:?}",
+                    path
+                ),
+            }
+        }
+        assert!(compared > 10, "{}", compared);
+        assert!(hot > 5, "{}", hot);
+    }
+}
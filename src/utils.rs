@@ -0,0 +1,265 @@
+use crate::{Electrum2DescriptorError, ScriptKind};
+use bitcoin::{
+    address::NetworkUnchecked,
+    secp256k1::{All, Secp256k1},
+    Address, AddressType, Network,
+};
+use std::{fmt, str::FromStr, sync::OnceLock};
+
+/// The script kind an address reveals
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddressKind {
+    /// The address is only used by one script kind, `Standard` being p2pkh
+    Known(ScriptKind),
+    /// A p2sh address, which may wrap p2wpkh or p2wsh or be a legacy multisig
+    Ambiguous,
+}
+
+impl AddressKind {
+    /// The kind of the addresses of a wallet with keys of the script kind
+    pub fn for_script_kind(kind: ScriptKind, multisig: bool) -> AddressKind {
+        match kind {
+            ScriptKind::Standard if multisig => AddressKind::Ambiguous,
+            ScriptKind::P2wpkhP2sh | ScriptKind::P2wshP2sh => AddressKind::Ambiguous,
+            kind => AddressKind::Known(kind),
+        }
+    }
+}
+
+impl fmt::Display for AddressKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AddressKind::Known(ScriptKind::Standard) => write!(f, "p2pkh"),
+            AddressKind::Known(kind) => write!(f, "{}", kind),
+            AddressKind::Ambiguous => write!(f, "p2sh"),
+        }
+    }
+}
+
+/// The script kind and network of an address. Base58 addresses don't tell testnet from regtest and signet,
+/// they are reported as testnet.
+pub fn kind_from_address(
+    address: &Address<NetworkUnchecked>,
+) -> Result<(AddressKind, Network), Electrum2DescriptorError> {
+    let network = address.network;
+    let address = address.clone().assume_checked();
+    let kind = match address.address_type() {
+        Some(AddressType::P2pkh) => AddressKind::Known(ScriptKind::Standard),
+        Some(AddressType::P2sh) => AddressKind::Ambiguous,
+        Some(AddressType::P2wpkh) => AddressKind::Known(ScriptKind::P2wpkh),
+        Some(AddressType::P2wsh) => AddressKind::Known(ScriptKind::P2wsh),
+        Some(AddressType::P2tr) => AddressKind::Known(ScriptKind::P2tr),
+        _ => {
+            return Err(Electrum2DescriptorError::UnknownScriptKind(
+                address.to_string(),
+            ))
+        }
+    };
+    Ok((kind, network))
+}
+
+/// The script kind and network of an address given as string
+pub fn kind_from_address_str(
+    address: &str,
+) -> Result<(AddressKind, Network), Electrum2DescriptorError> {
+    kind_from_address(&Address::from_str(address)?)
+}
+
+/// The characters of base58 encoded keys
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// The quotes pasted keys may come wrapped in, opening and closing
+const QUOTE_PAIRS: &[(char, char)] = &[
+    ('"', '"'),
+    ('\'', '\''),
+    ('`', '`'),
+    ('\u{201c}', '\u{201d}'),
+    ('\u{2018}', '\u{2019}'),
+    ('\u{201e}', '\u{201c}'),
+    ('\u{00ab}', '\u{00bb}'),
+];
+
+/// Whether a character is whitespace or doesn't show, like the zero-width space. Neither is part of keys or
+/// descriptors, but both come along when copying from PDFs and chat clients.
+fn is_invisible(c: char) -> bool {
+    c.is_whitespace()
+        || matches!(
+            c,
+            '\u{00ad}' | '\u{200b}'..='\u{200f}' | '\u{2060}'..='\u{2064}' | '\u{feff}'
+        )
+}
+
+/// The characters of pasted text without the invisible ones and the quotes around it, each with its position in
+/// the text
+fn clean_chars(text: &str) -> Vec<(usize, char)> {
+    let mut chars = text
+        .chars()
+        .enumerate()
+        .filter(|(_, c)| !is_invisible(*c))
+        .collect::<Vec<_>>();
+    while let (Some((_, first)), Some((_, last))) = (chars.first(), chars.last()) {
+        if chars.len() < 2 || !QUOTE_PAIRS.contains(&(*first, *last)) {
+            break;
+        }
+        chars.pop();
+        chars.remove(0);
+    }
+    chars
+}
+
+/// Pasted text, e.g. a descriptor, without whitespace, invisible characters and the quotes around it
+#[cfg(feature = "wallet_file")]
+pub(crate) fn clean_text(text: &str) -> String {
+    clean_chars(text).into_iter().map(|(_, c)| c).collect()
+}
+
+/// A pasted base58 key cleaned like [`clean_text`]. Fails for the first character left that isn't base58, with
+/// its position in the pasted key counted from 1. Valid keys are returned unchanged.
+pub(crate) fn clean_key(key: &str) -> Result<String, Electrum2DescriptorError> {
+    let chars = clean_chars(key);
+    if let Some((position, c)) = chars.iter().find(|(_, c)| !BASE58_ALPHABET.contains(*c)) {
+        return Err(Electrum2DescriptorError::InvalidKeyCharacter(
+            *c,
+            position + 1,
+        ));
+    }
+    Ok(chars.into_iter().map(|(_, c)| c).collect())
+}
+
+/// The secp256k1 context shared by the crate. Creating a context allocates and randomizes it, so it is only created
+/// on first use, when keys are derived or messages signed.
+pub(crate) fn secp() -> &'static Secp256k1<All> {
+    static SECP: OnceLock<Secp256k1<All>> = OnceLock::new();
+    SECP.get_or_init(Secp256k1::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind_from_address() {
+        let known = AddressKind::Known;
+        let cases = [
+            (
+                "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH",
+                known(ScriptKind::Standard),
+                Network::Bitcoin,
+            ),
+            (
+                "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy",
+                AddressKind::Ambiguous,
+                Network::Bitcoin,
+            ),
+            (
+                "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+                known(ScriptKind::P2wpkh),
+                Network::Bitcoin,
+            ),
+            (
+                "bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3",
+                known(ScriptKind::P2wsh),
+                Network::Bitcoin,
+            ),
+            (
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+                known(ScriptKind::P2tr),
+                Network::Bitcoin,
+            ),
+            (
+                "mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn",
+                known(ScriptKind::Standard),
+                Network::Testnet,
+            ),
+            (
+                "2MzQwSSnBHWHqSAqtTVQ6v47XtaisrJa1Vc",
+                AddressKind::Ambiguous,
+                Network::Testnet,
+            ),
+            (
+                "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx",
+                known(ScriptKind::P2wpkh),
+                Network::Testnet,
+            ),
+            (
+                "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+                known(ScriptKind::P2wsh),
+                Network::Testnet,
+            ),
+            (
+                "tb1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq47zagq",
+                known(ScriptKind::P2tr),
+                Network::Testnet,
+            ),
+            // regtest uses the base58 prefixes of testnet
+            (
+                "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080",
+                known(ScriptKind::P2wpkh),
+                Network::Regtest,
+            ),
+            (
+                "bcrt1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qzf4jry",
+                known(ScriptKind::P2wsh),
+                Network::Regtest,
+            ),
+            (
+                "bcrt1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqc8gma6",
+                known(ScriptKind::P2tr),
+                Network::Regtest,
+            ),
+        ];
+        for (address, kind, network) in cases {
+            assert_eq!(
+                kind_from_address_str(address).unwrap(),
+                (kind, network),
+                "{}",
+                address
+            );
+        }
+        assert!(kind_from_address_str("not an address").is_err());
+    }
+
+    #[test]
+    fn test_for_script_kind() {
+        let kind = AddressKind::for_script_kind;
+        assert_eq!(
+            kind(ScriptKind::Standard, false),
+            AddressKind::Known(ScriptKind::Standard)
+        );
+        assert_eq!(kind(ScriptKind::Standard, true), AddressKind::Ambiguous);
+        assert_eq!(kind(ScriptKind::P2wpkhP2sh, false), AddressKind::Ambiguous);
+        assert_eq!(
+            kind(ScriptKind::P2wsh, true),
+            AddressKind::Known(ScriptKind::P2wsh)
+        );
+    }
+    #[test]
+    fn test_shared_secp() {
+        assert!(std::ptr::eq(secp(), secp()));
+    }
+
+    #[test]
+    fn test_clean_key() {
+        let key = "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8";
+        assert_eq!(clean_key(key).unwrap(), key);
+        let pasted = format!(
+            "\u{201c}\u{200b}{}\n{}\u{00a0}\u{201d}",
+            &key[..50],
+            &key[50..]
+        );
+        assert_eq!(clean_key(&pasted).unwrap(), key);
+        assert_eq!(clean_key(&format!("'{}'", key)).unwrap(), key);
+        // unmatched quotes stay and are reported
+        assert!(matches!(
+            clean_key(&format!("\u{201c}{}", key)),
+            Err(Electrum2DescriptorError::InvalidKeyCharacter('\u{201c}', 1))
+        ));
+        let typo = format!("\u{200b}{}0{}", &key[..10], &key[10..]);
+        assert!(matches!(
+            clean_key(&typo),
+            Err(Electrum2DescriptorError::InvalidKeyCharacter('0', 12))
+        ));
+        #[cfg(feature = "wallet_file")]
+        assert_eq!(clean_text(" 'wpkh(xpub/0/*)'\n"), "wpkh(xpub/0/*)");
+    }
+}
@@ -0,0 +1,182 @@
+use crate::electrum_wallet_file::{KeyOrder, WalletType};
+use crate::{Electrum2DescriptorError, ElectrumWalletFile};
+use bitcoin::hashes::{sha256, Hash};
+use std::fmt;
+
+/// How many hex characters of the hash make a wallet id
+const WALLET_ID_LEN: usize = 16;
+
+/// A difference between two wallets, given from the point of view of the first one
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WalletDifference {
+    WalletType(WalletType, WalletType),
+    /// The kind strings of the extended keys, like `wpkh` or `sh(wsh`
+    ScriptKind(String, String),
+    /// A cosigner, as bip32 xpub, only in the first wallet
+    MissingCosigner(String),
+    /// A cosigner, as bip32 xpub, only in the second wallet
+    ExtraCosigner(String),
+    /// The first receiving addresses differ
+    FirstAddress(String, String),
+}
+
+impl ElectrumWalletFile {
+    /// The differences to another wallet that make them refer to different coins.
+    /// Extended key prefixes and the order of cosigners don't matter, labels and history are not compared.
+    pub fn diff(
+        &self,
+        other: &ElectrumWalletFile,
+    ) -> Result<Vec<WalletDifference>, Electrum2DescriptorError> {
+        self.check_complete()?;
+        other.check_complete()?;
+        let mut differences = Vec::new();
+        if self.wallet_type() != other.wallet_type() {
+            differences.push(WalletDifference::WalletType(
+                self.wallet_type().clone(),
+                other.wallet_type().clone(),
+            ));
+        }
+
+        let (kind, xpubs) = kind_and_xpubs(self)?;
+        let (other_kind, other_xpubs) = kind_and_xpubs(other)?;
+        if kind != other_kind {
+            differences.push(WalletDifference::ScriptKind(kind, other_kind));
+        }
+        for xpub in xpubs.iter().filter(|xpub| !other_xpubs.contains(xpub)) {
+            differences.push(WalletDifference::MissingCosigner(xpub.clone()));
+        }
+        for xpub in other_xpubs.iter().filter(|xpub| !xpubs.contains(xpub)) {
+            differences.push(WalletDifference::ExtraCosigner(xpub.clone()));
+        }
+
+        let address = self.address(false, 0)?.to_string();
+        let other_address = other.address(false, 0)?.to_string();
+        if address != other_address {
+            differences.push(WalletDifference::FirstAddress(address, other_address));
+        }
+        Ok(differences)
+    }
+
+    /// Whether the two wallets refer to the same coins
+    pub fn is_equivalent(
+        &self,
+        other: &ElectrumWalletFile,
+    ) -> Result<bool, Electrum2DescriptorError> {
+        Ok(self.diff(other)?.is_empty())
+    }
+
+    /// A stable identifier of the wallet, e.g. to deduplicate backups: equivalent wallets get the same id, whether
+    /// watch-only or not, and it doesn't change when addresses are added or labels edited.
+    ///
+    /// The id is the first 16 hex characters of the sha256 of the receiving and the change descriptor, without
+    /// checksums, joined by a newline. The descriptors are built from the bip32 encoded xpubs (`xpub`/`tpub`) with the
+    /// script kind of the wallet, the cosigners sorted by xpub and without key origins. The script kind is taken from
+    /// the slip-0132 prefix of the first key, a singlesig prefix of a multisig wallet meaning its multisig kind and the
+    /// other way round. For example
+    /// `wsh(sortedmulti(2,tpubA/0/*,tpubB/0/*))\nwsh(sortedmulti(2,tpubA/1/*,tpubB/1/*))` or
+    /// `sh(wpkh(xpubA/0/*))\nsh(wpkh(xpubA/1/*))`. Legacy multisig is `sh(sortedmulti(...))`. With
+    /// [`KeyOrder::AsStored`] the cosigners stay in keystore order in `multi(...)`. Cosigners with other
+    /// [`crate::electrum_wallet_file::Keystore::chains`] have theirs instead of `/0/*` and `/1/*`.
+    pub fn wallet_id(&self) -> Result<String, Electrum2DescriptorError> {
+        self.check_complete()?;
+        let preimage = [0, 1]
+            .iter()
+            .map(|chain| canonical_descriptor(self, *chain))
+            .collect::<Result<Vec<_>, _>>()?
+            .join("\n");
+        let hash = sha256::Hash::hash(preimage.as_bytes()).to_string();
+        Ok(hash[..WALLET_ID_LEN].to_string())
+    }
+}
+
+/// The public descriptor of a chain the wallet id is computed from, see [`ElectrumWalletFile::wallet_id`]
+fn canonical_descriptor(
+    wallet: &ElectrumWalletFile,
+    chain: u32,
+) -> Result<String, Electrum2DescriptorError> {
+    let (kind, xpubs) = kind_and_xpubs(wallet)?;
+    let mut keys = xpubs
+        .iter()
+        .zip(wallet.keystores())
+        .map(|(xpub, keystore)| format!("{}/{}/*", xpub, keystore.chain(chain == 1)))
+        .collect::<Vec<_>>();
+    // the xpubs have the same length, the keys sort by them
+    let multi = match wallet.key_order() {
+        KeyOrder::Sorted => {
+            keys.sort();
+            "sortedmulti"
+        }
+        KeyOrder::AsStored => "multi",
+    };
+    // the slip-0132 prefixes of singlesig and multisig keys are mixed up by some exports
+    let (wrappers, core) = match (wallet.wallet_type(), kind.as_str()) {
+        (WalletType::Standard, "wsh") => ("wpkh", keys.join(",")),
+        (WalletType::Standard, "sh(wsh") => ("sh(wpkh", keys.join(",")),
+        (WalletType::Standard, kind) => (kind, keys.join(",")),
+        (WalletType::Imported, _) => return Err(Electrum2DescriptorError::ImportedKeys),
+        (WalletType::Multisig(_), "tr") => return Err(Electrum2DescriptorError::TaprootMultisig),
+        (WalletType::Multisig(multisig), kind) => {
+            let wrappers = match kind {
+                "pkh" => "sh",
+                "wpkh" => "wsh",
+                "sh(wpkh" => "sh(wsh",
+                kind => kind,
+            };
+            (
+                wrappers,
+                format!("{}({},{})", multi, multisig.threshold(), keys.join(",")),
+            )
+        }
+    };
+    Ok(wrappers
+        .rsplit('(')
+        .fold(core, |inner, wrapper| format!("{}({})", wrapper, inner)))
+}
+
+/// The kind and the bip32 xpubs of the keystores
+fn kind_and_xpubs(
+    wallet: &ElectrumWalletFile,
+) -> Result<(String, Vec<String>), Electrum2DescriptorError> {
+    let xpubs = wallet
+        .keystores()
+        .iter()
+        .map(|ks| ks.xpub())
+        .collect::<Result<Vec<_>, _>>()?;
+    let kind = xpubs[0].1.as_kind_str().to_string();
+    Ok((
+        kind,
+        xpubs
+            .iter()
+            .map(|(xpub, _kind, _network)| xpub.to_string())
+            .collect(),
+    ))
+}
+
+impl fmt::Display for WalletDifference {
+    /// Diff style, `-` for the first wallet and `+` for the second one
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let wallet_type = |wallet_type: &WalletType| match wallet_type {
+            WalletType::Standard => "standard".to_string(),
+            WalletType::Multisig(multisig) => {
+                format!("{}of{}", multisig.threshold(), multisig.cosigners())
+            }
+            WalletType::Imported => "imported".to_string(),
+        };
+        match self {
+            WalletDifference::WalletType(a, b) => write!(
+                f,
+                "- wallet type {}\n+ wallet type {}",
+                wallet_type(a),
+                wallet_type(b)
+            ),
+            WalletDifference::ScriptKind(a, b) => {
+                write!(f, "- script kind {}\n+ script kind {}", a, b)
+            }
+            WalletDifference::MissingCosigner(xpub) => write!(f, "- cosigner {}", xpub),
+            WalletDifference::ExtraCosigner(xpub) => write!(f, "+ cosigner {}", xpub),
+            WalletDifference::FirstAddress(a, b) => {
+                write!(f, "- first address {}\n+ first address {}", a, b)
+            }
+        }
+    }
+}
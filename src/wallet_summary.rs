@@ -0,0 +1,254 @@
+use crate::electrum_wallet_file::WalletType;
+use crate::{Electrum2DescriptorError, ElectrumWalletFile, ScriptKind};
+use bitcoin::{bip32::Fingerprint, Network};
+use serde::Serialize;
+use std::fmt;
+
+/// A digest of an electrum wallet file. It holds no secret material, only whether such material is present.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct WalletSummary {
+    pub wallet_type: WalletType,
+    pub script_kind: ScriptKind,
+    pub network: Network,
+    /// No keystore holds private keys, neither in software nor on a hardware wallet
+    pub watch_only: bool,
+    /// Some keystore secrets are encrypted with the wallet password
+    pub encrypted: bool,
+    pub has_seed: bool,
+    pub cosigners: Vec<CosignerSummary>,
+    /// The slots of the cosigners that weren't added yet, see [`ElectrumWalletFile::unfinished_slots`]
+    pub unfinished_slots: Vec<String>,
+    pub receiving_addresses: usize,
+    pub change_addresses: usize,
+}
+
+/// The digest of one keystore of a wallet
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct CosignerSummary {
+    /// Position in the keystores of the wallet, starting from 0
+    pub index: usize,
+    pub fingerprint: Option<Fingerprint>,
+    pub derivation: Option<String>,
+    pub label: Option<String>,
+    /// The keystore can sign without external help
+    pub can_sign: bool,
+}
+
+/// The format of a [`ElectrumWalletFile::to_report`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    #[default]
+    Text,
+    Markdown,
+}
+
+/// Options for [`ElectrumWalletFile::to_report`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReportOptions {
+    pub format: ReportFormat,
+    /// The name the wallet is known by, e.g. its file name
+    pub name: Option<String>,
+}
+
+impl ElectrumWalletFile {
+    /// A page for the participants of a signing ceremony to check the wallet against: its policy, script kind,
+    /// network, first receiving address, checksummed public descriptor and [`ElectrumWalletFile::wallet_id`], and the
+    /// label, origin and xpub of each cosigner. It holds no private material, even for hot wallets.
+    pub fn to_report(&self, options: &ReportOptions) -> Result<String, Electrum2DescriptorError> {
+        let summary = self.summary()?;
+        let policy = match summary.wallet_type {
+            WalletType::Standard => "single signature".to_string(),
+            WalletType::Multisig(multisig) => {
+                format!("{} of {}", multisig.threshold(), multisig.cosigners())
+            }
+            WalletType::Imported => "imported keys".to_string(),
+        };
+        let fields = [
+            (
+                "Wallet",
+                options.name.clone().unwrap_or_else(|| "-".to_string()),
+            ),
+            ("Policy", policy),
+            ("Script kind", summary.script_kind.to_string()),
+            ("Network", summary.network.to_string()),
+            (
+                "First receiving address",
+                self.address(false, 0)?.to_string(),
+            ),
+            ("Wallet id", self.wallet_id()?),
+        ];
+        let descriptor = public_descriptor(self)?;
+        let unknown = || "unknown".to_string();
+        let cosigners = summary
+            .cosigners
+            .iter()
+            .zip(self.keystores())
+            .map(|(cosigner, keystore)| {
+                [
+                    self.keystore_name(cosigner.index),
+                    cosigner.label.clone().unwrap_or_else(|| "-".to_string()),
+                    cosigner
+                        .fingerprint
+                        .map(|fp| fp.to_string())
+                        .unwrap_or_else(unknown),
+                    cosigner.derivation.clone().unwrap_or_else(unknown),
+                    keystore.xpub.clone(),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let mut lines = Vec::new();
+        match options.format {
+            ReportFormat::Text => {
+                for (field, value) in &fields {
+                    lines.push(format!("{}: {}", field, value));
+                }
+                lines.push(format!("Descriptor: {}", descriptor));
+                for [slot, label, fingerprint, derivation, xpub] in &cosigners {
+                    lines.push(String::new());
+                    lines.push(format!("Cosigner {}", slot));
+                    lines.push(format!("  label: {}", label));
+                    lines.push(format!("  fingerprint: {}", fingerprint));
+                    lines.push(format!("  derivation: {}", derivation));
+                    lines.push(format!("  xpub: {}", xpub));
+                }
+            }
+            ReportFormat::Markdown => {
+                lines.push("# Wallet report".to_string());
+                lines.push(String::new());
+                lines.push("| | |".to_string());
+                lines.push("|---|---|".to_string());
+                for (field, value) in &fields {
+                    lines.push(format!("| {} | {} |", field, markdown_cell(value)));
+                }
+                lines.push(String::new());
+                lines.push("## Cosigners".to_string());
+                lines.push(String::new());
+                lines.push("| Slot | Label | Fingerprint | Derivation | Xpub |".to_string());
+                lines.push("|---|---|---|---|---|".to_string());
+                for cells in &cosigners {
+                    let cells = cells
+                        .iter()
+                        .map(|cell| markdown_cell(cell))
+                        .collect::<Vec<_>>();
+                    lines.push(format!("| {} |", cells.join(" | ")));
+                }
+                lines.push(String::new());
+                lines.push("## Descriptor".to_string());
+                lines.push(String::new());
+                lines.push("```".to_string());
+                lines.push(descriptor);
+                lines.push("```".to_string());
+            }
+        }
+        lines.push(String::new());
+        Ok(lines.join("\n"))
+    }
+
+    /// Create a summary of the wallet. A multisig wallet whose setup wasn't finished is summarized with the cosigners
+    /// added so far.
+    pub fn summary(&self) -> Result<WalletSummary, Electrum2DescriptorError> {
+        self.check_parsed()?;
+        let keystores = self.keystores();
+        let (_xpub, script_kind, _network) = keystores[0].xpub()?;
+        let network = self.network()?;
+        let cosigners = keystores
+            .iter()
+            .zip(self.fingerprints())
+            .map(|(keystore, (index, fingerprint))| CosignerSummary {
+                index,
+                fingerprint,
+                derivation: keystore.derivation.as_deref().map(String::from),
+                label: keystore.label.as_deref().map(String::from),
+                can_sign: keystore.can_sign(),
+            })
+            .collect();
+
+        Ok(WalletSummary {
+            wallet_type: self.wallet_type().clone(),
+            script_kind,
+            network,
+            watch_only: keystores
+                .iter()
+                .all(|ks| ks.xprv.is_none() && !ks.is_hardware()),
+            encrypted: keystores.iter().any(|ks| ks.has_encrypted_secrets()),
+            has_seed: keystores.iter().any(|ks| ks.seed.is_some()),
+            cosigners,
+            unfinished_slots: self.unfinished_slots().to_vec(),
+            receiving_addresses: self.addresses().receiving.len(),
+            change_addresses: self.addresses().change.len(),
+        })
+    }
+}
+
+impl fmt::Display for WalletSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let wallet_type = match self.wallet_type {
+            WalletType::Standard => "standard".to_string(),
+            WalletType::Multisig(multisig) => {
+                format!("{}of{}", multisig.threshold(), multisig.cosigners())
+            }
+            WalletType::Imported => "imported".to_string(),
+        };
+        writeln!(f, "wallet type: {}", wallet_type)?;
+        writeln!(f, "script kind: {}", self.script_kind)?;
+        writeln!(f, "network: {}", self.network)?;
+        writeln!(f, "watch only: {}", self.watch_only)?;
+        writeln!(f, "encrypted: {}", self.encrypted)?;
+        writeln!(f, "seed: {}", self.has_seed)?;
+        writeln!(
+            f,
+            "addresses: {} receiving, {} change",
+            self.receiving_addresses, self.change_addresses
+        )?;
+        for cosigner in &self.cosigners {
+            let name = match self.wallet_type {
+                WalletType::Standard | WalletType::Imported => "keystore".to_string(),
+                WalletType::Multisig(_) => format!("x{}/", cosigner.index + 1),
+            };
+            let unknown = "unknown".to_string();
+            write!(
+                f,
+                "{}: fingerprint {}, derivation {}",
+                name,
+                cosigner
+                    .fingerprint
+                    .map(|fp| fp.to_string())
+                    .unwrap_or_else(|| unknown.clone()),
+                cosigner.derivation.as_ref().unwrap_or(&unknown),
+            )?;
+            if let Some(label) = &cosigner.label {
+                write!(f, ", label {:?}", label)?;
+            }
+            writeln!(f, ", can sign: {}", cosigner.can_sign)?;
+        }
+        for slot in &self.unfinished_slots {
+            writeln!(f, "{}: no xpub, wallet setup was not finished", slot)?;
+        }
+        Ok(())
+    }
+}
+
+/// The checksummed external descriptor of the wallet with the xpubs of all keystores
+fn public_descriptor(wallet: &ElectrumWalletFile) -> Result<String, Electrum2DescriptorError> {
+    let keystores = wallet
+        .keystores()
+        .iter()
+        .map(|keystore| {
+            let mut keystore = keystore.clone();
+            keystore.xprv = None;
+            keystore.seed = None;
+            keystore.passphrase = None;
+            keystore
+        })
+        .collect::<Vec<_>>();
+    let threshold = wallet.wallet_type().threshold();
+    let mut public = ElectrumWalletFile::new(&keystores, threshold)?;
+    public.set_key_order(wallet.key_order())?;
+    Ok(public.to_descriptors()?.with_checksums()?.external)
+}
+
+/// A value in a markdown table, whose cells are delimited by `|`
+fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|")
+}
@@ -0,0 +1,91 @@
+#![cfg(feature = "arbitrary")]
+use bdk::bitcoin::hashes::{sha256, Hash};
+use libelectrum2descriptors::{
+    arbitrary::{Arbitrary, ArbitraryInput},
+    electrum_wallet_file::WalletType,
+    ElectrumWalletFile, ScriptKind,
+};
+use std::str::FromStr;
+
+/// The generated wallets of deterministic inputs of varying length, including none
+fn wallets(count: u32) -> impl Iterator<Item = ElectrumWalletFile> {
+    (0..count).map(|i| {
+        let data = (0..i % 5)
+            .flat_map(|j| {
+                sha256::Hash::hash(&[i.to_le_bytes(), j.to_le_bytes()].concat()).to_byte_array()
+            })
+            .collect::<Vec<_>>();
+        ElectrumWalletFile::arbitrary(&mut ArbitraryInput::new(&data))
+    })
+}
+
+#[test]
+fn serialize_round_trip() {
+    for wallet in wallets(1000) {
+        let parsed = ElectrumWalletFile::from_str(&wallet.to_string()).unwrap();
+        assert_eq!(parsed, wallet);
+        assert_eq!(parsed.to_string(), wallet.to_string());
+    }
+}
+
+#[test]
+fn descriptor_round_trip() {
+    for wallet in wallets(1000) {
+        let descriptors = wallet.to_descriptors().unwrap();
+        let (_xpub, kind, _network) = wallet.keystores()[0].xpub().unwrap();
+        // from_descriptor doesn't read taproot descriptors
+        if kind == ScriptKind::P2tr {
+            assert!(descriptors.external.starts_with("tr("));
+            continue;
+        }
+        let restored = ElectrumWalletFile::from_descriptor(&descriptors.external).unwrap();
+        assert_eq!(restored.wallet_type(), wallet.wallet_type());
+        assert_eq!(restored.keystores().len(), wallet.keystores().len());
+        assert!(
+            restored
+                .keystores()
+                .iter()
+                .zip(wallet.keystores())
+                .all(|(restored, keystore)| restored.same_key(keystore)),
+            "{}",
+            descriptors.external
+        );
+        assert_eq!(restored.to_descriptors().unwrap(), descriptors);
+    }
+}
+
+#[test]
+fn invariants() {
+    let mut kinds = Vec::new();
+    for wallet in wallets(500) {
+        let keys = wallet
+            .keystores()
+            .iter()
+            .map(|keystore| keystore.xpub().unwrap())
+            .collect::<Vec<_>>();
+        match *wallet.wallet_type() {
+            WalletType::Standard => assert_eq!(keys.len(), 1),
+            WalletType::Multisig(multisig) => {
+                assert!(1 <= multisig.threshold() && multisig.threshold() <= multisig.cosigners());
+                assert_eq!(keys.len(), multisig.cosigners() as usize);
+            }
+            WalletType::Imported => panic!("generated an imported wallet"),
+        }
+        assert!(keys
+            .iter()
+            .all(|(_, kind, network)| (kind, network) == (&keys[0].1, &keys[0].2)));
+        kinds.push(keys[0].1);
+    }
+    for kind in ScriptKind::ALL {
+        assert!(kinds.contains(&kind), "{} was never generated", kind);
+    }
+}
+
+#[test]
+fn deterministic() {
+    let data = [7; 100];
+    assert_eq!(
+        ElectrumWalletFile::arbitrary(&mut ArbitraryInput::new(&data)).to_string(),
+        ElectrumWalletFile::arbitrary(&mut ArbitraryInput::new(&data)).to_string()
+    );
+}
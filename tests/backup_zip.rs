@@ -0,0 +1,263 @@
+#![cfg(feature = "zip")]
+use libelectrum2descriptors::{Electrum2DescriptorError, ElectrumWalletFile};
+use std::path::Path;
+use tempfile::tempdir;
+
+#[test]
+fn from_backup_zip() {
+    let wallets =
+        ElectrumWalletFile::from_backup_zip(Path::new("tests/backups/android.zip"), None).unwrap();
+    let names = wallets
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["default_segwit", "encrypted"]);
+
+    let expected =
+        ElectrumWalletFile::from_file(Path::new("tests/wallets/default_segwit")).unwrap();
+    assert_eq!(wallets[0].1.as_ref().unwrap(), &expected);
+    assert!(matches!(
+        wallets[1].1,
+        Err(Electrum2DescriptorError::EncryptedWallet)
+    ));
+}
+
+#[test]
+fn from_backup_zip_invalid() {
+    let archive = std::fs::read("tests/backups/android.zip").unwrap();
+    let tempdir = tempdir().unwrap();
+    let path = tempdir.path().join("backup.zip");
+
+    std::fs::write(&path, &archive[..archive.len() / 2]).unwrap();
+    assert!(matches!(
+        ElectrumWalletFile::from_backup_zip(&path, None),
+        Err(Electrum2DescriptorError::InvalidZip(_))
+    ));
+
+    // a flipped byte in the compressed wallet fails its crc or its decompression
+    let mut corrupt = archive.clone();
+    let wallet = archive
+        .windows(22)
+        .position(|window| window == b"wallets/default_segwit")
+        .unwrap();
+    corrupt[wallet + 22 + 100] ^= 0x55;
+    std::fs::write(&path, &corrupt).unwrap();
+    let wallets = ElectrumWalletFile::from_backup_zip(&path, None).unwrap();
+    assert!(matches!(
+        wallets[0].1,
+        Err(Electrum2DescriptorError::InvalidZip(_))
+    ));
+
+    std::fs::write(&path, b"PK\x05\x06\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0").unwrap();
+    assert!(matches!(
+        ElectrumWalletFile::from_backup_zip(&path, None),
+        Err(Electrum2DescriptorError::InvalidZip(e)) if e == "no wallet file in the archive"
+    ));
+}
+
+#[test]
+fn from_backup_zip_password() {
+    // a wallet whose keystore secrets are encrypted and one also encrypted as a whole, both with "password"
+    let path = Path::new("tests/backups/android_password.zip");
+    let expected =
+        ElectrumWalletFile::from_file(Path::new("tests/wallets/default_segwit")).unwrap();
+    let wallets = ElectrumWalletFile::from_backup_zip(path, Some("password")).unwrap();
+    let names = wallets
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["encrypted_secrets", "encrypted_password"]);
+    for (name, wallet) in &wallets {
+        assert_eq!(wallet.as_ref().unwrap(), &expected, "{}", name);
+    }
+
+    for (_, wallet) in ElectrumWalletFile::from_backup_zip(path, Some("wrong")).unwrap() {
+        assert!(matches!(
+            wallet,
+            Err(Electrum2DescriptorError::InvalidPassword)
+        ));
+    }
+
+    let wallets = ElectrumWalletFile::from_backup_zip(path, None).unwrap();
+    let secrets_encrypted = wallets[0].1.as_ref().unwrap();
+    assert!(!secrets_encrypted.can_sign());
+    assert!(secrets_encrypted.keystores()[0].has_encrypted_secrets());
+    assert_eq!(
+        secrets_encrypted.keystores()[0].electrum_xpub(),
+        expected.keystores()[0].electrum_xpub()
+    );
+    assert!(matches!(
+        wallets[1].1,
+        Err(Electrum2DescriptorError::EncryptedWallet)
+    ));
+}
+
+/// A zip archive of one entry, with the sizes and offsets it declares
+struct Archive<'a> {
+    method: u16,
+    data: &'a [u8],
+    compressed_size: u32,
+    size: u32,
+    local_header: u32,
+    central_directory: Option<u32>,
+}
+
+impl<'a> Archive<'a> {
+    fn new(method: u16, data: &'a [u8], size: u32) -> Self {
+        Archive {
+            method,
+            data,
+            compressed_size: data.len() as u32,
+            size,
+            local_header: 0,
+            central_directory: None,
+        }
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        let name = b"wallets/default_segwit";
+        let header = |signature: &[u8], fields: &[u8]| {
+            let mut header = signature.to_vec();
+            header.extend_from_slice(fields);
+            header.extend_from_slice(&self.method.to_le_bytes());
+            header.extend_from_slice(&[0; 8]);
+            header.extend_from_slice(&self.compressed_size.to_le_bytes());
+            header.extend_from_slice(&self.size.to_le_bytes());
+            header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            header
+        };
+        let mut archive = header(b"PK\x03\x04", &[20, 0, 0, 0]);
+        archive.extend_from_slice(&[0; 2]);
+        archive.extend_from_slice(name);
+        archive.extend_from_slice(self.data);
+        let central_directory = archive.len() as u32;
+        archive.extend(header(b"PK\x01\x02", &[20, 0, 20, 0, 0, 0]));
+        archive.extend_from_slice(&[0; 12]);
+        archive.extend_from_slice(&self.local_header.to_le_bytes());
+        archive.extend_from_slice(name);
+        let central_directory_size = archive.len() as u32 - central_directory;
+        archive.extend_from_slice(b"PK\x05\x06\0\0\0\0\x01\0\x01\0");
+        archive.extend_from_slice(&central_directory_size.to_le_bytes());
+        archive.extend_from_slice(
+            &self
+                .central_directory
+                .unwrap_or(central_directory)
+                .to_le_bytes(),
+        );
+        archive.extend_from_slice(&[0; 2]);
+        archive
+    }
+}
+
+fn read_archive(archive: &[u8]) -> Result<Vec<String>, Electrum2DescriptorError> {
+    let tempdir = tempdir().unwrap();
+    let path = tempdir.path().join("backup.zip");
+    std::fs::write(&path, archive).unwrap();
+    let wallets = ElectrumWalletFile::from_backup_zip(&path, None)?;
+    Ok(wallets
+        .into_iter()
+        .map(|(_, wallet)| match wallet {
+            Ok(_) => "ok".to_string(),
+            Err(e) => e.to_string(),
+        })
+        .collect())
+}
+
+#[test]
+fn from_backup_zip_malicious() {
+    let wallet = std::fs::read("tests/wallets/default_segwit").unwrap();
+    let size = wallet.len() as u32;
+    assert_eq!(
+        read_archive(&Archive::new(0, &wallet, size).bytes()).unwrap(),
+        vec!["Invalid zip backup: wallets/default_segwit is corrupt"]
+    );
+
+    let invalid = |archive: Archive, reason: &str| match read_archive(&archive.bytes()) {
+        Err(Electrum2DescriptorError::InvalidZip(e)) => assert_eq!(e, reason),
+        result => panic!("{:?}", result),
+    };
+    let mut archive = Archive::new(0, &wallet, size);
+    archive.compressed_size = u32::MAX - 1;
+    invalid(archive, "truncated entry");
+    let mut archive = Archive::new(0, &wallet, size);
+    archive.local_header = u32::MAX - 1;
+    invalid(archive, "invalid local file header");
+    let mut archive = Archive::new(0, &wallet, size);
+    archive.central_directory = Some(size);
+    invalid(archive, "invalid central directory");
+    assert_eq!(
+        read_archive(&Archive::new(12, &wallet, size).bytes()).unwrap(),
+        vec![
+            "Invalid zip backup: wallets/default_segwit uses the unsupported compression method 12"
+        ]
+    );
+
+    // a back reference before the start of the data, which python's zlib rejects as "invalid distance too far back"
+    assert_eq!(
+        read_archive(&Archive::new(8, &[0x03, 0x02, 0x00], 3).bytes()).unwrap(),
+        vec!["Invalid zip backup: invalid deflate data, distance too far back"]
+    );
+}
+
+#[test]
+fn from_backup_zip_bomb() {
+    // 10 MiB of zeros deflated to 10 KB, declaring smaller sizes stops inflating early
+    let archive = std::fs::read("tests/backups/zeros.zip").unwrap();
+    let central_directory = archive.len() - 22 - 59;
+    assert_eq!(
+        &archive[central_directory..central_directory + 4],
+        b"PK\x01\x02"
+    );
+    let declare = |size: u32| {
+        let mut archive = archive.clone();
+        archive[22..26].copy_from_slice(&size.to_le_bytes());
+        archive[central_directory + 24..central_directory + 28]
+            .copy_from_slice(&size.to_le_bytes());
+        archive
+    };
+    assert_eq!(
+        read_archive(&declare(10_000)).unwrap(),
+        vec!["Invalid zip backup: invalid deflate data, more data than the entry size"]
+    );
+    assert_eq!(
+        read_archive(&declare(1 << 30)).unwrap(),
+        vec!["Invalid zip backup: wallets/zeros is too large for a wallet file"]
+    );
+    // the zeros aren't json, so there is no wallet
+    assert!(matches!(
+        read_archive(&archive),
+        Err(Electrum2DescriptorError::InvalidZip(e)) if e == "no wallet file in the archive"
+    ));
+}
+
+#[test]
+fn from_backup_zip_corpus() {
+    // truncated and corrupted archives fail or parse, but never panic
+    for backup in [
+        "tests/backups/android.zip",
+        "tests/backups/android_password.zip",
+    ] {
+        let archive = std::fs::read(backup).unwrap();
+        // every byte of the headers, and a sample of the data
+        let headers = archive
+            .windows(2)
+            .enumerate()
+            .filter(|(_, window)| window == b"PK")
+            .map(|(position, _)| position)
+            .collect::<Vec<_>>();
+        let positions = (0..archive.len()).filter(|position| {
+            position % 97 == 0
+                || headers
+                    .iter()
+                    .any(|header| (*header..header + 46).contains(position))
+        });
+        for length in positions.clone() {
+            let _ = read_archive(&archive[..length]);
+        }
+        for position in positions {
+            let mut corrupt = archive.clone();
+            corrupt[position] ^= 0xa5;
+            let _ = read_archive(&corrupt);
+        }
+    }
+}
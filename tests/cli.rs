@@ -0,0 +1,1408 @@
+#![cfg(feature = "wallet_file")]
+use libelectrum2descriptors::{checksum, ElectrumWalletFile};
+use rstest::rstest;
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+    str::FromStr,
+};
+use tempfile::tempdir;
+
+fn electrum2descriptors(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_electrum2descriptors"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn convert_xpub() {
+    let output = electrum2descriptors(&["vpub5VXaSncXqxLbdmvrC4Y8z9CszPwuEscADoetWhfrxDFzPUbL5nbVtanYDkrVEutkv9n5A5aCcvRC9swbjDKgHjCZ2tAeae8VsBuPbS8KpXv"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Descriptors { external: \"wpkh(tpubD9ZjaMn3rbP1cAVwJy6UcEjFfTLT7W6DbfHdS3Wn48meExtVfKmiH9meWCrSmE9qXLYbGcHC5LxLcdfLZTzwme23qAJoRzRhzbd68dHeyjp/0/*)\", change: \"wpkh(tpubD9ZjaMn3rbP1cAVwJy6UcEjFfTLT7W6DbfHdS3Wn48meExtVfKmiH9meWCrSmE9qXLYbGcHC5LxLcdfLZTzwme23qAJoRzRhzbd68dHeyjp/1/*)\" }\n"
+    );
+}
+
+#[test]
+fn descriptor_to_wallet_with_label() {
+    let tempdir = tempdir().unwrap();
+    let wallet_file = tempdir.path().join("wallet");
+    let output = electrum2descriptors(&[
+        "wpkh(tpubD9ZjaMn3rbP1cAVwJy6UcEjFfTLT7W6DbfHdS3Wn48meExtVfKmiH9meWCrSmE9qXLYbGcHC5LxLcdfLZTzwme23qAJoRzRhzbd68dHeyjp/0/*)",
+        "--label",
+        "Ledger Nano S #2",
+        "--output",
+        wallet_file.to_str().unwrap(),
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let wallet = ElectrumWalletFile::from_file(&wallet_file).unwrap();
+    assert_eq!(
+        wallet.keystores()[0].label.as_deref(),
+        Some("Ledger Nano S #2")
+    );
+}
+
+#[test]
+fn multisig_with_cosigner_labels() {
+    let tempdir = tempdir().unwrap();
+    let wallet_file = tempdir.path().join("wallet");
+    let output = electrum2descriptors(&[
+        "multisig",
+        "--threshold",
+        "2",
+        "--cosigner",
+        "Vpub5mUs4UNPA6T3VAmcTWTJ2nCV2oAEFQqBNQQDH62NQNpdAMSyL2Nd3vZXF6uQfNeiCst7asUapZWM9AKmsYCK1BjUrEVhiVm9M4qnbHvDRDe,label=bb2 (27d81095)",
+        "--cosigner",
+        "Vpub5mTgvNLEMssnVd4fezZgnDhLefVaYCb94GsjHfgrhXksbrHRbMa2AwjWX9eczB1dG19oZmEnVNCeVLWoygXQrkL8nuyAgWDxnePWUQ5fE3N,label=T-Rex",
+        "--output",
+        wallet_file.to_str().unwrap(),
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let wallet = ElectrumWalletFile::from_file(Path::new(&wallet_file)).unwrap();
+    let labels = wallet
+        .keystores()
+        .iter()
+        .map(|ks| ks.label.as_deref())
+        .collect::<Vec<_>>();
+    assert_eq!(labels, vec![Some("bb2 (27d81095)"), Some("T-Rex")]);
+}
+
+#[test]
+fn refuse_overwrite_without_force() {
+    let tempdir = tempdir().unwrap();
+    let wallet_file = tempdir.path().join("wallet");
+    std::fs::write(&wallet_file, "previous").unwrap();
+    let descriptor = "wpkh(tpubD9ZjaMn3rbP1cAVwJy6UcEjFfTLT7W6DbfHdS3Wn48meExtVfKmiH9meWCrSmE9qXLYbGcHC5LxLcdfLZTzwme23qAJoRzRhzbd68dHeyjp/0/*)";
+    let output = electrum2descriptors(&[descriptor, "--output", wallet_file.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("use --force to replace it"));
+    assert_eq!(std::fs::read_to_string(&wallet_file).unwrap(), "previous");
+
+    let output = electrum2descriptors(&[
+        descriptor,
+        "--output",
+        wallet_file.to_str().unwrap(),
+        "--force",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    ElectrumWalletFile::from_file(&wallet_file).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(tempdir.path().join("wallet.bak")).unwrap(),
+        "previous"
+    );
+}
+
+#[test]
+fn descriptor_with_addresses() {
+    let tempdir = tempdir().unwrap();
+    let wallet_file = tempdir.path().join("wallet");
+    let descriptor = "wpkh(tpubD9ZjaMn3rbP1cAVwJy6UcEjFfTLT7W6DbfHdS3Wn48meExtVfKmiH9meWCrSmE9qXLYbGcHC5LxLcdfLZTzwme23qAJoRzRhzbd68dHeyjp/0/*)";
+    let output = electrum2descriptors(&[
+        descriptor,
+        "--addresses",
+        "3",
+        "--output",
+        wallet_file.to_str().unwrap(),
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let wallet = ElectrumWalletFile::from_file(&wallet_file).unwrap();
+    assert_eq!(wallet.addresses().receiving.len(), 3);
+    assert_eq!(wallet.addresses().change.len(), 3);
+    assert_eq!(
+        wallet.addresses().receiving[0],
+        wallet.address(false, 0).unwrap().to_string()
+    );
+
+    let output = electrum2descriptors(&[descriptor, "--addresses", "many"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn convert_directory() {
+    let tempdir = tempdir().unwrap();
+    for wallet in ["default_segwit", "imported_addr"] {
+        std::fs::copy(
+            Path::new("tests/wallets").join(wallet),
+            tempdir.path().join(wallet),
+        )
+        .unwrap();
+    }
+    let output = electrum2descriptors(&[tempdir.path().to_str().unwrap()]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("default_segwit: Descriptors { external: \"wpkh("));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("imported_addr: "), "{}", stderr);
+}
+
+#[cfg(feature = "zip")]
+#[test]
+fn convert_zip() {
+    let output = electrum2descriptors(&["tests/backups/android.zip"]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("default_segwit: Descriptors { external: \"wpkh("));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("encrypted: The wallet file is encrypted"),
+        "{}",
+        stderr
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_electrum2descriptors"))
+        .args([
+            "tests/backups/android_password.zip",
+            "--password-env",
+            "E2D_TEST_PASSWORD",
+        ])
+        .env("E2D_TEST_PASSWORD", "password")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert!(lines[0].starts_with("encrypted_secrets: Descriptors { external: \"wpkh("));
+    assert!(lines[1].starts_with("encrypted_password: Descriptors { external: \"wpkh("));
+}
+
+#[cfg(feature = "qr")]
+#[test]
+fn convert_qr() {
+    let xpub = "vpub5VXaSncXqxLbdmvrC4Y8z9CszPwuEscADoetWhfrxDFzPUbL5nbVtanYDkrVEutkv9n5A5aCcvRC9swbjDKgHjCZ2tAeae8VsBuPbS8KpXv";
+    let output = electrum2descriptors(&[xpub, "--qr"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let external = "wpkh(tpubD9ZjaMn3rbP1cAVwJy6UcEjFfTLT7W6DbfHdS3Wn48meExtVfKmiH9meWCrSmE9qXLYbGcHC5LxLcdfLZTzwme23qAJoRzRhzbd68dHeyjp/0/*)";
+    let code = libelectrum2descriptors::qr::QrCode::new(external.as_bytes()).unwrap();
+    assert_eq!(stdout, code.to_terminal());
+
+    let tempdir = tempdir().unwrap();
+    let qr_file = tempdir.path().join("descriptor.png");
+    let args = [
+        xpub,
+        "--qr-file",
+        qr_file.to_str().unwrap(),
+        "--change-only",
+    ];
+    let output = electrum2descriptors(&args);
+    assert!(output.status.success(), "{:?}", output);
+    // the descriptor is printed as well
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .ends_with("/1/*)\n"));
+    assert!(std::fs::read(&qr_file).unwrap().starts_with(b"\x89PNG"));
+    let output = electrum2descriptors(&args);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn check() {
+    let output = electrum2descriptors(&["convert", "tests/wallets/default_segwit", "--check"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "PASS tests/wallets/default_segwit\n"
+    );
+
+    let output = electrum2descriptors(&["tests/wallets/stringified_numbers", "--check"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("PASS tests/wallets/stringified_numbers\n  warning: "));
+
+    let output = electrum2descriptors(&["tests/wallets/imported_addr", "--check"]);
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("FAIL tests/wallets/imported_addr: "));
+
+    let tempdir = tempdir().unwrap();
+    for wallet in ["default_segwit", "stringified_numbers"] {
+        std::fs::copy(
+            Path::new("tests/wallets").join(wallet),
+            tempdir.path().join(wallet),
+        )
+        .unwrap();
+    }
+    let output = electrum2descriptors(&[tempdir.path().to_str().unwrap(), "--check"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.matches("PASS ").count(), 2);
+}
+
+#[test]
+fn truncated_wallet_in_dir() {
+    let tempdir = tempdir().unwrap();
+    let wallet = std::fs::read("tests/wallets/default_segwit").unwrap();
+    std::fs::write(tempdir.path().join("default_segwit"), &wallet).unwrap();
+    std::fs::write(tempdir.path().join("truncated"), &wallet[..2000]).unwrap();
+    std::fs::write(tempdir.path().join("notes.txt"), "not a wallet").unwrap();
+    std::fs::write(tempdir.path().join("binary"), [b'{', 0xff, 0xfe, 0]).unwrap();
+    let dir = tempdir.path().to_str().unwrap();
+
+    let output = electrum2descriptors(&["convert", dir, "--check"]);
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.matches("PASS ").count(), 1, "{}", stdout);
+    assert_eq!(stdout.matches("FAIL ").count(), 1, "{}", stdout);
+    assert!(stdout.contains("truncated: "), "{}", stdout);
+
+    let output = electrum2descriptors(&["inspect", "--all", dir]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3, "{}", stdout);
+    assert!(lines[1].starts_with("default_segwit "));
+    assert!(lines[2].starts_with("truncated ") && lines[2].contains(" error "));
+}
+
+#[test]
+fn check_repair() {
+    let tempdir = tempdir().unwrap();
+    let wallet_file = tempdir.path().join("wallet");
+    let mut json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string("tests/wallets/default_segwit").unwrap())
+            .unwrap();
+    json["wallet_type"] = "1of2".into();
+    std::fs::write(&wallet_file, json.to_string()).unwrap();
+    let output = electrum2descriptors(&[wallet_file.to_str().unwrap(), "--check", "--repair"]);
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("REPAIR "), "{}", stdout);
+    assert!(stdout.ends_with(": changed the wallet_type from \"1of2\" to \"standard\"\n"));
+    let repaired = ElectrumWalletFile::from_file(&tempdir.path().join("wallet.repaired")).unwrap();
+    assert_eq!(
+        repaired.to_descriptors().unwrap(),
+        ElectrumWalletFile::from_file(Path::new("tests/wallets/default_segwit"))
+            .unwrap()
+            .to_descriptors()
+            .unwrap()
+    );
+
+    // nothing to fix, nothing written
+    let output = electrum2descriptors(&["tests/wallets/default_segwit", "--check", "--repair"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(!Path::new("tests/wallets/default_segwit.repaired").exists());
+}
+
+const HW_SEGWIT_A: &str = "tpubDEcw4ooTbmw62zBKdkYepoP3z4WWugdeRzPHHAbk8XVsPfBE9AAZMNghiqwtdFgtabaeppBTPmezUkRkQZidLcSJp3XTASbMakHcYauWehZ/0/*";
+const HW_SEGWIT_B: &str = "tpubDEbkvhmJoZMq3SUNqEf3aEsubvqsCUPc7rroHkGERgS7qA1gQVMxUPrgzth6x43odirLohwf4aMHpvcnWi3jCB2xkizv8T4B2KqLRZVLC6K/0/*";
+
+#[test]
+fn verify_matching() {
+    let descriptor = checksum::with_checksum(&format!(
+        "wsh(sortedmulti(2,{},{}))",
+        HW_SEGWIT_B, HW_SEGWIT_A
+    ))
+    .unwrap();
+    let output = electrum2descriptors(&[
+        "verify",
+        "tests/wallets/multisig_hw_segwit",
+        "--descriptor",
+        &descriptor,
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let (bare, _checksum) = descriptor.split_once('#').unwrap();
+    let output = electrum2descriptors(&[
+        "verify",
+        "tests/wallets/multisig_hw_segwit",
+        "--descriptor",
+        &format!("{}#checksum", bare),
+    ]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("invalid checksum checksum"), "{}", stderr);
+
+    let tempdir = tempdir().unwrap();
+    let descriptor_file = tempdir.path().join("descriptor");
+    std::fs::write(&descriptor_file, format!("{}\n", descriptor)).unwrap();
+    let output = electrum2descriptors(&[
+        "verify",
+        "tests/wallets/multisig_hw_segwit",
+        "--descriptor-file",
+        descriptor_file.to_str().unwrap(),
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_electrum2descriptors"))
+        .args([
+            "verify",
+            "tests/wallets/multisig_hw_segwit",
+            "--descriptor-file",
+            "-",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(descriptor.as_bytes())
+        .unwrap();
+    assert!(child.wait_with_output().unwrap().status.success());
+}
+
+#[test]
+fn verify_threshold_mismatch() {
+    let descriptor = format!("wsh(sortedmulti(1,{},{}))", HW_SEGWIT_A, HW_SEGWIT_B);
+    let output = electrum2descriptors(&[
+        "verify",
+        "tests/wallets/multisig_hw_segwit",
+        "--descriptor",
+        &descriptor,
+    ]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("- wallet type 2of2\n+ wallet type 1of2\n"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn verify_cosigner_mismatch() {
+    let other = "tpubDEWD9NBeWP59xXmdqSNt4VYdtTGwbpyP8WS962BuqpQeMZmX9Pur14dhXdZT5a7wR1pK6dPtZ9fP5WR493hPzemnBvkfLLYxnUjAKj1JCQV/0/*";
+    let descriptor = format!("wsh(sortedmulti(2,{},{}))", HW_SEGWIT_A, other);
+    let output = electrum2descriptors(&[
+        "verify",
+        "tests/wallets/multisig_hw_segwit",
+        "--descriptor",
+        &descriptor,
+    ]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains(&format!(
+            "- cosigner {}\n",
+            HW_SEGWIT_B.trim_end_matches("/0/*")
+        )),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains(&format!("+ cosigner {}\n", other.trim_end_matches("/0/*"))));
+}
+
+#[test]
+fn conversion_warnings() {
+    let output = electrum2descriptors(&["tests/wallets/hot_history"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("warning: the seed is not part of the descriptors\n"));
+    assert!(stderr.contains("warning: labels with 3 entries is not converted\n"));
+
+    let output = electrum2descriptors(&["tests/wallets/hot_history", "--quiet"]);
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn recover_scan() {
+    let wallet = ElectrumWalletFile::from_file(Path::new("tests/wallets/default_segwit")).unwrap();
+    let keystore = &wallet.keystores()[0];
+    let output = electrum2descriptors(&["recover", "scan", "tests/wallets/default_segwit"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(&keystore.xpub));
+    assert!(stdout.contains("xprv testnet p2wpkh <redacted>"));
+    assert!(!stdout.contains(keystore.xprv.as_deref().unwrap()));
+
+    let output = electrum2descriptors(&[
+        "recover",
+        "scan",
+        "tests/wallets/default_segwit",
+        "--show-secrets",
+    ]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(keystore.xprv.as_deref().unwrap()));
+}
+
+#[test]
+fn salvage() {
+    let output = electrum2descriptors(&["salvage", "tests/wallets/garbled_xpub"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("addr(muVsjryQ7q1YvQ9Ji7MqrxNAnSSMccDYeS)\n"));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("warning: left out a keystore"));
+    assert!(stderr.contains("the addresses are p2pkh addresses on testnet"));
+
+    let output = electrum2descriptors(&["salvage", "tests/wallets/default_legacy_watch"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .starts_with("Descriptors {"));
+}
+
+#[test]
+fn unfinished_setup() {
+    let output = electrum2descriptors(&["tests/wallets/multisig_unfinished"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("UnfinishedSetup([\"x2/\", \"x3/\"])"));
+
+    let output = electrum2descriptors(&["salvage", "tests/wallets/multisig_unfinished"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("wallet type: 2of3"), "{}", stderr);
+    assert!(stderr.contains("x1/: fingerprint f6083804"), "{}", stderr);
+    assert!(stderr.contains("x3/: no xpub, wallet setup was not finished"));
+
+    let output = electrum2descriptors(&["inspect", "tests/wallets/multisig_unfinished"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("x2/: no xpub, wallet setup was not finished"));
+}
+
+#[test]
+fn edit() {
+    let tempdir = tempdir().unwrap();
+    let wallet_file = tempdir.path().join("wallet");
+    std::fs::copy("tests/wallets/multisig_segwit", &wallet_file).unwrap();
+    let original = std::fs::read_to_string(&wallet_file).unwrap();
+    let other: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string("tests/wallets/multisig_distinct_paths").unwrap(),
+    )
+    .unwrap();
+    let cosigner_file = tempdir.path().join("cosigner.json");
+    std::fs::write(&cosigner_file, other["x1/"].to_string()).unwrap();
+    let edit = |extra: &[&str]| {
+        let mut args = vec![
+            "edit",
+            wallet_file.to_str().unwrap(),
+            "--add-cosigner-file",
+            cosigner_file.to_str().unwrap(),
+            "--set-threshold",
+            "3",
+        ];
+        args.extend(extra);
+        electrum2descriptors(&args)
+    };
+
+    let output = edit(&["--dry-run"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("wallet type: 3of3"), "{}", stdout);
+    assert!(stdout.contains("wsh(sortedmulti(3,"), "{}", stdout);
+    assert_eq!(std::fs::read_to_string(&wallet_file).unwrap(), original);
+
+    let output = edit(&[]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        std::fs::read_to_string(tempdir.path().join("wallet.bak")).unwrap(),
+        original
+    );
+    let output = electrum2descriptors(&["inspect", wallet_file.to_str().unwrap()]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("wallet type: 3of3"), "{}", stdout);
+    assert!(
+        stdout.contains("x3/: fingerprint 27d81095, derivation m/48'/1'/0'/2'"),
+        "{}",
+        stdout
+    );
+
+    // the threshold is lowered before the cosigner is removed
+    std::fs::copy("tests/wallets/multisig_distinct_paths", &wallet_file).unwrap();
+    let output = electrum2descriptors(&[
+        "edit",
+        wallet_file.to_str().unwrap(),
+        "--remove-cosigner",
+        "3",
+        "--set-threshold",
+        "1",
+        "--no-backup",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let wallet = ElectrumWalletFile::from_file(&wallet_file).unwrap();
+    assert_eq!(wallet.keystores().len(), 2);
+    assert_eq!(
+        wallet
+            .to_descriptors()
+            .unwrap()
+            .external
+            .matches("sortedmulti(1,")
+            .count(),
+        1
+    );
+
+    let output = electrum2descriptors(&[
+        "edit",
+        wallet_file.to_str().unwrap(),
+        "--remove-cosigner",
+        "0",
+    ]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn inspect_restore_command() {
+    let output = electrum2descriptors(&[
+        "inspect",
+        "tests/wallets/default_legacy_watch",
+        "--restore-command",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "electrum --testnet restore tpubD6NzVbkrYhZ4Y1ozBYSfoyVp2iGgP6iZAy18p2opXjVv8jTNccGuRs3jMCMe4ncfwy2RUJsoZLSXsGiFhN47xFbJgtRvCuV3RP3UnxpsrZt\n"
+    );
+    let output = electrum2descriptors(&[
+        "inspect",
+        "tests/wallets/default_legacy_watch",
+        "--restore-command",
+        "--private",
+    ]);
+    assert!(!output.status.success());
+}
+
+#[rstest]
+#[case::text("text", "multisig_distinct_paths.txt")]
+#[case::markdown("md", "multisig_distinct_paths.md")]
+fn inspect_report(#[case] format: &str, #[case] snapshot: &str) {
+    let output = electrum2descriptors(&[
+        "inspect",
+        "tests/wallets/multisig_distinct_paths",
+        "--report",
+        format,
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        std::fs::read_to_string(Path::new("tests/reports").join(snapshot)).unwrap()
+    );
+}
+
+#[test]
+fn inspect_report_unknown_format() {
+    let output = electrum2descriptors(&[
+        "inspect",
+        "tests/wallets/multisig_distinct_paths",
+        "--report",
+        "html",
+    ]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn convert_private_key_export() {
+    let output = electrum2descriptors(&["tests/private_key_exports/export.csv"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "pkh(cN9spWsvaxA8taS7DFMxnk1yJD2gaF2PX1npuTpy3vuZFJdwavaw)#ksfhzpe6\n\
+        sh(wpkh(cNj3zTdrLAMQtUhdFPPVJtRY7a3TdUF38ShW5MrJkVh1CVaeuEGU))#x670x650\n\
+        wpkh(cPJEAQPn5NYgtNy9HXR1q2q6vw4EghTgjscBFFseT4UT9gbpoDY9)#93rn7t0z\n"
+    );
+
+    let tempdir = tempdir().unwrap();
+    let wallet_file = tempdir.path().join("imported");
+    let output = electrum2descriptors(&[
+        "tests/private_key_exports/export.json",
+        "--output",
+        wallet_file.to_str().unwrap(),
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let wallet = ElectrumWalletFile::from_file(&wallet_file).unwrap();
+    assert_eq!(
+        wallet,
+        ElectrumWalletFile::from_private_key_export(
+            &std::fs::read_to_string("tests/private_key_exports/export.json").unwrap()
+        )
+        .unwrap()
+    );
+    // an existing file is kept
+    let output = electrum2descriptors(&[
+        "tests/private_key_exports/export.json",
+        "--output",
+        wallet_file.to_str().unwrap(),
+    ]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn assume_network() {
+    let tpub = "tpubD6NzVbkrYhZ4Y1ozBYSfoyVp2iGgP6iZAy18p2opXjVv8jTNccGuRs3jMCMe4ncfwy2RUJsoZLSXsGiFhN47xFbJgtRvCuV3RP3UnxpsrZt";
+    let output = electrum2descriptors(&[tpub, "--assume-network", "mainnet"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .starts_with("Descriptors { external: \"pkh(xpub"));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("WARNING: the prefix of tpub"));
+
+    let output = electrum2descriptors(&[
+        "tests/wallets/default_legacy_watch",
+        "--assume-network",
+        "mainnet",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("pkh(xpub"));
+
+    let output = electrum2descriptors(&[tpub, "--assume-network", "moon"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn multisig_cosigner_origins() {
+    let wallet =
+        ElectrumWalletFile::from_file(Path::new("tests/wallets/multisig_distinct_paths")).unwrap();
+    let cosigners = wallet
+        .keystores()
+        .iter()
+        .map(|ks| {
+            format!(
+                "{},fingerprint={},derivation={}",
+                ks.xpub,
+                ks.root_fingerprint.as_deref().unwrap(),
+                ks.derivation.as_deref().unwrap()
+            )
+        })
+        .collect::<Vec<_>>();
+    let mut args = vec!["multisig", "--threshold", "2"];
+    for cosigner in &cosigners {
+        args.extend(["--cosigner", cosigner.as_str()]);
+    }
+    let output = electrum2descriptors(&args);
+    assert!(output.status.success(), "{:?}", output);
+    let created = ElectrumWalletFile::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert_eq!(created, wallet);
+
+    let xpub = &wallet.keystores()[0].xpub;
+    let output = electrum2descriptors(&[
+        "multisig",
+        "--threshold",
+        "1",
+        "--cosigner",
+        &format!("{},fingerprint=27d81095", xpub),
+        "--cosigner",
+        xpub,
+    ]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn expected_fingerprint() {
+    let descriptor = "pkh(tpubD6NzVbkrYhZ4Y1ozBYSfoyVp2iGgP6iZAy18p2opXjVv8jTNccGuRs3jMCMe4ncfwy2RUJsoZLSXsGiFhN47xFbJgtRvCuV3RP3UnxpsrZt/0/*)";
+    let output = electrum2descriptors(&[descriptor, "--expected-fingerprint", "230b70d2"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = electrum2descriptors(&[descriptor, "--expected-fingerprint", "aabbccdd"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("230b70d2"));
+
+    let output = electrum2descriptors(&[
+        "tests/wallets/multisig_hw_segwit",
+        "--expected-fingerprint",
+        "27d81095",
+        "--expected-fingerprint",
+        "aabbccdd",
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("6bfac2d6"));
+}
+
+#[test]
+fn descriptor_file() {
+    let segwit = "wpkh(tpubD9ZjaMn3rbP1cAVwJy6UcEjFfTLT7W6DbfHdS3Wn48meExtVfKmiH9meWCrSmE9qXLYbGcHC5LxLcdfLZTzwme23qAJoRzRhzbd68dHeyjp/0/*)";
+    let legacy = "pkh(tpubD6NzVbkrYhZ4Y1ozBYSfoyVp2iGgP6iZAy18p2opXjVv8jTNccGuRs3jMCMe4ncfwy2RUJsoZLSXsGiFhN47xFbJgtRvCuV3RP3UnxpsrZt/0/*)";
+    let multisig = ElectrumWalletFile::from_file(Path::new("tests/wallets/multisig_hw_segwit"))
+        .unwrap()
+        .to_descriptors()
+        .unwrap();
+    let tempdir = tempdir().unwrap();
+    let descriptor_file = tempdir.path().join("descriptors.txt");
+    std::fs::write(
+        &descriptor_file,
+        format!(
+            "# our wallets\nsavings: {}\n{}\n\n  vault: {}\n{}\n",
+            segwit,
+            segwit.replace("/0/*", "/1/*"),
+            multisig.external,
+            libelectrum2descriptors::checksum::with_checksum(legacy).unwrap(),
+        ),
+    )
+    .unwrap();
+    let output_dir = tempdir.path().join("restored");
+    std::fs::create_dir(&output_dir).unwrap();
+    let args = [
+        "--descriptor-file",
+        descriptor_file.to_str().unwrap(),
+        "--output-dir",
+        output_dir.to_str().unwrap(),
+    ];
+    let output = electrum2descriptors(&args);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8(output.stdout).unwrap().lines().count(), 3);
+    for (name, external) in [
+        ("savings", segwit),
+        ("vault", multisig.external.as_str()),
+        ("230b70d2", legacy),
+    ] {
+        let wallet = ElectrumWalletFile::from_file(&output_dir.join(name)).unwrap();
+        assert_eq!(wallet.to_descriptors().unwrap().external, external);
+    }
+
+    // the wallet files are never replaced
+    let output = electrum2descriptors(&args);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("AlreadyExists"));
+
+    std::fs::write(&descriptor_file, format!("{}\n{}\n", legacy, legacy)).unwrap();
+    let output = electrum2descriptors(&args);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("DuplicateDescriptor(2, 1)"));
+
+    std::fs::write(&descriptor_file, format!("a: {}\na: {}\n", legacy, segwit)).unwrap();
+    let output = electrum2descriptors(&args);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("more than one wallet"));
+    assert!(!output_dir.join("a").exists());
+}
+
+#[rstest]
+#[case::external(&["--external-only"], "wpkh(tpubD9ZjaMn3rbP1cAVwJy6UcEjFfTLT7W6DbfHdS3Wn48meExtVfKmiH9meWCrSmE9qXLYbGcHC5LxLcdfLZTzwme23qAJoRzRhzbd68dHeyjp/0/*)\n")]
+#[case::change(&["--change-only"], "wpkh(tpubD9ZjaMn3rbP1cAVwJy6UcEjFfTLT7W6DbfHdS3Wn48meExtVfKmiH9meWCrSmE9qXLYbGcHC5LxLcdfLZTzwme23qAJoRzRhzbd68dHeyjp/1/*)\n")]
+#[case::change_checksum(&["--change-only", "--with-checksum"], "wpkh(tpubD9ZjaMn3rbP1cAVwJy6UcEjFfTLT7W6DbfHdS3Wn48meExtVfKmiH9meWCrSmE9qXLYbGcHC5LxLcdfLZTzwme23qAJoRzRhzbd68dHeyjp/1/*)#c9vr58a6\n")]
+#[case::external_json(&["--external-only", "--json"], "{\"external\":\"wpkh(tpubD9ZjaMn3rbP1cAVwJy6UcEjFfTLT7W6DbfHdS3Wn48meExtVfKmiH9meWCrSmE9qXLYbGcHC5LxLcdfLZTzwme23qAJoRzRhzbd68dHeyjp/0/*)\"}\n")]
+#[case::json(&["--json"], "{\"external\":\"wpkh(tpubD9ZjaMn3rbP1cAVwJy6UcEjFfTLT7W6DbfHdS3Wn48meExtVfKmiH9meWCrSmE9qXLYbGcHC5LxLcdfLZTzwme23qAJoRzRhzbd68dHeyjp/0/*)\",\"change\":\"wpkh(tpubD9ZjaMn3rbP1cAVwJy6UcEjFfTLT7W6DbfHdS3Wn48meExtVfKmiH9meWCrSmE9qXLYbGcHC5LxLcdfLZTzwme23qAJoRzRhzbd68dHeyjp/1/*)\"}\n")]
+fn single_descriptor(#[case] flags: &[&str], #[case] expected: &str) {
+    let mut args = vec!["vpub5VXaSncXqxLbdmvrC4Y8z9CszPwuEscADoetWhfrxDFzPUbL5nbVtanYDkrVEutkv9n5A5aCcvRC9swbjDKgHjCZ2tAeae8VsBuPbS8KpXv"];
+    args.extend(flags);
+    let output = electrum2descriptors(&args);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn single_descriptor_exclusive() {
+    let output = electrum2descriptors(&[
+        "tests/wallets/default_segwit",
+        "--external-only",
+        "--change-only",
+    ]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+}
+
+#[rstest]
+#[case::plain("plain", "receiving 0 tb1qt63km8fkfuwnl6hzfktu7g2nh8tc6jhr6vequq\nreceiving 1 tb1qnlyqzt2nrq6rejy6ldwuu6qn72exxvvfvrdtph\n")]
+#[case::csv("csv", "keychain,index,address,source\nreceiving,0,tb1qt63km8fkfuwnl6hzfktu7g2nh8tc6jhr6vequq,stored\nreceiving,1,tb1qnlyqzt2nrq6rejy6ldwuu6qn72exxvvfvrdtph,stored\n")]
+#[case::json("json", "[{\"address\":\"tb1qt63km8fkfuwnl6hzfktu7g2nh8tc6jhr6vequq\",\"index\":0,\"keychain\":\"receiving\",\"source\":\"stored\"},{\"address\":\"tb1qnlyqzt2nrq6rejy6ldwuu6qn72exxvvfvrdtph\",\"index\":1,\"keychain\":\"receiving\",\"source\":\"stored\"}]\n")]
+fn addresses(#[case] format: &str, #[case] expected: &str) {
+    let output = electrum2descriptors(&[
+        "addresses",
+        "tests/wallets/default_segwit",
+        "--keychain",
+        "receiving",
+        "--limit",
+        "2",
+        "--format",
+        format,
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+}
+
+#[test]
+fn addresses_multisig() {
+    let output = electrum2descriptors(&[
+        "addresses",
+        "tests/wallets/multisig_segwit",
+        "--format",
+        "csv",
+        "--verify",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 31);
+    assert_eq!(lines[0], "keychain,index,address,source,matches,derived");
+    assert_eq!(lines[21], "change,0,tb1q7s26yhh8hmq2t6r8wa7ckpjhcugkhjshnw5hsg0m9u82kmmy5zrqdm2qc2,stored,true,tb1q7s26yhh8hmq2t6r8wa7ckpjhcugkhjshnw5hsg0m9u82kmmy5zrqdm2qc2");
+}
+
+#[test]
+fn addresses_derived() {
+    let tempdir = tempdir().unwrap();
+    let wallet_file = tempdir.path().join("wallet");
+    let mut json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string("tests/wallets/default_segwit").unwrap())
+            .unwrap();
+    json["addresses"]["receiving"] = serde_json::json!([]);
+    std::fs::write(&wallet_file, json.to_string()).unwrap();
+    let output = electrum2descriptors(&[
+        "addresses",
+        wallet_file.to_str().unwrap(),
+        "--keychain",
+        "receiving",
+        "--format",
+        "json",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let entries = json.as_array().unwrap();
+    assert_eq!(entries.len(), 20);
+    assert_eq!(entries[0]["source"], "derived");
+    assert_eq!(
+        entries[0]["address"],
+        "tb1qt63km8fkfuwnl6hzfktu7g2nh8tc6jhr6vequq"
+    );
+}
+
+#[test]
+fn addresses_verify_mismatch() {
+    let output = electrum2descriptors(&[
+        "addresses",
+        "tests/wallets/addresses_inserted",
+        "--keychain",
+        "receiving",
+        "--verify",
+    ]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("receiving 3 tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx MISMATCH, derived")
+    );
+    assert!(stdout.contains("receiving 0 tb1qt63km8fkfuwnl6hzfktu7g2nh8tc6jhr6vequq\n"));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("note: 21 receiving addresses from the wallet file"));
+}
+
+#[test]
+fn inspect_all() {
+    let tempdir = tempdir().unwrap();
+    for wallet in [
+        "multisig_hw_segwit",
+        "default_segwit",
+        "encrypted",
+        "default_legacy_watch",
+    ] {
+        std::fs::copy(
+            Path::new("tests/wallets").join(wallet),
+            tempdir.path().join(wallet),
+        )
+        .unwrap();
+    }
+    std::fs::write(
+        tempdir.path().join("broken"),
+        "{\"wallet_type\": \"standard\"}",
+    )
+    .unwrap();
+    std::fs::write(tempdir.path().join("notes.txt"), "not a wallet").unwrap();
+    let dir = tempdir.path().to_str().unwrap();
+
+    let output = electrum2descriptors(&["inspect", "--all", dir]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 6);
+    assert!(lines[0].starts_with("file "));
+    assert!(lines[1].starts_with("broken "));
+    assert!(lines[1].contains(" error "));
+    let default_segwit = lines[3].split_whitespace().collect::<Vec<_>>();
+    assert_eq!(
+        default_segwit,
+        vec![
+            "default_segwit",
+            "standard",
+            "p2wpkh",
+            "testnet",
+            "false",
+            "b88448fb",
+            "no"
+        ]
+    );
+    assert!(lines[4].starts_with("encrypted ") && lines[4].ends_with(" file"));
+    assert!(lines[5].contains(" 27d81095,6bfac2d6 "));
+
+    let output = electrum2descriptors(&["inspect", "--all", dir, "--sort", "type", "--json"]);
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let files = json
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|row| row["file"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        files,
+        vec![
+            "broken",
+            "multisig_hw_segwit",
+            "default_legacy_watch",
+            "default_segwit",
+            "encrypted"
+        ]
+    );
+    assert_eq!(
+        json[1]["fingerprints"],
+        serde_json::json!(["27d81095", "6bfac2d6"])
+    );
+
+    for (filter, expected) in [
+        ("watch-only", vec!["default_legacy_watch"]),
+        ("multisig", vec!["multisig_hw_segwit"]),
+        ("encrypted", vec!["encrypted"]),
+    ] {
+        let output = electrum2descriptors(&["inspect", "--all", dir, "--filter", filter, "--json"]);
+        assert!(output.status.success(), "{:?}", output);
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        let files = json
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|row| row["file"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(files, expected, "{}", filter);
+    }
+}
+
+#[test]
+fn password_sources() {
+    let tempdir = tempdir().unwrap();
+    let password_file = tempdir.path().join("password");
+    std::fs::write(&password_file, "password\n").unwrap();
+    let password_file = password_file.to_str().unwrap();
+    let wrong_file = tempdir.path().join("wrong");
+    std::fs::write(&wrong_file, "wrong").unwrap();
+    let wrong_file = wrong_file.to_str().unwrap();
+    let expected = electrum2descriptors(&["tests/wallets/default_segwit"]).stdout;
+
+    // the whole file is encrypted, and the secrets of the keystore too
+    let output = Command::new(env!("CARGO_BIN_EXE_electrum2descriptors"))
+        .args([
+            "tests/wallets/encrypted_password",
+            "--password-env",
+            "E2D_TEST_PASSWORD",
+        ])
+        .env("E2D_TEST_PASSWORD", "password")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(output.stdout, expected);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_electrum2descriptors"))
+        .args([
+            "tests/wallets/encrypted_password",
+            "--password-env",
+            "E2D_TEST_PASSWORD",
+        ])
+        .env_remove("E2D_TEST_PASSWORD")
+        .output()
+        .unwrap();
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("the environment variable E2D_TEST_PASSWORD is not set"));
+
+    // only the secrets of the keystore are encrypted
+    let output = electrum2descriptors(&[
+        "tests/wallets/encrypted_secrets",
+        "--password-file",
+        password_file,
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(output.stdout, expected);
+
+    let output = electrum2descriptors(&[
+        "tests/wallets/encrypted_password",
+        "--password-file",
+        wrong_file,
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("InvalidPassword"));
+
+    // one line of stdin, as file descriptor 0
+    let mut child = Command::new(env!("CARGO_BIN_EXE_electrum2descriptors"))
+        .args(["tests/wallets/encrypted_password", "--password-fd", "0"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"password\nnot the password\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(output.stdout, expected);
+
+    let output = electrum2descriptors(&[
+        "tests/wallets/encrypted_password",
+        "--password-file",
+        password_file,
+        "--password-env",
+        "E2D_TEST_PASSWORD",
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("only one of --password-env, --password-fd, --password-file can be given"));
+
+    let output = electrum2descriptors(&["tests/wallets/encrypted_password"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("EncryptedWallet"));
+
+    // without encryption the password isn't needed
+    let output = electrum2descriptors(&[
+        "tests/wallets/default_segwit",
+        "--password-file",
+        password_file,
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(output.stdout, expected);
+}
+
+/// Split a csv table into rows of fields, unquoting them as RFC 4180 says
+fn parse_csv(table: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = table.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => row.push(std::mem::take(&mut field)),
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    rows
+}
+
+#[rstest]
+#[case::csv("csv")]
+#[case::tsv("tsv")]
+fn convert_dir_table(#[case] format: &str) {
+    let tempdir = tempdir().unwrap();
+    for wallet in ["multisig_hw_segwit", "default_segwit", "encrypted"] {
+        std::fs::copy(
+            Path::new("tests/wallets").join(wallet),
+            tempdir.path().join(wallet),
+        )
+        .unwrap();
+    }
+    let dir = tempdir.path().to_str().unwrap();
+
+    let output = electrum2descriptors(&[dir, "--format", format]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let rows = if format == "csv" {
+        parse_csv(&stdout)
+    } else {
+        stdout
+            .lines()
+            .map(|line| line.split('\t').map(String::from).collect())
+            .collect()
+    };
+    assert_eq!(rows.len(), 4);
+    assert!(rows.iter().all(|row| row.len() == 9), "{:?}", rows);
+    assert_eq!(
+        rows[0],
+        [
+            "file",
+            "wallet_type",
+            "script_kind",
+            "network",
+            "watch_only",
+            "external",
+            "change",
+            "fingerprints",
+            "error"
+        ]
+    );
+
+    let wallet =
+        ElectrumWalletFile::from_file(Path::new("tests/wallets/multisig_hw_segwit")).unwrap();
+    let descriptors = wallet.to_descriptors().unwrap();
+    assert_eq!(rows[3][0], "multisig_hw_segwit");
+    assert_eq!(rows[3][1..5], ["2of2", "p2wsh", "testnet", "false"]);
+    assert_eq!(rows[3][5], descriptors.external);
+    assert_eq!(rows[3][6], descriptors.change);
+    assert_eq!(rows[3][7].split(';').count(), 2);
+    assert_eq!(rows[3][8], "");
+
+    assert_eq!(rows[1][0], "default_segwit");
+    assert_eq!(rows[1][7], "b88448fb");
+    assert_eq!(rows[2][0], "encrypted");
+    assert!(rows[2][1..8].iter().all(String::is_empty));
+    assert!(!rows[2][8].is_empty());
+
+    let output = electrum2descriptors(&[dir, "--format", "xlsx"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn convert_with_origin() {
+    let output = electrum2descriptors(&[
+        "tests/wallets/multisig_hw_segwit",
+        "--origin",
+        "fp=11111111,path=m/48h/1h/0h/2h",
+        "--origin",
+        "path=m/48h/1h/1h/2h,fp=22222222",
+        "--external-only",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("wsh(sortedmulti(2,[11111111/48'/1'/0'/2']tpub"));
+    assert!(stdout.contains(",[22222222/48'/1'/1'/2']tpub"));
+
+    let output = electrum2descriptors(&[
+        "vpub5VXaSncXqxLbdmvrC4Y8z9CszPwuEscADoetWhfrxDFzPUbL5nbVtanYDkrVEutkv9n5A5aCcvRC9swbjDKgHjCZ2tAeae8VsBuPbS8KpXv",
+        "--origin",
+        "fp=aabbccdd,path=m/0h",
+        "--external-only",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .starts_with("wpkh([aabbccdd/0']tpub"));
+
+    for origin in [
+        "fp=aabbccdd",
+        "fp=xyz,path=m/0h",
+        "fp=aabbccdd,path=m/0h,x=1",
+    ] {
+        let output = electrum2descriptors(&["tests/wallets/default_segwit", "--origin", origin]);
+        assert!(!output.status.success(), "{}", origin);
+    }
+    let output = electrum2descriptors(&[
+        "tests/wallets/multisig_hw_segwit",
+        "--origin",
+        "fp=11111111,path=m/48h/1h/0h/2h",
+    ]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn addresses_network() {
+    let output = electrum2descriptors(&[
+        "addresses",
+        "tests/wallets/default_segwit",
+        "--network",
+        "regtest",
+        "--limit",
+        "2",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 4);
+    assert!(stdout.lines().all(|line| line
+        .split_whitespace()
+        .nth(2)
+        .unwrap()
+        .starts_with("bcrt1")));
+
+    let output = electrum2descriptors(&[
+        "addresses",
+        "tests/wallets/default_segwit",
+        "--network",
+        "mainnet",
+    ]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn watch() {
+    let tempdir = tempdir().unwrap();
+    let wallet_file = tempdir.path().join("wallet");
+    std::fs::copy("tests/wallets/default_segwit", &wallet_file).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_electrum2descriptors"))
+        .args([
+            "convert",
+            wallet_file.to_str().unwrap(),
+            "--watch",
+            "--watch-interval",
+            "50",
+            "--json",
+        ])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut lines = std::io::BufRead::lines(std::io::BufReader::new(child.stdout.take().unwrap()));
+    let mut next_descriptors = || {
+        let line = lines.next().unwrap().unwrap();
+        serde_json::from_str::<serde_json::Value>(&line).unwrap()["external"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    };
+    assert!(next_descriptors().starts_with("wpkh([b88448fb/0']tprv"));
+
+    // rewriting the same wallet doesn't print it again, replacing it by renaming like electrum does prints the new one
+    let wallet = ElectrumWalletFile::from_file(&wallet_file).unwrap();
+    std::fs::write(&wallet_file, wallet.to_string()).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    let renamed = tempdir.path().join("wallet.tmp");
+    std::fs::copy("tests/wallets/multisig_segwit", &renamed).unwrap();
+    std::fs::rename(&renamed, &wallet_file).unwrap();
+    assert!(next_descriptors().starts_with("wsh(sortedmulti("));
+
+    // ctrl-c stops it cleanly
+    #[cfg(unix)]
+    {
+        let interrupted = Command::new("kill")
+            .args(["-INT", &child.id().to_string()])
+            .status()
+            .unwrap();
+        assert!(interrupted.success());
+        assert!(child.wait().unwrap().success());
+    }
+    #[cfg(not(unix))]
+    {
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+}
+
+#[test]
+fn loss_report() {
+    let output =
+        electrum2descriptors(&["tests/wallets/feature_rich", "--loss-report", "descriptors"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("transaction history: 3 transactions\n"));
+    assert!(stdout.contains("labels: 3 entries\n"));
+
+    let tempdir = tempdir().unwrap();
+    let fresh = tempdir.path().join("fresh");
+    let args = [
+        "tests/wallets/feature_rich",
+        "--fresh-wallet",
+        fresh.to_str().unwrap(),
+    ];
+    let output = electrum2descriptors(&args);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("lightning channels: 1 channels\n"));
+    assert!(!fresh.exists());
+
+    let output = electrum2descriptors(&[&args[..], &["--acknowledge-loss", "--quiet"]].concat());
+    assert!(output.status.success(), "{:?}", output);
+    assert!(output.stderr.is_empty());
+    let wallet = ElectrumWalletFile::from_file(&fresh).unwrap();
+    assert!(wallet.extra().is_empty());
+
+    // the convert subcommand writes the same fresh wallet file
+    let converted = tempdir.path().join("converted");
+    let output = electrum2descriptors(&[
+        "convert",
+        "tests/wallets/feature_rich",
+        "--fresh-wallet",
+        converted.to_str().unwrap(),
+        "--acknowledge-loss",
+        "--quiet",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(ElectrumWalletFile::from_file(&converted).unwrap(), wallet);
+}
+
+#[rstest]
+#[case::xpub("vpub5VXaSncXqxLbdmvrC4Y8z9CszPwuEscADoetWhfrxDFzPUbL5nbVtanYDkrVEutkv9n5A5aCcvRC9swbjDKgHjCZ2tAeae8VsBuPbS8KpXv")]
+#[case::descriptor("wpkh(tpubD9ZjaMn3rbP1cAVwJy6UcEjFfTLT7W6DbfHdS3Wn48meExtVfKmiH9meWCrSmE9qXLYbGcHC5LxLcdfLZTzwme23qAJoRzRhzbd68dHeyjp/0/*)")]
+#[case::directory("tests/wallets")]
+fn fresh_wallet_not_a_wallet_file(#[case] input: &str) {
+    let tempdir = tempdir().unwrap();
+    let fresh = tempdir.path().join("fresh");
+    let output =
+        electrum2descriptors(&["convert", input, "--fresh-wallet", fresh.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("need an electrum wallet file"));
+    assert!(!fresh.exists());
+}
+
+#[test]
+fn scrub_history() {
+    let tempdir = tempdir().unwrap();
+    let scrubbed = tempdir.path().join("scrubbed");
+    let output = electrum2descriptors(&[
+        "tests/wallets/hot_history",
+        "--scrub-history",
+        scrubbed.to_str().unwrap(),
+        "--drop-labels",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let wallet = ElectrumWalletFile::from_file(&scrubbed).unwrap();
+    assert_eq!(wallet.raw_field("transactions"), None);
+    assert_eq!(wallet.raw_field("labels"), None);
+
+    let wallet_file = tempdir.path().join("wallet");
+    std::fs::copy("tests/wallets/hot_history", &wallet_file).unwrap();
+    let output = electrum2descriptors(&[
+        "edit",
+        wallet_file.to_str().unwrap(),
+        "--scrub-history",
+        "--no-backup",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let wallet = ElectrumWalletFile::from_file(&wallet_file).unwrap();
+    assert_eq!(wallet.raw_field("verified_tx3"), None);
+    assert!(wallet.raw_field("labels").is_some());
+}
+
+#[test]
+fn convert_with_kind() {
+    let tpub = "tpubD6NzVbkrYhZ4Y1ozBYSfoyVp2iGgP6iZAy18p2opXjVv8jTNccGuRs3jMCMe4ncfwy2RUJsoZLSXsGiFhN47xFbJgtRvCuV3RP3UnxpsrZt";
+    let output = electrum2descriptors(&[tpub, "--kind", "p2wpkh"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains(&format!("external: \"wpkh({}/0/*)\"", tpub)));
+
+    let output = electrum2descriptors(&["tests/wallets/default_legacy_watch", "--kind", "p2tr"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains(&format!("external: \"tr({}/0/*)\"", tpub)));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("the key of keystore is used for p2tr as requested"));
+
+    let vpub = "vpub5VXaSncXqxLbdmvrC4Y8z9CszPwuEscADoetWhfrxDFzPUbL5nbVtanYDkrVEutkv9n5A5aCcvRC9swbjDKgHjCZ2tAeae8VsBuPbS8KpXv";
+    let output = electrum2descriptors(&[vpub, "--kind", "p2pkh"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("ScriptKindConflict"));
+    let output = electrum2descriptors(&[tpub, "--kind", "p2wsh"]);
+    assert!(!output.status.success());
+}
@@ -0,0 +1,134 @@
+#![cfg(feature = "log")]
+use libelectrum2descriptors::ElectrumWalletFile;
+use std::{path::Path, sync::Mutex};
+
+/// Collects the records of the crate, with the spans they are in
+struct Recorder(Mutex<Vec<(String, String)>>);
+
+impl log::Log for Recorder {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.target() == "electrum2descriptors"
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            let span = record
+                .key_values()
+                .get(log::kv::Key::from("span"))
+                .map(|span| span.to_string())
+                .unwrap_or_default();
+            self.0
+                .lock()
+                .unwrap()
+                .push((span, record.args().to_string()));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static RECORDER: Recorder = Recorder(Mutex::new(Vec::new()));
+
+#[test]
+fn conversion_records() {
+    log::set_logger(&RECORDER).unwrap();
+    log::set_max_level(log::LevelFilter::Debug);
+
+    let path = Path::new("tests/wallets/multisig_wrapped");
+    let wallet = ElectrumWalletFile::from_file(path).unwrap();
+    let descriptors = wallet.to_descriptors().unwrap();
+    ElectrumWalletFile::from_descriptor(&descriptors.external).unwrap();
+    let tempdir = tempfile::tempdir().unwrap();
+    wallet.to_file(&tempdir.path().join("wallet")).unwrap();
+    let records = RECORDER.0.lock().unwrap().clone();
+
+    let expected = [
+        ("", "reading the wallet file tests/wallets/multisig_wrapped"),
+        ("parse", "parse watch-only: false"),
+        (
+            "parse/validate",
+            "validating the 2 keystores of a Multisig(2, 2) wallet",
+        ),
+        (
+            "parse",
+            "keystore x1/: p2wsh-p2sh on testnet, xprv: true, seed: true",
+        ),
+        (
+            "parse",
+            "keystore x2/: p2wsh-p2sh on testnet, xprv: false, seed: false",
+        ),
+        ("convert", "convert Multisig(2, 2) wallet"),
+        // the miniscript feature builds the descriptors with miniscript instead
+        (
+            "convert",
+            if cfg!(feature = "miniscript") {
+                "descriptors of the Multisig(2, 2) wallet built with miniscript"
+            } else {
+                "descriptors of the Multisig(2, 2) wallet built as strings"
+            },
+        ),
+        ("parse_descriptor", "parse_descriptor strict: false"),
+        ("parse_descriptor", "parsing a multisig descriptor"),
+        ("parse_descriptor/validate", "validate 2 keystores"),
+    ];
+    for (span, expected) in expected {
+        assert!(
+            records.iter().any(|r| r.0 == span && r.1 == expected),
+            "{} in {} not in {:#?}",
+            expected,
+            span,
+            records
+        );
+    }
+    assert!(records.iter().any(|(span, r)| span == "parse"
+        && r.starts_with("parsed a Multisig(2, 2) wallet with 2 keystores, fields [")));
+    // the spans end in the order they started
+    let ends = records
+        .iter()
+        .filter(|(_, r)| r.contains(" done in "))
+        .map(|(span, _)| span.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ends,
+        [
+            "parse/validate",
+            "parse",
+            "convert",
+            "parse_descriptor/validate",
+            "parse_descriptor",
+            "write",
+        ]
+    );
+    assert!(records
+        .iter()
+        .any(|(span, r)| span == "write" && r.starts_with("write ") && r.ends_with("/wallet")));
+
+    // nothing secret nor identifying is logged
+    let file: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    let mut secrets = vec![descriptors.external.clone(), descriptors.change.clone()];
+    for slot in ["x1/", "x2/"] {
+        for field in ["xprv", "xpub", "seed"] {
+            if let Some(value) = file[slot][field].as_str() {
+                secrets.push(value.to_string());
+            }
+        }
+    }
+    for keystore in wallet.keystores() {
+        secrets.push(keystore.xpub().unwrap().0.to_string());
+        if let Some(xprv) = keystore.xprv().unwrap() {
+            secrets.push(xprv.to_string());
+        }
+    }
+    secrets.extend(wallet.addresses().receiving.iter().cloned());
+    for (_span, record) in &records {
+        for secret in &secrets {
+            assert!(
+                !record.contains(secret.as_str()),
+                "{} leaks {}",
+                record,
+                secret
+            );
+        }
+    }
+}
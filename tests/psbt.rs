@@ -0,0 +1,139 @@
+#![cfg(feature = "psbt")]
+use bdk::bitcoin::{
+    absolute::LockTime,
+    bip32::{ChildNumber, DerivationPath, Fingerprint},
+    opcodes::all::OP_CHECKMULTISIG,
+    psbt::PartiallySignedTransaction,
+    script::Builder,
+    secp256k1::Secp256k1,
+    sighash::SighashCache,
+    OutPoint, PublicKey, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness,
+};
+use libelectrum2descriptors::{Electrum2DescriptorError, ElectrumWalletFile};
+use std::{path::Path, str::FromStr};
+
+fn wallet(name: &str) -> ElectrumWalletFile {
+    ElectrumWalletFile::from_file(&Path::new("tests/wallets").join(name)).unwrap()
+}
+
+/// A psbt spending a coin on the first receiving address of the wallet. The keys are described with the
+/// keystores' root fingerprint and derivation, or with `fingerprint` when given.
+fn psbt_spending_first_address(
+    wallet: &ElectrumWalletFile,
+    fingerprint: Option<Fingerprint>,
+) -> PartiallySignedTransaction {
+    let secp = Secp256k1::verification_only();
+    let tx = Transaction {
+        version: 2,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::from_str(
+                "7f3a1c0e9b8d7f6e5d4c3b2a19080706050403020100ffeeddccbbaa99887766:1",
+            )
+            .unwrap(),
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: 90_000,
+            script_pubkey: wallet.address(true, 0).unwrap().script_pubkey(),
+        }],
+    };
+    let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+    psbt.inputs[0].witness_utxo = Some(TxOut {
+        value: 100_000,
+        script_pubkey: wallet.address(false, 0).unwrap().script_pubkey(),
+    });
+
+    let mut pubkeys = Vec::new();
+    for keystore in wallet.keystores() {
+        let (xpub, _kind, _network) = keystore.xpub().unwrap();
+        let relative = [
+            ChildNumber::Normal { index: 0 },
+            ChildNumber::Normal { index: 0 },
+        ];
+        let pubkey = xpub.derive_pub(&secp, &relative).unwrap().public_key;
+        let path = DerivationPath::from_str(keystore.derivation.as_deref().unwrap())
+            .unwrap()
+            .extend(relative);
+        let fingerprint =
+            fingerprint.unwrap_or_else(|| keystore.root_fingerprint().unwrap().unwrap());
+        psbt.inputs[0]
+            .bip32_derivation
+            .insert(pubkey, (fingerprint, path));
+        pubkeys.push(PublicKey::new(pubkey));
+    }
+    if let libelectrum2descriptors::electrum_wallet_file::WalletType::Multisig(multisig) =
+        wallet.wallet_type()
+    {
+        pubkeys.sort_by_key(|pk| pk.to_bytes());
+        let script = pubkeys
+            .iter()
+            .fold(
+                Builder::new().push_int(multisig.threshold().into()),
+                |builder, pk| builder.push_key(pk),
+            )
+            .push_int(pubkeys.len() as i64)
+            .push_opcode(OP_CHECKMULTISIG)
+            .into_script();
+        psbt.inputs[0].witness_script = Some(script);
+    }
+    psbt
+}
+
+/// Check the partial signatures against the sighash of the input
+fn verify_signatures(psbt: &PartiallySignedTransaction) -> usize {
+    let secp = Secp256k1::verification_only();
+    let mut cache = SighashCache::new(&psbt.unsigned_tx);
+    let (msg, _sighash_type) = psbt.sighash_ecdsa(0, &mut cache).unwrap();
+    for (pubkey, signature) in &psbt.inputs[0].partial_sigs {
+        assert!(psbt.inputs[0].bip32_derivation.contains_key(&pubkey.inner));
+        secp.verify_ecdsa(&msg, &signature.sig, &pubkey.inner)
+            .unwrap();
+    }
+    psbt.inputs[0].partial_sigs.len()
+}
+
+#[test]
+fn sign_singlesig() {
+    let wallet = wallet("default_segwit");
+    let mut psbt = psbt_spending_first_address(&wallet, None);
+    assert_eq!(wallet.sign_psbt(&mut psbt).unwrap(), 1);
+    assert_eq!(verify_signatures(&psbt), 1);
+}
+
+#[test]
+fn sign_by_trial_derivation() {
+    let wallet = wallet("default_segwit");
+    let unknown = Fingerprint::from_str("00000000").unwrap();
+    let mut psbt = psbt_spending_first_address(&wallet, Some(unknown));
+    assert_eq!(wallet.sign_psbt(&mut psbt).unwrap(), 1);
+    assert_eq!(verify_signatures(&psbt), 1);
+}
+
+#[test]
+fn sign_multisig_partially() {
+    let wallet = wallet("multisig_segwit");
+    let mut psbt = psbt_spending_first_address(&wallet, None);
+    assert_eq!(wallet.sign_psbt(&mut psbt).unwrap(), 1);
+    assert_eq!(verify_signatures(&psbt), 1);
+}
+
+#[test]
+fn sign_foreign_input() {
+    let wallet = wallet("default_segwit");
+    let mut psbt = psbt_spending_first_address(&self::wallet("default_legacy_watch"), None);
+    assert_eq!(wallet.sign_psbt(&mut psbt).unwrap(), 0);
+    assert!(psbt.inputs[0].partial_sigs.is_empty());
+}
+
+#[test]
+fn sign_watch_only() {
+    let wallet = wallet("multisig_hw_segwit");
+    let mut psbt = psbt_spending_first_address(&wallet, None);
+    assert!(matches!(
+        wallet.sign_psbt(&mut psbt),
+        Err(Electrum2DescriptorError::NoPrivateKeys)
+    ));
+}
@@ -0,0 +1,338 @@
+#![cfg(feature = "qr")]
+use bitcoin::hashes::{sha256, Hash};
+use libelectrum2descriptors::qr::{capacity, qr_codes, QrCode, MAX_VERSION};
+use libelectrum2descriptors::Electrum2DescriptorError;
+use rstest::rstest;
+
+/// "electrum2descriptors" as made by Kazuhiko Arase's QR code generator for javascript at level M with mask 2, which
+/// is the mask of the lowest penalty. Arase's generator scores the masks differently than the specification.
+const ELECTRUM2DESCRIPTORS: [&str; 25] = [
+    "#######..###.#..#.#######",
+    "#.....#......###..#.....#",
+    "#.###.#.#.###.###.#.###.#",
+    "#.###.#.#.#######.#.###.#",
+    "#.###.#.##.#..#.#.#.###.#",
+    "#.....#.##...#.#..#.....#",
+    "#######.#.#.#.#.#.#######",
+    "........##.....#.........",
+    "#.#####...##.####.#####..",
+    "####.#..###...#.#..#.....",
+    "##..#.##.#.#.#.####.#..##",
+    ".#.#....#.#.#.##..#.....#",
+    ".###..#..........#######.",
+    "#..#....#.#.##..#....#...",
+    "#...#.#.##...###.###..###",
+    "#.##...#.##.##...####..##",
+    "#.#.###..##.#.#######.###",
+    "........#.......#...#....",
+    "#######..####...#.#.##.##",
+    "#.....#.#.##..#.#...#...#",
+    "#.###.#.#.##...######.###",
+    "#.###.#.#.#.###.###.##.##",
+    "#.###.#.###..###.#.#.##.#",
+    "#.....#...#.##.##.##....#",
+    "#######.#.#.#.#..#.#..###",
+];
+
+/// The centers of the alignment patterns, from the table of the specification
+const ALIGNMENT_POSITIONS: [&[usize]; 21] = [
+    &[],
+    &[],
+    &[6, 18],
+    &[6, 22],
+    &[6, 26],
+    &[6, 30],
+    &[6, 34],
+    &[6, 22, 38],
+    &[6, 24, 42],
+    &[6, 26, 46],
+    &[6, 28, 50],
+    &[6, 30, 54],
+    &[6, 32, 58],
+    &[6, 34, 62],
+    &[6, 26, 46, 66],
+    &[6, 26, 48, 70],
+    &[6, 26, 50, 74],
+    &[6, 30, 54, 78],
+    &[6, 30, 56, 82],
+    &[6, 30, 58, 86],
+    &[6, 34, 62, 90],
+];
+
+/// The error correction blocks and their codewords at level M, from the table of the specification
+const BLOCKS: [(usize, usize); 21] = [
+    (0, 0),
+    (1, 10),
+    (1, 16),
+    (1, 26),
+    (2, 18),
+    (2, 24),
+    (4, 16),
+    (4, 18),
+    (4, 22),
+    (5, 22),
+    (5, 26),
+    (5, 30),
+    (8, 22),
+    (9, 22),
+    (9, 24),
+    (10, 24),
+    (10, 28),
+    (11, 28),
+    (13, 26),
+    (14, 26),
+    (16, 26),
+];
+
+fn gf_multiply(a: u8, b: u8) -> u8 {
+    let (mut a, mut b, mut product) = (a, b, 0u8);
+    while b != 0 {
+        if b & 1 == 1 {
+            product ^= a;
+        }
+        a = (a << 1) ^ if a & 0x80 != 0 { 0x1d } else { 0 };
+        b >>= 1;
+    }
+    product
+}
+
+/// Read a code back the way a scanner does, from its format information to the byte mode data, checking the
+/// error correction of each block
+fn decode(code: &QrCode) -> Vec<u8> {
+    let size = code.size();
+    let version = (size - 17) / 4;
+    assert_eq!(version, usize::from(code.version()));
+
+    let format_bit = |i: usize| match i {
+        0..=5 => code.is_dark(8, i),
+        6 => code.is_dark(8, 7),
+        7 => code.is_dark(8, 8),
+        8 => code.is_dark(7, 8),
+        _ => code.is_dark(14 - i, 8),
+    };
+    let copy_bit = |i: usize| match i {
+        0..=7 => code.is_dark(size - 1 - i, 8),
+        _ => code.is_dark(8, size - 15 + i),
+    };
+    let format = (0..15).fold(0u16, |bits, i| bits | (format_bit(i) as u16) << i);
+    assert_eq!(
+        format,
+        (0..15).fold(0u16, |b, i| b | (copy_bit(i) as u16) << i)
+    );
+    let format = format ^ 0x5412;
+    assert_eq!(format >> 13, 0, "error correction level M");
+    let mask = (format >> 10) & 7;
+    assert!(code.is_dark(8, size - 8));
+
+    let alignments = ALIGNMENT_POSITIONS[version];
+    let near = |a: usize, b: usize| a.max(b) - a.min(b) <= 2;
+    let function = |x: usize, y: usize| {
+        let finder = (y < 9 && (x < 9 || x >= size - 8)) || (x < 9 && y >= size - 8);
+        let alignment = alignments.iter().any(|cx| {
+            alignments.iter().any(|cy| {
+                near(x, *cx)
+                    && near(y, *cy)
+                    && !(*cx < 9 && (*cy < 9 || *cy > size - 9))
+                    && !(*cx > size - 9 && *cy < 9)
+            })
+        });
+        let version_info = version >= 7 && ((x < 6 && y >= size - 11) || (y < 6 && x >= size - 11));
+        finder || alignment || version_info || x == 6 || y == 6
+    };
+    let masked = |x: usize, y: usize| match mask {
+        0 => (x + y).is_multiple_of(2),
+        1 => y.is_multiple_of(2),
+        2 => x.is_multiple_of(3),
+        3 => (x + y).is_multiple_of(3),
+        4 => (x / 3 + y / 2).is_multiple_of(2),
+        5 => (x * y) % 2 + (x * y) % 3 == 0,
+        6 => ((x * y) % 2 + (x * y) % 3).is_multiple_of(2),
+        _ => ((x + y) % 2 + (x * y) % 3).is_multiple_of(2),
+    };
+
+    // the columns pairs from the right, alternately upwards and downwards, skipping the vertical timing pattern
+    let mut bits = Vec::new();
+    let mut columns = (0..size).rev().filter(|x| *x != 6).collect::<Vec<_>>();
+    columns.push(usize::MAX);
+    for (pair, xs) in columns.chunks(2).enumerate() {
+        let rows = (0..size).collect::<Vec<_>>();
+        let rows = if pair.is_multiple_of(2) {
+            rows.into_iter().rev().collect()
+        } else {
+            rows
+        };
+        for y in rows {
+            for x in xs.iter().filter(|x| **x != usize::MAX) {
+                if !function(*x, y) {
+                    bits.push(code.is_dark(*x, y) ^ masked(*x, y));
+                }
+            }
+        }
+    }
+    let codewords = bits
+        .chunks_exact(8)
+        .map(|byte| byte.iter().fold(0u8, |b, bit| b << 1 | *bit as u8))
+        .collect::<Vec<_>>();
+
+    let (blocks, ecc) = BLOCKS[version];
+    let short_len = codewords.len() / blocks;
+    let long_blocks = codewords.len() % blocks;
+    let mut split = vec![Vec::new(); blocks];
+    let mut next = codewords.iter();
+    for i in 0..short_len + 1 {
+        for (block, codewords) in split.iter_mut().enumerate() {
+            let len = short_len + usize::from(block >= blocks - long_blocks);
+            if i < len - ecc {
+                codewords.push(*next.next().unwrap());
+            }
+        }
+    }
+    for _ in 0..ecc {
+        for codewords in &mut split {
+            codewords.push(*next.next().unwrap());
+        }
+    }
+    // the block evaluates to 0 at the roots of the generator polynomial
+    let mut root = 1u8;
+    for _ in 0..ecc {
+        for block in &split {
+            let syndrome = block
+                .iter()
+                .fold(0u8, |value, codeword| gf_multiply(value, root) ^ codeword);
+            assert_eq!(syndrome, 0);
+        }
+        root = gf_multiply(root, 2);
+    }
+
+    let data = split
+        .iter()
+        .flat_map(|block| &block[..block.len() - ecc])
+        .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1 == 1))
+        .collect::<Vec<_>>();
+    let read = |from: usize, len: usize| {
+        data[from..from + len]
+            .iter()
+            .fold(0usize, |value, bit| value << 1 | *bit as usize)
+    };
+    assert_eq!(read(0, 4), 0b0100, "byte mode");
+    let count_bits = if version <= 9 { 8 } else { 16 };
+    let len = read(4, count_bits);
+    (0..len)
+        .map(|i| read(4 + count_bits + i * 8, 8) as u8)
+        .collect()
+}
+
+/// The modules of a code row by row, `#` for dark ones
+fn modules(code: &QrCode) -> Vec<String> {
+    (0..code.size())
+        .map(|y| {
+            (0..code.size())
+                .map(|x| if code.is_dark(x, y) { '#' } else { '.' })
+                .collect()
+        })
+        .collect()
+}
+
+/// Text filling a code of the given capacity
+fn filler(len: usize) -> String {
+    (0..len)
+        .map(|i| (b'!' + (i * 7 % 90) as u8) as char)
+        .collect()
+}
+
+#[test]
+fn qr_code_known_answer() {
+    let code = QrCode::new(b"electrum2descriptors").unwrap();
+    assert_eq!(code.version(), 2);
+    assert_eq!(modules(&code), ELECTRUM2DESCRIPTORS);
+}
+
+/// The sha256 of the modules of codes made by Kazuhiko Arase's generator for javascript, with the mask chosen by the
+/// crate, e.g. mask 5 for version 20. They cover the version information from version 7 on, the 16 bit length from
+/// version 10 on and the largest version.
+#[rstest]
+#[case::empty(
+    "",
+    1,
+    "fc776d7963fc85fc22bac1ce4e1b56b343f6cba180f5622a5da2b0041abbff90"
+)]
+#[case::xpub(
+    "vpub5VXaSncXqxLbdmvrC4Y8z9CszPwuEscADoetWhfrxDFzPUbL5nbVtanYDkrVEutkv9n5A5aCcvRC9swbjDKgHjCZ2tAeae8VsBuPbS8KpXv",
+    7,
+    "38366ebef0940e0d36f0863cb247725822eb658a3907da38e7bb640df72166ae"
+)]
+#[case::descriptor(
+    "wpkh([b88448fb/0']tpubD9ZjaMn3rbP1cAVwJy6UcEjFfTLT7W6DbfHdS3Wn48meExtVfKmiH9meWCrSmE9qXLYbGcHC5LxLcdfLZTzwme23qAJoRzRhzbd68dHeyjp/0/*)",
+    8,
+    "4a7ccc1b76e38f21f284240d21f631b183f5bec610835ad575012cc01691e7be"
+)]
+#[case::version_7(&filler(122), 7, "48bf228e513eb12b41b23afacf618e2c505760663fb59c066c44df50524abe35")]
+#[case::version_10(&filler(213), 10, "b4ad0f45cb0f803deb1a8490052ea9b57d4ac4356b97d88b1f5c144a8d65541e")]
+#[case::version_20(&filler(666), 20, "7ea2fde67f5d13be1feb3479b7a9890721628c31f63b30c0592155c7805c51ec")]
+fn qr_code_known_answers(#[case] text: &str, #[case] version: u8, #[case] digest: &str) {
+    let code = QrCode::new(text.as_bytes()).unwrap();
+    assert_eq!(code.version(), version);
+    assert_eq!(
+        sha256::Hash::hash(modules(&code).join("\n").as_bytes()).to_string(),
+        digest
+    );
+}
+
+#[rstest]
+#[case::xpub("vpub5VXaSncXqxLbdmvrC4Y8z9CszPwuEscADoetWhfrxDFzPUbL5nbVtanYDkrVEutkv9n5A5aCcvRC9swbjDKgHjCZ2tAeae8VsBuPbS8KpXv")]
+#[case::descriptor("wpkh([b88448fb/0']tpubD9ZjaMn3rbP1cAVwJy6UcEjFfTLT7W6DbfHdS3Wn48meExtVfKmiH9meWCrSmE9qXLYbGcHC5LxLcdfLZTzwme23qAJoRzRhzbd68dHeyjp/0/*)")]
+#[case::empty("")]
+fn qr_code(#[case] text: &str) {
+    let code = QrCode::new(text.as_bytes()).unwrap();
+    assert_eq!(decode(&code), text.as_bytes());
+    assert_eq!(qr_codes(text), vec![code]);
+}
+
+#[test]
+fn qr_code_versions() {
+    for version in 1..=MAX_VERSION {
+        let text = filler(capacity(version));
+        let code = QrCode::new(text.as_bytes()).unwrap();
+        assert_eq!(code.version(), version);
+        assert_eq!(code.size(), usize::from(version) * 4 + 17);
+        assert_eq!(decode(&code), text.as_bytes(), "version {}", version);
+        if version < MAX_VERSION {
+            let code = QrCode::new(format!("{}.", text).as_bytes()).unwrap();
+            assert_eq!(code.version(), version + 1);
+        }
+    }
+}
+
+#[test]
+fn qr_code_parts() {
+    let text = "é".repeat(capacity(MAX_VERSION));
+    assert!(matches!(
+        QrCode::new(text.as_bytes()),
+        Err(Electrum2DescriptorError::QrCodeTooLong(len, max)) if len == 2 * max
+    ));
+    let codes = qr_codes(&text);
+    assert_eq!(codes.len(), 2);
+    let decoded = codes.iter().flat_map(decode).collect::<Vec<_>>();
+    assert_eq!(String::from_utf8(decoded).unwrap(), text);
+}
+
+#[test]
+fn qr_code_rendering() {
+    let code = QrCode::new(b"electrum2descriptors").unwrap();
+    let terminal = code.to_terminal();
+    let lines = terminal.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), (code.size() + 9) / 2);
+    assert!(lines
+        .iter()
+        .all(|line| line.chars().count() == code.size() + 8));
+    // the quiet zone is light
+    assert!(lines[0].chars().all(|c| c == '█'));
+    // the top left finder starts 4 modules in
+    assert!(lines[2].starts_with("████ ▄▄▄▄▄ "));
+
+    let png = code.to_png();
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR"));
+    let width = u32::from_be_bytes([png[16], png[17], png[18], png[19]]) as usize;
+    assert_eq!(width, (code.size() + 8) * 8);
+    assert!(png.ends_with(b"IEND\xae\x42\x60\x82"));
+}
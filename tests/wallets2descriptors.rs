@@ -1,6 +1,24 @@
 #![cfg(feature = "wallet_file")]
-use bdk::{bitcoin::Network, database::MemoryDatabase, wallet::AddressIndex, Wallet};
-use libelectrum2descriptors::{Descriptors, ElectrumWalletFile};
+use bdk::bitcoin::bip32::{DerivationPath, ExtendedPubKey, Fingerprint};
+use bdk::bitcoin::sign_message::{signed_msg_hash, MessageSignature};
+use bdk::{
+    bitcoin::{Address, Network},
+    database::MemoryDatabase,
+    wallet::AddressIndex,
+    Wallet,
+};
+use libelectrum2descriptors::{
+    batch,
+    electrum_wallet_file::{
+        self, CanonicalizeOptions, DescriptorOptions, JsonIndent, JsonStyle, KeyOrder, Keystore,
+        ParseOptions, RepairAction, RepairPolicy, WalletType, WriteOptions, SECRET_FIELDS,
+    },
+    recover::{self, FoundKey, FoundKeyType},
+    ConversionTarget, ConversionWarning, Descriptors, Electrum2DescriptorError,
+    ElectrumExtendedKey, ElectrumExtendedPrivKey, ElectrumExtendedPubKey, ElectrumWalletFile,
+    LossReport, PrivateKeyExport, ReportFormat, ReportOptions, ScriptKind, WalletDifference,
+    WalletSummary,
+};
 use rstest::rstest;
 use std::{
     path::{Path, PathBuf},
@@ -16,17 +34,17 @@ use tempfile::tempdir;
     "pkh(tpubD6NzVbkrYhZ4Y1ozBYSfoyVp2iGgP6iZAy18p2opXjVv8jTNccGuRs3jMCMe4ncfwy2RUJsoZLSXsGiFhN47xFbJgtRvCuV3RP3UnxpsrZt/0/*)",
     "pkh(tpubD6NzVbkrYhZ4Y1ozBYSfoyVp2iGgP6iZAy18p2opXjVv8jTNccGuRs3jMCMe4ncfwy2RUJsoZLSXsGiFhN47xFbJgtRvCuV3RP3UnxpsrZt/1/*)")]
 #[case::default_segwit("default_segwit", 
-    "wpkh(tprv8cvkZzx9zA7EfFDbH945mK23r7hg6EHXUk79wVUSRukwyctFS1AdpSpkZcykAMDveCj8RA3R4jwFTKMwMbWexJox8NMqq7YphJLDumfCSfu/0/*)",
-    "wpkh(tprv8cvkZzx9zA7EfFDbH945mK23r7hg6EHXUk79wVUSRukwyctFS1AdpSpkZcykAMDveCj8RA3R4jwFTKMwMbWexJox8NMqq7YphJLDumfCSfu/1/*)")]
+    "wpkh([b88448fb/0']tprv8cvkZzx9zA7EfFDbH945mK23r7hg6EHXUk79wVUSRukwyctFS1AdpSpkZcykAMDveCj8RA3R4jwFTKMwMbWexJox8NMqq7YphJLDumfCSfu/0/*)",
+    "wpkh([b88448fb/0']tprv8cvkZzx9zA7EfFDbH945mK23r7hg6EHXUk79wVUSRukwyctFS1AdpSpkZcykAMDveCj8RA3R4jwFTKMwMbWexJox8NMqq7YphJLDumfCSfu/1/*)")]
 #[case::multisig_hw_segwit("multisig_hw_segwit", 
-    "wsh(sortedmulti(2,tpubDEcw4ooTbmw62zBKdkYepoP3z4WWugdeRzPHHAbk8XVsPfBE9AAZMNghiqwtdFgtabaeppBTPmezUkRkQZidLcSJp3XTASbMakHcYauWehZ/0/*,tpubDEbkvhmJoZMq3SUNqEf3aEsubvqsCUPc7rroHkGERgS7qA1gQVMxUPrgzth6x43odirLohwf4aMHpvcnWi3jCB2xkizv8T4B2KqLRZVLC6K/0/*))",
-    "wsh(sortedmulti(2,tpubDEcw4ooTbmw62zBKdkYepoP3z4WWugdeRzPHHAbk8XVsPfBE9AAZMNghiqwtdFgtabaeppBTPmezUkRkQZidLcSJp3XTASbMakHcYauWehZ/1/*,tpubDEbkvhmJoZMq3SUNqEf3aEsubvqsCUPc7rroHkGERgS7qA1gQVMxUPrgzth6x43odirLohwf4aMHpvcnWi3jCB2xkizv8T4B2KqLRZVLC6K/1/*))")]
+    "wsh(sortedmulti(2,[27d81095/48'/1'/0'/2']tpubDEcw4ooTbmw62zBKdkYepoP3z4WWugdeRzPHHAbk8XVsPfBE9AAZMNghiqwtdFgtabaeppBTPmezUkRkQZidLcSJp3XTASbMakHcYauWehZ/0/*,[6bfac2d6/48'/100'/0'/2']tpubDEbkvhmJoZMq3SUNqEf3aEsubvqsCUPc7rroHkGERgS7qA1gQVMxUPrgzth6x43odirLohwf4aMHpvcnWi3jCB2xkizv8T4B2KqLRZVLC6K/0/*))",
+    "wsh(sortedmulti(2,[27d81095/48'/1'/0'/2']tpubDEcw4ooTbmw62zBKdkYepoP3z4WWugdeRzPHHAbk8XVsPfBE9AAZMNghiqwtdFgtabaeppBTPmezUkRkQZidLcSJp3XTASbMakHcYauWehZ/1/*,[6bfac2d6/48'/100'/0'/2']tpubDEbkvhmJoZMq3SUNqEf3aEsubvqsCUPc7rroHkGERgS7qA1gQVMxUPrgzth6x43odirLohwf4aMHpvcnWi3jCB2xkizv8T4B2KqLRZVLC6K/1/*))")]
 #[case::multisig_legacy("multisig_legacy", 
     "sh(sortedmulti(2,tprv8ZgxMBicQKsPeLPWr5WbJDAhANr6irc1Yf7eUNCYjGYap27HU4bDBXWGMT3X75FhDyxNXr6pK4QeHcCBvkqchQzK8wZ4JbGv5X5MWtXQtqy/0/*,tpubD6NzVbkrYhZ4Y1ozBYSfoyVp2iGgP6iZAy18p2opXjVv8jTNccGuRs3jMCMe4ncfwy2RUJsoZLSXsGiFhN47xFbJgtRvCuV3RP3UnxpsrZt/0/*))",
     "sh(sortedmulti(2,tprv8ZgxMBicQKsPeLPWr5WbJDAhANr6irc1Yf7eUNCYjGYap27HU4bDBXWGMT3X75FhDyxNXr6pK4QeHcCBvkqchQzK8wZ4JbGv5X5MWtXQtqy/1/*,tpubD6NzVbkrYhZ4Y1ozBYSfoyVp2iGgP6iZAy18p2opXjVv8jTNccGuRs3jMCMe4ncfwy2RUJsoZLSXsGiFhN47xFbJgtRvCuV3RP3UnxpsrZt/1/*))")]
 #[case::multisig_segwit("multisig_segwit", 
-    "wsh(sortedmulti(2,tprv8dNybiDsdyms39SAWTxyiNHABTTgiqmJpScmxGrdKEuZ7TwXcaYXT4f4ddVjWiiQs9zowHqyDmvaebN6fU2Lu6iAYnYuepiLkvzGdcZZi8D/0/*,tpubD9cniQzQ8XnuagyP9Xwg3sWCX77wQPWoLPW7jqzcPn37r8hq2X86uztCEyFbMY16amzwdJ1CcNRXhF3vykn1wuDv2ULzryRtaCcN5Cr8F9y/0/*))",
-    "wsh(sortedmulti(2,tprv8dNybiDsdyms39SAWTxyiNHABTTgiqmJpScmxGrdKEuZ7TwXcaYXT4f4ddVjWiiQs9zowHqyDmvaebN6fU2Lu6iAYnYuepiLkvzGdcZZi8D/1/*,tpubD9cniQzQ8XnuagyP9Xwg3sWCX77wQPWoLPW7jqzcPn37r8hq2X86uztCEyFbMY16amzwdJ1CcNRXhF3vykn1wuDv2ULzryRtaCcN5Cr8F9y/1/*))")]
+    "wsh(sortedmulti(2,[f6083804/1']tprv8dNybiDsdyms39SAWTxyiNHABTTgiqmJpScmxGrdKEuZ7TwXcaYXT4f4ddVjWiiQs9zowHqyDmvaebN6fU2Lu6iAYnYuepiLkvzGdcZZi8D/0/*,[b88448fb/1']tpubD9cniQzQ8XnuagyP9Xwg3sWCX77wQPWoLPW7jqzcPn37r8hq2X86uztCEyFbMY16amzwdJ1CcNRXhF3vykn1wuDv2ULzryRtaCcN5Cr8F9y/0/*))",
+    "wsh(sortedmulti(2,[f6083804/1']tprv8dNybiDsdyms39SAWTxyiNHABTTgiqmJpScmxGrdKEuZ7TwXcaYXT4f4ddVjWiiQs9zowHqyDmvaebN6fU2Lu6iAYnYuepiLkvzGdcZZi8D/1/*,[b88448fb/1']tpubD9cniQzQ8XnuagyP9Xwg3sWCX77wQPWoLPW7jqzcPn37r8hq2X86uztCEyFbMY16amzwdJ1CcNRXhF3vykn1wuDv2ULzryRtaCcN5Cr8F9y/1/*))")]
 #[case::multisig_wrapped_watch("multisig_wrapped_watch", 
     "sh(wsh(sortedmulti(3,tpubDEsqS36T4DVsKJd9UH8pAKzrkGBYPLEt9jZMwpKtzh1G6mgYehfHt9WCgk7MJG5QGSFWf176KaBNoXbcuFcuadAFKxDpUdMDKGBha7bY3QM/0/*,tpubDF3cpwfs7fMvXXuoQbohXtLjNM6ehwYT287LWtmLsd4r77YLg6MZg4vTETx5MSJ2zkfigbYWu31VA2Z2Vc1cZugCYXgS7FQu6pE8V6TriEH/0/*,tpubDE1SKfcW76Tb2AASv5bQWMuScYNAdoqLHoexw13sNDXwmUhQDBbCD3QAedKGLhxMrWQdMDKENzYtnXPDRvexQPNuDrLj52wAjHhNEm8sJ4p/0/*,tpubDFLc6oXwJmhm3FGGzXkfJNTh2KitoY3WhmmQvuAjMhD8YbyWn5mAqckbxXfm2etM3p5J6JoTpSrMqRSTfMLtNW46poDaEZJ1kjd3csRSjwH/0/*,tpubDEWD9NBeWP59xXmdqSNt4VYdtTGwbpyP8WS962BuqpQeMZmX9Pur14dhXdZT5a7wR1pK6dPtZ9fP5WR493hPzemnBvkfLLYxnUjAKj1JCQV/0/*,tpubDEHyZkkwd7gZWCTgQuYQ9C4myF2hMEmyHsBCCmLssGqoqUxeT3gzohF5uEVURkf9TtmeepJgkSUmteac38FwZqirjApzNX59XSHLcwaTZCH/0/*,tpubDEqLouCekwnMUWN486kxGzD44qVgeyuqHyxUypNEiQt5RnUZNJe386TKPK99fqRV1vRkZjYAjtXGTECz98MCsdLcnkM67U6KdYRzVubeCgZ/0/*)))",
     "sh(wsh(sortedmulti(3,tpubDEsqS36T4DVsKJd9UH8pAKzrkGBYPLEt9jZMwpKtzh1G6mgYehfHt9WCgk7MJG5QGSFWf176KaBNoXbcuFcuadAFKxDpUdMDKGBha7bY3QM/1/*,tpubDF3cpwfs7fMvXXuoQbohXtLjNM6ehwYT287LWtmLsd4r77YLg6MZg4vTETx5MSJ2zkfigbYWu31VA2Z2Vc1cZugCYXgS7FQu6pE8V6TriEH/1/*,tpubDE1SKfcW76Tb2AASv5bQWMuScYNAdoqLHoexw13sNDXwmUhQDBbCD3QAedKGLhxMrWQdMDKENzYtnXPDRvexQPNuDrLj52wAjHhNEm8sJ4p/1/*,tpubDFLc6oXwJmhm3FGGzXkfJNTh2KitoY3WhmmQvuAjMhD8YbyWn5mAqckbxXfm2etM3p5J6JoTpSrMqRSTfMLtNW46poDaEZJ1kjd3csRSjwH/1/*,tpubDEWD9NBeWP59xXmdqSNt4VYdtTGwbpyP8WS962BuqpQeMZmX9Pur14dhXdZT5a7wR1pK6dPtZ9fP5WR493hPzemnBvkfLLYxnUjAKj1JCQV/1/*,tpubDEHyZkkwd7gZWCTgQuYQ9C4myF2hMEmyHsBCCmLssGqoqUxeT3gzohF5uEVURkf9TtmeepJgkSUmteac38FwZqirjApzNX59XSHLcwaTZCH/1/*,tpubDEqLouCekwnMUWN486kxGzD44qVgeyuqHyxUypNEiQt5RnUZNJe386TKPK99fqRV1vRkZjYAjtXGTECz98MCsdLcnkM67U6KdYRzVubeCgZ/1/*)))")]
@@ -72,14 +90,35 @@ fn first_address_from_wallet_file(wallet_name: &str) -> String {
     wallet.addresses().receiving[0].clone()
 }
 
-/// Since converting a wallet with imported keys or addresses can't be converted to a descriptor anyway, we just leave a not so descriptive error message due to a different json format of such wallets.
-#[rstest]
-#[case::imported_addr("imported_addr")]
-#[case::imported_privkey("imported_privkey")]
-#[should_panic(expected = "missing field `change`")]
-fn parse_imported(#[case] wallet_name: &str) {
-    let wallet_file = get_test_wallet_file(wallet_name);
-    let _wallet = ElectrumWalletFile::from_file(wallet_file.as_path()).unwrap();
+/// Imported keys are kept, but have no descriptor of a chain of addresses. Imported addresses have no keystore at all.
+#[test]
+fn parse_imported() {
+    let wallet_file = get_test_wallet_file("imported_privkey");
+    let wallet = ElectrumWalletFile::from_file(wallet_file.as_path()).unwrap();
+    assert_eq!(wallet.wallet_type(), &WalletType::Imported);
+    assert!(wallet.keystores()[0].is_imported());
+    assert!(wallet.has_secrets());
+    assert!(matches!(
+        wallet.to_descriptors(),
+        Err(Electrum2DescriptorError::ImportedKeys)
+    ));
+    assert_eq!(
+        wallet.to_value().unwrap(),
+        serde_json::from_str::<serde_json::Value>(&std::fs::read_to_string(&wallet_file).unwrap())
+            .unwrap()
+    );
+
+    let watch_only = ElectrumWalletFile::from_file_watch_only(wallet_file.as_path()).unwrap();
+    assert!(!watch_only.has_secrets());
+    assert_eq!(
+        watch_only.raw_field("addresses"),
+        wallet.raw_field("addresses")
+    );
+
+    assert!(matches!(
+        ElectrumWalletFile::from_file(get_test_wallet_file("imported_addr").as_path()),
+        Err(Electrum2DescriptorError::Serde(_))
+    ));
 }
 
 #[rstest]
@@ -88,13 +127,13 @@ fn parse_imported(#[case] wallet_name: &str) {
 #[case::default_legacy_watch("default_legacy_watch", 
     "pkh(tpubD6NzVbkrYhZ4Y1ozBYSfoyVp2iGgP6iZAy18p2opXjVv8jTNccGuRs3jMCMe4ncfwy2RUJsoZLSXsGiFhN47xFbJgtRvCuV3RP3UnxpsrZt/0/*)")]
 #[case::default_segwit("default_segwit", 
-    "wpkh(tprv8cvkZzx9zA7EfFDbH945mK23r7hg6EHXUk79wVUSRukwyctFS1AdpSpkZcykAMDveCj8RA3R4jwFTKMwMbWexJox8NMqq7YphJLDumfCSfu/0/*)")]
+    "wpkh([b88448fb/0']tprv8cvkZzx9zA7EfFDbH945mK23r7hg6EHXUk79wVUSRukwyctFS1AdpSpkZcykAMDveCj8RA3R4jwFTKMwMbWexJox8NMqq7YphJLDumfCSfu/0/*)")]
 #[case::multisig_hw_segwit("multisig_hw_segwit", 
-    "wsh(sortedmulti(2,tpubDEcw4ooTbmw62zBKdkYepoP3z4WWugdeRzPHHAbk8XVsPfBE9AAZMNghiqwtdFgtabaeppBTPmezUkRkQZidLcSJp3XTASbMakHcYauWehZ/0/*,tpubDEbkvhmJoZMq3SUNqEf3aEsubvqsCUPc7rroHkGERgS7qA1gQVMxUPrgzth6x43odirLohwf4aMHpvcnWi3jCB2xkizv8T4B2KqLRZVLC6K/0/*))")]
+    "wsh(sortedmulti(2,[27d81095/48'/1'/0'/2']tpubDEcw4ooTbmw62zBKdkYepoP3z4WWugdeRzPHHAbk8XVsPfBE9AAZMNghiqwtdFgtabaeppBTPmezUkRkQZidLcSJp3XTASbMakHcYauWehZ/0/*,[6bfac2d6/48'/100'/0'/2']tpubDEbkvhmJoZMq3SUNqEf3aEsubvqsCUPc7rroHkGERgS7qA1gQVMxUPrgzth6x43odirLohwf4aMHpvcnWi3jCB2xkizv8T4B2KqLRZVLC6K/0/*))")]
 #[case::multisig_legacy("multisig_legacy", 
     "sh(sortedmulti(2,tprv8ZgxMBicQKsPeLPWr5WbJDAhANr6irc1Yf7eUNCYjGYap27HU4bDBXWGMT3X75FhDyxNXr6pK4QeHcCBvkqchQzK8wZ4JbGv5X5MWtXQtqy/0/*,tpubD6NzVbkrYhZ4Y1ozBYSfoyVp2iGgP6iZAy18p2opXjVv8jTNccGuRs3jMCMe4ncfwy2RUJsoZLSXsGiFhN47xFbJgtRvCuV3RP3UnxpsrZt/0/*))")]
 #[case::multisig_segwit("multisig_segwit", 
-    "wsh(sortedmulti(2,tprv8dNybiDsdyms39SAWTxyiNHABTTgiqmJpScmxGrdKEuZ7TwXcaYXT4f4ddVjWiiQs9zowHqyDmvaebN6fU2Lu6iAYnYuepiLkvzGdcZZi8D/0/*,tpubD9cniQzQ8XnuagyP9Xwg3sWCX77wQPWoLPW7jqzcPn37r8hq2X86uztCEyFbMY16amzwdJ1CcNRXhF3vykn1wuDv2ULzryRtaCcN5Cr8F9y/0/*))")]
+    "wsh(sortedmulti(2,[f6083804/1']tprv8dNybiDsdyms39SAWTxyiNHABTTgiqmJpScmxGrdKEuZ7TwXcaYXT4f4ddVjWiiQs9zowHqyDmvaebN6fU2Lu6iAYnYuepiLkvzGdcZZi8D/0/*,[b88448fb/1']tpubD9cniQzQ8XnuagyP9Xwg3sWCX77wQPWoLPW7jqzcPn37r8hq2X86uztCEyFbMY16amzwdJ1CcNRXhF3vykn1wuDv2ULzryRtaCcN5Cr8F9y/0/*))")]
 #[case::multisig_wrapped_watch("multisig_wrapped_watch", 
     "sh(wsh(sortedmulti(3,tpubDEsqS36T4DVsKJd9UH8pAKzrkGBYPLEt9jZMwpKtzh1G6mgYehfHt9WCgk7MJG5QGSFWf176KaBNoXbcuFcuadAFKxDpUdMDKGBha7bY3QM/0/*,tpubDF3cpwfs7fMvXXuoQbohXtLjNM6ehwYT287LWtmLsd4r77YLg6MZg4vTETx5MSJ2zkfigbYWu31VA2Z2Vc1cZugCYXgS7FQu6pE8V6TriEH/0/*,tpubDE1SKfcW76Tb2AASv5bQWMuScYNAdoqLHoexw13sNDXwmUhQDBbCD3QAedKGLhxMrWQdMDKENzYtnXPDRvexQPNuDrLj52wAjHhNEm8sJ4p/0/*,tpubDFLc6oXwJmhm3FGGzXkfJNTh2KitoY3WhmmQvuAjMhD8YbyWn5mAqckbxXfm2etM3p5J6JoTpSrMqRSTfMLtNW46poDaEZJ1kjd3csRSjwH/0/*,tpubDEWD9NBeWP59xXmdqSNt4VYdtTGwbpyP8WS962BuqpQeMZmX9Pur14dhXdZT5a7wR1pK6dPtZ9fP5WR493hPzemnBvkfLLYxnUjAKj1JCQV/0/*,tpubDEHyZkkwd7gZWCTgQuYQ9C4myF2hMEmyHsBCCmLssGqoqUxeT3gzohF5uEVURkf9TtmeepJgkSUmteac38FwZqirjApzNX59XSHLcwaTZCH/0/*,tpubDEqLouCekwnMUWN486kxGzD44qVgeyuqHyxUypNEiQt5RnUZNJe386TKPK99fqRV1vRkZjYAjtXGTECz98MCsdLcnkM67U6KdYRzVubeCgZ/0/*)))")]
 fn descriptor_electrum_wallet_roundtrip(#[case] wallet_name: &str, #[case] descriptor: &str) {
@@ -118,13 +157,13 @@ fn descriptor_electrum_wallet_roundtrip(#[case] wallet_name: &str, #[case] descr
 #[case::default_legacy_watch(
     "pkh(tpubD6NzVbkrYhZ4Y1ozBYSfoyVp2iGgP6iZAy18p2opXjVv8jTNccGuRs3jMCMe4ncfwy2RUJsoZLSXsGiFhN47xFbJgtRvCuV3RP3UnxpsrZt/0/*)")]
 #[case::default_segwit(
-    "wpkh(tprv8cvkZzx9zA7EfFDbH945mK23r7hg6EHXUk79wVUSRukwyctFS1AdpSpkZcykAMDveCj8RA3R4jwFTKMwMbWexJox8NMqq7YphJLDumfCSfu/0/*)")]
+    "wpkh([b88448fb/0']tprv8cvkZzx9zA7EfFDbH945mK23r7hg6EHXUk79wVUSRukwyctFS1AdpSpkZcykAMDveCj8RA3R4jwFTKMwMbWexJox8NMqq7YphJLDumfCSfu/0/*)")]
 #[case::multisig_hw_segwit(
-    "wsh(sortedmulti(2,tpubDEcw4ooTbmw62zBKdkYepoP3z4WWugdeRzPHHAbk8XVsPfBE9AAZMNghiqwtdFgtabaeppBTPmezUkRkQZidLcSJp3XTASbMakHcYauWehZ/0/*,tpubDEbkvhmJoZMq3SUNqEf3aEsubvqsCUPc7rroHkGERgS7qA1gQVMxUPrgzth6x43odirLohwf4aMHpvcnWi3jCB2xkizv8T4B2KqLRZVLC6K/0/*))")]
+    "wsh(sortedmulti(2,[27d81095/48'/1'/0'/2']tpubDEcw4ooTbmw62zBKdkYepoP3z4WWugdeRzPHHAbk8XVsPfBE9AAZMNghiqwtdFgtabaeppBTPmezUkRkQZidLcSJp3XTASbMakHcYauWehZ/0/*,[6bfac2d6/48'/100'/0'/2']tpubDEbkvhmJoZMq3SUNqEf3aEsubvqsCUPc7rroHkGERgS7qA1gQVMxUPrgzth6x43odirLohwf4aMHpvcnWi3jCB2xkizv8T4B2KqLRZVLC6K/0/*))")]
 #[case::multisig_legacy(
     "sh(sortedmulti(2,tprv8ZgxMBicQKsPeLPWr5WbJDAhANr6irc1Yf7eUNCYjGYap27HU4bDBXWGMT3X75FhDyxNXr6pK4QeHcCBvkqchQzK8wZ4JbGv5X5MWtXQtqy/0/*,tpubD6NzVbkrYhZ4Y1ozBYSfoyVp2iGgP6iZAy18p2opXjVv8jTNccGuRs3jMCMe4ncfwy2RUJsoZLSXsGiFhN47xFbJgtRvCuV3RP3UnxpsrZt/0/*))")]
 #[case::multisig_segwit(
-    "wsh(sortedmulti(2,tprv8dNybiDsdyms39SAWTxyiNHABTTgiqmJpScmxGrdKEuZ7TwXcaYXT4f4ddVjWiiQs9zowHqyDmvaebN6fU2Lu6iAYnYuepiLkvzGdcZZi8D/0/*,tpubD9cniQzQ8XnuagyP9Xwg3sWCX77wQPWoLPW7jqzcPn37r8hq2X86uztCEyFbMY16amzwdJ1CcNRXhF3vykn1wuDv2ULzryRtaCcN5Cr8F9y/0/*))")]
+    "wsh(sortedmulti(2,[f6083804/1']tprv8dNybiDsdyms39SAWTxyiNHABTTgiqmJpScmxGrdKEuZ7TwXcaYXT4f4ddVjWiiQs9zowHqyDmvaebN6fU2Lu6iAYnYuepiLkvzGdcZZi8D/0/*,[b88448fb/1']tpubD9cniQzQ8XnuagyP9Xwg3sWCX77wQPWoLPW7jqzcPn37r8hq2X86uztCEyFbMY16amzwdJ1CcNRXhF3vykn1wuDv2ULzryRtaCcN5Cr8F9y/0/*))")]
 #[case::multisig_wrapped_watch(
     "sh(wsh(sortedmulti(3,tpubDEsqS36T4DVsKJd9UH8pAKzrkGBYPLEt9jZMwpKtzh1G6mgYehfHt9WCgk7MJG5QGSFWf176KaBNoXbcuFcuadAFKxDpUdMDKGBha7bY3QM/0/*,tpubDF3cpwfs7fMvXXuoQbohXtLjNM6ehwYT287LWtmLsd4r77YLg6MZg4vTETx5MSJ2zkfigbYWu31VA2Z2Vc1cZugCYXgS7FQu6pE8V6TriEH/0/*,tpubDE1SKfcW76Tb2AASv5bQWMuScYNAdoqLHoexw13sNDXwmUhQDBbCD3QAedKGLhxMrWQdMDKENzYtnXPDRvexQPNuDrLj52wAjHhNEm8sJ4p/0/*,tpubDFLc6oXwJmhm3FGGzXkfJNTh2KitoY3WhmmQvuAjMhD8YbyWn5mAqckbxXfm2etM3p5J6JoTpSrMqRSTfMLtNW46poDaEZJ1kjd3csRSjwH/0/*,tpubDEWD9NBeWP59xXmdqSNt4VYdtTGwbpyP8WS962BuqpQeMZmX9Pur14dhXdZT5a7wR1pK6dPtZ9fP5WR493hPzemnBvkfLLYxnUjAKj1JCQV/0/*,tpubDEHyZkkwd7gZWCTgQuYQ9C4myF2hMEmyHsBCCmLssGqoqUxeT3gzohF5uEVURkf9TtmeepJgkSUmteac38FwZqirjApzNX59XSHLcwaTZCH/0/*,tpubDEqLouCekwnMUWN486kxGzD44qVgeyuqHyxUypNEiQt5RnUZNJe386TKPK99fqRV1vRkZjYAjtXGTECz98MCsdLcnkM67U6KdYRzVubeCgZ/0/*)))")]
 fn descriptor_string_roundtrip(#[case] descriptor: &str) {
@@ -138,3 +177,3985 @@ fn descriptor_string_roundtrip(#[case] descriptor: &str) {
     let desc = wallet.to_descriptors().unwrap();
     assert_eq!(desc.external, descriptor);
 }
+
+#[test]
+fn descriptor_checksum() {
+    let descriptor = wallet_name_to_descriptors("default_segwit").external;
+    let with_checksum = libelectrum2descriptors::checksum::with_checksum(&descriptor).unwrap();
+    assert_eq!(
+        ElectrumWalletFile::from_descriptor(&with_checksum).unwrap(),
+        ElectrumWalletFile::from_descriptor(&descriptor).unwrap()
+    );
+
+    let (_, checksum) = with_checksum.split_once('#').unwrap();
+    let wrong = format!(
+        "{}#{}",
+        descriptor,
+        checksum.chars().rev().collect::<String>()
+    );
+    assert!(matches!(
+        ElectrumWalletFile::from_descriptor(&wrong),
+        Err(Electrum2DescriptorError::UnknownDescriptorFormat(e)) if e.starts_with("invalid checksum")
+    ));
+}
+
+#[rstest]
+#[case::derivation_null("derivation_null", Some(serde_json::Value::Null))]
+#[case::derivation_empty("derivation_empty", Some(serde_json::Value::from("")))]
+#[case::derivation_missing("derivation_missing", None)]
+fn parse_null_derivation(#[case] wallet_name: &str, #[case] expected: Option<serde_json::Value>) {
+    let wallet_file = get_test_wallet_file(wallet_name);
+    let wallet = ElectrumWalletFile::from_file(wallet_file.as_path()).unwrap();
+    let keystore = &wallet.keystores()[0];
+    assert_eq!(keystore.derivation.as_deref(), None);
+    assert_eq!(keystore.root_fingerprint.as_deref(), None);
+
+    let desc = wallet.to_descriptors().unwrap();
+    assert_eq!(desc.external, "pkh(tpubD6NzVbkrYhZ4Y1ozBYSfoyVp2iGgP6iZAy18p2opXjVv8jTNccGuRs3jMCMe4ncfwy2RUJsoZLSXsGiFhN47xFbJgtRvCuV3RP3UnxpsrZt/0/*)");
+
+    let json: serde_json::Value = serde_json::from_str(&wallet.to_string()).unwrap();
+    assert_eq!(json["keystore"].get("derivation"), expected.as_ref());
+    assert_eq!(json["keystore"].get("root_fingerprint"), expected.as_ref());
+}
+
+#[test]
+fn parse_stringified_numbers() {
+    let wallet_file = get_test_wallet_file("stringified_numbers");
+    let wallet = ElectrumWalletFile::from_file(wallet_file.as_path()).unwrap();
+    assert_eq!(wallet.seed_version(), Some(18));
+    assert_eq!(wallet.keystores()[0].pw_hash_version, Some(1));
+
+    let strict = ParseOptions {
+        strict: true,
+        ..ParseOptions::default()
+    };
+    let err = ElectrumWalletFile::from_file_with_options(wallet_file.as_path(), &strict)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("expected a number, found the string \"")
+            && [
+                "addr_history height",
+                "pw_hash_version",
+                "seed_version",
+                "stored_height"
+            ]
+            .iter()
+            .any(|field| err.contains(field)),
+        "{}",
+        err
+    );
+
+    // fix the numbers one by one, in the order of the file
+    let mut json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&wallet_file).unwrap()).unwrap();
+    let fixes = [
+        (
+            "addr_history height",
+            "759990",
+            "/addr_history/mfgj7Gt1du65VjhheX9JmoWJFFrC3T6eN4/0/1",
+        ),
+        ("pw_hash_version", "1", "/keystore/pw_hash_version"),
+        ("seed_version", "18", "/seed_version"),
+        ("stored_height", "760000", "/stored_height"),
+    ];
+    for (field, number, pointer) in fixes {
+        let err = ElectrumWalletFile::from_str_with_options(&json.to_string(), &strict)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains(&format!(
+                "{}: expected a number, found the string \"{}\"",
+                field, number
+            )),
+            "{}",
+            err
+        );
+        *json.pointer_mut(pointer).unwrap() = serde_json::from_str(number).unwrap();
+    }
+    ElectrumWalletFile::from_str_with_options(&json.to_string(), &strict).unwrap();
+}
+
+#[test]
+fn parse_unconfirmed_history() {
+    // electrum records unconfirmed transactions at height 0 and those with unconfirmed parents at -1
+    let wallet_file = get_test_wallet_file("unconfirmed_history");
+    let wallet = ElectrumWalletFile::from_file(wallet_file.as_path()).unwrap();
+    let strict = ParseOptions {
+        strict: true,
+        ..ParseOptions::default()
+    };
+    let strict_wallet =
+        ElectrumWalletFile::from_file_with_options(wallet_file.as_path(), &strict).unwrap();
+    assert_eq!(wallet, strict_wallet);
+}
+
+#[rstest]
+#[case::lenient(false)]
+#[case::strict(true)]
+fn parse_nested_ignored_fields(#[case] strict: bool) {
+    let wallet_file = get_test_wallet_file("nested_ignored_fields");
+    let wallet = ElectrumWalletFile::from_file_with_options(
+        wallet_file.as_path(),
+        &ParseOptions {
+            strict,
+            ..ParseOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        wallet.to_descriptors().unwrap(),
+        wallet_name_to_descriptors("default_legacy_watch")
+    );
+}
+
+#[test]
+fn preserve_lightning() {
+    let wallet_file = get_test_wallet_file("lightning");
+    let wallet = ElectrumWalletFile::from_file(wallet_file.as_path()).unwrap();
+    let original: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&wallet_file).unwrap()).unwrap();
+
+    let tempdir = tempdir().unwrap();
+    let filename = tempdir.path().join("lightning");
+    wallet.to_file(&filename).unwrap();
+    let rewritten: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&filename).unwrap()).unwrap();
+    for field in [
+        "channel_backups",
+        "channels",
+        "imported_channel_backups",
+        "lightning_payments",
+        "lightning_preimages",
+        "lightning_xprv",
+        "onchain_channel_backups",
+        "submarine_swaps",
+    ] {
+        assert!(original.get(field).is_some(), "{}", field);
+        assert_eq!(original.get(field), rewritten.get(field), "{}", field);
+    }
+    assert!(SECRET_FIELDS.contains(&"lightning_xprv"));
+
+    let strict = ParseOptions {
+        strict: true,
+        ..ParseOptions::default()
+    };
+    ElectrumWalletFile::from_file_with_options(wallet_file.as_path(), &strict).unwrap();
+}
+
+#[rstest]
+#[case::default_legacy("default_legacy")]
+#[case::default_legacy_watch("default_legacy_watch")]
+#[case::default_segwit("default_segwit")]
+#[case::multisig_hw_segwit("multisig_hw_segwit")]
+#[case::multisig_legacy("multisig_legacy")]
+#[case::multisig_segwit("multisig_segwit")]
+#[case::multisig_wrapped_watch("multisig_wrapped_watch")]
+#[case::multisig_wrapped("multisig_wrapped")]
+fn parse_strict(#[case] wallet_name: &str) {
+    let wallet_file = get_test_wallet_file(wallet_name);
+    let strict = ParseOptions {
+        strict: true,
+        ..ParseOptions::default()
+    };
+    let wallet =
+        ElectrumWalletFile::from_file_with_options(wallet_file.as_path(), &strict).unwrap();
+    assert_eq!(
+        wallet,
+        ElectrumWalletFile::from_file(wallet_file.as_path()).unwrap()
+    );
+
+    let mut json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&wallet_file).unwrap()).unwrap();
+    json["unexpected"] = serde_json::Value::Null;
+    let err = ElectrumWalletFile::from_str_with_options(&json.to_string(), &strict).unwrap_err();
+    assert!(err.to_string().contains("unknown field `unexpected`"));
+    assert!(ElectrumWalletFile::from_str(&json.to_string()).is_ok());
+}
+
+#[rstest]
+#[case::master_xprv("default_legacy", Some("230b70d2"), Some("230b70d2"))]
+#[case::master_xpub("default_legacy_watch", Some("230b70d2"), Some("230b70d2"))]
+#[case::master_xpub_without_field("derivation_missing", Some("230b70d2"), Some("230b70d2"))]
+#[case::account_xpub_with_field("multisig_hw_segwit", None, Some("27d81095"))]
+#[case::account_xprv_with_field("default_segwit", None, Some("b88448fb"))]
+#[case::account_xpub_without_field("multisig_wrapped_watch", None, None)]
+fn root_fingerprint(
+    #[case] wallet_name: &str,
+    #[case] computed: Option<&str>,
+    #[case] expected: Option<&str>,
+) {
+    let wallet_file = get_test_wallet_file(wallet_name);
+    let wallet = ElectrumWalletFile::from_file(wallet_file.as_path()).unwrap();
+    let keystore = &wallet.keystores()[0];
+    assert_eq!(
+        keystore.compute_root_fingerprint().map(|f| f.to_string()),
+        computed.map(String::from)
+    );
+    assert_eq!(
+        keystore.root_fingerprint().unwrap().map(|f| f.to_string()),
+        expected.map(String::from)
+    );
+}
+
+#[test]
+fn keystore_labels() {
+    let wallet_file = get_test_wallet_file("multisig_hw_segwit");
+    let wallet = ElectrumWalletFile::from_file(wallet_file.as_path()).unwrap();
+    let labels = wallet
+        .keystores()
+        .iter()
+        .map(|ks| ks.label.as_deref())
+        .collect::<Vec<_>>();
+    assert_eq!(labels, vec![Some("bb2 (27d81095)"), Some("T-Rex")]);
+
+    let imported = ElectrumWalletFile::from_str(&wallet.to_string()).unwrap();
+    assert_eq!(wallet, imported);
+
+    let mut wallet = ElectrumWalletFile::from_descriptor("wpkh([b88448fb/0']tprv8cvkZzx9zA7EfFDbH945mK23r7hg6EHXUk79wVUSRukwyctFS1AdpSpkZcykAMDveCj8RA3R4jwFTKMwMbWexJox8NMqq7YphJLDumfCSfu/0/*)").unwrap();
+    wallet.set_label("Bürokasse \"main\" ✓").unwrap();
+    let imported = ElectrumWalletFile::from_str(&wallet.to_string()).unwrap();
+    assert_eq!(
+        imported.keystores()[0].label.as_deref(),
+        Some("Bürokasse \"main\" ✓")
+    );
+}
+
+#[rstest]
+#[case::default_legacy(
+    "default_legacy",
+    "wallet type: standard
+script kind: standard
+network: testnet
+watch only: false
+encrypted: false
+seed: true
+addresses: 20 receiving, 10 change
+keystore: fingerprint 230b70d2, derivation m, can sign: true
+"
+)]
+#[case::default_legacy_watch(
+    "default_legacy_watch",
+    "wallet type: standard
+script kind: standard
+network: testnet
+watch only: true
+encrypted: false
+seed: false
+addresses: 20 receiving, 10 change
+keystore: fingerprint 230b70d2, derivation m, can sign: false
+"
+)]
+#[case::multisig_hw_segwit(
+    "multisig_hw_segwit",
+    "wallet type: 2of2
+script kind: p2wsh
+network: testnet
+watch only: false
+encrypted: false
+seed: false
+addresses: 20 receiving, 10 change
+x1/: fingerprint 27d81095, derivation m/48'/1'/0'/2', label \"bb2 (27d81095)\", can sign: false
+x2/: fingerprint 6bfac2d6, derivation m/48'/100'/0'/2', label \"T-Rex\", can sign: false
+"
+)]
+#[case::multisig_segwit(
+    "multisig_segwit",
+    "wallet type: 2of2
+script kind: p2wsh
+network: testnet
+watch only: false
+encrypted: false
+seed: true
+addresses: 20 receiving, 10 change
+x1/: fingerprint f6083804, derivation m/1', can sign: true
+x2/: fingerprint b88448fb, derivation m/1', can sign: false
+"
+)]
+fn wallet_summary(#[case] wallet_name: &str, #[case] expected: &str) {
+    let wallet_file = get_test_wallet_file(wallet_name);
+    let wallet = ElectrumWalletFile::from_file(wallet_file.as_path()).unwrap();
+    let summary = wallet.summary().unwrap();
+    assert_eq!(summary.to_string(), expected);
+
+    let json = serde_json::to_string(&summary).unwrap();
+    for keystore in wallet.keystores() {
+        if let Some(xprv) = &keystore.xprv {
+            assert!(!json.contains(xprv));
+        }
+        if let Some(seed) = &keystore.seed {
+            assert!(!json.contains(seed));
+        }
+    }
+}
+
+const BIP32_XPUBS: [&str; 3] = [
+    "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8",
+    "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw",
+    "xpub661MyMwAqRbcFW31YEwpkMuc5THy2PSt5bDMsktWQcFF8syAmRUapSCGu8ED9W6oDMSgv6Zz8idoc4a6mr8BDzTJY47LJhkJ8UB7WEGuduB",
+];
+
+fn to_electrum_xpub(xpub: &str, kind: &str) -> String {
+    ElectrumExtendedPubKey::new(ExtendedPubKey::from_str(xpub).unwrap(), kind.to_string())
+        .electrum_xpub()
+        .unwrap()
+}
+
+#[test]
+fn new_multisig_from_xpubs() {
+    let zpubs = BIP32_XPUBS.map(|xpub| to_electrum_xpub(xpub, "wsh"));
+    let zpubs = zpubs.iter().map(String::as_str).collect::<Vec<_>>();
+    assert!(zpubs.iter().all(|zpub| zpub.starts_with("Zpub")));
+    let wallet = ElectrumWalletFile::new_multisig_from_xpubs(2, &zpubs).unwrap();
+    assert_eq!(wallet.wallet_type(), &WalletType::multisig(2, 3).unwrap());
+
+    let desc = wallet.to_descriptors().unwrap();
+    let expected = format!(
+        "wsh(sortedmulti(2,{}/0/*,{}/0/*,{}/0/*))",
+        BIP32_XPUBS[0], BIP32_XPUBS[1], BIP32_XPUBS[2]
+    );
+    assert_eq!(desc.external, expected);
+
+    let json: serde_json::Value = serde_json::from_str(&wallet.to_string()).unwrap();
+    assert_eq!(json["wallet_type"], "2of3");
+    for (i, zpub) in zpubs.iter().enumerate() {
+        assert_eq!(json[format!("x{}/", i + 1)]["xpub"], *zpub);
+    }
+    assert!(json.get("x4/").is_none());
+    assert!(json.get("keystore").is_none());
+}
+
+#[test]
+fn new_multisig_from_xpubs_errors() {
+    let zpubs = BIP32_XPUBS.map(|xpub| to_electrum_xpub(xpub, "wsh"));
+    let ypub = to_electrum_xpub(BIP32_XPUBS[1], "sh(wsh");
+    let mixed = ElectrumWalletFile::new_multisig_from_xpubs(2, &[&zpubs[0], &ypub]);
+    assert!(matches!(
+        mixed,
+        Err(Electrum2DescriptorError::MixedScriptKinds(_, _))
+    ));
+
+    let plain = ElectrumWalletFile::new_multisig_from_xpubs(2, &BIP32_XPUBS[0..2]);
+    assert!(matches!(
+        plain,
+        Err(Electrum2DescriptorError::AmbiguousScriptKind(_))
+    ));
+
+    let vpub = "Vpub5mUs4UNPA6T3VAmcTWTJ2nCV2oAEFQqBNQQDH62NQNpdAMSyL2Nd3vZXF6uQfNeiCst7asUapZWM9AKmsYCK1BjUrEVhiVm9M4qnbHvDRDe";
+    let networks = ElectrumWalletFile::new_multisig_from_xpubs(2, &[&zpubs[0], vpub]);
+    assert!(matches!(
+        networks,
+        Err(Electrum2DescriptorError::MixedNetworks(_, _))
+    ));
+
+    let threshold = ElectrumWalletFile::new_multisig_from_xpubs(3, &[&zpubs[0], &zpubs[1]]);
+    assert!(matches!(
+        threshold,
+        Err(Electrum2DescriptorError::NumberSignaturesKeyStores(3, 2))
+    ));
+}
+
+#[cfg(unix)]
+#[rstest]
+#[case::xprv("default_legacy", true)]
+#[case::watch_only("default_legacy_watch", false)]
+#[case::lightning("lightning", true)]
+fn to_file_permissions(#[case] wallet_name: &str, #[case] secret: bool) {
+    use std::os::unix::fs::PermissionsExt;
+
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file(wallet_name)).unwrap();
+    assert_eq!(wallet.has_secrets(), secret);
+
+    let tempdir = tempdir().unwrap();
+    let default_file = tempdir.path().join("default");
+    std::fs::File::create(&default_file).unwrap();
+    let default_mode = std::fs::metadata(&default_file)
+        .unwrap()
+        .permissions()
+        .mode();
+
+    let filename = tempdir.path().join(wallet_name);
+    let options = WriteOptions {
+        skip_sync: true,
+        ..WriteOptions::default()
+    };
+    wallet.to_file_with_options(&filename, &options).unwrap();
+    let mode = std::fs::metadata(&filename).unwrap().permissions().mode();
+    if secret {
+        assert_eq!(mode & 0o777, 0o600);
+    } else {
+        assert_eq!(mode, default_mode);
+    }
+    assert_eq!(ElectrumWalletFile::from_file(&filename).unwrap(), wallet);
+    assert_eq!(std::fs::read_dir(tempdir.path()).unwrap().count(), 2);
+}
+
+#[rstest]
+#[case::new_file(false, false, false)]
+#[case::new_file_overwrite(false, true, false)]
+#[case::exists(true, false, false)]
+#[case::exists_overwrite(true, true, false)]
+#[case::exists_overwrite_no_backup(true, true, true)]
+fn to_file_existing(#[case] exists: bool, #[case] overwrite: bool, #[case] no_backup: bool) {
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file("default_legacy")).unwrap();
+    let tempdir = tempdir().unwrap();
+    let filename = tempdir.path().join("wallet");
+    let backup = tempdir.path().join("wallet.bak");
+    if exists {
+        std::fs::write(&filename, "previous").unwrap();
+    }
+
+    let options = WriteOptions {
+        overwrite,
+        no_backup,
+        skip_sync: true,
+        ..WriteOptions::default()
+    };
+    let written = wallet.to_file_with_options(&filename, &options);
+    if exists && !overwrite {
+        assert!(matches!(
+            written,
+            Err(Electrum2DescriptorError::AlreadyExists(_))
+        ));
+        assert_eq!(std::fs::read_to_string(&filename).unwrap(), "previous");
+    } else {
+        written.unwrap();
+        assert_eq!(ElectrumWalletFile::from_file(&filename).unwrap(), wallet);
+    }
+    if exists && overwrite && !no_backup {
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), "previous");
+    } else {
+        assert!(!backup.exists());
+    }
+}
+
+#[test]
+fn to_file_concurrent() {
+    // every writer has its own temporary file, exactly one creates the target
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file("default_legacy")).unwrap();
+    let tempdir = tempdir().unwrap();
+    let filename = tempdir.path().join("wallet");
+    let options = WriteOptions {
+        skip_sync: true,
+        ..WriteOptions::default()
+    };
+    let written = std::thread::scope(|scope| {
+        let writers = (0..8)
+            .map(|_| scope.spawn(|| wallet.to_file_with_options(&filename, &options)))
+            .collect::<Vec<_>>();
+        writers
+            .into_iter()
+            .map(|writer| writer.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    assert_eq!(written.iter().filter(|written| written.is_ok()).count(), 1);
+    assert!(written.iter().all(|written| matches!(
+        written,
+        Ok(()) | Err(Electrum2DescriptorError::AlreadyExists(_))
+    )));
+    assert_eq!(ElectrumWalletFile::from_file(&filename).unwrap(), wallet);
+    assert_eq!(std::fs::read_dir(tempdir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn to_file_overwrite() {
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file("default_legacy")).unwrap();
+    let tempdir = tempdir().unwrap();
+    let filename = tempdir.path().join("wallet");
+    wallet.to_file(&filename).unwrap();
+    assert!(wallet.to_file(&filename).is_err());
+    wallet.to_file_overwrite(&filename).unwrap();
+    assert_eq!(
+        ElectrumWalletFile::from_file(&tempdir.path().join("wallet.bak")).unwrap(),
+        wallet
+    );
+
+    // a later write keeps the earlier backups
+    std::fs::write(&filename, "edited").unwrap();
+    wallet.to_file_overwrite(&filename).unwrap();
+    assert_eq!(
+        ElectrumWalletFile::from_file(&tempdir.path().join("wallet.bak")).unwrap(),
+        wallet
+    );
+    assert_eq!(
+        std::fs::read_to_string(tempdir.path().join("wallet.bak.1")).unwrap(),
+        "edited"
+    );
+    assert_eq!(std::fs::read_dir(tempdir.path()).unwrap().count(), 3);
+}
+
+#[rstest]
+#[case::default_legacy("default_legacy", Some("standard"), false)]
+#[case::default_segwit("default_segwit", Some("standard"), false)]
+#[case::imported_addr("imported_addr", Some("imported"), false)]
+#[case::imported_privkey("imported_privkey", Some("imported"), false)]
+#[case::multisig_hw_segwit("multisig_hw_segwit", Some("2of2"), false)]
+#[case::multisig_wrapped_watch("multisig_wrapped_watch", Some("3of7"), false)]
+#[case::lightning("lightning", Some("standard"), false)]
+#[case::encrypted("encrypted", None, true)]
+fn peek(#[case] wallet_name: &str, #[case] wallet_type: Option<&str>, #[case] encrypted: bool) {
+    let peeked = ElectrumWalletFile::peek(&get_test_wallet_file(wallet_name)).unwrap();
+    assert_eq!(peeked.wallet_type.as_deref(), wallet_type);
+    assert_eq!(peeked.encrypted, encrypted);
+}
+
+#[test]
+fn peek_invalid() {
+    let tempdir = tempdir().unwrap();
+    let filename = tempdir.path().join("wallet");
+    std::fs::write(&filename, "not a wallet").unwrap();
+    assert!(ElectrumWalletFile::peek(&filename).is_err());
+    std::fs::write(&filename, "{\"seed_version\": 18}").unwrap();
+    assert_eq!(
+        ElectrumWalletFile::peek(&filename).unwrap().wallet_type,
+        None
+    );
+}
+
+#[rstest]
+#[case::encrypted_password("encrypted_password", true)]
+#[case::encrypted_secrets("encrypted_secrets", true)]
+#[case::default_segwit("default_segwit", false)]
+fn from_file_with_password(#[case] wallet_name: &str, #[case] encrypted: bool) {
+    // encrypted with "password" the way electrum does, by a python script independent of the crate
+    let expected = ElectrumWalletFile::from_file(&get_test_wallet_file("default_segwit")).unwrap();
+    let path = get_test_wallet_file(wallet_name);
+    let options = ParseOptions::default();
+    let wallet = ElectrumWalletFile::from_file_with_password(&path, "password", &options).unwrap();
+    assert_eq!(wallet, expected);
+    assert!(wallet.can_sign());
+    assert_eq!(wallet.raw_field("use_encryption"), Some(&false.into()));
+
+    let wrong = ElectrumWalletFile::from_file_with_password(&path, "wrong", &options);
+    if encrypted {
+        assert!(matches!(
+            wrong,
+            Err(Electrum2DescriptorError::InvalidPassword)
+        ));
+    } else {
+        assert_eq!(wrong.unwrap(), expected);
+    }
+}
+
+#[test]
+fn decrypt_secrets() {
+    let path = get_test_wallet_file("encrypted_secrets");
+    let mut wallet = ElectrumWalletFile::from_file(&path).unwrap();
+    assert!(!wallet.can_sign());
+    let encrypted = wallet.clone();
+    assert!(matches!(
+        wallet.decrypt_secrets("wrong"),
+        Err(Electrum2DescriptorError::InvalidPassword)
+    ));
+    assert_eq!(wallet, encrypted);
+    wallet.decrypt_secrets("password").unwrap();
+    assert_eq!(
+        wallet,
+        ElectrumWalletFile::from_file(&get_test_wallet_file("default_segwit")).unwrap()
+    );
+
+    // parsed watch-only, there are no secrets to decrypt
+    let options = ParseOptions {
+        watch_only: true,
+        ..ParseOptions::default()
+    };
+    let wallet = ElectrumWalletFile::from_file_with_password(&path, "wrong", &options).unwrap();
+    assert!(!wallet.can_sign());
+
+    // the encrypted wallet only looks like one, its ephemeral key isn't a point of the curve
+    assert!(matches!(
+        ElectrumWalletFile::from_file_with_password(
+            &get_test_wallet_file("encrypted"),
+            "password",
+            &options
+        ),
+        Err(Electrum2DescriptorError::InvalidEncryptedData(_))
+    ));
+}
+
+#[rstest]
+#[case::nested(
+    r#"{"a": {"wallet_type": "2of3"}, "wallet_type": "standard"}"#,
+    Ok(Some("standard"))
+)]
+#[case::in_value(
+    r#"{"a": "wallet_type", "b": ["wallet_type"], "wallet_type": "standard"}"#,
+    Ok(Some("standard"))
+)]
+#[case::escaped(r#"{"a\"": "\"{[", "wallet_ty\u0070e": "1of2"}"#, Ok(Some("1of2")))]
+#[case::missing(r#"{"a": [{"b": 1}], "seed_version": 18}"#, Ok(None))]
+#[case::not_a_string(r#"{"wallet_type": 3}"#, Err(()))]
+#[case::array(r#"["wallet_type", "standard"]"#, Err(()))]
+#[case::truncated(r#"{"transactions": {"00": "0200"#, Err(()))]
+fn peek_skims(#[case] json: &str, #[case] expected: Result<Option<&str>, ()>) {
+    let tempdir = tempdir().unwrap();
+    let filename = tempdir.path().join("wallet");
+    std::fs::write(&filename, json).unwrap();
+    match (ElectrumWalletFile::peek(&filename), expected) {
+        (Ok(peeked), Ok(wallet_type)) => assert_eq!(peeked.wallet_type.as_deref(), wallet_type),
+        (Err(Electrum2DescriptorError::Serde(_)), Err(())) => {}
+        (peeked, _) => panic!("{:?}", peeked),
+    }
+}
+
+#[test]
+fn convert_dir() {
+    let tempdir = tempdir().unwrap();
+    let wallets = [
+        "default_segwit",
+        "encrypted",
+        "imported_addr",
+        "multisig_legacy",
+    ];
+    for wallet in wallets {
+        std::fs::copy(get_test_wallet_file(wallet), tempdir.path().join(wallet)).unwrap();
+    }
+    std::fs::create_dir(tempdir.path().join("nested")).unwrap();
+    std::fs::copy(
+        get_test_wallet_file("default_legacy"),
+        tempdir.path().join("nested").join("default_legacy"),
+    )
+    .unwrap();
+    std::fs::write(tempdir.path().join("notes.txt"), "not a wallet").unwrap();
+    std::fs::write(tempdir.path().join("config.json"), "{}").unwrap();
+    std::fs::write(tempdir.path().join(".default_segwit.tmp"), "{").unwrap();
+
+    let converted =
+        libelectrum2descriptors::convert_dir(tempdir.path(), &Default::default()).unwrap();
+    let names = converted
+        .iter()
+        .map(|(path, _)| path.strip_prefix(tempdir.path()).unwrap().to_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        vec![
+            "default_segwit",
+            "encrypted",
+            "imported_addr",
+            "multisig_legacy",
+            "nested/default_legacy"
+        ]
+    );
+    assert_eq!(
+        converted[0].1.as_ref().unwrap(),
+        &wallet_name_to_descriptors("default_segwit")
+    );
+    assert!(matches!(
+        converted[1].1,
+        Err(Electrum2DescriptorError::EncryptedWallet)
+    ));
+    assert!(converted[2].1.is_err());
+    assert_eq!(
+        converted[3].1.as_ref().unwrap(),
+        &wallet_name_to_descriptors("multisig_legacy")
+    );
+    assert_eq!(
+        converted[4].1.as_ref().unwrap(),
+        &wallet_name_to_descriptors("default_legacy")
+    );
+}
+
+#[rstest]
+#[case::default_legacy("default_legacy")]
+#[case::default_segwit("default_segwit")]
+#[case::multisig_hw_segwit("multisig_hw_segwit")]
+#[case::multisig_legacy("multisig_legacy")]
+#[case::multisig_segwit("multisig_segwit")]
+#[case::multisig_wrapped_watch("multisig_wrapped_watch")]
+#[case::multisig_wrapped("multisig_wrapped")]
+fn derive_addresses(#[case] wallet_name: &str) {
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file(wallet_name)).unwrap();
+    for (change, addresses) in [
+        (false, &wallet.addresses().receiving),
+        (true, &wallet.addresses().change),
+    ] {
+        assert!(!addresses.is_empty());
+        for (index, address) in addresses.iter().enumerate() {
+            assert_eq!(
+                &wallet.address(change, index as u32).unwrap().to_string(),
+                address
+            );
+        }
+    }
+}
+
+const HW_SEGWIT_XPUBS: [&str; 2] = [
+    "tpubDEcw4ooTbmw62zBKdkYepoP3z4WWugdeRzPHHAbk8XVsPfBE9AAZMNghiqwtdFgtabaeppBTPmezUkRkQZidLcSJp3XTASbMakHcYauWehZ",
+    "tpubDEbkvhmJoZMq3SUNqEf3aEsubvqsCUPc7rroHkGERgS7qA1gQVMxUPrgzth6x43odirLohwf4aMHpvcnWi3jCB2xkizv8T4B2KqLRZVLC6K",
+];
+
+#[test]
+fn diff() {
+    let wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_hw_segwit")).unwrap();
+    let [a, b] = HW_SEGWIT_XPUBS;
+    let swapped_order =
+        ElectrumWalletFile::from_descriptor(&format!("wsh(sortedmulti(2,{}/0/*,{}/0/*))", b, a))
+            .unwrap();
+    assert!(wallet.is_equivalent(&swapped_order).unwrap());
+
+    let threshold =
+        ElectrumWalletFile::from_descriptor(&format!("wsh(sortedmulti(1,{}/0/*,{}/0/*))", a, b))
+            .unwrap();
+    let differences = wallet.diff(&threshold).unwrap();
+    assert_eq!(
+        differences[0],
+        WalletDifference::WalletType(
+            WalletType::multisig(2, 2).unwrap(),
+            WalletType::multisig(1, 2).unwrap()
+        )
+    );
+    assert!(matches!(
+        differences[1],
+        WalletDifference::FirstAddress(_, _)
+    ));
+    assert_eq!(differences.len(), 2);
+
+    let other = BIP32_XPUBS[0];
+    let replaced = ElectrumWalletFile::from_descriptor(&format!(
+        "sh(wsh(sortedmulti(2,{}/0/*,{}/0/*)))",
+        a, other
+    ))
+    .unwrap();
+    let differences = wallet.diff(&replaced).unwrap();
+    assert_eq!(
+        differences[..3],
+        [
+            WalletDifference::ScriptKind("wsh".to_string(), "sh(wsh".to_string()),
+            WalletDifference::MissingCosigner(b.to_string()),
+            WalletDifference::ExtraCosigner(other.to_string()),
+        ]
+    );
+    assert_eq!(differences[1].to_string(), format!("- cosigner {}", b));
+}
+
+#[test]
+fn conversion_warnings() {
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file("hot_history")).unwrap();
+    let mut warnings = Vec::new();
+    let descriptors = wallet.to_descriptors_with_warnings(&mut warnings).unwrap();
+    assert_eq!(descriptors, wallet_name_to_descriptors("default_segwit"));
+    let dropped = |name: &str, entries| ConversionWarning::DroppedField(name.to_string(), entries);
+    assert_eq!(
+        warnings,
+        vec![
+            ConversionWarning::SeedNotRepresentableInDescriptor,
+            dropped("addr_history", 2),
+            dropped("labels", 3),
+            dropped("lightning_privkey2", 1),
+            dropped("transactions", 2),
+            dropped("tx_fees", 1),
+            dropped("verified_tx3", 2),
+        ]
+    );
+
+    let wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_wrapped_watch")).unwrap();
+    let mut warnings = Vec::new();
+    wallet.to_descriptors_with_warnings(&mut warnings).unwrap();
+    assert_eq!(warnings, vec![]);
+}
+
+#[rstest]
+#[case::xpub("pkh", ScriptKind::Standard, Network::Bitcoin)]
+#[case::ypub("sh(wpkh", ScriptKind::P2wpkhP2sh, Network::Bitcoin)]
+#[case::ypub_multisig("sh(wsh", ScriptKind::P2wshP2sh, Network::Bitcoin)]
+#[case::zpub("wpkh", ScriptKind::P2wpkh, Network::Bitcoin)]
+#[case::zpub_multisig("wsh", ScriptKind::P2wsh, Network::Bitcoin)]
+#[case::tpub("pkh", ScriptKind::Standard, Network::Testnet)]
+#[case::upub("sh(wpkh", ScriptKind::P2wpkhP2sh, Network::Testnet)]
+#[case::upub_multisig("sh(wsh", ScriptKind::P2wshP2sh, Network::Testnet)]
+#[case::vpub("wpkh", ScriptKind::P2wpkh, Network::Testnet)]
+#[case::vpub_multisig("wsh", ScriptKind::P2wsh, Network::Testnet)]
+fn keystore_xpub(#[case] kind: &str, #[case] script_kind: ScriptKind, #[case] network: Network) {
+    let mut xpub = ExtendedPubKey::from_str(BIP32_XPUBS[1]).unwrap();
+    xpub.network = network;
+    let electrum_xpub = to_electrum_xpub(&xpub.to_string(), kind);
+    let keystore = Keystore::from_electrum_xkey(&electrum_xpub).unwrap();
+    assert_eq!(keystore.electrum_xpub(), electrum_xpub);
+    assert_eq!(keystore.xpub().unwrap(), (xpub, script_kind, network));
+    assert_eq!(keystore.xprv().unwrap(), None);
+}
+
+#[test]
+fn keystore_xprv() {
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file("default_segwit")).unwrap();
+    let keystore = &wallet.keystores()[0];
+    let xprv = keystore.xprv().unwrap().unwrap();
+    let (xpub, kind, network) = keystore.xpub().unwrap();
+    let secp = bdk::bitcoin::secp256k1::Secp256k1::new();
+    assert_eq!(ExtendedPubKey::from_priv(&secp, &xprv), xpub);
+    assert_eq!(kind, ScriptKind::P2wpkh);
+    assert_eq!(network, Network::Testnet);
+
+    let mut encrypted = keystore.clone();
+    encrypted.xprv = Some("c2VjcmV0".to_string());
+    let err = encrypted.xprv().unwrap_err();
+    assert!(matches!(
+        err,
+        Electrum2DescriptorError::InvalidKeystoreField("xprv", _)
+    ));
+    assert!(err.to_string().starts_with("Invalid keystore xprv: "));
+    encrypted.xpub = "c2VjcmV0".to_string();
+    assert!(encrypted
+        .xpub()
+        .unwrap_err()
+        .to_string()
+        .starts_with("Invalid keystore xpub: "));
+}
+
+#[test]
+fn keystore_identity() {
+    let zpub = Keystore::from_electrum_xkey(&to_electrum_xpub(BIP32_XPUBS[1], "wpkh")).unwrap();
+    let xpub = Keystore::from_electrum_xkey(BIP32_XPUBS[1])
+        .unwrap()
+        .with_label("same key");
+    assert_ne!(zpub.xpub, xpub.xpub);
+    assert!(zpub.same_key(&xpub));
+    assert_eq!(zpub.key_id(), xpub.key_id());
+    assert_eq!(
+        zpub.key_id().as_bytes(),
+        ExtendedPubKey::from_str(BIP32_XPUBS[1]).unwrap().encode()
+    );
+
+    let other = Keystore::from_electrum_xkey(BIP32_XPUBS[0]).unwrap();
+    assert!(!zpub.same_key(&other));
+    let unique = [&zpub, &xpub, &other]
+        .iter()
+        .map(|ks| ks.key_id())
+        .collect::<std::collections::HashSet<_>>();
+    assert_eq!(unique.len(), 2);
+    assert!(unique.contains(&xpub.key_id()));
+
+    // the later keystore of the same key replaces the earlier one
+    let by_key = vec![&zpub, &other, &xpub]
+        .into_iter()
+        .map(|ks| (ks.key_id(), ks))
+        .collect::<std::collections::BTreeMap<_, _>>();
+    assert_eq!(by_key.len(), 2);
+    assert_eq!(by_key[&zpub.key_id()], &xpub);
+
+    let mut sorted = vec![zpub.clone(), other.clone()];
+    sorted.sort_by_key(Keystore::key_id);
+    let mut reversed = vec![other.clone(), zpub.clone()];
+    reversed.sort_by_key(Keystore::key_id);
+    assert_eq!(sorted, reversed);
+    assert_eq!(
+        by_key.values().map(|ks| ks.key_id()).collect::<Vec<_>>(),
+        sorted.iter().map(Keystore::key_id).collect::<Vec<_>>()
+    );
+}
+
+#[rstest]
+#[case::hot_history("hot_history")]
+#[case::multisig_segwit("multisig_segwit")]
+#[case::lightning("lightning")]
+fn parse_watch_only(#[case] wallet_name: &str) {
+    let wallet_file = get_test_wallet_file(wallet_name);
+    let full = ElectrumWalletFile::from_file(&wallet_file).unwrap();
+    assert!(full.has_secrets());
+
+    let wallet = ElectrumWalletFile::from_file_watch_only(&wallet_file).unwrap();
+    assert!(wallet.is_watch_only_copy());
+    assert!(!wallet.has_secrets());
+    for keystore in wallet.keystores() {
+        assert_eq!(keystore.xprv, None);
+        assert_eq!(keystore.seed, None);
+    }
+    assert!(wallet.summary().unwrap().watch_only);
+    let json = wallet.to_string();
+    assert!(!json.contains("tprv"), "{}", json);
+    assert!(!json.contains("\"seed\""), "{}", json);
+    for field in SECRET_FIELDS {
+        assert!(wallet.extra().get(*field).is_none());
+    }
+
+    let descriptors = wallet.to_descriptors().unwrap();
+    assert!(!descriptors.external.contains("prv"));
+    for (full, watch_only) in full.keystores().iter().zip(wallet.keystores()) {
+        assert_eq!(full.xpub, watch_only.xpub);
+    }
+
+    let tempdir = tempdir().unwrap();
+    let filename = tempdir.path().join("wallet");
+    wallet.to_file(&filename).unwrap();
+    assert!(matches!(
+        wallet.to_file_overwrite(&filename),
+        Err(Electrum2DescriptorError::WatchOnlyOverwrite(_))
+    ));
+}
+
+#[test]
+fn recover_truncated_wallet() {
+    let path = Path::new("tests/wallets/multisig_segwit");
+    let content = std::fs::read_to_string(path).unwrap();
+    let wallet = ElectrumWalletFile::from_file(path).unwrap();
+    let x1 = &wallet.keystores()[0];
+    // cut in the middle of the xpub of x2/
+    let x2_xpub = content.find(&wallet.keystores()[1].xpub).unwrap();
+    let truncated = &content[..x2_xpub + 60];
+
+    let found = recover::scan_text(truncated);
+    let keys = found.iter().map(FoundKey::as_str).collect::<Vec<_>>();
+    assert_eq!(keys, vec![x1.xprv.as_deref().unwrap(), x1.xpub.as_str()]);
+    assert_eq!(found[0].key_type, FoundKeyType::ExtendedPrivKey);
+    assert_eq!(found[1].key_type, FoundKeyType::ExtendedPubKey);
+    assert_eq!(found[1].script_kind, Some(ScriptKind::P2wsh));
+    assert_eq!(&truncated[found[1].offset..][..x1.xpub.len()], x1.xpub);
+    assert!(found[1].to_wallet().is_none());
+    assert!(found[1].to_descriptors().is_some());
+}
+
+#[test]
+fn recover_singlesig_wallet() {
+    let path = Path::new("tests/wallets/default_segwit");
+    let watch_only = ElectrumWalletFile::from_file_watch_only(path).unwrap();
+    let text = format!(
+        "pasted from my notes: {} ...",
+        watch_only.keystores()[0].xpub
+    );
+    let found = recover::scan_text(&text);
+    assert_eq!(found.len(), 1);
+    assert_eq!(
+        found[0]
+            .to_wallet()
+            .unwrap()
+            .to_descriptors()
+            .unwrap()
+            .external,
+        watch_only
+            .to_descriptors()
+            .unwrap()
+            .external
+            .replace("[b88448fb/0']", "")
+    );
+}
+
+#[test]
+fn parse_partial() {
+    let path = Path::new("tests/wallets/garbled_xpub");
+    // the xpub is only checked when converting
+    let wallet = ElectrumWalletFile::from_file(path).unwrap();
+    assert!(wallet.to_descriptors().is_err());
+
+    let options = ParseOptions {
+        partial: true,
+        ..ParseOptions::default()
+    };
+    let wallet = ElectrumWalletFile::from_file_with_options(path, &options).unwrap();
+    assert!(wallet.is_incomplete());
+    assert_eq!(wallet.keystore_errors().len(), 1);
+    assert_eq!(wallet.wallet_type(), &WalletType::Standard);
+    assert!(matches!(
+        wallet.to_descriptors(),
+        Err(Electrum2DescriptorError::IncompleteWallet(_))
+    ));
+    let tempdir = tempdir().unwrap();
+    assert!(wallet.to_file(&tempdir.path().join("wallet")).is_err());
+
+    let addrs = wallet.addr_descriptors();
+    assert_eq!(
+        addrs.len(),
+        wallet.addresses().receiving.len() + wallet.addresses().change.len()
+    );
+    assert_eq!(addrs[0], "addr(muVsjryQ7q1YvQ9Ji7MqrxNAnSSMccDYeS)");
+
+    let intact = Path::new("tests/wallets/default_legacy_watch");
+    let wallet = ElectrumWalletFile::from_file_with_options(intact, &options).unwrap();
+    assert!(!wallet.is_incomplete());
+    assert!(wallet.to_descriptors().is_ok());
+}
+
+#[rstest]
+#[case::default_legacy("default_legacy")]
+#[case::default_segwit("default_segwit")]
+#[case::lightning("lightning")]
+#[case::multisig_legacy("multisig_legacy")]
+#[case::multisig_segwit("multisig_segwit")]
+#[case::coldcard_ckcc_xfp("coldcard_ckcc_xfp")]
+fn to_redacted_json(#[case] wallet_name: &str) {
+    let path = Path::new("tests/wallets").join(wallet_name);
+    let wallet = ElectrumWalletFile::from_file(&path).unwrap();
+    let redacted = wallet.to_redacted_json().unwrap();
+    assert_eq!(redacted, wallet.to_redacted_json().unwrap());
+
+    let original = wallet.to_string();
+    let mut secrets = Vec::new();
+    for keystore in wallet.keystores() {
+        secrets.push(keystore.xpub.clone());
+        secrets.extend(keystore.xprv.clone());
+        secrets.extend(keystore.seed.clone());
+        secrets.extend(keystore.root_fingerprint.as_deref().map(String::from));
+        // the key material of the fields we don't model, e.g. ckcc_xpub and ckcc_xfp of coldcards
+        for (field, value) in &keystore.extra {
+            match value {
+                serde_json::Value::String(xkey) if ExtendedPubKey::from_str(xkey).is_ok() => {
+                    secrets.push(xkey.clone())
+                }
+                value if field == "ckcc_xfp" => secrets.push(value.to_string()),
+                _ => (),
+            }
+        }
+    }
+    secrets.extend(SECRET_FIELDS.iter().filter_map(|field| {
+        wallet
+            .extra()
+            .get(*field)
+            .and_then(|v| v.as_str())
+            .map(String::from)
+    }));
+    secrets.extend(wallet.addresses().receiving.iter().cloned());
+    secrets.extend(wallet.addresses().change.iter().cloned());
+    for secret in secrets {
+        assert!(original.contains(&secret));
+        assert!(!redacted.contains(&secret), "{} survived", secret);
+    }
+
+    let parsed = ElectrumWalletFile::from_str(&redacted).unwrap();
+    assert_eq!(parsed.wallet_type(), wallet.wallet_type());
+    assert_eq!(
+        parsed.extra().keys().collect::<Vec<_>>(),
+        wallet.extra().keys().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        parsed.addresses().receiving.len(),
+        wallet.addresses().receiving.len()
+    );
+    for (redacted, keystore) in parsed.keystores().iter().zip(wallet.keystores()) {
+        assert_eq!(redacted.xpub[..4], keystore.xpub[..4]);
+        assert_eq!(redacted.xprv.is_some(), keystore.xprv.is_some());
+        assert_eq!(redacted.derivation, keystore.derivation);
+        assert_eq!(
+            redacted.extra.keys().collect::<Vec<_>>(),
+            keystore.extra.keys().collect::<Vec<_>>()
+        );
+        if let Some(xfp) = redacted.extra.get("ckcc_xfp") {
+            let xfp = xfp.as_u64().unwrap() as u32;
+            assert_eq!(
+                redacted.fingerprint(),
+                Some(Fingerprint::from(xfp.to_le_bytes()))
+            );
+        }
+    }
+    assert!(parsed.to_descriptors().is_ok());
+}
+
+#[test]
+fn to_redacted_json_lightning() {
+    fn strings<'a>(value: &'a serde_json::Value, found: &mut Vec<&'a str>) {
+        match value {
+            serde_json::Value::String(s) => found.push(s),
+            serde_json::Value::Array(values) => values.iter().for_each(|v| strings(v, found)),
+            serde_json::Value::Object(map) => map.values().for_each(|v| strings(v, found)),
+            _ => (),
+        }
+    }
+
+    let wallet = ElectrumWalletFile::from_file(Path::new("tests/wallets/lightning")).unwrap();
+    assert!(wallet.has_secrets());
+    let redacted = wallet.to_redacted_json().unwrap();
+    let mut secrets = Vec::new();
+    for field in SECRET_FIELDS {
+        if let Some(value) = wallet.extra().get(*field) {
+            strings(value, &mut secrets);
+        }
+    }
+    assert!(secrets.len() > 5, "{:?}", secrets);
+    for secret in secrets {
+        assert!(!redacted.contains(secret), "{} survived", secret);
+    }
+    let parsed: serde_json::Value = serde_json::from_str(&redacted).unwrap();
+    let channels = parsed["channels"].as_object().unwrap();
+    let channel = channels.values().next().unwrap();
+    assert_eq!(channel["constraints"]["capacity"], 200000);
+}
+
+#[test]
+fn to_redacted_json_keeps_relationships() {
+    // the second cosigner of the multisig wallet is the key of the standard wallet
+    let multisig =
+        ElectrumWalletFile::from_file(Path::new("tests/wallets/multisig_legacy")).unwrap();
+    let standard =
+        ElectrumWalletFile::from_file(Path::new("tests/wallets/default_legacy_watch")).unwrap();
+    assert_eq!(multisig.keystores()[1].xpub, standard.keystores()[0].xpub);
+    let multisig = ElectrumWalletFile::from_str(&multisig.to_redacted_json().unwrap()).unwrap();
+    let standard = ElectrumWalletFile::from_str(&standard.to_redacted_json().unwrap()).unwrap();
+    assert_eq!(multisig.keystores()[1].xpub, standard.keystores()[0].xpub);
+}
+
+#[test]
+fn assume_network() {
+    // a mainnet key exported with the testnet prefix
+    let path = Path::new("tests/wallets/default_legacy_watch");
+    let mut wallet = ElectrumWalletFile::from_file(path).unwrap();
+    let tpub = wallet.keystores()[0].xpub.clone();
+    let warnings = wallet.assume_network(Network::Bitcoin).unwrap();
+    assert_eq!(
+        warnings,
+        vec![ConversionWarning::NetworkAssumed(
+            tpub.clone(),
+            Network::Testnet,
+            Network::Bitcoin
+        )]
+    );
+    assert!(wallet.keystores()[0].xpub.starts_with("xpub"));
+    assert!(wallet.assume_network(Network::Bitcoin).unwrap().is_empty());
+
+    let mainnet = ExtendedPubKey {
+        network: Network::Bitcoin,
+        ..ExtendedPubKey::from_str(&tpub).unwrap()
+    };
+    let expected =
+        first_address_from_descriptor(&format!("pkh({}/0/*)", mainnet), Network::Bitcoin);
+    assert_eq!(wallet.addresses().receiving[0], expected);
+    assert_eq!(wallet.address(false, 0).unwrap().to_string(), expected);
+    assert_eq!(
+        wallet.to_descriptors().unwrap().external,
+        format!("pkh({}/0/*)", mainnet)
+    );
+}
+
+#[rstest]
+#[case::default_legacy("default_legacy")]
+#[case::default_segwit("default_segwit")]
+#[case::multisig_hw_segwit("multisig_hw_segwit")]
+#[case::multisig_legacy("multisig_legacy")]
+#[case::multisig_segwit("multisig_segwit")]
+#[case::multisig_wrapped_watch("multisig_wrapped_watch")]
+#[case::multisig_wrapped("multisig_wrapped")]
+fn descriptor_at(#[case] wallet_name: &str) {
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file(wallet_name)).unwrap();
+    for change in [false, true] {
+        let descriptors = wallet.descriptors_at(change, 6..8).unwrap();
+        assert_eq!(descriptors.len(), 2);
+        assert_eq!(descriptors[1], wallet.descriptor_at(change, 7).unwrap());
+        assert!(!descriptors[1].contains('*'));
+        assert!(descriptors[1].contains(&format!("/{}/7]", change as u32)));
+        assert_eq!(
+            first_address_from_descriptor(&descriptors[1], Network::Testnet),
+            wallet.address(change, 7).unwrap().to_string()
+        );
+    }
+}
+
+#[test]
+fn taproot() {
+    // bip-0086 test vectors
+    let xpub = "xpub6BgBgsespWvERF3LHQu6CnqdvfEvtMcQjYrcRzx53QJjSxarj2afYWcLteoGVky7D3UKDP9QyrLprQ3VCECoY49yfdDEHGCtMMj92pReUsQ";
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file("taproot")).unwrap();
+    assert_eq!(wallet.keystores()[0].xpub().unwrap().1, ScriptKind::P2tr);
+    assert_eq!(
+        wallet.to_descriptors().unwrap(),
+        Descriptors {
+            external: format!("tr([73c5da0a/86'/0'/0']{}/0/*)", xpub),
+            change: format!("tr([73c5da0a/86'/0'/0']{}/1/*)", xpub),
+        }
+    );
+    assert_eq!(
+        wallet.address(false, 0).unwrap().to_string(),
+        "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+    );
+    assert_eq!(
+        wallet.address(true, 0).unwrap().to_string(),
+        "bc1p3qkhfews2uk44qtvauqyr2ttdsw7svhkl9nkm9s9c3x4ax5h60wqwruhk7"
+    );
+    assert_eq!(
+        first_address_from_descriptor(&wallet.to_descriptors().unwrap().external, Network::Bitcoin),
+        wallet.addresses().receiving[0]
+    );
+    assert!(wallet
+        .descriptor_at(false, 0)
+        .unwrap()
+        .starts_with("tr([73c5da0a/86'/0'/0'/0/0]xpub"));
+
+    // marked by a field instead of the derivation
+    let json = wallet.to_string().replace("m/86'/0'/0'", "m/0'").replace(
+        "\"type\": \"bip32\"",
+        "\"script_type\": \"p2tr\",\n\"type\": \"bip32\"",
+    );
+    let marked = ElectrumWalletFile::from_str(&json).unwrap();
+    assert!(marked.keystores()[0].is_taproot());
+    // the derivation isn't as deep as the key, so it isn't written as origin
+    assert_eq!(
+        marked.to_descriptors().unwrap().external,
+        format!("tr({}/0/*)", xpub)
+    );
+    assert!(marked.to_string().contains("\"script_type\": \"p2tr\""));
+
+    let mut keystore = wallet.keystores()[0].clone();
+    keystore.derivation = "m/86'/0'/1'".into();
+    let multisig = ElectrumWalletFile::new(&[wallet.keystores()[0].clone(), keystore], 2).unwrap();
+    assert!(matches!(
+        multisig.to_descriptors(),
+        Err(Electrum2DescriptorError::TaprootMultisig)
+    ));
+    assert!(matches!(
+        multisig.address(false, 0),
+        Err(Electrum2DescriptorError::TaprootMultisig)
+    ));
+}
+
+#[rstest]
+#[case::default_segwit("default_segwit")]
+#[case::multisig_segwit("multisig_segwit")]
+fn extend_addresses(#[case] wallet_name: &str) {
+    let original = ElectrumWalletFile::from_file(&get_test_wallet_file(wallet_name)).unwrap();
+    let mut json = serde_json::to_value(&original).unwrap();
+    for chain in ["receiving", "change"] {
+        json["addresses"][chain].as_array_mut().unwrap().truncate(5);
+    }
+    let mut wallet: ElectrumWalletFile = serde_json::from_value(json.clone()).unwrap();
+
+    assert_eq!(wallet.extend_addresses(25, 25).unwrap(), (20, 20));
+    assert_eq!(wallet.extend_addresses(25, 25).unwrap(), (0, 0));
+    let addresses = wallet.addresses();
+    assert_eq!(addresses.receiving.len(), 25);
+    assert_eq!(addresses.change.len(), 25);
+    for (stored, extended) in [
+        (&original.addresses().receiving, &addresses.receiving),
+        (&original.addresses().change, &addresses.change),
+    ] {
+        assert_eq!(stored[..], extended[..stored.len()]);
+    }
+    for i in 0..25 {
+        assert_eq!(
+            addresses.receiving[i],
+            wallet.address(false, i as u32).unwrap().to_string()
+        );
+        assert_eq!(
+            addresses.change[i],
+            wallet.address(true, i as u32).unwrap().to_string()
+        );
+    }
+
+    json["addresses"]["receiving"]
+        .as_array_mut()
+        .unwrap()
+        .swap(0, 1);
+    let mut mismatched: ElectrumWalletFile = serde_json::from_value(json).unwrap();
+    assert!(matches!(
+        mismatched.extend_addresses(25, 25),
+        Err(Electrum2DescriptorError::AddressMismatch(0, _, _))
+    ));
+    assert_eq!(mismatched.addresses().receiving.len(), 5);
+}
+
+#[rstest]
+#[case::legacy("default_legacy")]
+#[case::segwit("default_segwit")]
+#[case::multisig_legacy("multisig_legacy")]
+#[case::multisig_segwit("multisig_hw_segwit")]
+#[case::multisig_wrapped("multisig_wrapped")]
+fn fill_addresses(#[case] wallet_name: &str) {
+    let file = ElectrumWalletFile::from_file(&get_test_wallet_file(wallet_name)).unwrap();
+    let descriptors = file.to_descriptors().unwrap();
+    let mut wallet = ElectrumWalletFile::from_descriptor(&descriptors.external).unwrap();
+    assert!(wallet.addresses().receiving.is_empty());
+    wallet.fill_addresses(5, Network::Testnet).unwrap();
+    let addresses = wallet.addresses();
+    assert_eq!(addresses.receiving[..], file.addresses().receiving[..5]);
+    assert_eq!(addresses.change[..], file.addresses().change[..5]);
+
+    let reparsed = ElectrumWalletFile::from_str(&wallet.to_string()).unwrap();
+    assert_eq!(reparsed.addresses(), addresses);
+    assert_eq!(reparsed.to_descriptors().unwrap(), descriptors);
+
+    // the stored addresses are kept
+    let mut filled = file.clone();
+    filled.fill_addresses(5, Network::Testnet).unwrap();
+    assert_eq!(filled.addresses(), file.addresses());
+    assert_eq!(filled.to_descriptors().unwrap(), descriptors);
+}
+
+#[test]
+fn fill_addresses_network() {
+    let file = ElectrumWalletFile::from_file(&get_test_wallet_file("default_segwit")).unwrap();
+    let external = file.to_descriptors().unwrap().external;
+    let mut wallet = ElectrumWalletFile::from_descriptor(&external).unwrap();
+    wallet.fill_addresses(3, Network::Regtest).unwrap();
+    assert_eq!(wallet.addresses().receiving.len(), 3);
+    assert!(wallet
+        .addresses()
+        .receiving
+        .iter()
+        .chain(&wallet.addresses().change)
+        .all(|address| address.starts_with("bcrt1")));
+
+    let mut wallet = ElectrumWalletFile::from_descriptor(&external).unwrap();
+    assert!(matches!(
+        wallet.fill_addresses(3, Network::Bitcoin),
+        Err(Electrum2DescriptorError::MixedNetworks(_, _))
+    ));
+    assert!(wallet.addresses().receiving.is_empty());
+}
+
+#[test]
+fn distinct_cosigner_paths() {
+    let origins = [
+        ("27d81095", "m/48'/1'/0'/2'"),
+        ("6bfac2d6", "m/48'/100'/0'/2'"),
+        ("f6083804", "m/1'"),
+    ];
+    let wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_distinct_paths")).unwrap();
+    let reparsed = ElectrumWalletFile::from_str(&wallet.to_string()).unwrap();
+    for wallet in [&wallet, &reparsed] {
+        for (keystore, (fingerprint, derivation)) in wallet.keystores().iter().zip(origins) {
+            assert_eq!(keystore.root_fingerprint.as_deref(), Some(fingerprint));
+            assert_eq!(keystore.derivation.as_deref(), Some(derivation));
+        }
+    }
+    let descriptor = wallet.descriptor_at(false, 0).unwrap();
+    for (fingerprint, derivation) in origins {
+        assert!(descriptor.contains(&format!(
+            "[{}{}/0/0]",
+            fingerprint,
+            derivation.trim_start_matches('m')
+        )));
+    }
+    let from_descriptor =
+        ElectrumWalletFile::from_descriptor(&wallet.to_descriptors().unwrap().external).unwrap();
+    assert_eq!(from_descriptor.keystores(), wallet.keystores());
+
+    let keystores = wallet
+        .keystores()
+        .iter()
+        .map(|ks| {
+            let (fingerprint, derivation) = ks.origin().unwrap().unwrap();
+            Keystore::from_electrum_xkey(&ks.xpub)
+                .unwrap()
+                .with_origin(fingerprint, &derivation)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ElectrumWalletFile::new(&keystores, 2).unwrap().to_string(),
+        wallet.to_string()
+    );
+
+    let single_sig_path = DerivationPath::from_str("m/84'/1'/0'").unwrap();
+    let mut mismatched = keystores.clone();
+    mismatched[2] = mismatched[2]
+        .clone()
+        .with_origin(Fingerprint::from_str("f6083804").unwrap(), &single_sig_path);
+    assert!(matches!(
+        ElectrumWalletFile::new(&mismatched, 2),
+        Err(Electrum2DescriptorError::DerivationMismatch(_, _, _))
+    ));
+}
+
+#[test]
+fn expected_fingerprints() {
+    let options = |fingerprints: &[&str]| DescriptorOptions {
+        expected_fingerprints: fingerprints
+            .iter()
+            .map(|fp| Fingerprint::from_str(fp).unwrap())
+            .collect(),
+        ..DescriptorOptions::default()
+    };
+    // a master key, whose fingerprint is known
+    let master = "pkh(tpubD6NzVbkrYhZ4Y1ozBYSfoyVp2iGgP6iZAy18p2opXjVv8jTNccGuRs3jMCMe4ncfwy2RUJsoZLSXsGiFhN47xFbJgtRvCuV3RP3UnxpsrZt/0/*)";
+    assert!(ElectrumWalletFile::from_descriptor_with_opts(master, &options(&["230b70d2"])).is_ok());
+    assert!(matches!(
+        ElectrumWalletFile::from_descriptor_with_opts(master, &options(&["aabbccdd"])),
+        Err(Electrum2DescriptorError::UnexpectedFingerprint(expected, found))
+            if expected == "aabbccdd" && found == "230b70d2"
+    ));
+    // an account key without origin
+    let account = "wpkh(tpubD9ZjaMn3rbP1cAVwJy6UcEjFfTLT7W6DbfHdS3Wn48meExtVfKmiH9meWCrSmE9qXLYbGcHC5LxLcdfLZTzwme23qAJoRzRhzbd68dHeyjp/0/*)";
+    assert!(ElectrumWalletFile::from_descriptor_with_opts(account, &options(&[])).is_ok());
+    assert!(matches!(
+        ElectrumWalletFile::from_descriptor_with_opts(account, &options(&["aabbccdd"])),
+        Err(Electrum2DescriptorError::UnexpectedFingerprint(_, found)) if found == "no fingerprints"
+    ));
+
+    let wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_hw_segwit")).unwrap();
+    let fingerprints = options(&["6bfac2d6", "27d81095"]).expected_fingerprints;
+    assert!(wallet.check_fingerprints(&fingerprints).is_ok());
+    assert!(matches!(
+        wallet.check_fingerprints(&options(&["27d81095", "aabbccdd"]).expected_fingerprints),
+        Err(Electrum2DescriptorError::UnexpectedFingerprint(_, found))
+            if found == "27d81095, 6bfac2d6"
+    ));
+}
+
+#[rstest]
+#[case::electrum(JsonStyle::ELECTRUM)]
+#[case::compact(JsonStyle::COMPACT)]
+#[case::pretty2_sorted(JsonStyle { indent: JsonIndent::Pretty2, sort_keys: true })]
+#[case::pretty4_unsorted(JsonStyle { indent: JsonIndent::Pretty4, sort_keys: false })]
+fn to_json_with(#[case] style: JsonStyle) {
+    let wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_hw_segwit")).unwrap();
+    let json = wallet.to_json_with(style).unwrap();
+    assert_eq!(ElectrumWalletFile::from_str(&json).unwrap(), wallet);
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value, serde_json::to_value(&wallet).unwrap());
+    match style.indent {
+        JsonIndent::Compact => assert!(!json.contains('\n')),
+        JsonIndent::Pretty2 => {
+            assert!(json.contains("\n  \"") && !json.contains("\n    \"addresses"))
+        }
+        JsonIndent::Pretty4 => assert!(json.contains("\n    \"addresses")),
+    }
+    // electrum's unmodeled fields are written after the modeled ones unless sorted
+    let position = |key: &str| json.find(&format!("\"{}\"", key)).unwrap();
+    assert_eq!(
+        position("addr_history") < position("addresses"),
+        style.sort_keys
+    );
+}
+
+#[test]
+fn to_file_electrum_style() {
+    let wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_hw_segwit")).unwrap();
+    let tempdir = tempdir().unwrap();
+    let filename = tempdir.path().join("wallet");
+    wallet.to_file(&filename).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&filename).unwrap(),
+        wallet.to_json_with(JsonStyle::ELECTRUM).unwrap()
+    );
+}
+
+#[rstest]
+#[case::default_legacy("default_legacy")]
+#[case::default_segwit("default_segwit")]
+#[case::multisig_legacy("multisig_legacy")]
+#[case::multisig_hw_segwit("multisig_hw_segwit")]
+#[case::multisig_wrapped_watch("multisig_wrapped_watch")]
+#[case::multisig_wrapped("multisig_wrapped")]
+fn verify_addresses(#[case] wallet_name: &str) {
+    let path = get_test_wallet_file(wallet_name);
+    let wallet = ElectrumWalletFile::from_file(&path).unwrap();
+    wallet.verify_addresses().unwrap();
+
+    // a taproot address
+    let taproot = "tb1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq47zagq";
+    let mut json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    json["addresses"]["receiving"][5] = taproot.into();
+    let mixed = ElectrumWalletFile::from_str(&json.to_string()).unwrap();
+    assert!(matches!(
+        mixed.verify_addresses(),
+        Err(Electrum2DescriptorError::AddressKindMismatch(address, found, _))
+            if address == taproot && found == "p2tr"
+    ));
+}
+
+#[rstest]
+#[case::multisig_segwit("multisig_segwit", "multisig_segwit_cosmetic")]
+#[case::derivation_missing("derivation_missing", "derivation_null")]
+fn canonicalize(#[case] wallet_name: &str, #[case] cosmetic_name: &str) {
+    let mut wallet = ElectrumWalletFile::from_file(&get_test_wallet_file(wallet_name)).unwrap();
+    let mut cosmetic = ElectrumWalletFile::from_file(&get_test_wallet_file(cosmetic_name)).unwrap();
+    assert_ne!(
+        wallet.to_json_with(JsonStyle::ELECTRUM).unwrap(),
+        cosmetic.to_json_with(JsonStyle::ELECTRUM).unwrap()
+    );
+    wallet.canonicalize().unwrap();
+    cosmetic.canonicalize().unwrap();
+    let json = wallet.to_json_with(JsonStyle::ELECTRUM).unwrap();
+    assert_eq!(json, cosmetic.to_json_with(JsonStyle::ELECTRUM).unwrap());
+    assert_eq!(
+        wallet.to_descriptors().unwrap(),
+        cosmetic.to_descriptors().unwrap()
+    );
+
+    // canonical wallets stay as they are
+    let mut canonical = ElectrumWalletFile::from_str(&json).unwrap();
+    canonical.canonicalize().unwrap();
+    assert_eq!(canonical.to_json_with(JsonStyle::ELECTRUM).unwrap(), json);
+}
+
+#[test]
+fn change_descriptor() {
+    let strict = DescriptorOptions {
+        strict: true,
+        ..DescriptorOptions::default()
+    };
+    let single = "wpkh(tpubD9ZjaMn3rbP1cAVwJy6UcEjFfTLT7W6DbfHdS3Wn48meExtVfKmiH9meWCrSmE9qXLYbGcHC5LxLcdfLZTzwme23qAJoRzRhzbd68dHeyjp/0/*)";
+    let multi = ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_hw_segwit"))
+        .unwrap()
+        .to_descriptors()
+        .unwrap();
+    for (external, change) in [
+        (single.to_string(), single.replace("/0/*", "/1/*")),
+        (multi.external, multi.change),
+    ] {
+        let mut warnings = Vec::new();
+        let wallet = ElectrumWalletFile::from_descriptor_with_warnings(
+            &change,
+            &DescriptorOptions::default(),
+            &mut warnings,
+        )
+        .unwrap();
+        assert_eq!(
+            wallet,
+            ElectrumWalletFile::from_descriptor(&external).unwrap()
+        );
+        assert_eq!(wallet.to_descriptors().unwrap().change, change);
+        assert_eq!(
+            warnings,
+            vec![ConversionWarning::ChangeDescriptor(change.clone())]
+        );
+
+        assert!(matches!(
+            ElectrumWalletFile::from_descriptor_with_opts(&change, &strict),
+            Err(Electrum2DescriptorError::ChangeDescriptor(_))
+        ));
+        let mut warnings = Vec::new();
+        ElectrumWalletFile::from_descriptor_with_warnings(&external, &strict, &mut warnings)
+            .unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    let mixed = "wsh(sortedmulti(2,[27d81095/48'/1'/0'/2']tpubDEcw4ooTbmw62zBKdkYepoP3z4WWugdeRzPHHAbk8XVsPfBE9AAZMNghiqwtdFgtabaeppBTPmezUkRkQZidLcSJp3XTASbMakHcYauWehZ/0/*,[6bfac2d6/48'/100'/0'/2']tpubDEbkvhmJoZMq3SUNqEf3aEsubvqsCUPc7rroHkGERgS7qA1gQVMxUPrgzth6x43odirLohwf4aMHpvcnWi3jCB2xkizv8T4B2KqLRZVLC6K/1/*))";
+    assert!(matches!(
+        ElectrumWalletFile::from_descriptor(mixed),
+        Err(Electrum2DescriptorError::MixedDescriptorChains(_))
+    ));
+}
+
+#[test]
+fn mixed_private_public_cosigners() {
+    let descriptor = "wsh(sortedmulti(2,tprv8dNybiDsdyms39SAWTxyiNHABTTgiqmJpScmxGrdKEuZ7TwXcaYXT4f4ddVjWiiQs9zowHqyDmvaebN6fU2Lu6iAYnYuepiLkvzGdcZZi8D/0/*,tpubDEcw4ooTbmw62zBKdkYepoP3z4WWugdeRzPHHAbk8XVsPfBE9AAZMNghiqwtdFgtabaeppBTPmezUkRkQZidLcSJp3XTASbMakHcYauWehZ/0/*,tpubDEbkvhmJoZMq3SUNqEf3aEsubvqsCUPc7rroHkGERgS7qA1gQVMxUPrgzth6x43odirLohwf4aMHpvcnWi3jCB2xkizv8T4B2KqLRZVLC6K/0/*))";
+    let wallet = ElectrumWalletFile::from_descriptor(descriptor).unwrap();
+    let private = wallet
+        .keystores()
+        .iter()
+        .map(|ks| ks.xprv.is_some())
+        .collect::<Vec<_>>();
+    assert_eq!(private, vec![true, false, false]);
+
+    let tempdir = tempdir().unwrap();
+    let filename = tempdir.path().join("wallet");
+    wallet.to_file(&filename).unwrap();
+    let restored = ElectrumWalletFile::from_file(&filename).unwrap();
+    assert!(restored.keystores()[0].can_sign());
+    assert!(restored.keystores()[1..].iter().all(|ks| ks.xprv.is_none()));
+    assert_eq!(restored.to_descriptors().unwrap().external, descriptor);
+
+    // a mainnet cosigner of the testnet xprv
+    let mixed = descriptor.replace(
+        "tpubDEbkvhmJoZMq3SUNqEf3aEsubvqsCUPc7rroHkGERgS7qA1gQVMxUPrgzth6x43odirLohwf4aMHpvcnWi3jCB2xkizv8T4B2KqLRZVLC6K",
+        "xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL",
+    );
+    assert!(matches!(
+        ElectrumWalletFile::from_descriptor(&mixed),
+        Err(Electrum2DescriptorError::MixedNetworks(..))
+    ));
+}
+
+#[test]
+fn sign_message() {
+    let secp = bdk::bitcoin::secp256k1::Secp256k1::new();
+    let message = "I own this address";
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file("default_segwit")).unwrap();
+    for (change, index) in [(false, 0), (true, 3)] {
+        let address = wallet.address(change, index).unwrap();
+        let signature = wallet.sign_message(&address, message).unwrap();
+        let signature = MessageSignature::from_base64(&signature).unwrap();
+        let pubkey = signature
+            .recover_pubkey(&secp, signed_msg_hash(message))
+            .unwrap();
+        assert_eq!(Address::p2wpkh(&pubkey, Network::Testnet).unwrap(), address);
+        assert!(wallet
+            .verify_message(&address, message, &signature.to_base64())
+            .unwrap());
+        assert!(!wallet
+            .verify_message(&address, "I don't", &signature.to_base64())
+            .unwrap());
+    }
+
+    let not_stored = wallet.address(false, 1000).unwrap();
+    assert!(matches!(
+        wallet.sign_message(&not_stored, message),
+        Err(Electrum2DescriptorError::AddressNotInWallet(_))
+    ));
+    let signature = wallet
+        .sign_message(&wallet.address(false, 0).unwrap(), message)
+        .unwrap();
+    assert!(matches!(
+        wallet.verify_message(&not_stored, message, &signature),
+        Err(Electrum2DescriptorError::AddressNotInWallet(_))
+    ));
+
+    let watch_only =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("default_legacy_watch")).unwrap();
+    assert!(matches!(
+        watch_only.sign_message(&watch_only.address(false, 0).unwrap(), message),
+        Err(Electrum2DescriptorError::NoPrivateKeys)
+    ));
+
+    let multisig = ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_segwit")).unwrap();
+    assert!(matches!(
+        multisig.sign_message(&multisig.address(false, 0).unwrap(), message),
+        Err(Electrum2DescriptorError::MessageSigning(_))
+    ));
+}
+
+#[test]
+fn fingerprints() {
+    let fingerprint = |fp| Some(Fingerprint::from_str(fp).unwrap());
+    // stored
+    let wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_hw_segwit")).unwrap();
+    assert_eq!(
+        wallet.fingerprints(),
+        vec![(0, fingerprint("27d81095")), (1, fingerprint("6bfac2d6"))]
+    );
+    // computed from a master key
+    let wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("derivation_missing")).unwrap();
+    assert_eq!(wallet.fingerprints(), vec![(0, fingerprint("230b70d2"))]);
+    // an account key
+    let wallet = ElectrumWalletFile::from_descriptor("wpkh(tpubD9ZjaMn3rbP1cAVwJy6UcEjFfTLT7W6DbfHdS3Wn48meExtVfKmiH9meWCrSmE9qXLYbGcHC5LxLcdfLZTzwme23qAJoRzRhzbd68dHeyjp/0/*)").unwrap();
+    assert_eq!(wallet.fingerprints(), vec![(0, None)]);
+    assert_eq!(wallet.summary().unwrap().cosigners[0].fingerprint, None);
+}
+
+#[test]
+fn signing_keystores() {
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file("default_segwit")).unwrap();
+    assert_eq!(wallet.signing_keystores(), vec![0]);
+    assert!(wallet.can_sign());
+
+    let wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("default_legacy_watch")).unwrap();
+    assert!(wallet.signing_keystores().is_empty());
+    assert!(!wallet.can_sign());
+
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_segwit")).unwrap();
+    assert_eq!(wallet.signing_keystores(), vec![0]);
+
+    // an xprv encrypted with the wallet password doesn't sign, and the error says so
+    let mut json: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(get_test_wallet_file("default_segwit")).unwrap(),
+    )
+    .unwrap();
+    json["keystore"]["xprv"] = "QklFMSTgT0FEaBz5dJ1D6XTtEYb2SVSG8a".into();
+    let wallet = ElectrumWalletFile::from_str(&json.to_string()).unwrap();
+    assert!(!wallet.can_sign());
+    let address = wallet.address(false, 0).unwrap();
+    assert!(matches!(
+        wallet.sign_message(&address, "message"),
+        Err(Electrum2DescriptorError::SecretsEncrypted(name)) if name == "keystore"
+    ));
+}
+
+#[test]
+fn keystore_for_fingerprint() {
+    let fingerprint = |fp| Fingerprint::from_str(fp).unwrap();
+    // stored
+    let wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_hw_segwit")).unwrap();
+    let (index, keystore) = wallet
+        .keystore_for_fingerprint(fingerprint("6bfac2d6"), false)
+        .unwrap();
+    assert_eq!(index, 1);
+    assert_eq!(keystore, &wallet.keystores()[1]);
+    // computed from a master key
+    let wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("derivation_missing")).unwrap();
+    assert_eq!(
+        wallet
+            .keystore_for_fingerprint(fingerprint("230b70d2"), false)
+            .map(|(i, _)| i),
+        Some(0)
+    );
+    // the account xpub, only as fallback
+    let wallet = ElectrumWalletFile::from_descriptor("wpkh(tpubD9ZjaMn3rbP1cAVwJy6UcEjFfTLT7W6DbfHdS3Wn48meExtVfKmiH9meWCrSmE9qXLYbGcHC5LxLcdfLZTzwme23qAJoRzRhzbd68dHeyjp/0/*)").unwrap();
+    let account = wallet.keystores()[0].xpub().unwrap().0.fingerprint();
+    assert!(wallet.keystore_for_fingerprint(account, false).is_none());
+    assert_eq!(
+        wallet
+            .keystore_for_fingerprint(account, true)
+            .map(|(i, _)| i),
+        Some(0)
+    );
+    // a miss
+    assert!(wallet
+        .keystore_for_fingerprint(fingerprint("deadbeef"), true)
+        .is_none());
+}
+
+#[test]
+fn duplicate_addresses() {
+    let wallet_file = get_test_wallet_file("duplicate_addresses");
+    let strict = ParseOptions {
+        strict: true,
+        ..ParseOptions::default()
+    };
+    let err = ElectrumWalletFile::from_file_with_options(wallet_file.as_path(), &strict)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("is listed as receiving address 20 and already as receiving address 2"),
+        "{}",
+        err
+    );
+
+    let mut wallet = ElectrumWalletFile::from_file(wallet_file.as_path()).unwrap();
+    assert_eq!(
+        wallet.parse_warnings(),
+        &[
+            ConversionWarning::DuplicateAddress(
+                "tb1qys5xz7kju4k86wau7rusgjqgtf08nxe57hus7c".to_string(),
+                "receiving address 20".to_string(),
+                "receiving address 2".to_string()
+            ),
+            ConversionWarning::DuplicateAddress(
+                "tb1qt63km8fkfuwnl6hzfktu7g2nh8tc6jhr6vequq".to_string(),
+                "change address 10".to_string(),
+                "receiving address 0".to_string()
+            ),
+        ]
+    );
+    let original = ElectrumWalletFile::from_file(&get_test_wallet_file("default_segwit")).unwrap();
+    assert_eq!(wallet.addresses(), original.addresses());
+    let mut warnings = Vec::new();
+    wallet.to_descriptors_with_warnings(&mut warnings).unwrap();
+    assert!(warnings.contains(&wallet.parse_warnings()[0]));
+    wallet.extend_addresses(25, 12).unwrap();
+}
+
+#[rstest]
+#[case::deleted(
+    "addresses_deleted",
+    Electrum2DescriptorError::AddressesDeleted("receiving", 1, 3)
+)]
+#[case::inserted(
+    "addresses_inserted",
+    Electrum2DescriptorError::ForeignAddress(
+        "receiving",
+        3,
+        "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx".to_string()
+    )
+)]
+fn repair_addresses(#[case] name: &str, #[case] expected: Electrum2DescriptorError) {
+    let mut wallet = ElectrumWalletFile::from_file(&get_test_wallet_file(name)).unwrap();
+    let err = wallet.verify_addresses().unwrap_err();
+    assert_eq!(err.to_string(), expected.to_string());
+
+    let stored = wallet.repair_addresses().unwrap();
+    assert_eq!(&stored.receiving[..3], &wallet.addresses().receiving[..3]);
+    wallet.verify_addresses().unwrap();
+    let original = ElectrumWalletFile::from_file(&get_test_wallet_file("default_segwit")).unwrap();
+    assert_eq!(wallet.addresses(), original.addresses());
+}
+
+#[test]
+fn wallet_id() {
+    let wallet_file = get_test_wallet_file("default_segwit");
+    let mut wallet = ElectrumWalletFile::from_file(&wallet_file).unwrap();
+    // sha256 of "wpkh(tpubD9cni.../0/*)\nwpkh(tpubD9cni.../1/*)"
+    assert_eq!(wallet.wallet_id().unwrap(), "4e8ddbbddd3ade2f");
+    let watch_only = ElectrumWalletFile::from_file_watch_only(&wallet_file).unwrap();
+    assert_eq!(watch_only.wallet_id().unwrap(), "4e8ddbbddd3ade2f");
+    wallet.extend_addresses(40, 20).unwrap();
+    wallet.set_label("renamed").unwrap();
+    assert_eq!(wallet.wallet_id().unwrap(), "4e8ddbbddd3ade2f");
+
+    let multisig = ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_segwit")).unwrap();
+    let cosmetic =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_segwit_cosmetic")).unwrap();
+    assert_eq!(multisig.wallet_id().unwrap(), cosmetic.wallet_id().unwrap());
+    let mut reversed = multisig.keystores().to_vec();
+    reversed.reverse();
+    let reversed = ElectrumWalletFile::new(&reversed, 2).unwrap();
+    assert!(multisig.is_equivalent(&reversed).unwrap());
+    assert_eq!(multisig.wallet_id().unwrap(), reversed.wallet_id().unwrap());
+    assert_ne!(multisig.wallet_id().unwrap(), wallet.wallet_id().unwrap());
+}
+
+#[test]
+fn duplicate_fields() {
+    let wallet_file = std::fs::read_to_string(get_test_wallet_file("default_segwit")).unwrap();
+    // the last one wins, as in python's json module
+    let duplicated = wallet_file.replace(
+        "\"wallet_type\": \"standard\"",
+        "\"wallet_type\": \"2of2\",\n    \"wallet_type\": \"standard\"",
+    );
+    let wallet = ElectrumWalletFile::from_str(&duplicated).unwrap();
+    assert_eq!(wallet.wallet_type(), &WalletType::Standard);
+    assert_eq!(
+        wallet.parse_warnings(),
+        &[ConversionWarning::DuplicateField("wallet_type".to_string())]
+    );
+    let duplicated = wallet_file.replace(
+        "\"wallet_type\": \"standard\"",
+        "\"wallet_type\": \"standard\",\n    \"wallet_type\": \"2of2\"",
+    );
+    assert!(ElectrumWalletFile::from_str(&duplicated).is_err());
+
+    let wallet_file = std::fs::read_to_string(get_test_wallet_file("multisig_segwit")).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&wallet_file).unwrap();
+    let duplicated = wallet_file.replacen('{', &format!("{{\n    \"x1/\": {},", json["x2/"]), 1);
+    let err = ElectrumWalletFile::from_str(&duplicated)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("duplicate keystore `x1/`"), "{}", err);
+}
+
+#[test]
+fn raw_fields() {
+    let mut wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("default_segwit")).unwrap();
+    let height = wallet.raw_field("stored_height").unwrap().clone();
+    assert!(height.is_u64());
+    assert!(wallet.raw_field("addresses").is_none());
+
+    assert_eq!(
+        wallet
+            .set_raw_field("stored_height", serde_json::json!(0))
+            .unwrap(),
+        Some(height)
+    );
+    assert_eq!(
+        wallet
+            .set_raw_field("my_tool", serde_json::json!({"version": 1}))
+            .unwrap(),
+        None
+    );
+    let json: serde_json::Value = serde_json::from_str(&wallet.to_string()).unwrap();
+    assert_eq!(json["stored_height"], 0);
+    assert_eq!(json["my_tool"]["version"], 1);
+
+    for modeled in [
+        "addresses",
+        "keystore",
+        "x1/",
+        "wallet_type",
+        "seed_version",
+    ] {
+        assert!(matches!(
+            wallet.set_raw_field(modeled, serde_json::Value::Null),
+            Err(Electrum2DescriptorError::ModeledField(field, _)) if field == modeled
+        ));
+    }
+}
+
+#[test]
+fn wrapped_singlesig_descriptors() {
+    let external = "sh(wpkh(tpubD9ZjaMn3rbP1cAVwJy6UcEjFfTLT7W6DbfHdS3Wn48meExtVfKmiH9meWCrSmE9qXLYbGcHC5LxLcdfLZTzwme23qAJoRzRhzbd68dHeyjp/0/*))";
+    let wallet = ElectrumWalletFile::from_descriptor(external).unwrap();
+    let descriptors = wallet.to_descriptors().unwrap();
+    assert_eq!(descriptors.external, external);
+    assert_eq!(descriptors.change, external.replace("/0/*", "/1/*"));
+}
+
+#[test]
+fn account_descriptors() {
+    // bip-0032 test vector 1
+    let master = "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi";
+    let wallet = ElectrumWalletFile::from_descriptor(&format!("wpkh({}/0/*)", master)).unwrap();
+    let accounts = wallet
+        .account_descriptors(ScriptKind::P2wpkh, 0..3)
+        .unwrap();
+    assert_eq!(accounts.len(), 3);
+    for (i, (account, descriptors)) in accounts.iter().enumerate() {
+        assert_eq!(*account, i as u32);
+        let origin = format!("wpkh([3442193e/84'/0'/{}']xpub", i);
+        assert!(descriptors.external.starts_with(&origin));
+        assert!(descriptors.external.ends_with("/0/*)"));
+        assert!(descriptors.change.starts_with(&origin));
+        assert!(descriptors.change.ends_with("/1/*)"));
+    }
+    assert_ne!(accounts[0].1.external, accounts[1].1.external);
+
+    let wrapped = wallet
+        .account_descriptors(ScriptKind::P2wpkhP2sh, 1..2)
+        .unwrap();
+    assert!(wrapped[0]
+        .1
+        .external
+        .starts_with("sh(wpkh([3442193e/49'/0'/1']xpub"));
+    assert!(wrapped[0].1.external.ends_with("/0/*))"));
+
+    let account_level =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("default_segwit")).unwrap();
+    assert!(matches!(
+        account_level.account_descriptors(ScriptKind::P2wpkh, 0..3),
+        Err(Electrum2DescriptorError::NotMasterKey(1))
+    ));
+    let watch_only =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("derivation_missing")).unwrap();
+    assert!(matches!(
+        watch_only.account_descriptors(ScriptKind::P2wpkh, 0..3),
+        Err(Electrum2DescriptorError::NoPrivateKeys)
+    ));
+}
+
+#[rstest]
+#[case::standard("default_legacy")]
+#[case::segwit("default_segwit")]
+fn keystore_from_seed(#[case] wallet_name: &str) {
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file(wallet_name)).unwrap();
+    let stored = &wallet.keystores()[0];
+    let seed = stored.seed.as_deref().unwrap().to_uppercase();
+    let keystore = Keystore::from_seed(&seed, "", Network::Testnet).unwrap();
+    assert_eq!(keystore.xprv, stored.xprv);
+    assert_eq!(keystore.xpub, stored.xpub);
+    assert_eq!(keystore.derivation, stored.derivation);
+    assert_eq!(keystore.seed, stored.seed);
+    assert!(!keystore.has_seed_extension());
+
+    let extended = Keystore::from_seed(&seed, "my extension", Network::Testnet).unwrap();
+    assert_ne!(extended.xpub, stored.xpub);
+    assert_eq!(extended.passphrase.as_deref(), Some("my extension"));
+    assert!(extended.has_seed_extension());
+}
+
+#[rstest]
+// the standard seed of electrum's test_wallet_vertical
+#[case::standard(
+    "cycle rocket west magnet parrot shuffle foot correct salt library feed song",
+    "",
+    "xprv9s21ZrQH143K32jECVM729vWgGq4mUDJCk1ozqAStTphzQtCTuoFmFafNoG1g55iCnBTXUzz3zWnDb5CVLGiFvmaZjuazHDL8a81cPQ8KL6",
+    "xpub661MyMwAqRbcFWohJWt7PHsFEJfZAvw9ZxwQoDa4SoMgsDDM1T7WK3u9E4edkC4ugRnZ8E4xDZRpk8Rnts3Nbt97dPwT52CwBdDWroaZf8U"
+)]
+// the segwit seed with an extension of electrum's test_mnemonic, keys derived from its bip32 seed with python
+#[case::segwit_extension(
+    "wild father tree among universe such mobile favorite target dynamic credit identify",
+    "Did you ever hear the tragedy of Darth Plagueis the Wise?",
+    "zprvAZqYHHDskBDRqnfmHx7JT8qTZSP5NcgyyxoxgYjZD91HxRq5giFEegZxQEZhSBtCTjF9eCPQyEqR9bNM1JEEpHDNAzp2igMe6G9mEbJFejM",
+    "zpub6nptgnkmaYmj4GkEPyeJpGnC7UDZn5QqMBjZUw9AmUYGqEAEEFZVCUtSFY66ZHP5nxn9uMF4J8sh2EXs5u9BsbaNL1gk8xt3sQ9fDf6WeEn"
+)]
+fn keystore_from_seed_known_answer(
+    #[case] seed: &str,
+    #[case] extension: &str,
+    #[case] xprv: &str,
+    #[case] xpub: &str,
+) {
+    let keystore = Keystore::from_seed(seed, extension, Network::Bitcoin).unwrap();
+    assert_eq!(keystore.xprv.as_ref().unwrap().to_string(), xprv);
+    assert_eq!(keystore.xpub.to_string(), xpub);
+}
+
+#[test]
+fn seed_extension() {
+    let path = get_test_wallet_file("default_segwit");
+    let mut json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    json["keystore"]["passphrase"] = serde_json::json!("my extension");
+    let wallet = ElectrumWalletFile::from_str(&json.to_string()).unwrap();
+    assert!(wallet.keystores()[0].has_seed_extension());
+
+    let rewritten: serde_json::Value = serde_json::from_str(&wallet.to_string()).unwrap();
+    assert_eq!(rewritten["keystore"]["passphrase"], "my extension");
+
+    let redacted: serde_json::Value =
+        serde_json::from_str(&wallet.to_redacted_json().unwrap()).unwrap();
+    assert_eq!(redacted["keystore"]["passphrase"], "<redacted>");
+
+    let watch_only = ElectrumWalletFile::from_str_with_options(
+        &json.to_string(),
+        &ParseOptions {
+            watch_only: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert!(watch_only.keystores()[0].passphrase.is_none());
+
+    let mut warnings = Vec::new();
+    wallet.to_descriptors_with_warnings(&mut warnings).unwrap();
+    assert!(warnings.contains(&ConversionWarning::SeedExtension("keystore".to_string())));
+}
+
+#[test]
+fn wrapped_multisig_round_trip() {
+    let external = "sh(wsh(sortedmulti(2,[f6083804/1']tprv8dNybiDsdyms39SAWTxyiNHABTTgiqmJpScmxGrdKEuZ7TwXcaYXT4f4ddVjWiiQs9zowHqyDmvaebN6fU2Lu6iAYnYuepiLkvzGdcZZi8D/0/*,[b88448fb/1']tpubD9cniQzQ8XnuagyP9Xwg3sWCX77wQPWoLPW7jqzcPn37r8hq2X86uztCEyFbMY16amzwdJ1CcNRXhF3vykn1wuDv2ULzryRtaCcN5Cr8F9y/0/*)))";
+    let change = external.replace("/0/*", "/1/*");
+    let file = ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_wrapped")).unwrap();
+    assert_eq!(
+        file.to_descriptors().unwrap(),
+        Descriptors {
+            external: external.to_string(),
+            change: change.clone(),
+        }
+    );
+
+    let wallet = ElectrumWalletFile::from_descriptor(external).unwrap();
+    assert_eq!(wallet.keystores().len(), file.keystores().len());
+    for (keystore, stored) in wallet.keystores().iter().zip(file.keystores()) {
+        assert!(keystore.xpub.starts_with("Upub"));
+        assert_eq!(keystore.xpub, stored.xpub);
+        assert_eq!(keystore.xprv, stored.xprv);
+    }
+    assert_eq!(wallet.to_descriptors().unwrap().external, external);
+    assert_eq!(wallet.to_descriptors().unwrap().change, change);
+
+    let bdk = Wallet::new(
+        external,
+        Some(&change),
+        Network::Testnet,
+        MemoryDatabase::default(),
+    )
+    .unwrap();
+    for (index, address) in file.addresses().receiving.iter().enumerate() {
+        assert!(address.starts_with('2'));
+        assert_eq!(
+            &wallet.address(false, index as u32).unwrap().to_string(),
+            address
+        );
+        assert_eq!(
+            &bdk.get_address(AddressIndex::Peek(index as u32))
+                .unwrap()
+                .to_string(),
+            address
+        );
+    }
+    for (index, address) in file.addresses().change.iter().enumerate() {
+        assert_eq!(
+            &bdk.get_internal_address(AddressIndex::Peek(index as u32))
+                .unwrap()
+                .to_string(),
+            address
+        );
+    }
+}
+
+#[rstest]
+#[case::zero("0of2", None)]
+#[case::one("1of2", Some(WalletType::multisig(1, 2).unwrap()))]
+#[case::all("2of2", Some(WalletType::multisig(2, 2).unwrap()))]
+#[case::above("5of3", None)]
+#[case::threshold_above_u8("256of3", None)]
+#[case::cosigners_above_u8("2of256", None)]
+#[case::max_cosigners("255of255", Some(WalletType::multisig(255, 255).unwrap()))]
+#[case::above_usize("99999999999999999999999of2", None)]
+fn wallet_type_threshold(#[case] wallet_type: &str, #[case] expected: Option<WalletType>) {
+    let parsed = WalletType::from_str(wallet_type).ok();
+    assert_eq!(parsed, expected);
+    if let Some(WalletType::Multisig(multisig)) = parsed {
+        let (threshold, cosigners) = wallet_type.split_once("of").unwrap();
+        assert_eq!(multisig.threshold().to_string(), threshold);
+        assert_eq!(multisig.cosigners().to_string(), cosigners);
+        assert_eq!(
+            format!("{:?}", WalletType::Multisig(multisig)),
+            format!("Multisig({}, {})", threshold, cosigners)
+        );
+    }
+}
+
+#[rstest]
+#[case::zero(0, 2, false)]
+#[case::one(1, 2, true)]
+#[case::all(2, 2, true)]
+#[case::above(3, 2, false)]
+#[case::above_u8(300, 2, false)]
+#[case::cosigners_above_u8(2, 300, false)]
+fn validate_threshold(#[case] threshold: usize, #[case] cosigners: usize, #[case] valid: bool) {
+    let result = electrum_wallet_file::validate_threshold(threshold, cosigners);
+    assert_eq!(result.is_ok(), valid);
+    if threshold == 0 || threshold > cosigners && cosigners <= 255 {
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("at least 1 and at most"), "{}", message);
+    }
+}
+
+#[rstest]
+#[case::zero("0", false)]
+#[case::one("1", true)]
+#[case::all("2", true)]
+#[case::above("5", false)]
+#[case::two_digits("10", false)]
+#[case::above_u8("256", false)]
+#[case::above_usize("99999999999999999999999", false)]
+fn descriptor_threshold(#[case] threshold: &str, #[case] valid: bool) {
+    let descriptor = format!(
+        "wsh(sortedmulti({},{}/0/*,{}/0/*))",
+        threshold, HW_SEGWIT_XPUBS[0], HW_SEGWIT_XPUBS[1]
+    );
+    assert_eq!(
+        ElectrumWalletFile::from_descriptor(&descriptor).is_ok(),
+        valid
+    );
+}
+
+#[test]
+fn wallet_file_threshold() {
+    let path = get_test_wallet_file("multisig_segwit");
+    let mut json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    for wallet_type in ["0of2", "5of3", "3of2"] {
+        json["wallet_type"] = wallet_type.into();
+        assert!(ElectrumWalletFile::from_str(&json.to_string()).is_err());
+    }
+    let keystores = ElectrumWalletFile::from_str(&json.to_string().replace("3of2", "2of2"))
+        .unwrap()
+        .keystores()
+        .clone();
+    assert!(matches!(
+        ElectrumWalletFile::new(&keystores, 0),
+        Err(Electrum2DescriptorError::NumberSignaturesKeyStores(0, 2))
+    ));
+}
+
+#[test]
+fn to_descriptors_with_origin() {
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file("default_segwit")).unwrap();
+    let origin = (
+        Fingerprint::from_str("aabbccdd").unwrap(),
+        DerivationPath::from_str("m/0h").unwrap(),
+    );
+    let descriptors = wallet
+        .to_descriptors_with_origin(std::slice::from_ref(&origin))
+        .unwrap();
+    let plain = wallet.to_descriptors().unwrap();
+    assert_eq!(
+        descriptors.external,
+        plain.external.replace("[b88448fb/0']", "[aabbccdd/0']")
+    );
+    assert_eq!(
+        descriptors.change,
+        plain.change.replace("[b88448fb/0']", "[aabbccdd/0']")
+    );
+
+    assert!(matches!(
+        wallet.to_descriptors_with_origin(&[origin.clone(), origin]),
+        Err(Electrum2DescriptorError::WrongNumberOfOrigins(2, 1))
+    ));
+    let deep = (
+        Fingerprint::from_str("aabbccdd").unwrap(),
+        DerivationPath::from_str("m/84h/1h/0h").unwrap(),
+    );
+    assert!(matches!(
+        wallet.to_descriptors_with_origin(&[deep]),
+        Err(Electrum2DescriptorError::OriginDepthMismatch(_, 3, 1))
+    ));
+
+    let multisig =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_hw_segwit")).unwrap();
+    let origins = [
+        ("11111111", "m/48'/1'/0'/2'"),
+        ("22222222", "m/48'/1'/1'/2'"),
+    ]
+    .map(|(fp, path)| {
+        (
+            Fingerprint::from_str(fp).unwrap(),
+            DerivationPath::from_str(path).unwrap(),
+        )
+    });
+    let descriptors = multisig.to_descriptors_with_origin(&origins).unwrap();
+    assert_eq!(
+        descriptors.external,
+        format!(
+            "wsh(sortedmulti(2,[11111111/48'/1'/0'/2']{}/0/*,[22222222/48'/1'/1'/2']{}/0/*))",
+            HW_SEGWIT_XPUBS[0], HW_SEGWIT_XPUBS[1]
+        )
+    );
+    assert!(descriptors.change.contains("[22222222/48'/1'/1'/2']"));
+}
+
+#[test]
+fn coldcard_ckcc_xfp() {
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file("coldcard_ckcc_xfp")).unwrap();
+    let keystore = &wallet.keystores()[0];
+    let fingerprint = Fingerprint::from_str("3442193e").unwrap();
+    assert_eq!(keystore.root_fingerprint.as_deref(), Some("3442193e"));
+    assert_eq!(
+        keystore.origin().unwrap(),
+        Some((
+            fingerprint,
+            DerivationPath::from_str("m/84'/1'/0'").unwrap()
+        ))
+    );
+    assert!(wallet
+        .descriptor_at(false, 0)
+        .unwrap()
+        .starts_with("wpkh([3442193e/84'/1'/0'/0/0]"));
+    assert_eq!(
+        first_address_from_descriptor(&wallet.to_descriptors().unwrap().external, Network::Testnet),
+        wallet.addresses().receiving[0]
+    );
+
+    let json: serde_json::Value = serde_json::from_str(&wallet.to_string()).unwrap();
+    assert_eq!(json["keystore"]["ckcc_xfp"], 1041842740);
+    assert_eq!(json["keystore"]["hw_type"], "coldcard");
+    assert_eq!(json["keystore"]["root_fingerprint"], "3442193e");
+
+    // a root_fingerprint takes precedence
+    let path = get_test_wallet_file("coldcard_ckcc_xfp");
+    let mut json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    json["keystore"]["root_fingerprint"] = "aabbccdd".into();
+    let wallet = ElectrumWalletFile::from_str(&json.to_string()).unwrap();
+    assert_eq!(
+        wallet.keystores()[0].root_fingerprint.as_deref(),
+        Some("aabbccdd")
+    );
+}
+
+#[rstest]
+#[case::regtest(Network::Regtest, "bcrt1")]
+#[case::signet(Network::Signet, "tb1")]
+#[case::testnet(Network::Testnet, "tb1")]
+fn set_network(#[case] network: Network, #[case] hrp: &str) {
+    let mut wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("default_segwit")).unwrap();
+    let receiving = wallet.addresses().receiving.len();
+    wallet.set_network(network).unwrap();
+    assert_eq!(wallet.network().unwrap(), network);
+    assert_eq!(wallet.addresses().receiving.len(), receiving);
+    // the history is of the testnet addresses
+    assert_eq!(
+        wallet.raw_field("addr_history").is_none(),
+        network == Network::Regtest
+    );
+    assert!(wallet
+        .addresses()
+        .receiving
+        .iter()
+        .all(|a| a.starts_with(hrp)));
+    assert_eq!(
+        wallet.addresses().receiving[0],
+        first_address_from_descriptor(&wallet.to_descriptors().unwrap().external, network)
+    );
+    wallet.verify_addresses().unwrap();
+    // the keys stay encoded for testnet
+    assert!(wallet.keystores()[0].xpub.starts_with("vpub"));
+
+    let reparsed = ElectrumWalletFile::from_str(&wallet.to_string()).unwrap();
+    assert_eq!(reparsed.addresses(), wallet.addresses());
+    reparsed.verify_addresses().unwrap();
+    if network == Network::Regtest {
+        assert_eq!(reparsed.network().unwrap(), Network::Regtest);
+        assert_eq!(reparsed.summary().unwrap().network, Network::Regtest);
+    }
+
+    assert!(matches!(
+        wallet.set_network(Network::Bitcoin),
+        Err(Electrum2DescriptorError::MixedNetworks(_, _))
+    ));
+}
+
+#[test]
+fn regtest_addresses() {
+    // base58 addresses are the same on all test networks
+    let mut legacy =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("default_legacy")).unwrap();
+    let testnet = legacy.addresses().clone();
+    legacy.set_network(Network::Regtest).unwrap();
+    assert_eq!(legacy.addresses(), &testnet);
+
+    let mut multisig =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_segwit")).unwrap();
+    multisig.set_network(Network::Regtest).unwrap();
+    assert!(multisig
+        .address(false, 0)
+        .unwrap()
+        .to_string()
+        .starts_with("bcrt1q"));
+    multisig.verify_addresses().unwrap();
+
+    // testnet addresses in a wallet whose addresses are for regtest
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file("default_segwit")).unwrap();
+    let mut regtest = wallet.clone();
+    regtest.set_network(Network::Regtest).unwrap();
+    let mut json: serde_json::Value = serde_json::from_str(&regtest.to_string()).unwrap();
+    json["addresses"]["change"][0] = wallet.addresses().change[0].clone().into();
+    let mixed = ElectrumWalletFile::from_str(&json.to_string()).unwrap();
+    assert!(mixed.verify_addresses().is_err());
+}
+
+#[rstest]
+#[case::multisig_legacy("multisig_legacy")]
+#[case::multisig_segwit("multisig_segwit")]
+#[case::multisig_wrapped("multisig_wrapped")]
+#[case::multisig_hw_segwit("multisig_hw_segwit")]
+fn key_order(#[case] wallet_name: &str) {
+    let sorted = ElectrumWalletFile::from_file(&get_test_wallet_file(wallet_name)).unwrap();
+    let network = sorted.network().unwrap();
+    let mut as_stored = sorted.clone();
+    as_stored.set_key_order(KeyOrder::AsStored).unwrap();
+    for wallet in [&sorted, &as_stored] {
+        let descriptors = wallet.to_descriptors().unwrap();
+        assert_eq!(
+            first_address_from_descriptor(&descriptors.external, network),
+            wallet.address(false, 0).unwrap().to_string()
+        );
+        wallet.verify_addresses().unwrap();
+    }
+    // the keystores of the fixtures aren't in bip-0067 order
+    assert_ne!(
+        sorted.address(false, 0).unwrap(),
+        as_stored.address(false, 0).unwrap()
+    );
+    assert_ne!(sorted.wallet_id().unwrap(), as_stored.wallet_id().unwrap());
+
+    let descriptors = as_stored.to_descriptors().unwrap();
+    assert!(descriptors.external.contains("(multi("));
+    assert!(as_stored
+        .descriptor_at(false, 0)
+        .unwrap()
+        .contains("(multi("));
+    let mut warnings = Vec::new();
+    as_stored
+        .to_descriptors_with_warnings(&mut warnings)
+        .unwrap();
+    assert!(warnings.contains(&ConversionWarning::UnsortedKeys));
+
+    // the order survives a round trip through the descriptor
+    let restored = ElectrumWalletFile::from_descriptor(&descriptors.external).unwrap();
+    assert_eq!(restored.key_order(), KeyOrder::AsStored);
+    assert_eq!(
+        restored.address(false, 0).unwrap(),
+        as_stored.address(false, 0).unwrap()
+    );
+    let original =
+        ElectrumWalletFile::from_descriptor(&sorted.to_descriptors().unwrap().external).unwrap();
+    assert_eq!(original.key_order(), KeyOrder::Sorted);
+}
+
+#[rstest]
+#[case::multisig_legacy("multisig_legacy")]
+#[case::multisig_segwit("multisig_segwit")]
+#[case::multisig_wrapped("multisig_wrapped")]
+#[case::multisig_hw_segwit("multisig_hw_segwit")]
+fn sort_cosigners(#[case] wallet_name: &str) {
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file(wallet_name)).unwrap();
+    let network = wallet.network().unwrap();
+    let threshold = wallet.wallet_type().threshold();
+    let mut reversed_keystores = wallet.keystores().to_vec();
+    reversed_keystores.reverse();
+    let mut stored = ElectrumWalletFile::new(wallet.keystores(), threshold).unwrap();
+    let mut reversed = ElectrumWalletFile::new(&reversed_keystores, threshold).unwrap();
+    assert_ne!(stored, reversed);
+
+    let address = wallet.address(false, 0).unwrap();
+    let wallet_id = wallet.wallet_id().unwrap();
+    stored.sort_cosigners().unwrap();
+    reversed
+        .canonicalize_with(&CanonicalizeOptions {
+            sort_cosigners: true,
+        })
+        .unwrap();
+    stored.canonicalize().unwrap();
+    assert_eq!(stored, reversed);
+    assert!(stored
+        .keystores()
+        .windows(2)
+        .all(|k| k[0].key_id() <= k[1].key_id()));
+    for sorted in [&stored, &reversed] {
+        let descriptors = sorted.to_descriptors().unwrap();
+        assert_eq!(
+            first_address_from_descriptor(&descriptors.external, network),
+            address.to_string()
+        );
+        assert_eq!(sorted.address(false, 0).unwrap(), address);
+        assert_eq!(sorted.wallet_id().unwrap(), wallet_id);
+    }
+
+    // the stored addresses stay valid
+    let mut sorted = wallet.clone();
+    sorted.sort_cosigners().unwrap();
+    sorted.verify_addresses().unwrap();
+
+    let mut as_stored = wallet;
+    as_stored.set_key_order(KeyOrder::AsStored).unwrap();
+    assert!(matches!(
+        as_stored.sort_cosigners(),
+        Err(Electrum2DescriptorError::FixedKeyOrder)
+    ));
+}
+
+#[test]
+fn unfinished_setup() {
+    let wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_unfinished")).unwrap();
+    assert!(wallet.is_incomplete());
+    assert_eq!(wallet.unfinished_slots(), ["x2/", "x3/"]);
+    assert_eq!(wallet.keystores().len(), 1);
+    let e = wallet.to_descriptors().unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "x2/ has no xpub, x3/ has no xpub — wallet setup was not finished"
+    );
+    assert_eq!(
+        wallet.descriptor_at(false, 0).unwrap_err().to_string(),
+        e.to_string()
+    );
+
+    let summary = wallet.summary().unwrap();
+    assert_eq!(summary.wallet_type, WalletType::multisig(2, 3).unwrap());
+    assert_eq!(summary.cosigners.len(), 1);
+    assert_eq!(summary.unfinished_slots, ["x2/", "x3/"]);
+
+    // a finished slot after an unfinished one
+    let mut json: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(get_test_wallet_file("multisig_unfinished")).unwrap(),
+    )
+    .unwrap();
+    json["x3/"] = json["x1/"].clone();
+    json["x2/"]["xpub"] = serde_json::Value::Null;
+    let wallet = ElectrumWalletFile::from_str(&json.to_string()).unwrap();
+    assert_eq!(wallet.unfinished_slots(), ["x2/"]);
+    assert_eq!(wallet.keystores().len(), 2);
+}
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn send_sync() {
+    assert_send_sync::<ElectrumWalletFile>();
+    assert_send_sync::<Keystore>();
+    assert_send_sync::<Box<dyn ElectrumExtendedKey>>();
+    assert_send_sync::<ElectrumExtendedPubKey>();
+    assert_send_sync::<ElectrumExtendedPrivKey>();
+    assert_send_sync::<Electrum2DescriptorError>();
+    assert_send_sync::<Descriptors>();
+    assert_send_sync::<ConversionWarning>();
+    assert_send_sync::<WalletSummary>();
+    assert_send_sync::<FoundKey>();
+    assert_send_sync::<batch::Mapped<Descriptors>>();
+    assert_send_sync::<<batch::Mapped<WalletSummary> as IntoIterator>::IntoIter>();
+}
+
+#[test]
+fn remove_cosigner() {
+    let mut wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_distinct_paths")).unwrap();
+    let keystores = wallet.keystores().to_vec();
+    wallet
+        .set_raw_field("txi", serde_json::json!({"txid": {}}))
+        .unwrap();
+    let removed = wallet.remove_cosigner(1).unwrap();
+    assert_eq!(removed, keystores[1]);
+    assert_eq!(wallet.wallet_type(), &WalletType::multisig(2, 2).unwrap());
+    assert!(wallet.addresses().receiving.is_empty());
+    assert!(wallet.addresses().change.is_empty());
+    assert!(wallet.raw_field("txi").is_none());
+    let expected =
+        ElectrumWalletFile::new(&[keystores[0].clone(), keystores[2].clone()], 2).unwrap();
+    assert_eq!(
+        wallet.to_descriptors().unwrap(),
+        expected.to_descriptors().unwrap()
+    );
+
+    // the slots are renumbered
+    let json: serde_json::Value = serde_json::from_str(&wallet.to_string()).unwrap();
+    assert_eq!(json["wallet_type"], "2of2");
+    assert_eq!(json["x1/"]["xpub"], keystores[0].xpub.as_str());
+    assert_eq!(json["x2/"]["xpub"], keystores[2].xpub.as_str());
+    assert!(json.get("x3/").is_none());
+    let reparsed = ElectrumWalletFile::from_str(&wallet.to_string()).unwrap();
+    assert_eq!(reparsed.keystores(), wallet.keystores());
+
+    assert!(matches!(
+        wallet.remove_cosigner(0),
+        Err(Electrum2DescriptorError::MultisigFewSigners)
+    ));
+    let mut three_of_three = ElectrumWalletFile::new(&keystores, 3).unwrap();
+    assert!(matches!(
+        three_of_three.remove_cosigner(0),
+        Err(Electrum2DescriptorError::ThresholdAboveCosigners(3, 2))
+    ));
+    assert_eq!(three_of_three.keystores().len(), 3);
+    assert!(matches!(
+        three_of_three.remove_cosigner(3),
+        Err(Electrum2DescriptorError::UnknownCosigner(3, 3))
+    ));
+    let mut standard =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("default_segwit")).unwrap();
+    assert!(matches!(
+        standard.remove_cosigner(0),
+        Err(Electrum2DescriptorError::NotMultisig)
+    ));
+}
+
+#[rstest]
+#[case::lower(1, Ok(()))]
+#[case::same(2, Ok(()))]
+#[case::all(3, Ok(()))]
+#[case::zero(0, Err("Threshold 0 is out of range"))]
+#[case::above(4, Err("Threshold 4 is out of range"))]
+fn set_threshold(#[case] threshold: u8, #[case] expected: Result<(), &str>) {
+    let original =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_distinct_paths")).unwrap();
+    let mut wallet = original.clone();
+    wallet
+        .set_raw_field("addr_history", serde_json::json!({"address": []}))
+        .unwrap();
+    let original = wallet.clone();
+    match (wallet.set_threshold(threshold), expected) {
+        (Ok(()), Ok(())) => {
+            assert_eq!(
+                wallet.wallet_type(),
+                &WalletType::multisig(threshold.into(), 3).unwrap()
+            );
+            assert!(wallet.addresses().receiving.is_empty());
+            assert!(wallet.addresses().change.is_empty());
+            assert!(wallet.raw_field("addr_history").is_none());
+            let expected = ElectrumWalletFile::new(original.keystores(), threshold).unwrap();
+            assert_eq!(
+                wallet.to_descriptors().unwrap(),
+                expected.to_descriptors().unwrap()
+            );
+            assert!(wallet
+                .to_descriptors()
+                .unwrap()
+                .external
+                .contains(&format!("sortedmulti({},", threshold)));
+        }
+        (Err(e), Err(message)) => {
+            assert!(e.to_string().starts_with(message), "{}", e);
+            assert_eq!(wallet, original);
+        }
+        (result, expected) => panic!("{:?}, expected {:?}", result, expected),
+    }
+}
+
+#[test]
+fn set_threshold_standard() {
+    let mut wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("default_segwit")).unwrap();
+    assert!(matches!(
+        wallet.set_threshold(1),
+        Err(Electrum2DescriptorError::NotMultisig)
+    ));
+}
+
+#[test]
+fn add_cosigner() {
+    let mut wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_segwit")).unwrap();
+    let other =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_distinct_paths")).unwrap();
+    wallet.add_cosigner(other.keystores()[0].clone()).unwrap();
+    assert_eq!(wallet.wallet_type(), &WalletType::multisig(2, 3).unwrap());
+    assert_eq!(wallet.keystores()[2], other.keystores()[0]);
+    assert!(wallet.addresses().receiving.is_empty());
+    for field in ["addr_history", "txi", "txo"] {
+        assert!(wallet.raw_field(field).is_none());
+    }
+    assert!(wallet.raw_field("labels").is_some());
+    let json: serde_json::Value = serde_json::from_str(&wallet.to_string()).unwrap();
+    assert_eq!(json["x3/"]["xpub"], other.keystores()[0].xpub.as_str());
+
+    let legacy = ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_legacy")).unwrap();
+    assert!(matches!(
+        wallet.add_cosigner(legacy.keystores()[0].clone()),
+        Err(Electrum2DescriptorError::MixedScriptKinds(_, _))
+    ));
+    let mut standard =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("default_segwit")).unwrap();
+    assert!(matches!(
+        standard.add_cosigner(other.keystores()[0].clone()),
+        Err(Electrum2DescriptorError::NotMultisig)
+    ));
+}
+
+#[test]
+fn json_value_round_trip() {
+    let wallets = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/wallets");
+    let mut parsed = 0;
+    for entry in std::fs::read_dir(wallets).unwrap() {
+        let path = entry.unwrap().path();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let value: serde_json::Value = match serde_json::from_str(&text) {
+            Ok(value) => value,
+            // encrypted wallet files aren't json
+            Err(_) => continue,
+        };
+        match ElectrumWalletFile::from_str(&text) {
+            Ok(wallet) => {
+                assert_eq!(
+                    ElectrumWalletFile::from_value(value).unwrap(),
+                    wallet,
+                    "{:?}",
+                    path
+                );
+                let json = wallet.to_value().unwrap();
+                assert_eq!(
+                    json,
+                    serde_json::from_str::<serde_json::Value>(&wallet.to_string()).unwrap(),
+                    "{:?}",
+                    path
+                );
+                // the same as the string round trip, which e.g. leaves out the warnings of repairs
+                assert_eq!(
+                    ElectrumWalletFile::from_value(json).unwrap(),
+                    ElectrumWalletFile::from_str(&wallet.to_string()).unwrap(),
+                    "{:?}",
+                    path
+                );
+                parsed += 1;
+            }
+            // imported addresses without keys fail either way
+            Err(e) => assert_eq!(
+                ElectrumWalletFile::from_value(value)
+                    .unwrap_err()
+                    .to_string()
+                    .split(" at line")
+                    .next(),
+                e.to_string().split(" at line").next(),
+                "{:?}",
+                path
+            ),
+        }
+    }
+    assert!(parsed > 20);
+}
+
+#[rstest]
+#[case::legacy_watch("default_legacy_watch", false, "electrum --testnet restore tpub")]
+#[case::segwit("default_segwit", false, "electrum --testnet restore vpub")]
+#[case::segwit_private("default_segwit", true, "electrum --testnet restore vprv")]
+#[case::multisig("multisig_segwit", false, "electrum --testnet restore \"2of2 Vpub")]
+#[case::multisig_private("multisig_segwit", true, "electrum --testnet restore \"2of2 Vprv")]
+// cosigners with singlesig prefixes get the multisig ones
+#[case::multisig_cosmetic(
+    "multisig_segwit_cosmetic",
+    false,
+    "electrum --testnet restore \"2of2 Vpub"
+)]
+#[case::wrapped("multisig_wrapped", false, "electrum --testnet restore \"2of2 Upub")]
+fn restore_command(#[case] wallet_name: &str, #[case] private: bool, #[case] expected: &str) {
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file(wallet_name)).unwrap();
+    let command = wallet.to_restore_command(private).unwrap();
+    assert!(command.starts_with(expected), "{}", command);
+    let text = command.trim_start_matches("electrum --testnet restore ");
+    let keys = text.trim_matches('"').split(' ').collect::<Vec<_>>();
+    let keys = match wallet.wallet_type() {
+        WalletType::Standard => keys,
+        WalletType::Multisig(multisig) => {
+            assert_eq!(
+                keys[0],
+                format!("{}of{}", multisig.threshold(), multisig.cosigners())
+            );
+            keys[1..].to_vec()
+        }
+        WalletType::Imported => unreachable!("imported keys have no restore command"),
+    };
+    assert_eq!(keys.len(), wallet.keystores().len());
+    // the keys are the same as the stored ones, up to the prefix
+    for (key, keystore) in keys.iter().zip(wallet.keystores()) {
+        let restored = match Keystore::from_electrum_xkey(key).unwrap().xprv().unwrap() {
+            Some(xprv) => {
+                ExtendedPubKey::from_priv(&bdk::bitcoin::secp256k1::Secp256k1::new(), &xprv)
+            }
+            None => Keystore::from_electrum_xkey(key).unwrap().xpub().unwrap().0,
+        };
+        assert_eq!(restored, keystore.xpub().unwrap().0);
+    }
+}
+
+#[test]
+fn restore_command_errors() {
+    let watch =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("default_legacy_watch")).unwrap();
+    assert!(matches!(
+        watch.to_restore_command(true),
+        Err(Electrum2DescriptorError::NoPrivateKeys)
+    ));
+    let taproot = ElectrumWalletFile::from_file(&get_test_wallet_file("taproot")).unwrap();
+    assert!(matches!(
+        taproot.to_restore_command(false),
+        Err(Electrum2DescriptorError::NotRestorable(_))
+    ));
+    let mut regtest =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("default_segwit")).unwrap();
+    regtest.set_network(Network::Regtest).unwrap();
+    assert!(regtest
+        .to_restore_command(false)
+        .unwrap()
+        .starts_with("electrum --regtest restore vpub"));
+}
+
+#[rstest]
+#[case::xpub("pkh", Some(ScriptKind::Standard), "pkh({}/0/*)")]
+#[case::xpub_segwit("pkh", Some(ScriptKind::P2wpkh), "wpkh({}/0/*)")]
+#[case::ypub("sh(wpkh", None, "sh(wpkh({}/0/*))")]
+#[case::ypub_kind("sh(wpkh", Some(ScriptKind::P2wpkhP2sh), "sh(wpkh({}/0/*))")]
+#[case::zpub("wpkh", None, "wpkh({}/0/*)")]
+fn from_xpub(#[case] prefix: &str, #[case] kind: Option<ScriptKind>, #[case] descriptor: &str) {
+    for xpub in [BIP32_XPUBS[0], "tpubD9ZjaMn3rbP1cAVwJy6UcEjFfTLT7W6DbfHdS3Wn48meExtVfKmiH9meWCrSmE9qXLYbGcHC5LxLcdfLZTzwme23qAJoRzRhzbd68dHeyjp"] {
+        let wallet = ElectrumWalletFile::from_xpub(&to_electrum_xpub(xpub, prefix), kind).unwrap();
+        let expected = ElectrumWalletFile::from_descriptor(&descriptor.replace("{}", xpub)).unwrap();
+        assert_eq!(wallet, expected);
+        assert_eq!(wallet.to_descriptors().unwrap(), expected.to_descriptors().unwrap());
+    }
+}
+
+#[test]
+fn from_xprv() {
+    let tprv = "tprv8ZgxMBicQKsPeYnCHtn5QZqhTgkkDmXebfQMXWmX7ThXJFCbzDTKFNRsB43GUmHzu2pdGcnnegFy175kFcgZQYC5BFPnRdYDPQyqetpyjb5";
+    let vprv = ElectrumExtendedPrivKey::new(
+        bdk::bitcoin::bip32::ExtendedPrivKey::from_str(tprv).unwrap(),
+        "wpkh".to_string(),
+    )
+    .electrum_xprv()
+    .unwrap();
+    let expected = ElectrumWalletFile::from_descriptor(&format!("wpkh({}/0/*)", tprv)).unwrap();
+    assert_eq!(
+        ElectrumWalletFile::from_xpub(&vprv, None).unwrap(),
+        expected
+    );
+    assert_eq!(
+        ElectrumWalletFile::from_xpub(tprv, Some(ScriptKind::P2wpkh)).unwrap(),
+        expected
+    );
+    assert!(expected.keystores()[0].xprv().unwrap().is_some());
+}
+
+#[test]
+fn from_xpub_errors() {
+    assert!(matches!(
+        ElectrumWalletFile::from_xpub(BIP32_XPUBS[0], None),
+        Err(Electrum2DescriptorError::ScriptKindRequired(_))
+    ));
+    let zpub = to_electrum_xpub(BIP32_XPUBS[0], "wpkh");
+    assert!(matches!(
+        ElectrumWalletFile::from_xpub(&zpub, Some(ScriptKind::P2wpkhP2sh)),
+        Err(Electrum2DescriptorError::ScriptKindConflict(..))
+    ));
+    assert!(matches!(
+        ElectrumWalletFile::from_xpub(&to_electrum_xpub(BIP32_XPUBS[0], "wsh"), None),
+        Err(Electrum2DescriptorError::ScriptKindConflict(..))
+    ));
+    assert!(matches!(
+        ElectrumWalletFile::from_xpub(BIP32_XPUBS[0], Some(ScriptKind::P2wshP2sh)),
+        Err(Electrum2DescriptorError::ScriptKindConflict(..))
+    ));
+    // a valid base58check key with the version bytes of ltub, litecoin's xpub
+    let mut data = bdk::bitcoin::base58::decode_check(BIP32_XPUBS[0]).unwrap();
+    data[0..4].copy_from_slice(&[0x01, 0x9d, 0xa4, 0x62]);
+    let ltub = bdk::bitcoin::base58::encode_check(&data);
+    assert!(matches!(
+        ElectrumWalletFile::from_xpub(&ltub, Some(ScriptKind::P2wpkh)),
+        Err(Electrum2DescriptorError::UnknownKeyPrefix(_))
+    ));
+}
+
+#[rstest]
+#[case::tprv(Network::Testnet, "pkh", "tprv", "tpub")]
+#[case::uprv(Network::Testnet, "sh(wpkh", "uprv", "upub")]
+#[case::vprv(Network::Testnet, "wpkh", "vprv", "vpub")]
+#[case::uprv_multisig(Network::Testnet, "sh(wsh", "Uprv", "Upub")]
+#[case::vprv_multisig(Network::Testnet, "wsh", "Vprv", "Vpub")]
+#[case::xprv(Network::Bitcoin, "pkh", "xprv", "xpub")]
+#[case::yprv(Network::Bitcoin, "sh(wpkh", "yprv", "ypub")]
+#[case::zprv(Network::Bitcoin, "wpkh", "zprv", "zpub")]
+#[case::yprv_multisig(Network::Bitcoin, "sh(wsh", "Yprv", "Ypub")]
+#[case::zprv_multisig(Network::Bitcoin, "wsh", "Zprv", "Zpub")]
+fn slip132_private_prefixes(
+    #[case] network: Network,
+    #[case] kind: &str,
+    #[case] prv_prefix: &str,
+    #[case] pub_prefix: &str,
+) {
+    let xprvs = [
+        "tprv8ZgxMBicQKsPeLPWr5WbJDAhANr6irc1Yf7eUNCYjGYap27HU4bDBXWGMT3X75FhDyxNXr6pK4QeHcCBvkqchQzK8wZ4JbGv5X5MWtXQtqy",
+        "tprv8dNybiDsdyms39SAWTxyiNHABTTgiqmJpScmxGrdKEuZ7TwXcaYXT4f4ddVjWiiQs9zowHqyDmvaebN6fU2Lu6iAYnYuepiLkvzGdcZZi8D",
+    ]
+    .map(|tprv| {
+        let mut xprv = bdk::bitcoin::bip32::ExtendedPrivKey::from_str(tprv).unwrap();
+        xprv.network = network;
+        xprv
+    });
+    let electrum_xprvs = xprvs.map(|xprv| {
+        ElectrumExtendedPrivKey::new(xprv, kind.to_string())
+            .electrum_xprv()
+            .unwrap()
+    });
+    let closing = ")".repeat(kind.matches('(').count());
+    let descriptor = |keys: [String; 2]| {
+        if kind.ends_with("wsh") {
+            format!(
+                "{}(sortedmulti(1,{}/0/*,{}/0/*)){}",
+                kind, keys[0], keys[1], closing
+            )
+        } else {
+            format!("{}({}/0/*){}", kind, keys[0], closing)
+        }
+    };
+
+    // the slip-0132 keys are read into keystores with the matching xpub prefix
+    let wallet = ElectrumWalletFile::from_descriptor(&descriptor(electrum_xprvs.clone())).unwrap();
+    for keystore in wallet.keystores() {
+        assert!(keystore.xprv.as_deref().unwrap().starts_with(prv_prefix));
+        assert!(keystore.xpub.starts_with(pub_prefix));
+    }
+    // and written to descriptors as xprv or tprv, as read from the wallet file
+    let expected = descriptor(xprvs.map(|xprv| xprv.to_string()));
+    assert_eq!(wallet.to_descriptors().unwrap().external, expected);
+    let imported = ElectrumWalletFile::from_str(&wallet.to_string()).unwrap();
+    assert_eq!(imported.to_descriptors().unwrap().external, expected);
+    assert_eq!(
+        ElectrumWalletFile::from_descriptor(&expected)
+            .unwrap()
+            .to_string(),
+        wallet.to_string()
+    );
+}
+
+#[test]
+fn slip132_prefix_conflict() {
+    let tprv = bdk::bitcoin::bip32::ExtendedPrivKey::from_str("tprv8ZgxMBicQKsPeLPWr5WbJDAhANr6irc1Yf7eUNCYjGYap27HU4bDBXWGMT3X75FhDyxNXr6pK4QeHcCBvkqchQzK8wZ4JbGv5X5MWtXQtqy").unwrap();
+    let uprv = ElectrumExtendedPrivKey::new(tprv, "sh(wpkh".to_string())
+        .electrum_xprv()
+        .unwrap();
+    assert!(matches!(
+        ElectrumWalletFile::from_descriptor(&format!("wpkh({}/0/*)", uprv)),
+        Err(Electrum2DescriptorError::ScriptKindConflict(..))
+    ));
+    // singlesig prefixes stand for their multisig counterparts
+    let vprv = ElectrumExtendedPrivKey::new(tprv, "wpkh".to_string())
+        .electrum_xprv()
+        .unwrap();
+    let wallet = ElectrumWalletFile::from_descriptor(&format!(
+        "wsh(sortedmulti(1,{}/0/*,{}/0/*))",
+        vprv, "tpubD6NzVbkrYhZ4Y1ozBYSfoyVp2iGgP6iZAy18p2opXjVv8jTNccGuRs3jMCMe4ncfwy2RUJsoZLSXsGiFhN47xFbJgtRvCuV3RP3UnxpsrZt"
+    ))
+    .unwrap();
+    assert!(wallet.keystores()[0]
+        .xprv
+        .as_deref()
+        .unwrap()
+        .starts_with("Vprv"));
+}
+
+#[rstest]
+#[case::zero_width_space("\u{200b}{}\u{200b}")]
+#[case::non_breaking_space("\u{00a0}{}\u{00a0}")]
+#[case::curly_quotes("\u{201c}{}\u{201d}")]
+#[case::quoted_line_break("\u{2018}{}\r\n\u{2019}")]
+fn pasted_keys(#[case] contamination: &str) {
+    let vpub = "vpub5VXaSncXqxLbdmvrC4Y8z9CszPwuEscADoetWhfrxDFzPUbL5nbVtanYDkrVEutkv9n5A5aCcvRC9swbjDKgHjCZ2tAeae8VsBuPbS8KpXv";
+    let (start, end) = vpub.split_at(40);
+    let broken = format!("{}\u{200b}\n{}", start, end);
+    let pasted = contamination.replace("{}", &broken);
+    let tpub = ElectrumExtendedPubKey::from_str(vpub).unwrap().xkey_str();
+
+    assert_eq!(
+        ElectrumExtendedPubKey::from_str(&pasted)
+            .unwrap()
+            .electrum_xpub()
+            .unwrap(),
+        vpub
+    );
+    assert_eq!(
+        ElectrumWalletFile::from_xpub(&pasted, None)
+            .unwrap()
+            .to_string(),
+        ElectrumWalletFile::from_xpub(vpub, None)
+            .unwrap()
+            .to_string()
+    );
+    assert_eq!(Keystore::new("wpkh", &pasted).unwrap().xpub, vpub);
+    let expected = format!("wpkh({}/0/*)", tpub);
+    let descriptor = contamination.replace("{}", &expected.replace(&tpub, &broken));
+    assert_eq!(
+        ElectrumWalletFile::from_descriptor(&descriptor)
+            .unwrap()
+            .to_descriptors()
+            .unwrap()
+            .external,
+        expected
+    );
+}
+
+#[test]
+fn pasted_key_invalid_character() {
+    let vpub = "vpub5VXaSncXqxLbdmvrC4Y8z9CszPwuEscADoetWhfrxDFzPUbL5nbVtanYDkrVEutkv9n5A5aCcvRC9swbjDKgHjCZ2tAeae8VsBuPbS8KpXv";
+    let pasted = format!("\u{200b}{}\u{2013}{}", &vpub[..20], &vpub[20..]);
+    for result in [
+        ElectrumWalletFile::from_xpub(&pasted, None).map(|_| ()),
+        Keystore::new("wpkh", &pasted).map(|_| ()),
+        ElectrumExtendedPubKey::from_str(&pasted).map(|_| ()),
+    ] {
+        assert!(matches!(
+            result,
+            Err(Electrum2DescriptorError::InvalidKeyCharacter(
+                '\u{2013}', 22
+            ))
+        ));
+    }
+    let error = ElectrumExtendedPubKey::from_str(&pasted).err().unwrap();
+    assert_eq!(
+        error.to_string(),
+        "Invalid character '\u{2013}' at position 22 of the key"
+    );
+}
+
+/// Check a value against the parts of JSON schema that [`ElectrumWalletFile::json_schema`] uses
+fn schema_errors(
+    root: &serde_json::Value,
+    schema: &serde_json::Value,
+    value: &serde_json::Value,
+    path: &str,
+) -> Vec<String> {
+    use serde_json::Value;
+    if let Some(Value::String(reference)) = schema.get("$ref") {
+        let name = reference.trim_start_matches("#/$defs/");
+        return schema_errors(root, &root["$defs"][name], value, path);
+    }
+    let mut errors = Vec::new();
+    if let Some(types) = schema.get("type") {
+        let types = match types {
+            Value::Array(types) => types.iter().filter_map(|t| t.as_str()).collect(),
+            t => vec![t.as_str().unwrap()],
+        };
+        let matches = types.iter().any(|t| match *t {
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "string" => value.is_string(),
+            "integer" => value.is_u64() || value.is_i64(),
+            "null" => value.is_null(),
+            _ => false,
+        });
+        if !matches {
+            return vec![format!("{}: {} is not {:?}", path, value, types)];
+        }
+    }
+    if let Some(Value::Array(variants)) = schema.get("anyOf") {
+        if variants
+            .iter()
+            .all(|v| !schema_errors(root, v, value, path).is_empty())
+        {
+            errors.push(format!("{}: {} matches none of anyOf", path, value));
+        }
+    }
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(value) {
+            errors.push(format!("{}: {} is not in {:?}", path, value, allowed));
+        }
+    }
+    if let (Some(Value::String(pattern)), Some(s)) = (schema.get("pattern"), value.as_str()) {
+        if !regex::Regex::new(pattern).unwrap().is_match(s) {
+            errors.push(format!("{}: {} doesn't match {}", path, s, pattern));
+        }
+    }
+    if let (Some(Value::Array(required)), Some(object)) =
+        (schema.get("required"), value.as_object())
+    {
+        for field in required.iter().filter_map(|f| f.as_str()) {
+            if !object.contains_key(field) {
+                errors.push(format!("{}: {} is missing", path, field));
+            }
+        }
+    }
+    if let Some(object) = value.as_object() {
+        for (key, field) in object {
+            let field_path = format!("{}/{}", path, key);
+            if let Some(field_schema) = schema.get("properties").and_then(|p| p.get(key)) {
+                errors.extend(schema_errors(root, field_schema, field, &field_path));
+            }
+            for (pattern, field_schema) in schema
+                .get("patternProperties")
+                .and_then(|p| p.as_object())
+                .into_iter()
+                .flatten()
+            {
+                if regex::Regex::new(pattern).unwrap().is_match(key) {
+                    errors.extend(schema_errors(root, field_schema, field, &field_path));
+                }
+            }
+        }
+    }
+    if let (Some(items), Some(array)) = (schema.get("items"), value.as_array()) {
+        for (i, item) in array.iter().enumerate() {
+            errors.extend(schema_errors(root, items, item, &format!("{}/{}", path, i)));
+        }
+    }
+    errors
+}
+
+#[test]
+fn json_schema() {
+    let schema = ElectrumWalletFile::json_schema();
+    let mut validated = 0;
+    for entry in std::fs::read_dir("tests/wallets").unwrap() {
+        let path = entry.unwrap().path();
+        let wallet = match ElectrumWalletFile::from_file(&path) {
+            Ok(wallet) => wallet,
+            Err(_) => continue,
+        };
+        let value: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            schema_errors(&schema, &schema, &value, ""),
+            Vec::<String>::new(),
+            "{:?}",
+            path
+        );
+        let written = wallet.to_value().unwrap();
+        assert_eq!(
+            schema_errors(&schema, &schema, &written, ""),
+            Vec::<String>::new(),
+            "{:?}",
+            path
+        );
+        validated += 1;
+    }
+    assert!(validated > 20);
+
+    let mut bad: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(get_test_wallet_file("multisig_segwit")).unwrap(),
+    )
+    .unwrap();
+    bad["wallet_type"] = "two of two".into();
+    bad["x1/"]["xprv"] = 1.into();
+    bad["x2/"].as_object_mut().unwrap().remove("xpub");
+    assert_eq!(schema_errors(&schema, &schema, &bad, "").len(), 3);
+}
+
+#[rstest]
+#[case::distinct_paths("multisig_hw_segwit", "bluewallet_hw_segwit")]
+#[case::shared_path("multisig_wrapped", "bluewallet_wrapped")]
+fn bluewallet_text(#[case] wallet_name: &str, #[case] setup_name: &str) {
+    // only the xpubs are exported
+    let wallet =
+        ElectrumWalletFile::from_file_watch_only(&get_test_wallet_file(wallet_name)).unwrap();
+    let text = std::fs::read_to_string(Path::new("tests/setup_files").join(setup_name)).unwrap();
+    assert_eq!(wallet.to_bluewallet_text().unwrap(), text);
+
+    let imported = ElectrumWalletFile::from_bluewallet_text(&text).unwrap();
+    assert_eq!(imported.wallet_type(), wallet.wallet_type());
+    assert_eq!(
+        imported.to_descriptors().unwrap(),
+        wallet.to_descriptors().unwrap()
+    );
+    assert_eq!(imported.fingerprints(), wallet.fingerprints());
+    for (imported, keystore) in imported.keystores().iter().zip(wallet.keystores()) {
+        assert_eq!(imported.origin().unwrap(), keystore.origin().unwrap());
+    }
+}
+
+#[test]
+fn bluewallet_text_forms() {
+    let text = std::fs::read_to_string("tests/setup_files/bluewallet_hw_segwit").unwrap();
+    let expected = ElectrumWalletFile::from_bluewallet_text(&text).unwrap();
+    // the derivations as header lines, no format, a name and other letter case
+    let edited = text
+        .replace("# derivation:", "Derivation:")
+        .replace("Format: P2WSH\n", "")
+        .replace("Policy:", "Name: Vault\npolicy:")
+        .replace("27D81095", "27d81095");
+    assert_eq!(
+        ElectrumWalletFile::from_bluewallet_text(&edited).unwrap(),
+        expected
+    );
+
+    let wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_hw_segwit")).unwrap();
+    let tpubs = wallet
+        .keystores()
+        .iter()
+        .map(|ks| ks.xpub().unwrap().0.to_string())
+        .collect::<Vec<_>>();
+    let plain = text
+        .replace(&wallet.keystores()[0].xpub, &tpubs[0])
+        .replace(&wallet.keystores()[1].xpub, &tpubs[1]);
+    assert_eq!(
+        ElectrumWalletFile::from_bluewallet_text(&plain).unwrap(),
+        expected
+    );
+}
+
+#[test]
+fn bluewallet_text_errors() {
+    let text = std::fs::read_to_string("tests/setup_files/bluewallet_hw_segwit").unwrap();
+    assert!(matches!(
+        ElectrumWalletFile::from_bluewallet_text(&text.replace("2 of 2", "2 of 3")),
+        Err(Electrum2DescriptorError::PolicyMismatch(2, 3, 2))
+    ));
+    assert!(matches!(
+        ElectrumWalletFile::from_bluewallet_text(&text.replace("2 of 2", "3 of 2")),
+        Err(Electrum2DescriptorError::PolicyMismatch(3, 2, 2))
+    ));
+    assert!(matches!(
+        ElectrumWalletFile::from_bluewallet_text(&text.replace("Policy: 2 of 2\n", "")),
+        Err(Electrum2DescriptorError::InvalidSetupFile(0, _))
+    ));
+    assert!(matches!(
+        ElectrumWalletFile::from_bluewallet_text(
+            &text.replace("Format: P2WSH", "Format: P2SH-P2WSH")
+        ),
+        Err(Electrum2DescriptorError::ScriptKindConflict(..))
+    ));
+    assert!(matches!(
+        ElectrumWalletFile::from_bluewallet_text(&text.replace("Format: P2WSH", "Format: P2TR")),
+        Err(Electrum2DescriptorError::InvalidSetupFile(6, _))
+    ));
+    assert!(matches!(
+        ElectrumWalletFile::from_bluewallet_text(
+            &text.replace("# derivation: m/48'/1'/0'/2'\n", "")
+        ),
+        Err(Electrum2DescriptorError::InvalidSetupFile(8, _))
+    ));
+
+    let standard = ElectrumWalletFile::from_file(&get_test_wallet_file("default_segwit")).unwrap();
+    assert!(matches!(
+        standard.to_bluewallet_text(),
+        Err(Electrum2DescriptorError::NotMultisig)
+    ));
+    let zpubs = BIP32_XPUBS.map(|xpub| to_electrum_xpub(xpub, "wsh"));
+    let without_origins =
+        ElectrumWalletFile::new_multisig_from_xpubs(2, &[&zpubs[0], &zpubs[1]]).unwrap();
+    assert!(matches!(
+        without_origins.to_bluewallet_text(),
+        Err(Electrum2DescriptorError::MissingOrigin(_))
+    ));
+}
+
+#[test]
+fn coldcard_multisig() {
+    let text = std::fs::read_to_string("tests/setup_files/coldcard_hw_segwit").unwrap();
+    let wallet =
+        ElectrumWalletFile::from_file_watch_only(&get_test_wallet_file("multisig_hw_segwit"))
+            .unwrap();
+    assert_eq!(wallet.to_coldcard_multisig("Electrum 2of2").unwrap(), text);
+
+    let imported = ElectrumWalletFile::from_coldcard_multisig(&text).unwrap();
+    assert_eq!(
+        imported.to_descriptors().unwrap(),
+        wallet.to_descriptors().unwrap()
+    );
+    assert_eq!(
+        imported.to_coldcard_multisig("Electrum 2of2").unwrap(),
+        text
+    );
+    for (imported, keystore) in imported.keystores().iter().zip(wallet.keystores()) {
+        assert_eq!(imported.origin().unwrap(), keystore.origin().unwrap());
+    }
+
+    // without a format line the slip-0132 prefixes or the bip-0048 derivations tell the kind, else it is P2SH
+    let vpubs = wallet
+        .keystores()
+        .iter()
+        .map(|ks| ks.xpub.as_str())
+        .collect::<Vec<_>>();
+    let tpubs = wallet
+        .keystores()
+        .iter()
+        .map(|ks| ks.xpub().unwrap().0.to_string())
+        .collect::<Vec<_>>();
+    let prefixed = text
+        .replace("Format: P2WSH\n", "")
+        .replace(&tpubs[0], vpubs[0])
+        .replace(&tpubs[1], vpubs[1]);
+    assert_eq!(
+        ElectrumWalletFile::from_coldcard_multisig(&prefixed).unwrap(),
+        imported
+    );
+    let plain = text.replace("Format: P2WSH\n", "");
+    assert_eq!(
+        ElectrumWalletFile::from_coldcard_multisig(&plain).unwrap(),
+        imported
+    );
+    let legacy = ElectrumWalletFile::from_coldcard_multisig(
+        &plain
+            .replace("m/48'/1'/0'/2'", "m/45'")
+            .replace("m/48'/100'/0'/2'", "m/45'"),
+    )
+    .unwrap();
+    assert!(legacy
+        .to_descriptors()
+        .unwrap()
+        .external
+        .starts_with("sh(sortedmulti("));
+
+    for name in ["", "a name that is too long", "naïve"] {
+        assert!(matches!(
+            wallet.to_coldcard_multisig(name),
+            Err(Electrum2DescriptorError::InvalidArguments(_))
+        ));
+    }
+    assert!(matches!(
+        ElectrumWalletFile::from_coldcard_multisig(&text.replace("2 of 2", "1 of 3")),
+        Err(Electrum2DescriptorError::PolicyMismatch(1, 3, 2))
+    ));
+}
+
+/// A test wallet edited by `edit`, parsed without validating it
+fn broken_wallet(
+    wallet_name: &str,
+    edit: impl FnOnce(&mut serde_json::Value),
+) -> ElectrumWalletFile {
+    let mut json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(get_test_wallet_file(wallet_name)).unwrap())
+            .unwrap();
+    edit(&mut json);
+    let options = ParseOptions {
+        unvalidated: true,
+        ..ParseOptions::default()
+    };
+    ElectrumWalletFile::from_value_with_options(json, &options).unwrap()
+}
+
+/// Re-encode the keys of a keystore with the prefixes of `kind`
+fn reencode_keystore(keystore: &mut serde_json::Value, kind: &str) {
+    let xpub = ElectrumExtendedPubKey::from_str(keystore["xpub"].as_str().unwrap()).unwrap();
+    let xprv = ElectrumExtendedPrivKey::from_str(keystore["xprv"].as_str().unwrap()).unwrap();
+    keystore["xpub"] = ElectrumExtendedPubKey::new(*xpub.xpub(), kind.to_string())
+        .electrum_xpub()
+        .unwrap()
+        .into();
+    keystore["xprv"] = ElectrumExtendedPrivKey::new(*xprv.xprv(), kind.to_string())
+        .electrum_xprv()
+        .unwrap()
+        .into();
+}
+
+#[rstest]
+#[case::garbled_xpub("default_segwit", |json: &mut serde_json::Value| {
+    json["keystore"]["xpub"] = "vpub5Vadaqpt".into();
+}, RepairAction::XpubFromXprv("keystore".to_string()))]
+#[case::other_xpub("default_segwit", |json: &mut serde_json::Value| {
+    json["keystore"]["xpub"] = to_electrum_xpub(BIP32_XPUBS[0], "wpkh").into();
+}, RepairAction::XpubFromXprv("keystore".to_string()))]
+#[case::singlesig_prefix("multisig_segwit", |json: &mut serde_json::Value| {
+    reencode_keystore(&mut json["x1/"], "wpkh");
+}, RepairAction::Prefix("x1/".to_string(), ScriptKind::P2wpkh, ScriptKind::P2wsh))]
+#[case::multisig_prefix("default_segwit", |json: &mut serde_json::Value| {
+    reencode_keystore(&mut json["keystore"], "wsh");
+}, RepairAction::Prefix("keystore".to_string(), ScriptKind::P2wsh, ScriptKind::P2wpkh))]
+#[case::few_cosigners("multisig_wrapped_watch", |json: &mut serde_json::Value| {
+    json["wallet_type"] = "3of6".into();
+}, RepairAction::WalletType(WalletType::multisig(3, 6).unwrap(), WalletType::multisig(3, 7).unwrap()))]
+#[case::singlesig_multisig("default_segwit", |json: &mut serde_json::Value| {
+    json["wallet_type"] = "1of2".into();
+}, RepairAction::WalletType(WalletType::multisig(1, 2).unwrap(), WalletType::Standard))]
+fn repair(
+    #[case] wallet_name: &str,
+    #[case] edit: fn(&mut serde_json::Value),
+    #[case] expected: RepairAction,
+) {
+    let original = ElectrumWalletFile::from_file(&get_test_wallet_file(wallet_name)).unwrap();
+    let mut wallet = broken_wallet(wallet_name, edit);
+    assert_ne!(wallet, original);
+    assert_eq!(wallet.repair(RepairPolicy::default()), vec![expected]);
+    assert_eq!(wallet, original);
+    assert_eq!(wallet.repair(RepairPolicy::default()), vec![]);
+}
+
+#[test]
+fn repair_needs_attention() {
+    // two keystores of a 2of3 wallet are as much an unfinished setup as a wrong wallet_type
+    let mut wallet = broken_wallet("multisig_unfinished", |_json| {});
+    let unfinished = wallet.to_string();
+    let actions = wallet.repair(RepairPolicy::default());
+    assert!(matches!(actions[..], [RepairAction::NeedsAttention(_)]));
+    assert_eq!(wallet.to_string(), unfinished);
+
+    let edit = |json: &mut serde_json::Value| reencode_keystore(&mut json["x1/"], "wpkh");
+    let mut wallet = broken_wallet("multisig_segwit", edit);
+    let broken = wallet.to_string();
+    let policy = RepairPolicy {
+        prefixes: false,
+        ..RepairPolicy::default()
+    };
+    let actions = wallet.repair(policy);
+    assert!(matches!(actions[..], [RepairAction::NeedsAttention(_)]));
+    assert_eq!(wallet.to_string(), broken);
+
+    // the stored addresses are the ones of a 3of7 wallet, not of a 1of7 one
+    let mut wallet = broken_wallet("multisig_wrapped_watch", |json| {
+        json["wallet_type"] = "1of6".into();
+    });
+    let broken = wallet.to_string();
+    let actions = wallet.repair(RepairPolicy::default());
+    assert!(!actions.is_empty());
+    assert!(actions
+        .iter()
+        .all(|action| matches!(action, RepairAction::NeedsAttention(_))));
+    assert_eq!(wallet.to_string(), broken);
+}
+
+#[rstest]
+#[case::single_sig("default_segwit", "pk(b88448fb)")]
+#[case::taproot("taproot", "pk(73c5da0a)")]
+#[case::labelled("coldcard_ckcc_xfp", "pk(Coldcard 3442193e)")]
+// the label of x1/ has parentheses
+#[case::multisig("multisig_hw_segwit", "thresh(2,pk(27d81095),pk(T-Rex))")]
+#[case::three_cosigners(
+    "multisig_distinct_paths",
+    "thresh(2,pk(27d81095),pk(6bfac2d6),pk(f6083804))"
+)]
+#[case::no_fingerprints(
+    "multisig_wrapped_watch",
+    "thresh(3,pk(x1/),pk(x2/),pk(x3/),pk(x4/),pk(x5/),pk(x6/),pk(x7/))"
+)]
+fn to_policy(#[case] wallet_name: &str, #[case] expected: &str) {
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file(wallet_name)).unwrap();
+    assert_eq!(wallet.to_policy().unwrap(), expected);
+}
+
+#[test]
+fn to_policy_with_keys() {
+    let wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_hw_segwit")).unwrap();
+    assert_eq!(
+        wallet.to_policy_with_keys().unwrap(),
+        format!(
+            "thresh(2,pk([27d81095/48'/1'/0'/2']{}),pk([6bfac2d6/48'/100'/0'/2']{}))",
+            HW_SEGWIT_XPUBS[0], HW_SEGWIT_XPUBS[1]
+        )
+    );
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file("default_legacy")).unwrap();
+    assert_eq!(
+        wallet.to_policy_with_keys().unwrap(),
+        "pk([230b70d2]tpubD6NzVbkrYhZ4Y1ozBYSfoyVp2iGgP6iZAy18p2opXjVv8jTNccGuRs3jMCMe4ncfwy2RUJsoZLSXsGiFhN47xFbJgtRvCuV3RP3UnxpsrZt)"
+    );
+}
+
+#[test]
+fn to_policy_shared_names() {
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_segwit")).unwrap();
+    let keystores = wallet
+        .keystores()
+        .iter()
+        .map(|keystore| keystore.clone().with_label("laptop"))
+        .collect::<Vec<_>>();
+    let wallet = ElectrumWalletFile::new(&keystores, 2).unwrap();
+    assert_eq!(wallet.to_policy().unwrap(), "thresh(2,pk(x1/),pk(x2/))");
+
+    let wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_unfinished")).unwrap();
+    assert!(matches!(
+        wallet.to_policy(),
+        Err(Electrum2DescriptorError::UnfinishedSetup(_))
+    ));
+}
+
+#[test]
+fn loss_report() {
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file("feature_rich")).unwrap();
+    let report = wallet.loss_report(ConversionTarget::Descriptors);
+    let lost = |report: &LossReport| {
+        report
+            .items
+            .iter()
+            .map(|(what, count, _unit)| (what.clone(), *count))
+            .collect::<Vec<_>>()
+    };
+    let shared = [
+        ("lightning channels", 1),
+        ("frozen addresses", 2),
+        ("frozen coins", 1),
+        ("imported lightning channel backups", 1),
+        ("invoices", 1),
+        ("labels", 3),
+        ("lightning payments", 1),
+        ("lightning preimages", 1),
+        ("lightning key", 1),
+        ("onchain lightning channel backups", 1),
+        ("payment requests", 1),
+        ("submarine swaps", 1),
+        ("seed phrases", 1),
+    ]
+    .map(|(what, count)| (what.to_string(), count));
+    let expected = |history: (&str, usize), keystore_labels: bool| {
+        let mut expected = vec![(history.0.to_string(), history.1)];
+        expected.extend(shared.iter().cloned());
+        if keystore_labels {
+            expected.push(("keystore labels".to_string(), 1));
+        }
+        expected
+    };
+    assert_eq!(lost(&report), expected(("transaction history", 3), true));
+    assert!(report.to_string().contains("labels: 3 entries\n"));
+    assert!(report
+        .to_string()
+        .contains("transaction history: 3 transactions\n"));
+
+    // the confirmed transactions are found again on the chain
+    let report = wallet.loss_report(ConversionTarget::CoreImport);
+    assert_eq!(
+        lost(&report),
+        expected(("unconfirmed and local transactions", 1), true)
+    );
+    let report = wallet.loss_report(ConversionTarget::FreshWalletFile);
+    assert_eq!(
+        lost(&report),
+        expected(("unconfirmed and local transactions", 1), false)
+    );
+
+    let wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("default_legacy_watch")).unwrap();
+    assert!(wallet.loss_report(ConversionTarget::Descriptors).is_empty());
+}
+
+#[test]
+fn to_fresh_wallet() {
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file("feature_rich")).unwrap();
+    let fresh = wallet.to_fresh_wallet().unwrap();
+    assert_eq!(
+        fresh.to_descriptors().unwrap(),
+        wallet.to_descriptors().unwrap()
+    );
+    assert!(fresh
+        .loss_report(ConversionTarget::FreshWalletFile)
+        .is_empty());
+    assert_eq!(fresh.keystores()[0].label.as_deref(), Some("daily"));
+    assert_eq!(fresh.keystores()[0].seed, None);
+
+    let wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_hw_segwit")).unwrap();
+    let fresh = wallet.to_fresh_wallet().unwrap();
+    assert_eq!(fresh.wallet_type(), wallet.wallet_type());
+    assert_eq!(
+        fresh.to_descriptors().unwrap(),
+        wallet.to_descriptors().unwrap()
+    );
+}
+
+#[test]
+fn scrub_history() {
+    let mut wallet = ElectrumWalletFile::from_file(&get_test_wallet_file("hot_history")).unwrap();
+    // years of history, electrum keeps the transactions and their merkle proofs
+    for i in 0..200u32 {
+        let txid = format!("{:064x}", i);
+        let mut transactions = wallet.raw_field("transactions").unwrap().clone();
+        transactions[&txid] = serde_json::Value::from(format!("02000000{}", "11".repeat(200)));
+        wallet.set_raw_field("transactions", transactions).unwrap();
+        let mut verified = wallet.raw_field("verified_tx3").unwrap().clone();
+        verified[&txid] = serde_json::json!([2134400 + i, 1690000000, 1, "00".repeat(32)]);
+        wallet.set_raw_field("verified_tx3", verified).unwrap();
+    }
+    let scrubbed = wallet.scrub_history(true);
+    assert!(scrubbed.to_string().len() * 5 < wallet.to_string().len());
+
+    let scrubbed = ElectrumWalletFile::from_str(&scrubbed.to_string()).unwrap();
+    for field in [
+        "transactions",
+        "verified_tx3",
+        "tx_fees",
+        "invoices",
+        "payment_requests",
+    ] {
+        assert_eq!(scrubbed.raw_field(field), None, "{}", field);
+    }
+    let history = scrubbed
+        .raw_field("addr_history")
+        .unwrap()
+        .as_object()
+        .unwrap();
+    assert_eq!(
+        history.len(),
+        wallet
+            .raw_field("addr_history")
+            .unwrap()
+            .as_object()
+            .unwrap()
+            .len()
+    );
+    assert!(history.values().all(|h| h.as_array().unwrap().is_empty()));
+    assert_eq!(scrubbed.addresses(), wallet.addresses());
+    assert_eq!(
+        scrubbed.to_descriptors().unwrap(),
+        wallet.to_descriptors().unwrap()
+    );
+    assert_eq!(
+        serde_json::to_value(&scrubbed.keystores()[0]).unwrap(),
+        serde_json::to_value(&wallet.keystores()[0]).unwrap()
+    );
+    assert_eq!(scrubbed.raw_field("labels"), wallet.raw_field("labels"));
+    assert_eq!(
+        scrubbed.raw_field("winpos-qt"),
+        wallet.raw_field("winpos-qt")
+    );
+    assert_eq!(wallet.scrub_history(false).raw_field("labels"), None);
+
+    // composes with parsing without the secrets
+    let watch_only =
+        ElectrumWalletFile::from_file_watch_only(&get_test_wallet_file("hot_history")).unwrap();
+    let scrubbed = watch_only.scrub_history(true);
+    assert!(scrubbed.is_watch_only_copy());
+    assert_eq!(scrubbed.raw_field("lightning_privkey2"), None);
+    assert_eq!(scrubbed.raw_field("transactions"), None);
+}
+
+#[rstest]
+#[case::p2pkh(ScriptKind::Standard, "pkh({}/0/*)")]
+#[case::p2wpkh_p2sh(ScriptKind::P2wpkhP2sh, "sh(wpkh({}/0/*))")]
+#[case::p2wpkh(ScriptKind::P2wpkh, "wpkh({}/0/*)")]
+#[case::p2tr(ScriptKind::P2tr, "tr({}/0/*)")]
+fn kind_override(#[case] kind: ScriptKind, #[case] expected: &str) {
+    // the plain tpub of default_legacy_watch
+    let tpub = "tpubD6NzVbkrYhZ4Y1ozBYSfoyVp2iGgP6iZAy18p2opXjVv8jTNccGuRs3jMCMe4ncfwy2RUJsoZLSXsGiFhN47xFbJgtRvCuV3RP3UnxpsrZt";
+    let descriptors = ElectrumExtendedPubKey::from_str(tpub)
+        .unwrap()
+        .with_kind(kind)
+        .unwrap()
+        .to_descriptors();
+    assert_eq!(descriptors.external, expected.replace("{}", tpub));
+    assert_eq!(
+        ElectrumWalletFile::from_xpub(tpub, Some(kind))
+            .unwrap()
+            .to_descriptors()
+            .unwrap(),
+        descriptors
+    );
+    assert_eq!(
+        ElectrumWalletFile::from_descriptor(&descriptors.external)
+            .unwrap()
+            .to_descriptors()
+            .unwrap(),
+        descriptors
+    );
+
+    let mut wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("default_legacy_watch")).unwrap();
+    wallet.assume_kind(kind).unwrap();
+    assert_eq!(wallet.to_descriptors().unwrap(), descriptors);
+    assert_eq!(
+        wallet.addresses().receiving[0],
+        first_address_from_descriptor(&descriptors.external, Network::Testnet)
+    );
+    let mut warnings = Vec::new();
+    wallet.to_descriptors_with_warnings(&mut warnings).unwrap();
+    assert_eq!(
+        warnings.contains(&ConversionWarning::KindAssumed(
+            "keystore".to_string(),
+            kind
+        )),
+        kind != ScriptKind::Standard
+    );
+    let reparsed = ElectrumWalletFile::from_str(&wallet.to_string()).unwrap();
+    assert_eq!(reparsed.to_descriptors().unwrap(), descriptors);
+}
+
+#[test]
+fn kind_override_conflict() {
+    let vpub = "vpub5VXaSncXqxLbdmvrC4Y8z9CszPwuEscADoetWhfrxDFzPUbL5nbVtanYDkrVEutkv9n5A5aCcvRC9swbjDKgHjCZ2tAeae8VsBuPbS8KpXv";
+    let expub = ElectrumExtendedPubKey::from_str(vpub).unwrap();
+    assert!(matches!(
+        expub.with_kind(ScriptKind::P2tr),
+        Err(Electrum2DescriptorError::ScriptKindConflict(..))
+    ));
+    let expub = ElectrumExtendedPubKey::from_str(vpub).unwrap();
+    assert_eq!(expub.with_kind(ScriptKind::P2wpkh).unwrap().kind(), "wpkh");
+
+    let mut wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("default_segwit")).unwrap();
+    assert!(matches!(
+        wallet.assume_kind(ScriptKind::P2wpkhP2sh),
+        Err(Electrum2DescriptorError::ScriptKindConflict(..))
+    ));
+    // agreeing with the prefix changes nothing
+    wallet.assume_kind(ScriptKind::P2wpkh).unwrap();
+    assert!(wallet.parse_warnings().is_empty());
+
+    // singlesig kinds stand for the multisig ones
+    let mut wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_legacy")).unwrap();
+    wallet.assume_kind(ScriptKind::P2wpkh).unwrap();
+    let descriptors = wallet.to_descriptors().unwrap();
+    assert!(descriptors.external.starts_with("wsh(sortedmulti(2,"));
+    assert_eq!(
+        wallet.addresses().receiving[0],
+        first_address_from_descriptor(&descriptors.external, Network::Testnet)
+    );
+    assert!(matches!(
+        wallet.assume_kind(ScriptKind::P2tr),
+        Err(Electrum2DescriptorError::TaprootMultisig)
+    ));
+}
+
+#[rstest]
+#[case::next_chain(
+    "wsh(sortedmulti(2,{0}/0/*,{1}/2/*))",
+    "wsh(sortedmulti(2,{0}/0/*,{1}/2/*))",
+    "wsh(sortedmulti(2,{0}/1/*,{1}/3/*))",
+    [None, Some((2, 3))]
+)]
+#[case::multipath(
+    "sh(wsh(sortedmulti(2,{0}/4/*,{1}/<0;1>/*)))",
+    "sh(wsh(sortedmulti(2,{0}/4/*,{1}/0/*)))",
+    "sh(wsh(sortedmulti(2,{0}/5/*,{1}/1/*)))",
+    [Some((4, 5)), None]
+)]
+fn per_cosigner_chains(
+    #[case] descriptor: &str,
+    #[case] external: &str,
+    #[case] change: &str,
+    #[case] chains: [Option<(u32, u32)>; 2],
+) {
+    let keys = |template: &str| {
+        template
+            .replace("{0}", BIP32_XPUBS[0])
+            .replace("{1}", BIP32_XPUBS[1])
+    };
+    let wallet = ElectrumWalletFile::from_descriptor(&keys(descriptor)).unwrap();
+    assert_eq!(
+        wallet
+            .keystores()
+            .iter()
+            .map(|keystore| keystore.chains)
+            .collect::<Vec<_>>(),
+        chains
+    );
+    let descriptors = wallet.to_descriptors().unwrap();
+    assert_eq!(descriptors.external, keys(external));
+    assert_eq!(descriptors.change, keys(change));
+
+    for (is_change, descriptor) in [(false, &descriptors.external), (true, &descriptors.change)] {
+        assert_eq!(
+            wallet.address(is_change, 0).unwrap().to_string(),
+            first_address_from_descriptor(descriptor, Network::Bitcoin)
+        );
+    }
+    assert!(wallet
+        .descriptor_at(false, 0)
+        .unwrap()
+        .contains(&format!("/{}/0]", wallet.keystores()[0].chain(false))));
+
+    // the chains are kept in the wallet file
+    let reparsed = ElectrumWalletFile::from_str(&wallet.to_string()).unwrap();
+    assert_eq!(reparsed.to_descriptors().unwrap(), descriptors);
+    assert_eq!(reparsed.addresses(), wallet.addresses());
+
+    // the external descriptor gives the wallet back, the change one isn't taken for the change of other chains
+    let mut warnings = Vec::new();
+    let from_external = ElectrumWalletFile::from_descriptor_with_warnings(
+        &descriptors.external,
+        &DescriptorOptions::default(),
+        &mut warnings,
+    );
+    assert_eq!(from_external.unwrap(), wallet);
+    assert!(warnings.is_empty());
+    assert!(matches!(
+        ElectrumWalletFile::from_descriptor(&descriptors.change),
+        Err(Electrum2DescriptorError::UnsupportedKeyPath(_, _, _))
+    ));
+
+    // reading all keys as /0/* gives other addresses
+    let keystores = wallet
+        .keystores()
+        .iter()
+        .map(|keystore| Keystore {
+            chains: None,
+            ..keystore.clone()
+        })
+        .collect::<Vec<_>>();
+    let mut naive = ElectrumWalletFile::new(&keystores, 2).unwrap();
+    naive.set_key_order(wallet.key_order()).unwrap();
+    assert_ne!(
+        naive.address(false, 0).unwrap(),
+        wallet.address(false, 0).unwrap()
+    );
+    assert_ne!(naive.wallet_id().unwrap(), wallet.wallet_id().unwrap());
+    assert!(!naive.is_equivalent(&wallet).unwrap());
+    assert!(matches!(
+        wallet.to_restore_command(false),
+        Err(Electrum2DescriptorError::NotRestorable(_))
+    ));
+}
+
+#[rstest]
+#[case::hardened("/2h/*")]
+#[case::two_steps("/0/0/*")]
+#[case::no_wildcard("/0")]
+#[case::bare("")]
+#[case::wildcard_only("/*")]
+#[case::three_paths("/<0;1;2>/*")]
+#[case::same_paths("/<0;0>/*")]
+#[case::hardened_paths("/<0;1h>/*")]
+#[case::swapped_paths("/<1;0>/*")]
+#[case::other_paths("/<2;5>/*")]
+#[case::other_change_chain("/3/*")]
+fn per_cosigner_chains_unsupported(#[case] suffix: &str) {
+    let descriptor = format!(
+        "wsh(sortedmulti(2,{}/0/*,{}{}))",
+        BIP32_XPUBS[0], BIP32_XPUBS[1], suffix
+    );
+    match ElectrumWalletFile::from_descriptor(&descriptor) {
+        Err(Electrum2DescriptorError::UnsupportedKeyPath(key, path, _)) => {
+            assert_eq!(key, BIP32_XPUBS[1]);
+            assert_eq!(path, format!("{:?}", suffix));
+        }
+        result => panic!("{:?}", result.map(|w| w.to_string())),
+    }
+}
+
+#[rstest]
+#[case::apostrophe("/0'")]
+#[case::h("/0h")]
+fn descriptor_origins_singlesig(#[case] hardened: &str) {
+    let file = ElectrumWalletFile::from_file(&get_test_wallet_file("default_segwit")).unwrap();
+    let descriptors = file.to_descriptors().unwrap();
+    assert!(descriptors.external.starts_with("wpkh([b88448fb/0']tprv"));
+
+    for descriptor in [
+        descriptors
+            .external
+            .replace("/0']", &format!("{}]", hardened)),
+        descriptors.external.replace("/0/*", "/<0;1>/*"),
+    ] {
+        let wallet = ElectrumWalletFile::from_descriptor(&descriptor).unwrap();
+        let keystore = &wallet.keystores()[0];
+        assert_eq!(keystore.root_fingerprint.as_deref(), Some("b88448fb"));
+        assert_eq!(keystore.derivation.as_deref(), Some("m/0'"));
+        assert_eq!(keystore.chains, None);
+        assert_eq!(wallet.to_descriptors().unwrap(), descriptors);
+        assert_eq!(
+            wallet.address(false, 0).unwrap(),
+            file.address(false, 0).unwrap()
+        );
+    }
+
+    for chain in ["/2/*", "/<0;2>/*", "/<1;0>/*"] {
+        let descriptor = descriptors.external.replace("/0/*", chain);
+        assert!(matches!(
+            ElectrumWalletFile::from_descriptor(&descriptor),
+            Err(Electrum2DescriptorError::UnsupportedKeyPath(_, _, _))
+        ));
+    }
+
+    // the origin is as deep as the key
+    let descriptor = descriptors
+        .external
+        .replace("[b88448fb/0']", "[b88448fb/84'/1'/0']");
+    assert!(matches!(
+        ElectrumWalletFile::from_descriptor(&descriptor),
+        Err(Electrum2DescriptorError::OriginDepthMismatch(_, 3, 1))
+    ));
+
+    // keys only follow the ( or , before them, taproot script paths aren't supported
+    let key = descriptors.external.trim_start_matches("wpkh(");
+    for descriptor in [
+        format!("wpkh(pk{}", key),
+        format!("tr({},pk({}))", key.trim_end_matches(')'), key),
+    ] {
+        assert!(matches!(
+            ElectrumWalletFile::from_descriptor(&descriptor),
+            Err(Electrum2DescriptorError::UnknownDescriptorFormat(_))
+        ));
+    }
+}
+
+#[test]
+fn descriptor_origins_multisig() {
+    let file = ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_hw_segwit")).unwrap();
+    let descriptors = file.to_descriptors().unwrap();
+    // only the first cosigner has an origin, written with h
+    let external = descriptors
+        .external
+        .replace("[6bfac2d6/48'/100'/0'/2']", "");
+    let wallet = ElectrumWalletFile::from_descriptor(
+        &external
+            .replace("/0/*", "/<0;1>/*")
+            .replace("48'/1'/0'/2'", "48h/1h/0h/2h"),
+    )
+    .unwrap();
+    let origins = wallet
+        .keystores()
+        .iter()
+        .map(|keystore| keystore.origin().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(origins[0], file.keystores()[0].origin().unwrap());
+    assert_eq!(origins[1], None);
+    assert_eq!(wallet.to_descriptors().unwrap().external, external);
+    assert_eq!(
+        wallet.to_descriptors().unwrap().change,
+        external.replace("/0/*", "/1/*")
+    );
+    assert_eq!(
+        first_address_from_descriptor(&external, Network::Testnet),
+        file.address(false, 0).unwrap().to_string()
+    );
+
+    // the whole descriptor is a multisig script, with each cosigner once
+    let [a, b] = [0, 1].map(|i| format!("{}/0/*", BIP32_XPUBS[i]));
+    for descriptor in [
+        format!("pkh(wsh(sortedmulti(2,{},{})))", a, b),
+        format!("tr(wsh(sortedmulti(2,{},{})))", a, b),
+        format!("wsh(sortedmulti(2,{},{}),{})", a, b, a),
+        format!("wsh(sortedmulti(2,{},{})))", a, b),
+    ] {
+        assert!(
+            matches!(
+                ElectrumWalletFile::from_descriptor(&descriptor),
+                Err(Electrum2DescriptorError::UnknownDescriptorFormat(_))
+            ),
+            "{}",
+            descriptor
+        );
+    }
+    assert!(matches!(
+        ElectrumWalletFile::from_descriptor(&format!("wsh(sortedmulti(2,{},{},{}))", a, b, a)),
+        Err(Electrum2DescriptorError::DuplicateCosigner(_))
+    ));
+
+    // a key inside another argument is no cosigner
+    let hidden = external
+        .replacen(",[", ",pk([", 1)
+        .replacen("/0/*,", "/0/*),", 1);
+    assert!(matches!(
+        ElectrumWalletFile::from_descriptor(&hidden),
+        Err(Electrum2DescriptorError::UnknownDescriptorFormat(_))
+    ));
+    let deep = descriptors
+        .external
+        .replace("[6bfac2d6/48'/100'/0'/2']", "[6bfac2d6/48'/100'/0']");
+    assert!(matches!(
+        ElectrumWalletFile::from_descriptor(&deep),
+        Err(Electrum2DescriptorError::OriginDepthMismatch(_, 3, 4))
+    ));
+}
+
+#[rstest]
+#[case::text(ReportFormat::Text, "multisig_distinct_paths.txt")]
+#[case::markdown(ReportFormat::Markdown, "multisig_distinct_paths.md")]
+fn to_report(#[case] format: ReportFormat, #[case] snapshot: &str) {
+    let wallet =
+        ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_distinct_paths")).unwrap();
+    let options = ReportOptions {
+        format,
+        name: Some("multisig_distinct_paths".to_string()),
+    };
+    let report = wallet.to_report(&options).unwrap();
+    let expected = std::fs::read_to_string(Path::new("tests/reports").join(snapshot)).unwrap();
+    assert_eq!(report, expected);
+
+    let descriptor = wallet.to_descriptors().unwrap().external;
+    let address = first_address_from_descriptor(&descriptor, Network::Testnet);
+    assert!(report.contains(&address));
+    assert!(report.contains(&wallet.wallet_id().unwrap()));
+}
+
+#[test]
+fn to_report_without_secrets() {
+    let wallet = ElectrumWalletFile::from_file(&get_test_wallet_file("multisig_segwit")).unwrap();
+    let keystores = wallet
+        .keystores()
+        .iter()
+        .enumerate()
+        .map(|(i, keystore)| keystore.clone().with_label(&format!("signer | {}", i + 1)))
+        .collect::<Vec<_>>();
+    let wallet = ElectrumWalletFile::new(&keystores, 2).unwrap();
+    assert!(wallet
+        .keystores()
+        .iter()
+        .any(|keystore| keystore.xprv.is_some()));
+    for format in [ReportFormat::Text, ReportFormat::Markdown] {
+        let options = ReportOptions { format, name: None };
+        let report = wallet.to_report(&options).unwrap();
+        for keystore in wallet.keystores() {
+            if let Some(xprv) = keystore.xprv.as_deref() {
+                assert!(!report.contains(xprv));
+                let tprv = ElectrumExtendedPrivKey::from_str(xprv).unwrap().xkey_str();
+                assert!(!report.contains(&tprv));
+            }
+        }
+        assert!(!report.contains("prv"));
+        assert!(report.contains(&wallet.keystores()[1].xpub));
+        match format {
+            ReportFormat::Text => assert!(report.contains("  label: signer | 2\n")),
+            ReportFormat::Markdown => assert!(report.contains("| x2/ | signer \\| 2 |")),
+        }
+    }
+}
+
+#[rstest]
+#[case::json("export.json")]
+#[case::csv("export.csv")]
+fn private_key_export(#[case] export_name: &str) {
+    let text =
+        std::fs::read_to_string(Path::new("tests/private_key_exports").join(export_name)).unwrap();
+    assert!(PrivateKeyExport::is_export(&text));
+    let export = PrivateKeyExport::from_str(&text).unwrap();
+    let mut kinds = export.keys.iter().map(|key| key.kind).collect::<Vec<_>>();
+    kinds.sort_by_key(|kind| kind.to_string());
+    assert_eq!(
+        kinds,
+        [
+            ScriptKind::P2wpkh,
+            ScriptKind::P2wpkhP2sh,
+            ScriptKind::Standard
+        ]
+    );
+
+    let descriptors = export.to_descriptors().unwrap();
+    for (key, descriptor) in export.keys.iter().zip(&descriptors) {
+        assert_eq!(
+            first_address_from_descriptor(descriptor, Network::Testnet),
+            key.address.to_string()
+        );
+        assert!(descriptor.contains(&key.key.to_wif()));
+    }
+
+    let imported = ElectrumWalletFile::from_private_key_export(&text).unwrap();
+    assert_eq!(imported.wallet_type(), &WalletType::Imported);
+    let tempdir = tempdir().unwrap();
+    let wallet_file = tempdir.path().join("imported");
+    imported.to_file(&wallet_file).unwrap();
+    assert_eq!(
+        ElectrumWalletFile::from_file(&wallet_file).unwrap(),
+        imported
+    );
+    let wallet: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&wallet_file).unwrap()).unwrap();
+    assert_eq!(wallet["wallet_type"], "imported");
+    assert!(wallet["keystore"].get("xpub").is_none());
+    assert_eq!(wallet["keystore"]["type"], "imported");
+    for key in &export.keys {
+        let pubkey = key
+            .key
+            .public_key(&bdk::bitcoin::secp256k1::Secp256k1::new())
+            .to_string();
+        assert_eq!(wallet["keystore"]["keypairs"][&pubkey], key.key.to_wif());
+        assert_eq!(
+            wallet["addresses"][key.address.to_string()]["pubkey"],
+            pubkey
+        );
+    }
+
+    let debug = format!("{:?}", export);
+    assert!(debug.contains(&export.keys[0].address.to_string()));
+    assert!(export
+        .keys
+        .iter()
+        .all(|key| !debug.contains(&key.key.to_wif())));
+}
+
+#[test]
+fn private_key_export_mismatch() {
+    let text = std::fs::read_to_string("tests/private_key_exports/export.csv").unwrap();
+    // the keys of the second and third row swapped
+    let mut lines = text.lines().map(String::from).collect::<Vec<_>>();
+    let (address_2, key_2) = lines[2]
+        .split_once(',')
+        .map(|(a, k)| (a.to_string(), k.to_string()))
+        .unwrap();
+    let (address_3, key_3) = lines[3]
+        .split_once(',')
+        .map(|(a, k)| (a.to_string(), k.to_string()))
+        .unwrap();
+    lines[2] = format!("{},{}", address_2, key_3.replace("p2wpkh:", "p2wpkh-p2sh:"));
+    lines[3] = format!(
+        "  {},{}",
+        address_3,
+        key_2.replace("p2wpkh-p2sh:", "p2wpkh:")
+    );
+    match PrivateKeyExport::from_str(&lines.join("\n")) {
+        Err(Electrum2DescriptorError::PrivateKeyMismatch(row)) => {
+            assert_eq!(row, format!("line 3 ({})", address_2))
+        }
+        result => panic!("{:?}", result),
+    }
+
+    let json = format!(
+        "{{\"{}\": \"{}\"}}",
+        address_3,
+        key_3.replace("p2wpkh:", "p2pkh:")
+    );
+    assert!(PrivateKeyExport::is_export(&json));
+    match PrivateKeyExport::from_str(&json) {
+        Err(Electrum2DescriptorError::PrivateKeyMismatch(row)) => assert_eq!(row, address_3),
+        result => panic!("{:?}", result),
+    }
+    assert!(matches!(
+        PrivateKeyExport::from_str(&format!("{{\"{}\": \"p2tr:{}\"}}", address_3, key_3)),
+        Err(Electrum2DescriptorError::InvalidPrivateKeyExport(_))
+    ));
+
+    let wallet = std::fs::read_to_string(get_test_wallet_file("default_segwit")).unwrap();
+    assert!(!PrivateKeyExport::is_export(&wallet));
+}